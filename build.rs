@@ -17,6 +17,7 @@ fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed={}", ROOT);
     compile_simple("fill.frag")?;
     compile_simple("fill.vert")?;
+    compile_simple("texture.frag")?;
     Ok(())
 }
 
@@ -0,0 +1,135 @@
+//! Measures how fast a fixed scene can be rendered and presented. The control pane and
+//! the event-driven repaint of the test pane are bypassed.
+
+use {
+    crate::{
+        cmm::ColorMatrix,
+        protocols::{
+            wayland::{wl_compositor::WlCompositor, wl_display::WlDisplay},
+            xdg_shell::{
+                xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel, xdg_wm_base::XdgWmBase,
+            },
+        },
+        singletons::get_singletons,
+        test_pane::{Color, TestColorDescription},
+        vulkan::{Overlay, Scene, VulkanDevice},
+    },
+    std::{
+        cell::Cell,
+        rc::Rc,
+        time::{Duration, Instant},
+    },
+    wl_client::{proxy, Libwayland},
+};
+
+pub const DEFAULT_FRAMES: u32 = 1000;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+
+/// Renders `frames` frames and prints the results. Returns the exit code of the process.
+pub fn run(frames: u32) -> i32 {
+    let con = match Libwayland::open().and_then(|l| l.connect_to_default_display()) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Could not connect to the compositor: {e}");
+            return 1;
+        }
+    };
+    let queue = con.create_local_queue(c"bench");
+    let display = queue.display::<WlDisplay>();
+    let singletons = get_singletons(&display);
+    let wl_compositor: WlCompositor = singletons.get(1, 1);
+    let xdg_wm_base: XdgWmBase = singletons.get(1, 1);
+    proxy::set_event_handler(&xdg_wm_base, XdgWmBase::on_ping(|p, serial| p.pong(serial)));
+    let wl_surface = wl_compositor.create_surface();
+    let xdg_surface = xdg_wm_base.get_xdg_surface(&wl_surface);
+    let xdg_toplevel = xdg_surface.get_toplevel();
+    xdg_toplevel.set_title("benchmark");
+    let configured = Rc::new(Cell::new(false));
+    let configured2 = configured.clone();
+    proxy::set_event_handler_local(
+        &xdg_surface,
+        XdgSurface::on_configure(move |slf, serial| {
+            slf.ack_configure(serial);
+            configured2.set(true);
+        }),
+    );
+    let closed = Rc::new(Cell::new(false));
+    let closed2 = closed.clone();
+    proxy::set_event_handler_local(
+        &xdg_toplevel,
+        XdgToplevel::on_close(move |_| closed2.set(true)),
+    );
+    wl_surface.commit();
+    while !configured.get() {
+        if let Err(e) = queue.dispatch_blocking() {
+            eprintln!("Could not dispatch events: {e}");
+            return 1;
+        }
+    }
+    let res = VulkanDevice::create().and_then(|device| {
+        let surface = device.create_surface(con.wl_display(), &wl_surface)?;
+        Ok((device, surface))
+    });
+    let (device, surface) = match res {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Could not initialize vulkan: {e}");
+            return 1;
+        }
+    };
+    let params = TestColorDescription::None.render_params(ColorMatrix::XYZ_FROM_LMS);
+    let white = Color {
+        lumen: 203.0,
+        lightness: 1.0,
+        chroma: 0.0,
+        hue: 0.0,
+    };
+    let colors = [Color::default().to_lab(), white.to_lab()];
+    let mut times = Vec::with_capacity(frames as usize);
+    let start = Instant::now();
+    for _ in 0..frames {
+        if closed.get() {
+            break;
+        }
+        let frame_start = Instant::now();
+        let scene = Scene::Grid(colors, 16, 16);
+        let res = surface.render(WIDTH, HEIGHT, scene, None, &Overlay::default(), &params);
+        if let Err(e) = res {
+            eprintln!("Could not render: {e}");
+            return 1;
+        }
+        times.push(frame_start.elapsed());
+        if let Err(e) = queue.dispatch_pending() {
+            eprintln!("Could not dispatch events: {e}");
+            return 1;
+        }
+    }
+    let total = start.elapsed();
+    let memory = device.allocated_memory();
+    report(&times, total, memory);
+    0
+}
+
+fn report(times: &[Duration], total: Duration, memory: u64) {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let frames = times.len();
+    let min = times.iter().copied().min().map(ms).unwrap_or(0.0);
+    let max = times.iter().copied().max().map(ms).unwrap_or(0.0);
+    let avg = match frames {
+        0 => 0.0,
+        n => ms(times.iter().sum::<Duration>()) / n as f64,
+    };
+    let fps = frames as f64 / total.as_secs_f64();
+    println!("frames:     {frames}");
+    println!("total:      {:.3} s", total.as_secs_f64());
+    println!("frames/s:   {fps:.1}");
+    println!("frame time: min {min:.3} ms, avg {avg:.3} ms, max {max:.3} ms");
+    println!("gpu memory: {memory} bytes");
+    println!(
+        "{{\"frames\": {frames}, \"total_s\": {}, \"fps\": {fps}, \"min_ms\": {min}, \
+         \"avg_ms\": {avg}, \"max_ms\": {max}, \"gpu_memory_bytes\": {memory}}}",
+        total.as_secs_f64(),
+    );
+}
@@ -0,0 +1,126 @@
+//! Measures how much the blend of the blend scene depends on the blend space.
+//!
+//! The compositor's output cannot be read back. Instead, the pixels that the client
+//! sends are blended as a compositor would blend them and compared with the blend
+//! computed by the client.
+
+use {
+    crate::{
+        cmm::{ColorMatrix, Lms, Local},
+        histogram,
+        vulkan::{eotf_id, lms_to_lab, RenderParams},
+    },
+    std::collections::HashMap,
+};
+
+/// The difference between two blends in Oklab. A difference of 0.02 is about one
+/// just noticeable difference.
+#[derive(Copy, Clone, Debug)]
+pub struct Delta {
+    pub max: f64,
+    pub mean: f64,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BlendDelta {
+    /// Blending in linear light.
+    pub linear: Delta,
+    /// Blending the encoded values. Not set if the sub-surface uses a different
+    /// description.
+    pub encoded: Option<Delta>,
+}
+
+/// The pixels of one quadrant of the blend scene.
+pub struct Quadrant<'a> {
+    pub pixels: &'a [[f32; 4]],
+    pub params: &'a RenderParams,
+}
+
+/// Compares the blend of `foreground` over `background` with the blend computed by
+/// the client. The quadrants must have the same size. The foreground uses straight
+/// alpha. Returns `None` if the color space of a quadrant cannot be decoded to LMS.
+pub fn compute(background: Quadrant, foreground: Quadrant, client: Quadrant) -> Option<BlendDelta> {
+    let mut bg = Decoder::new(background.params)?;
+    let mut fg = Decoder::new(foreground.params)?;
+    let mut cl = Decoder::new(client.params)?;
+    let same_encoding = background.params.tf == foreground.params.tf
+        && background.params.tf_args == foreground.params.tf_args
+        && background.params.lms_to_local == foreground.params.lms_to_local;
+    let mut linear = vec![];
+    let mut encoded = vec![];
+    let pixels = background
+        .pixels
+        .iter()
+        .zip(foreground.pixels)
+        .zip(client.pixels);
+    for ((b, f), c) in pixels {
+        let a = f[3] as f64;
+        let client = cl.lab(c);
+        let b_lms = bg.lms(b);
+        let f_lms = fg.lms(f);
+        let blended = array_mix(b_lms, f_lms, a);
+        linear.push(distance(lms_to_lab(blended), client));
+        if same_encoding {
+            let mixed = [0, 1, 2].map(|i| (b[i] as f64 * (1.0 - a) + f[i] as f64 * a) as f32);
+            encoded.push(distance(
+                bg.lab(&[mixed[0], mixed[1], mixed[2], 1.0]),
+                client,
+            ));
+        }
+    }
+    Some(BlendDelta {
+        linear: delta(&linear),
+        encoded: same_encoding.then(|| delta(&encoded)),
+    })
+}
+
+/// Decodes encoded pixels to LMS.
+struct Decoder {
+    local_to_lms: ColorMatrix<Lms, Local>,
+    eotf: u32,
+    tf_args: [f32; 4],
+    decoded: HashMap<u32, f64>,
+}
+
+impl Decoder {
+    fn new(params: &RenderParams) -> Option<Self> {
+        Some(Self {
+            local_to_lms: params.lms_to_local.inverse()?,
+            eotf: eotf_id(params.tf),
+            tf_args: params.tf_args,
+            decoded: HashMap::new(),
+        })
+    }
+
+    fn lms(&mut self, px: &[f32; 4]) -> [f64; 3] {
+        let local = [px[0], px[1], px[2]].map(|c| {
+            *self
+                .decoded
+                .entry(c.to_bits())
+                .or_insert_with(|| histogram::decode(self.eotf, &self.tf_args, c))
+        });
+        self.local_to_lms * local
+    }
+
+    fn lab(&mut self, px: &[f32; 4]) -> [f32; 4] {
+        lms_to_lab(self.lms(px))
+    }
+}
+
+fn array_mix(b: [f64; 3], f: [f64; 3], a: f64) -> [f64; 3] {
+    [0, 1, 2].map(|i| b[i] * (1.0 - a) + f[i] * a)
+}
+
+fn distance(a: [f32; 4], b: [f32; 4]) -> f64 {
+    (0..3)
+        .map(|i| (a[i] as f64 - b[i] as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn delta(distances: &[f64]) -> Delta {
+    Delta {
+        max: distances.iter().copied().fold(0.0, f64::max),
+        mean: distances.iter().sum::<f64>() / distances.len().max(1) as f64,
+    }
+}
@@ -32,6 +32,9 @@ pub struct Xyz;
 pub struct Lms;
 #[derive(Copy, Clone)]
 pub struct Bradford;
+/// The LMS space of ICtCp in units of 10000 cd/m².
+#[derive(Copy, Clone)]
+pub struct IctcpLms;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Linearize)]
 pub enum NamedTransferFunction {
@@ -76,9 +79,30 @@ impl NamedTransferFunction {
             NamedTransferFunction::St428 => WpColorManagerV1TransferFunction::ST428,
         }
     }
+
+    /// The name shown in the UI.
+    pub const fn name(self) -> &'static str {
+        match self {
+            NamedTransferFunction::Srgb => "srgb",
+            NamedTransferFunction::Linear => "ext_linear",
+            NamedTransferFunction::St2084Pq => "st2084_pq",
+            NamedTransferFunction::Bt1886 => "bt1886",
+            NamedTransferFunction::Gamma22 => "gamma22",
+            NamedTransferFunction::Gamma28 => "gamma28",
+            NamedTransferFunction::St240 => "st240",
+            NamedTransferFunction::ExtSrgb => "ext_srgb",
+            NamedTransferFunction::Log100 => "log_100",
+            NamedTransferFunction::Log316 => "log_316",
+            NamedTransferFunction::St428 => "st428",
+        }
+    }
 }
 
-pub fn matrix_from_lms(primaries: Primaries, luminance: Luminance) -> ColorMatrix<Local, Lms> {
+pub fn matrix_from_lms(
+    primaries: Primaries,
+    luminance: Luminance,
+    xyz_from_lms: ColorMatrix<Xyz, Lms>,
+) -> ColorMatrix<Local, Lms> {
     let (_, mut mat) = primaries.matrices();
     if luminance != Luminance::SRGB {
         mat *= white_balance(&Luminance::SRGB, &luminance, primaries.wp);
@@ -86,7 +110,7 @@ pub fn matrix_from_lms(primaries: Primaries, luminance: Luminance) -> ColorMatri
     if primaries.wp != Primaries::SRGB.wp {
         mat *= bradford_adjustment(Primaries::SRGB.wp, primaries.wp);
     }
-    mat * ColorMatrix::XYZ_FROM_LMS
+    mat * xyz_from_lms
 }
 
 impl Luminance {
@@ -123,6 +147,62 @@ impl Luminance {
         // 80 cd/m^2.
         white: F64(Self::ST2084_PQ.white.0 / 80.0 * Self::ST2084_PQ.max.0),
     };
+
+    /// Returns the luminances that apply if a description with the transfer function
+    /// does not set them explicitly.
+    pub fn default_for(tf: TransferFunction) -> Self {
+        match tf {
+            TransferFunction::Named(NamedTransferFunction::St2084Pq) => Self::ST2084_PQ,
+            TransferFunction::Named(NamedTransferFunction::Bt1886) => Self::BT1886,
+            _ => Self::SRGB,
+        }
+    }
+
+    /// Converts to the arguments of `set_luminances`. All luminances are in cd/m² but
+    /// the protocol transmits the minimum in units of 0.0001 cd/m².
+    pub fn to_protocol(self) -> (u32, u32, u32) {
+        (
+            (self.min.0 * 10_000.0).round() as u32,
+            self.max.0.round() as u32,
+            self.white.0.round() as u32,
+        )
+    }
+
+    /// The inverse of `to_protocol`.
+    pub fn from_protocol(min: u32, max: u32, white: u32) -> Self {
+        Self {
+            min: min_luminance_from_protocol(min),
+            max: F64(max as f64),
+            white: F64(white as f64),
+        }
+    }
+}
+
+/// Converts a power transfer function exponent to the argument of `set_tf_power`,
+/// which is in units of 0.0001.
+pub fn tf_power_to_protocol(pow: f32) -> u32 {
+    (pow as f64 * 10_000.0).round() as u32
+}
+
+/// The inverse of `tf_power_to_protocol`.
+pub fn tf_power_from_protocol(eexp: u32) -> f32 {
+    (eexp as f64 / 10_000.0) as f32
+}
+
+/// Converts a chromaticity coordinate to the argument of `set_primaries`, which is in
+/// units of 0.000001. More precise values are rounded.
+pub fn chromaticity_to_protocol(c: F64) -> i32 {
+    (c.0 * 1_000_000.0).round() as i32
+}
+
+/// The inverse of `chromaticity_to_protocol`.
+pub fn chromaticity_from_protocol(c: i32) -> F64 {
+    F64(c as f64 / 1_000_000.0)
+}
+
+/// Converts a minimum luminance from units of 0.0001 cd/m² to cd/m².
+pub fn min_luminance_from_protocol(min: u32) -> F64 {
+    F64(min as f64 / 10_000.0)
 }
 
 impl Default for Luminance {
@@ -390,6 +470,20 @@ impl<T, U> ColorMatrix<T, U> {
         Self(m, PhantomData)
     }
 
+    /// Creates a matrix without a translation.
+    pub const fn from_3x3(m: [[f64; 3]; 3]) -> Self {
+        Self::new([
+            [m[0][0], m[0][1], m[0][2], 0.0],
+            [m[1][0], m[1][1], m[1][2], 0.0],
+            [m[2][0], m[2][1], m[2][2], 0.0],
+        ])
+    }
+
+    /// Returns the matrix without the translation.
+    pub fn to_3x3(self) -> [[f64; 3]; 3] {
+        self.0.map(|r| [r[0].0, r[1].0, r[2].0])
+    }
+
     pub const fn to_f32(self) -> [[f32; 4]; 4] {
         let m = self.0;
         macro_rules! map {
@@ -404,16 +498,64 @@ impl<T, U> ColorMatrix<T, U> {
             [0.0, 0.0, 0.0, 1.0],
         ]
     }
+
+    /// Returns `None` if the matrix is singular.
+    pub fn inverse(self) -> Option<ColorMatrix<U, T>> {
+        let m = self.0.map(|r| r.map(|v| v.0));
+        let cof = |r1: usize, r2: usize, c1: usize, c2: usize| {
+            m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+        };
+        let adj = [
+            [cof(1, 2, 1, 2), -cof(0, 2, 1, 2), cof(0, 1, 1, 2)],
+            [-cof(1, 2, 0, 2), cof(0, 2, 0, 2), -cof(0, 1, 0, 2)],
+            [cof(1, 2, 0, 1), -cof(0, 2, 0, 1), cof(0, 1, 0, 1)],
+        ];
+        let det = m[0][0] * adj[0][0] + m[0][1] * adj[1][0] + m[0][2] * adj[2][0];
+        if !det.is_normal() {
+            return None;
+        }
+        let inv = adj.map(|r| r.map(|v| v / det));
+        let t = [m[0][3], m[1][3], m[2][3]];
+        let row = |r: usize| {
+            let [a, b, c] = inv[r];
+            [a, b, c, -(a * t[0] + b * t[1] + c * t[2])]
+        };
+        Some(ColorMatrix::new([row(0), row(1), row(2)]))
+    }
 }
 
 impl ColorMatrix<Xyz, Lms> {
-    const XYZ_FROM_LMS: Self = Self::new([
+    pub const XYZ_FROM_LMS: Self = Self::new([
         [1.22701, -0.5578, 0.281256, 0.0],
         [-0.0405802, 1.11226, -0.0716767, 0.0],
         [-0.0763813, -0.421482, 1.58616, 0.0],
     ]);
 }
 
+impl ColorMatrix<IctcpLms, Local> {
+    /// Converts BT.2020 RGB to the LMS of ICtCp as defined by ITU-R BT.2100.
+    const ICTCP_LMS_FROM_BT2020: Self = Self::new([
+        [1688.0 / 4096.0, 2146.0 / 4096.0, 262.0 / 4096.0, 0.0],
+        [683.0 / 4096.0, 2951.0 / 4096.0, 462.0 / 4096.0, 0.0],
+        [99.0 / 4096.0, 309.0 / 4096.0, 3688.0 / 4096.0, 0.0],
+    ]);
+}
+
+/// Returns the matrix from the LMS of ICtCp to LMS, where 1 is the reference white of
+/// 203 cd/m². `xyz_from_lms` must be invertible.
+pub fn lms_from_ictcp_lms(xyz_from_lms: ColorMatrix<Xyz, Lms>) -> ColorMatrix<Lms, IctcpLms> {
+    let (xyz_from_bt2020, _) = Primaries::BT2020.matrices();
+    let scale = ColorMatrix::<IctcpLms, IctcpLms>::from_3x3([
+        [10000.0 / 203.0, 0.0, 0.0],
+        [0.0, 10000.0 / 203.0, 0.0],
+        [0.0, 0.0, 10000.0 / 203.0],
+    ]);
+    xyz_from_lms.inverse().unwrap()
+        * xyz_from_bt2020
+        * ColorMatrix::ICTCP_LMS_FROM_BT2020.inverse().unwrap()
+        * scale
+}
+
 impl ColorMatrix<Bradford, Xyz> {
     const BFD: Self = Self::new([
         [0.8951, 0.2664, -0.1614, 0.0],
@@ -448,6 +590,96 @@ pub fn bradford_adjustment(w_from: (F64, F64), w_to: (F64, F64)) -> ColorMatrix<
     ColorMatrix::BFD_INV * adj * ColorMatrix::BFD
 }
 
+/// Returns the chromaticity of a white with the given correlated color temperature.
+///
+/// Below 4000 K this is the Planckian locus (Kim et al.), above it the CIE daylight
+/// locus. Valid between 1667 K and 25000 K.
+pub fn cct_to_xy(kelvin: f64) -> (F64, F64) {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let (t1, t2, t3) = (1e3 / t, 1e6 / (t * t), 1e9 / (t * t * t));
+    let (x, y);
+    if t < 4000.0 {
+        x = -0.2661239 * t3 - 0.2343589 * t2 + 0.8776956 * t1 + 0.179910;
+        y = match t < 2222.0 {
+            true => -1.1063814 * x * x * x - 1.34811020 * x * x + 2.18555832 * x - 0.20219683,
+            false => -0.9549476 * x * x * x - 1.37418593 * x * x + 2.09137015 * x - 0.16748867,
+        };
+    } else {
+        x = match t <= 7000.0 {
+            true => -4.6070 * t3 + 2.9678 * t2 + 0.09911 * t1 + 0.244063,
+            false => -2.0064 * t3 + 1.9018 * t2 + 0.24748 * t1 + 0.237040,
+        };
+        y = -3.0 * x * x + 2.870 * x - 0.275;
+    }
+    (F64(x), F64(y))
+}
+
+/// Converts CIE xyY to XYZ.
+///
+/// Returns `None` if `y` is not positive since the chromaticity does not determine X
+/// and Z in that case.
+pub fn xyy_to_xyz(x: f64, y: f64, big_y: f64) -> Option<[f64; 3]> {
+    if y.is_nan() || y <= 1e-9 {
+        return None;
+    }
+    Some([x * big_y / y, big_y, (1.0 - x - y) * big_y / y])
+}
+
+/// Converts XYZ to CIE xyY. Returns `None` for black since its chromaticity is
+/// undefined.
+pub fn xyz_to_xyy(xyz: [f64; 3]) -> Option<(f64, f64, f64)> {
+    let [x, y, z] = xyz;
+    let sum = x + y + z;
+    if sum.is_nan() || sum.abs() <= 1e-9 {
+        return None;
+    }
+    Some((x / sum, y / sum, y))
+}
+
+/// Returns the chromaticity of monochromatic light. Uses the multi-lobe fit of the
+/// CIE 1931 color matching functions (Wyman et al.), like `horseshoe.wgsl`.
+pub fn wavelength_to_xy(wavelength: f64) -> (f64, f64) {
+    let g = |peak: f64, left: f64, right: f64| {
+        let d = wavelength - peak;
+        let f = if d < 0.0 { left } else { right };
+        (-0.5 * f * f * d * d).exp()
+    };
+    let x = 1.056 * g(599.8, 0.0264, 0.0323) + 0.362 * g(442.0, 0.0624, 0.0374)
+        - 0.065 * g(501.1, 0.0490, 0.0382);
+    let y = 0.821 * g(568.8, 0.0213, 0.0247) + 0.286 * g(530.9, 0.0613, 0.0322);
+    let z = 1.217 * g(437.0, 0.0845, 0.0278) + 0.681 * g(459.0, 0.0385, 0.0725);
+    (x / (x + y + z), y / (x + y + z))
+}
+
+/// Returns the point inside the spectral locus that is closest to `xy`. The locus is
+/// approximated by the same polygon that is drawn by `horseshoe.wgsl`.
+pub fn clamp_to_spectral_locus(xy: (F64, F64)) -> (F64, F64) {
+    let p = (xy.0 .0, xy.1 .0);
+    let locus: Vec<_> = (0..69)
+        .map(|i| wavelength_to_xy(440.0 + 3.0 * i as f64))
+        .collect();
+    let mut outside = false;
+    let mut closest = p;
+    let mut distance = f64::INFINITY;
+    for (i, &a) in locus.iter().enumerate() {
+        let b = locus[(i + 1) % locus.len()];
+        let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+        let (vx, vy) = (p.0 - a.0, p.1 - a.1);
+        outside |= ex * vy - ey * vx > 0.0;
+        let t = ((vx * ex + vy * ey) / (ex * ex + ey * ey)).clamp(0.0, 1.0);
+        let q = (a.0 + t * ex, a.1 + t * ey);
+        let d = (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2);
+        if d < distance {
+            distance = d;
+            closest = q;
+        }
+    }
+    match outside {
+        true => (F64(closest.0), F64(closest.1)),
+        false => xy,
+    }
+}
+
 impl Primaries {
     #[expect(non_snake_case)]
     pub const fn matrices(&self) -> (ColorMatrix<Xyz, Local>, ColorMatrix<Local, Xyz>) {
@@ -495,4 +727,166 @@ impl Primaries {
             ColorMatrix::new(local_from_XYZ),
         )
     }
+
+    /// Returns whether the white point is inside the triangle of the primaries or on
+    /// one of its edges.
+    pub fn white_point_inside(&self) -> bool {
+        self.contains(self.wp)
+    }
+
+    /// Returns whether the chromaticity is inside the triangle of the primaries or on
+    /// one of its edges.
+    pub fn contains(&self, (F64(x), F64(y)): (F64, F64)) -> bool {
+        let p = (x, y);
+        let vertices = [self.r, self.g, self.b].map(|(F64(x), F64(y))| (x, y));
+        let sides = (0..3).map(|i| {
+            let a = vertices[i];
+            let b = vertices[(i + 1) % 3];
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+        });
+        let (mut positive, mut negative) = (false, false);
+        for side in sides {
+            positive |= side > 0.0;
+            negative |= side < 0.0;
+        }
+        !(positive && negative)
+    }
+
+    /// Returns the centroid of the triangle of the primaries.
+    pub fn centroid(&self) -> (F64, F64) {
+        let x = (self.r.0 .0 + self.g.0 .0 + self.b.0 .0) / 3.0;
+        let y = (self.r.1 .0 + self.g.1 .0 + self.b.1 .0) / 3.0;
+        (F64(x), F64(y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply<T, U>(m: &ColorMatrix<T, U>, v: [f64; 3]) -> [f64; 3] {
+        std::array::from_fn(|i| {
+            let r = &m.0[i];
+            r[0].0 * v[0] + r[1].0 * v[1] + r[2].0 * v[2] + r[3].0
+        })
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        let m =
+            ColorMatrix::<Xyz, Lms>::from_3x3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]]);
+        assert!(m.inverse().is_none());
+        assert!(ColorMatrix::XYZ_FROM_LMS.inverse().is_some());
+    }
+
+    #[test]
+    fn windows_scrgb_white_is_80_nits() {
+        let (F64(x), F64(y)) = Primaries::SRGB.wp;
+        let white = [x / y, 1.0, (1.0 - x - y) / y];
+        let wb = white_balance(
+            &Luminance::WINDOWS_SCRGB,
+            &Luminance::ST2084_PQ,
+            Primaries::SRGB.wp,
+        );
+        let [_, y, _] = apply(&wb, white);
+        // scRGB (1, 1, 1) is 80 cd/m^2, i.e. 80 / 10000 of the PQ range.
+        assert!((y - 80.0 / 10000.0).abs() < 1e-12, "{y}");
+    }
+
+    #[test]
+    fn luminance_protocol_round_trip() {
+        for l in [
+            Luminance::SRGB,
+            Luminance::BT1886,
+            Luminance::ST2084_PQ,
+            Luminance {
+                min: F64(0.0001),
+                max: F64(1.0),
+                white: F64(1.0),
+            },
+        ] {
+            let (min, max, white) = l.to_protocol();
+            assert_eq!(Luminance::from_protocol(min, max, white), l);
+        }
+        // 0.2 cd/m² is not exactly representable.
+        assert_eq!(Luminance::SRGB.to_protocol(), (2000, 80, 80));
+    }
+
+    #[test]
+    fn tf_power_protocol_round_trip() {
+        // 2.6 is 2.5999999 as an f32 and must not be truncated to 25999.
+        assert_eq!(tf_power_to_protocol(2.6), 26_000);
+        for eexp in [10_000, 12_345, 22_000, 24_000, 99_999, 100_000] {
+            assert_eq!(tf_power_to_protocol(tf_power_from_protocol(eexp)), eexp);
+        }
+    }
+
+    #[test]
+    fn chromaticity_protocol_round_trip() {
+        let d65 = F64(0.31270);
+        assert_eq!(chromaticity_to_protocol(d65), 312_700);
+        assert_eq!(
+            chromaticity_from_protocol(chromaticity_to_protocol(d65)),
+            d65
+        );
+        // Digits beyond the precision of the protocol are rounded.
+        assert_eq!(chromaticity_to_protocol(F64(0.3127004)), 312_700);
+        assert_eq!(chromaticity_to_protocol(F64(0.3127006)), 312_701);
+        for c in [0, 1, 329_000, 1_000_000] {
+            assert_eq!(chromaticity_to_protocol(chromaticity_from_protocol(c)), c);
+        }
+    }
+
+    #[test]
+    fn clamp_to_locus() {
+        let d65 = Primaries::SRGB.wp;
+        assert_eq!(clamp_to_spectral_locus(d65), d65);
+        let (F64(x), F64(y)) = clamp_to_spectral_locus((F64(0.8), F64(0.8)));
+        assert!(x < 0.8 && y < 0.8, "{x}, {y}");
+        let again = clamp_to_spectral_locus((F64(x), F64(y)));
+        assert!((again.0 .0 - x).abs() < 1e-9 && (again.1 .0 - y).abs() < 1e-9);
+    }
+
+    #[test]
+    fn white_point_inside_primaries() {
+        assert!(Primaries::SRGB.white_point_inside());
+        let mut p = Primaries::SRGB;
+        p.wp = (F64(0.1), F64(0.8));
+        assert!(!p.white_point_inside());
+        p.wp = p.centroid();
+        assert!(p.white_point_inside());
+    }
+
+    #[test]
+    fn cct_of_d65() {
+        // D65 is defined via the daylight locus at 6504 K.
+        let (F64(x), F64(y)) = cct_to_xy(6504.0);
+        assert!((x - 0.3127).abs() < 1e-4, "{x}");
+        assert!((y - 0.3291).abs() < 1e-4, "{y}");
+    }
+
+    #[test]
+    fn windows_scrgb_reference_white() {
+        let m = matrix_from_lms(
+            Primaries::SRGB,
+            Luminance::WINDOWS_SCRGB,
+            ColorMatrix::XYZ_FROM_LMS,
+        );
+        // The reference white (LMS (1, 1, 1), 203 cd/m^2) is 203 / 80 = 2.5375 in
+        // scRGB. The Oklab LMS white matches D65 only to about 3 digits.
+        for c in apply(&m, [1.0; 3]) {
+            assert!((c / (203.0 / 80.0) - 1.0).abs() < 1e-3, "{c}");
+        }
+    }
+
+    #[test]
+    fn xyy_round_trip() {
+        let xyz = xyy_to_xyz(0.3127, 0.3290, 203.0).unwrap();
+        let (x, y, big_y) = xyz_to_xyy(xyz).unwrap();
+        assert!((x - 0.3127).abs() < 1e-12);
+        assert!((y - 0.3290).abs() < 1e-12);
+        assert!((big_y - 203.0).abs() < 1e-9);
+        assert!(xyy_to_xyz(0.3, 0.0, 1.0).is_none());
+        assert!(xyz_to_xyy([0.0; 3]).is_none());
+    }
 }
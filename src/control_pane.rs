@@ -1,29 +1,57 @@
 use {
     crate::{
+        blend_delta::BlendDelta,
         cmm::{
-            Luminance, NamedPrimaries, NamedTransferFunction, Primaries, TransferFunction,
-            TransferFunctionWithArgs,
+            cct_to_xy, clamp_to_spectral_locus, tf_power_from_protocol, tf_power_to_protocol,
+            ColorMatrix, Lms, Luminance, NamedPrimaries, NamedTransferFunction, Primaries,
+            TransferFunction, TransferFunctionWithArgs, Xyz,
         },
+        file_picker::FilePicker,
+        geometry::Geometry,
+        histogram::{BinScale, Histogram, BINS},
+        image_file,
         ordered_float::F64,
-        protocols::color_management_v1::wp_color_manager_v1::WpColorManagerV1Feature,
+        protocols::{
+            color_management_v1::wp_color_manager_v1::WpColorManagerV1Feature,
+            color_representation_v1::wp_color_representation_surface_v1::{
+                WpColorRepresentationSurfaceV1AlphaMode,
+                WpColorRepresentationSurfaceV1Coefficients, WpColorRepresentationSurfaceV1Range,
+            },
+        },
+        scene_saves::SceneSaves,
+        stress::{probe_luminance, InvalidLuminanceOutcome, StressRun},
         test_pane::{
-            Color, DescriptionData, TestColorDescription, TestPane, TestPrimaries, TestScene,
+            compare_description, compare_mastering, error_chain, wsi_description, BlendLayers,
+            Capablities, CodeValueSteps, Color, ColorSweep, DamageCoordinates, DescriptionData,
+            DescriptionDeltas, DescriptionIdentity, LayerStacking, Mastering, MetadataMatch,
+            OutputData, OutputInfo, PqLuminance, TestColorDescription, TestConfig, TestDamage,
+            TestOverlay, TestPane, TestPrimaries, TestRepresentation, TestScene, TestViewport,
+            ToplevelStates, MAX_BLEND_LAYERS,
+        },
+        trace,
+        vulkan::{
+            self, eotf_id, AlphaMode, ChannelAdjustment, GamutMapping, GradientSpace, OutputClamp,
+            SwapchainColorSpace, UploadMode, DEFAULT_IMAGE_COUNT,
         },
     },
     bytemuck::{bytes_of, NoUninit},
     egui::{
-        vec2, CentralPanel, Color32, ComboBox, Context, DragValue, FullOutput, Grid, Image,
-        RawInput, Slider, TextureId, Ui, ViewportBuilder, ViewportInfo, Widget, WidgetText,
+        pos2, vec2, Align2, Button, CentralPanel, Checkbox, Color32, ComboBox, Context, DragValue,
+        FontId, FullOutput, Grid, Image, Key, RawInput, Rect, Response, SelectableLabel, Sense,
+        Shape, Slider, Stroke, StrokeKind, TextureId, Ui, ViewportBuilder, ViewportInfo, Widget,
+        WidgetText,
     },
     egui_wgpu::{
         wgpu::{
-            Backends, BlendComponent, BlendState, ColorTargetState, DeviceDescriptor, Extent3d,
-            Features, FilterMode, FragmentState, IndexFormat, InstanceDescriptor, Limits, LoadOp,
-            Operations, PipelineLayoutDescriptor, PresentMode, PrimitiveState, PrimitiveTopology,
+            Backends, BlendComponent, BlendState, BufferDescriptor, BufferUsages, ColorTargetState,
+            DeviceDescriptor, Extent3d, Features, FilterMode, FragmentState, IndexFormat,
+            InstanceDescriptor, Limits, LoadOp, Maintain, MapMode, Operations,
+            PipelineLayoutDescriptor, PresentMode, PrimitiveState, PrimitiveTopology,
             PushConstantRange, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
             RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp,
-            TexelCopyTextureInfo, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-            TextureUsages, TextureView, TextureViewDescriptor, VertexState,
+            TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+            TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+            TextureViewDescriptor, VertexState, COPY_BYTES_PER_ROW_ALIGNMENT,
         },
         winit::Painter,
         RenderState, WgpuConfiguration, WgpuSetup, WgpuSetupCreateNew,
@@ -33,21 +61,26 @@ use {
         event_loop::ActiveEventLoop,
         window::{Window, WindowId},
     },
-    isnt::std_1::collections::IsntHashSetExt,
+    isnt::std_1::collections::{IsntHashSet2Ext, IsntHashSetExt},
+    itertools::Itertools,
     linearize::{Linearize, LinearizeExt},
     pollster::block_on,
     std::{
+        array,
         cell::Cell,
-        mem,
+        env, mem,
         num::NonZeroU32,
+        ops::RangeInclusive,
+        path::Path,
         rc::Rc,
-        sync::Arc,
+        sync::{mpsc, Arc},
         time::{Duration, Instant},
     },
 };
 
 pub struct ControlPane {
     ctx: Context,
+    geometry: Rc<Geometry>,
     window: Arc<Window>,
     window_id: WindowId,
     state: egui_winit::State,
@@ -64,26 +97,146 @@ pub struct DrawState {
     max_size: u32,
     config: ControlPaneConfig,
     cie_diagram: Option<CieDiagram>,
+    cie_view: CieView,
+    /// The result of the last export of the CIE diagram.
+    diagram_export: Option<Result<String, String>>,
     horseshoe_pipeline: RenderPipeline,
     triangle_pipeline: RenderPipeline,
-    pub create_description_error_message: Option<String>,
+    create_description_error_message: Option<String>,
     pub preferred_description_error_message: Option<String>,
-    pub preferred_description_data: Option<DescriptionData>,
+    preferred_description_data: Option<DescriptionData>,
+    pub preferred_identity: Option<DescriptionIdentity>,
+    /// Feedback captured into slots A and B.
+    feedback_snapshots: [Option<DescriptionData>; 2],
+    /// The parameters of the preferred description that "Match preferred" could not set.
+    match_preferred_dropped: Vec<&'static str>,
+    pub output_error_message: Option<String>,
+    pub render_error_message: Option<String>,
+    pub output_data: Option<OutputData>,
+    toplevel_states: ToplevelStates,
+    outputs: Vec<OutputInfo>,
+    /// The luminance histogram of the last frame, captured on request.
+    histogram: Option<Result<Histogram, String>>,
+    /// A description of the image of the image scene or the error that occurred while
+    /// loading it.
+    image: Option<Result<String, String>>,
+    /// The file browser of the image scene, if it is open.
+    image_picker: Option<FilePicker>,
+    /// The difference between the blends of the blend scene, measured on request.
+    blend_delta: Option<Result<BlendDelta, String>>,
+    scene_saves: SceneSaves,
+    /// The name under which the colors of the scene are saved next.
+    scene_save_name: String,
+    /// Set when the test pane should be destroyed and created again.
+    pub recreate_test_pane: bool,
+    /// When the attached description alternates next.
+    next_alternation: Option<Instant>,
+    /// The last stress run, if any.
+    stress: Option<StressRun>,
+    /// The result of the last invalid luminance probe.
+    invalid_luminance: Option<Result<InvalidLuminanceOutcome, String>>,
+    /// Set while the test pane does not show the current configuration.
+    pub updating: bool,
+    /// Set if the compositor has not sent the initial configure of the test pane.
+    pub waiting_for_configure: bool,
+}
+
+impl DrawState {
+    /// Drops the results that were measured on the previous test pane and uploads the
+    /// image of the image scene to the new one.
+    pub fn test_pane_recreated(&mut self, test_pane: &TestPane) {
+        self.histogram = None;
+        self.output_data = None;
+        self.output_error_message = None;
+        self.blend_delta = None;
+        if let Some(Ok(_)) = self.image {
+            self.image = Some(load_image(test_pane, &self.config.image_path));
+        }
+    }
+
+    /// Sets the error of the last created description. Errors are also recorded in the
+    /// stress run.
+    pub fn set_create_description_error_message(&mut self, msg: Option<String>) {
+        if let (Some(run), Some(msg)) = (&mut self.stress, &msg) {
+            run.record_error(msg);
+        }
+        self.create_description_error_message = msg;
+    }
+
+    /// Sets the parameters of the preferred description. The parameters that could not
+    /// be matched refer to the previous description and are discarded.
+    pub fn set_preferred_description_data(&mut self, data: DescriptionData) {
+        self.preferred_description_data = Some(data);
+        self.match_preferred_dropped.clear();
+    }
+
+    /// Updates the toplevel states of the test pane. Fullscreen changes made by the
+    /// compositor are reflected in the fullscreen toggle.
+    pub fn set_toplevel_states(&mut self, states: ToplevelStates) {
+        if states.fullscreen != self.toplevel_states.fullscreen {
+            self.config.fullscreen = states.fullscreen;
+        }
+        self.toplevel_states = states;
+    }
+
+    /// Updates the list of outputs. If the output selected for fullscreen was
+    /// removed, the compositor chooses the output instead.
+    pub fn set_outputs(&mut self, outputs: Vec<OutputInfo>) {
+        let selected = self.config.fullscreen_output;
+        if selected.is_some_and(|g| outputs.iter().all(|o| o.global != g)) {
+            self.config.fullscreen_output = None;
+        }
+        self.outputs = outputs;
+    }
 }
 
 struct CieDiagram {
     horseshoe_tex: Texture,
-    _horseshoe_view: TextureView,
+    horseshoe_view: TextureView,
+    horseshoe_data: Option<HorseshoeData>,
     tex: Texture,
     view: TextureView,
     id: TextureId,
     size: u32,
 }
 
+/// The region of the xy plane shown in the CIE diagram.
+#[derive(Copy, Clone, PartialEq, NoUninit)]
+#[repr(C)]
+struct CieView {
+    /// The xy coordinates of the bottom left corner.
+    offset: [f32; 2],
+    /// The width and height of the region.
+    scale: f32,
+    _padding: f32,
+}
+
+/// The push constants of the horseshoe shader.
+#[derive(Copy, Clone, PartialEq, NoUninit)]
+#[repr(C)]
+struct HorseshoeData {
+    view: CieView,
+    /// The sRGB-encoded color outside of the horseshoe.
+    background: [f32; 4],
+}
+
+impl Default for CieView {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            scale: 0.85,
+            _padding: 0.0,
+        }
+    }
+}
+
 impl ControlPane {
-    pub fn new(event_loop: &ActiveEventLoop, test_pane: &TestPane) -> Self {
+    pub fn new(event_loop: &ActiveEventLoop, test_pane: &TestPane, geometry: Rc<Geometry>) -> Self {
         let ctx = Context::default();
-        let viewport_builder = ViewportBuilder::default().with_title("control pane");
+        let mut viewport_builder = ViewportBuilder::default().with_title("control pane");
+        if let Some(size) = geometry.control_pane.get() {
+            viewport_builder = viewport_builder.with_inner_size(size);
+        }
         let window = egui_winit::create_window(&ctx, event_loop, &viewport_builder).unwrap();
         let window = Arc::new(window);
         let state = egui_winit::State::new(
@@ -128,6 +281,7 @@ impl ControlPane {
         raw_input.viewports.insert(ctx.viewport_id(), viewport_info);
         let mut slf = Self {
             ctx,
+            geometry,
             window_id: window.id(),
             window,
             state,
@@ -145,36 +299,7 @@ impl ControlPane {
     fn run(&mut self, test_pane: &TestPane, raw_input: RawInput) {
         let new_output = self.ctx.run(raw_input, |ctx| {
             draw_egui(ctx, test_pane, &mut self.draw_state);
-            let config = &self.draw_state.config;
-            let scene = match config.scene {
-                SelectedScene::Fill => TestScene::Fill(config.fill),
-                SelectedScene::FillLeftRight => TestScene::FillLeftRight(config.left_right),
-                SelectedScene::FillTopBottom => TestScene::FillTopBottom(config.top_bottom),
-                SelectedScene::FillFour => TestScene::FillFour(config.four_corners),
-                SelectedScene::CenterBox => {
-                    TestScene::CenterBox(config.center_box, config.center_box_size)
-                }
-                SelectedScene::Grid => {
-                    TestScene::Grid(config.grid, config.grid_rows, config.grid_cols)
-                }
-                SelectedScene::Blend => TestScene::Blend(config.blend, config.blend_alpha),
-            };
-            let desc = match config.cd_type {
-                ColorDescriptionType::None => TestColorDescription::None,
-                ColorDescriptionType::ScRgb => TestColorDescription::ScRgb,
-                ColorDescriptionType::Parametric => TestColorDescription::Parametric {
-                    primaries: match config.use_custom_primaries {
-                        true => TestPrimaries::Custom(config.primaries),
-                        false => TestPrimaries::Named(config.named_primaries),
-                    },
-                    transfer_function: TransferFunctionWithArgs {
-                        tf: config.tf,
-                        pow: config.tf_power,
-                    },
-                    luminance: config.enable_luminance.then_some(config.luminance),
-                },
-            };
-            test_pane.apply_config(desc, scene);
+            test_pane.apply_config(&self.draw_state.config.test_config());
         });
         self.output.append(new_output);
         let repaint_delay = self
@@ -225,6 +350,10 @@ impl ControlPane {
                 let height = NonZeroU32::new(size.height).unwrap_or(NonZeroU32::new(600).unwrap());
                 self.painter
                     .on_window_resized(self.ctx.viewport_id(), width, height);
+                let logical = size.to_logical::<f32>(self.window.scale_factor());
+                self.geometry
+                    .control_pane
+                    .set(Some((logical.width, logical.height)));
                 self.have_frame.set(true);
                 self.need_repaint = true;
             }
@@ -232,6 +361,7 @@ impl ControlPane {
                 self.have_frame.set(true);
             }
             WindowEvent::CloseRequested => {
+                self.geometry.save();
                 std::process::exit(0);
             }
             _ => {}
@@ -263,6 +393,7 @@ enum View {
     Scenes,
     ColorDescription,
     Feedback,
+    Output,
     Settings,
 }
 
@@ -270,12 +401,22 @@ enum View {
 enum SelectedScene {
     #[default]
     Fill,
-    FillLeftRight,
-    FillTopBottom,
+    LinearGradient,
     FillFour,
     CenterBox,
     Grid,
+    ColorGrid,
+    Crosshair,
+    Patch,
     Blend,
+    BlendBanding,
+    BlendLayers,
+    Primaries,
+    CompareEotf,
+    BlackDetail,
+    Image,
+    MovingBar,
+    TransferFunctions,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Default, Linearize)]
@@ -293,23 +434,39 @@ impl From<View> for WidgetText {
             View::ColorDescription => "color description",
             View::Scenes => "scenes",
             View::Feedback => "feedback",
+            View::Output => "output",
         };
         txt.into()
     }
 }
 
-impl From<SelectedScene> for WidgetText {
-    fn from(val: SelectedScene) -> Self {
-        let txt = match val {
+impl SelectedScene {
+    fn name(self) -> &'static str {
+        match self {
             SelectedScene::Fill => "fill",
-            SelectedScene::FillLeftRight => "gradient (L -> R)",
-            SelectedScene::FillTopBottom => "gradient (T -> B)",
+            SelectedScene::LinearGradient => "linear gradient",
             SelectedScene::FillFour => "four corners",
             SelectedScene::CenterBox => "center box",
             SelectedScene::Grid => "grid",
+            SelectedScene::ColorGrid => "color grid",
+            SelectedScene::Crosshair => "crosshair",
+            SelectedScene::Patch => "colorimeter patch",
             SelectedScene::Blend => "blend",
-        };
-        txt.into()
+            SelectedScene::BlendBanding => "blend banding",
+            SelectedScene::BlendLayers => "blend layers",
+            SelectedScene::Primaries => "primaries",
+            SelectedScene::CompareEotf => "compare eotf",
+            SelectedScene::BlackDetail => "black detail",
+            SelectedScene::Image => "image",
+            SelectedScene::MovingBar => "moving bar",
+            SelectedScene::TransferFunctions => "transfer functions",
+        }
+    }
+}
+
+impl From<SelectedScene> for WidgetText {
+    fn from(val: SelectedScene) -> Self {
+        val.name().into()
     }
 }
 
@@ -324,6 +481,120 @@ impl From<ColorDescriptionType> for WidgetText {
     }
 }
 
+impl From<DamageCoordinates> for WidgetText {
+    fn from(val: DamageCoordinates) -> Self {
+        let txt = match val {
+            DamageCoordinates::Buffer => "buffer (damage_buffer)",
+            DamageCoordinates::Surface => "surface (damage)",
+        };
+        txt.into()
+    }
+}
+
+impl From<PqLuminance> for WidgetText {
+    fn from(val: PqLuminance) -> Self {
+        let txt = match val {
+            PqLuminance::Relative => "relative to the reference white",
+            PqLuminance::Absolute => "absolute (1 lumen = 1 cd/m²)",
+        };
+        txt.into()
+    }
+}
+
+impl From<SwapchainColorSpace> for WidgetText {
+    fn from(val: SwapchainColorSpace) -> Self {
+        let txt = match val {
+            SwapchainColorSpace::PassThrough => "PASS_THROUGH_EXT",
+            SwapchainColorSpace::SrgbNonlinear => "SRGB_NONLINEAR_KHR",
+            SwapchainColorSpace::ExtendedSrgbLinear => "EXTENDED_SRGB_LINEAR_EXT",
+            SwapchainColorSpace::ExtendedSrgbNonlinear => "EXTENDED_SRGB_NONLINEAR_EXT",
+            SwapchainColorSpace::DisplayP3Nonlinear => "DISPLAY_P3_NONLINEAR_EXT",
+            SwapchainColorSpace::DisplayP3Linear => "DISPLAY_P3_LINEAR_EXT",
+            SwapchainColorSpace::DciP3Nonlinear => "DCI_P3_NONLINEAR_EXT",
+            SwapchainColorSpace::Bt709Linear => "BT709_LINEAR_EXT",
+            SwapchainColorSpace::Bt709Nonlinear => "BT709_NONLINEAR_EXT",
+            SwapchainColorSpace::Bt2020Linear => "BT2020_LINEAR_EXT",
+            SwapchainColorSpace::Hdr10St2084 => "HDR10_ST2084_EXT",
+            SwapchainColorSpace::AdobeRgbLinear => "ADOBERGB_LINEAR_EXT",
+            SwapchainColorSpace::AdobeRgbNonlinear => "ADOBERGB_NONLINEAR_EXT",
+        };
+        txt.into()
+    }
+}
+
+impl From<GradientSpace> for WidgetText {
+    fn from(val: GradientSpace) -> Self {
+        let txt = match val {
+            GradientSpace::Oklab => "Oklab",
+            GradientSpace::Ictcp => "ICtCp",
+        };
+        txt.into()
+    }
+}
+
+impl From<OutputClamp> for WidgetText {
+    fn from(val: OutputClamp) -> Self {
+        let txt = match val {
+            OutputClamp::Extended => "extended",
+            OutputClamp::NonNegative => "clamp negative",
+            OutputClamp::Unit => "clamp to [0, 1]",
+        };
+        txt.into()
+    }
+}
+
+impl From<GamutMapping> for WidgetText {
+    fn from(val: GamutMapping) -> Self {
+        let txt = match val {
+            GamutMapping::Clip => "clip",
+            GamutMapping::Compress => "compress chroma",
+        };
+        txt.into()
+    }
+}
+
+impl From<LayerStacking> for WidgetText {
+    fn from(val: LayerStacking) -> Self {
+        let txt = match val {
+            LayerStacking::PlaceAbove => "place above",
+            LayerStacking::PlaceBelow => "place below",
+        };
+        txt.into()
+    }
+}
+
+impl From<AlphaMode> for WidgetText {
+    fn from(val: AlphaMode) -> Self {
+        let txt = match val {
+            AlphaMode::Premultiplied => "premultiplied",
+            AlphaMode::Straight => "straight",
+        };
+        txt.into()
+    }
+}
+
+impl From<UploadMode> for WidgetText {
+    fn from(val: UploadMode) -> Self {
+        let txt = match val {
+            UploadMode::Graphics => "graphics",
+            UploadMode::TransferExclusive => "transfer (exclusive)",
+            UploadMode::TransferConcurrent => "transfer (concurrent)",
+        };
+        txt.into()
+    }
+}
+
+impl From<vulkan::PresentMode> for WidgetText {
+    fn from(val: vulkan::PresentMode) -> Self {
+        let txt = match val {
+            vulkan::PresentMode::Mailbox => "mailbox",
+            vulkan::PresentMode::Fifo => "fifo",
+            vulkan::PresentMode::Immediate => "immediate",
+        };
+        txt.into()
+    }
+}
+
 impl From<NamedPrimaries> for WidgetText {
     fn from(val: NamedPrimaries) -> Self {
         let txt = match val {
@@ -353,31 +624,71 @@ impl From<TransferFunction> for WidgetText {
 
 impl From<NamedTransferFunction> for WidgetText {
     fn from(val: NamedTransferFunction) -> Self {
+        val.name().into()
+    }
+}
+
+impl From<WpColorManagerV1Feature> for WidgetText {
+    fn from(val: WpColorManagerV1Feature) -> Self {
         let txt = match val {
-            NamedTransferFunction::Srgb => "srgb",
-            NamedTransferFunction::Linear => "ext_linear",
-            NamedTransferFunction::St2084Pq => "st2084_pq",
-            NamedTransferFunction::Bt1886 => "bt1886",
-            NamedTransferFunction::Gamma22 => "gamma22",
-            NamedTransferFunction::Gamma28 => "gamma28",
-            NamedTransferFunction::St240 => "st240",
-            NamedTransferFunction::ExtSrgb => "ext_srgb",
-            NamedTransferFunction::Log100 => "log_100",
-            NamedTransferFunction::Log316 => "log_316",
-            NamedTransferFunction::St428 => "st428",
+            WpColorManagerV1Feature::ICC_V2_V4 => "icc_v2_v4",
+            WpColorManagerV1Feature::PARAMETRIC => "parametric",
+            WpColorManagerV1Feature::SET_PRIMARIES => "set_primaries",
+            WpColorManagerV1Feature::SET_TF_POWER => "set_tf_power",
+            WpColorManagerV1Feature::SET_LUMINANCES => "set_luminances",
+            WpColorManagerV1Feature::SET_MASTERING_DISPLAY_PRIMARIES => {
+                "set_mastering_display_primaries"
+            }
+            WpColorManagerV1Feature::EXTENDED_TARGET_VOLUME => "extended_target_volume",
+            WpColorManagerV1Feature::WINDOWS_SCRGB => "windows_scrgb",
+            _ => return format!("unknown ({val:?})").into(),
         };
         txt.into()
     }
 }
 
-struct ControlPaneConfig {
-    view: View,
+impl From<WpColorRepresentationSurfaceV1AlphaMode> for WidgetText {
+    fn from(val: WpColorRepresentationSurfaceV1AlphaMode) -> Self {
+        let txt = match val {
+            WpColorRepresentationSurfaceV1AlphaMode::PREMULTIPLIED_ELECTRICAL => {
+                "premultiplied electrical"
+            }
+            WpColorRepresentationSurfaceV1AlphaMode::PREMULTIPLIED_OPTICAL => {
+                "premultiplied optical"
+            }
+            WpColorRepresentationSurfaceV1AlphaMode::STRAIGHT => "straight",
+            _ => return format!("unknown ({val:?})").into(),
+        };
+        txt.into()
+    }
+}
 
-    // settings
-    max_lumen: f32,
-    max_chroma: f32,
+fn coefficients_text(
+    coefficients: WpColorRepresentationSurfaceV1Coefficients,
+    range: WpColorRepresentationSurfaceV1Range,
+) -> String {
+    type C = WpColorRepresentationSurfaceV1Coefficients;
+    let coefficients = match coefficients {
+        C::IDENTITY => "identity",
+        C::BT709 => "bt709",
+        C::FCC => "fcc",
+        C::BT601 => "bt601",
+        C::SMPTE240 => "smpte240",
+        C::BT2020 => "bt2020",
+        C::BT2020_CL => "bt2020_cl",
+        C::ICTCP => "ictcp",
+        _ => return format!("unknown ({coefficients:?})"),
+    };
+    let range = match range {
+        WpColorRepresentationSurfaceV1Range::FULL => "full",
+        WpColorRepresentationSurfaceV1Range::LIMITED => "limited",
+        _ => return format!("{coefficients} (unknown range {range:?})"),
+    };
+    format!("{coefficients} ({range})")
+}
 
-    // color description
+#[derive(Copy, Clone)]
+struct DescriptionConfig {
     cd_type: ColorDescriptionType,
     named_primaries: NamedPrimaries,
     use_custom_primaries: bool,
@@ -386,17 +697,230 @@ struct ControlPaneConfig {
     enable_luminance: bool,
     luminance: Luminance,
     primaries: Primaries,
+    /// If set, scRGB is described with `TestColorDescription::PARAMETRIC_SCRGB`
+    /// instead of `create_windows_scrgb`.
+    scrgb_parametric: bool,
+    /// The last value of the color temperature slider in kelvin.
+    white_point_cct: f64,
+    /// If set, the HDR10 static metadata in `mastering` is sent instead of deriving
+    /// max_cll and max_fall from the white luminance.
+    enable_mastering: bool,
+    mastering: Mastering,
+}
+
+impl Default for DescriptionConfig {
+    fn default() -> Self {
+        Self {
+            cd_type: ColorDescriptionType::None,
+            named_primaries: NamedPrimaries::Srgb,
+            use_custom_primaries: false,
+            tf: TransferFunction::Named(NamedTransferFunction::Gamma22),
+            tf_power: 2.2,
+            enable_luminance: false,
+            luminance: Default::default(),
+            primaries: Primaries::SRGB,
+            scrgb_parametric: false,
+            white_point_cct: 6504.0,
+            enable_mastering: false,
+            mastering: Mastering {
+                primaries: Some(NamedPrimaries::DisplayP3.primaries()),
+                luminance: Some((F64(0.005), F64(1000.0))),
+                max_cll: Some(1000),
+                max_fall: Some(400),
+            },
+        }
+    }
+}
+
+impl DescriptionConfig {
+    fn to_test(self) -> TestColorDescription {
+        match self.cd_type {
+            ColorDescriptionType::None => TestColorDescription::None,
+            ColorDescriptionType::ScRgb => match self.scrgb_parametric {
+                true => TestColorDescription::PARAMETRIC_SCRGB,
+                false => TestColorDescription::ScRgb,
+            },
+            ColorDescriptionType::Parametric => TestColorDescription::Parametric {
+                primaries: match self.use_custom_primaries {
+                    true => TestPrimaries::Custom(self.primaries),
+                    false => TestPrimaries::Named(self.named_primaries),
+                },
+                transfer_function: TransferFunctionWithArgs {
+                    tf: self.tf,
+                    pow: self.tf_power,
+                },
+                luminance: self.enable_luminance.then_some(self.luminance),
+                mastering: self.enable_mastering.then_some(self.mastering),
+            },
+        }
+    }
+
+    /// Sets the parametric description to the parameters sent by the compositor.
+    /// Luminances and HDR10 static metadata that were not sent are disabled. Parameters
+    /// that the compositor does not accept are left unchanged and their names returned.
+    fn set_from_data(&mut self, data: DescriptionData, caps: &Capablities) -> Vec<&'static str> {
+        let supports_feature = |feature| caps.features.contains(&feature);
+        let mut dropped = vec![];
+        self.cd_type = ColorDescriptionType::Parametric;
+        match data.primaries {
+            TestPrimaries::Named(n) if caps.primaries.contains(&n.wayland()) => {
+                self.use_custom_primaries = false;
+                self.named_primaries = n;
+            }
+            TestPrimaries::Custom(p)
+                if supports_feature(WpColorManagerV1Feature::SET_PRIMARIES) =>
+            {
+                self.use_custom_primaries = true;
+                self.primaries = p;
+            }
+            _ => dropped.push("primaries"),
+        }
+        match data.tf {
+            TransferFunction::Named(n) if caps.tf.not_contains(&n.wayland()) => {
+                dropped.push("transfer function");
+            }
+            TransferFunction::Pow if !supports_feature(WpColorManagerV1Feature::SET_TF_POWER) => {
+                dropped.push("tf_power");
+            }
+            tf => {
+                self.tf = tf;
+                if tf == TransferFunction::Pow {
+                    self.tf_power = data.tf_power;
+                }
+            }
+        }
+        self.enable_luminance = false;
+        if let Some(l) = data.luminance {
+            match supports_feature(WpColorManagerV1Feature::SET_LUMINANCES) {
+                true => {
+                    self.enable_luminance = true;
+                    self.luminance = l;
+                }
+                false => dropped.push("luminances"),
+            }
+        }
+        self.mastering = Mastering {
+            primaries: data.target_primaries,
+            luminance: data.target_luminance,
+            max_cll: data.target_max_cll,
+            max_fall: data.target_max_fall,
+        };
+        let m = &mut self.mastering;
+        if !supports_feature(WpColorManagerV1Feature::SET_MASTERING_DISPLAY_PRIMARIES)
+            && (m.primaries.is_some() || m.luminance.is_some())
+        {
+            m.primaries = None;
+            m.luminance = None;
+            dropped.push("mastering display");
+        }
+        self.enable_mastering = self.mastering != Mastering::default();
+        dropped
+    }
+
+    fn primaries(self) -> Primaries {
+        match self.cd_type {
+            ColorDescriptionType::None => Primaries::SRGB,
+            ColorDescriptionType::ScRgb => Primaries::SRGB,
+            ColorDescriptionType::Parametric => match self.use_custom_primaries {
+                true => self.primaries,
+                false => self.named_primaries.primaries(),
+            },
+        }
+    }
+}
+
+struct ControlPaneConfig {
+    view: View,
+    freeze: bool,
+    pause: bool,
+    fullscreen: bool,
+    fullscreen_output: Option<u32>,
+    /// If set, clicking the chromaticity diagram sets a color of the scene.
+    eyedropper: bool,
+    /// The index in `scene_colors` of the color set by the eyedropper.
+    eyedropper_slot: usize,
+    histogram_scale: BinScale,
+    /// The sRGB color around the horseshoe of the CIE diagram.
+    diagram_background: [u8; 3],
+    /// The path that the CIE diagram is exported to.
+    diagram_export_path: String,
+
+    // settings
+    max_lumen: f32,
+    max_chroma: f32,
+    /// Whether colors are edited as CIE xyY instead of LCh.
+    xyy_entry: bool,
+    /// The largest difference of chromaticities that are considered equal when
+    /// comparing feedback with the requested description.
+    chromaticity_epsilon: f64,
+    upload_mode: UploadMode,
+    alpha_mode: AlphaMode,
+    image_count: u32,
+    present_mode: vulkan::PresentMode,
+    allow_tearing: bool,
+    representation: TestRepresentation,
+    enable_dither: bool,
+    dither_bits: u32,
+    gamut_mapping: GamutMapping,
+    gradient_space: GradientSpace,
+    output_clamp: OutputClamp,
+    channel_adjustment: ChannelAdjustment,
+    measure_present_latency: bool,
+    enable_peak: bool,
+    peak_lumen: f32,
+    override_xyz_from_lms: bool,
+    xyz_from_lms: [[f64; 3]; 3],
+    enable_viewport: bool,
+    viewport_source: (i32, i32),
+    enable_viewport_destination: bool,
+    viewport_destination: (i32, i32),
+    enable_damage: bool,
+    damage_coordinates: DamageCoordinates,
+    damage_rect: (i32, i32, i32, i32),
+
+    // color description
+    description: DescriptionConfig,
+    decouple: bool,
+    keep_buffer: bool,
+    verify_feedback: bool,
+    color_management_surface: bool,
+    color_space: SwapchainColorSpace,
+    linear_content: bool,
+    render_description: DescriptionConfig,
+    /// If set, the attached description alternates with `alternate_description`.
+    alternate: bool,
+    alternate_description: DescriptionConfig,
+    /// The interval in seconds.
+    alternate_interval: f32,
+    /// Whether `alternate_description` is currently attached.
+    alternate_second: bool,
+    stress_seconds: f32,
+    /// Luminances that are sent without validation.
+    invalid_luminance: Luminance,
+    /// The description attached by the running stress run.
+    stress_description: Option<TestColorDescription>,
+    separate_blend_description: bool,
+    blend_description: DescriptionConfig,
 
     // scene
     scene: SelectedScene,
 
-    fill: Color,
+    enable_clear: bool,
+    clear: Color,
+
+    enable_overlay: bool,
+    enable_legend: bool,
+    srgb_reference: bool,
+    overlay_spacing: u32,
+    overlay_color: Color,
 
-    left_right: [Color; 2],
+    fill: Color,
 
-    top_bottom: [Color; 2],
+    gradient: [Color; 2],
+    gradient_angle: f32,
 
     four_corners: [Color; 4],
+    four_corners_alpha: [f32; 4],
 
     center_box: [Color; 2],
     center_box_size: f32,
@@ -404,9 +928,166 @@ struct ControlPaneConfig {
     grid: [Color; 2],
     grid_rows: u32,
     grid_cols: u32,
+    color_grid: ColorSweep,
+
+    crosshair: [Color; 2],
+
+    patch: Color,
+    patch_size: u32,
+    patch_offset: (i32, i32),
 
     blend: [Color; 2],
     blend_alpha: f32,
+
+    blend_banding: [Color; 2],
+    blend_banding_alpha: [f32; 2],
+    blend_layers_background: Color,
+    blend_layers: BlendLayers,
+
+    primaries_white_lumen: f32,
+
+    compare_lumen: f32,
+    compare_reference: NamedTransferFunction,
+    compare_pq_luminance: PqLuminance,
+
+    black_detail: CodeValueSteps,
+
+    image_path: String,
+
+    moving_bar: [Color; 2],
+    /// In widths per second.
+    moving_bar_speed: f32,
+
+    /// The luminance at which each transfer function shows an encoded value of 1.
+    transfer_functions_lumen: f32,
+}
+
+/// Returns the configuration of the test pane when running without a control pane.
+///
+/// The defaults of the control pane are used. `--scene NAME` selects the scene, using
+/// the name shown in the control pane with spaces replaced by dashes. `--fullscreen`
+/// makes the test pane fullscreen.
+pub fn cli_test_config(args: &[String]) -> Result<TestConfig, String> {
+    let mut config = ControlPaneConfig::default();
+    if let Some(idx) = args.iter().position(|a| a == "--scene") {
+        let name = args
+            .get(idx + 1)
+            .ok_or_else(|| "--scene requires a name".to_string())?;
+        config.scene = SelectedScene::variants()
+            .find(|s| WidgetText::from(*s).text().replace(' ', "-") == *name)
+            .ok_or_else(|| format!("unknown scene {name}"))?;
+    }
+    config.fullscreen = args.iter().any(|a| a == "--fullscreen");
+    Ok(config.test_config())
+}
+
+impl ControlPaneConfig {
+    fn test_config(&self) -> TestConfig {
+        let scene = match self.scene {
+            SelectedScene::Fill => TestScene::Fill(self.fill),
+            SelectedScene::LinearGradient => {
+                TestScene::LinearGradient(self.gradient, self.gradient_angle)
+            }
+            SelectedScene::FillFour => {
+                TestScene::FillFour(self.four_corners, self.four_corners_alpha)
+            }
+            SelectedScene::CenterBox => TestScene::CenterBox(self.center_box, self.center_box_size),
+            SelectedScene::Grid => TestScene::Grid(self.grid, self.grid_rows, self.grid_cols),
+            SelectedScene::ColorGrid => TestScene::ColorGrid(self.color_grid),
+            SelectedScene::Crosshair => TestScene::Crosshair(self.crosshair),
+            SelectedScene::Patch => {
+                TestScene::Patch(self.patch, self.patch_size, self.patch_offset)
+            }
+            SelectedScene::Blend => TestScene::Blend(self.blend, self.blend_alpha),
+            SelectedScene::BlendBanding => {
+                TestScene::BlendBanding(self.blend_banding, self.blend_banding_alpha)
+            }
+            SelectedScene::BlendLayers => {
+                TestScene::BlendLayers(self.blend_layers_background, self.blend_layers)
+            }
+            SelectedScene::Primaries => TestScene::Primaries(self.primaries_white_lumen),
+            SelectedScene::CompareEotf => TestScene::CompareEotf(
+                self.compare_lumen,
+                self.compare_reference,
+                self.compare_pq_luminance,
+            ),
+            SelectedScene::BlackDetail => TestScene::BlackDetail(self.black_detail),
+            SelectedScene::Image => TestScene::Image,
+            SelectedScene::MovingBar => {
+                TestScene::MovingBar(self.moving_bar, self.moving_bar_speed)
+            }
+            SelectedScene::TransferFunctions => {
+                TestScene::TransferFunctions(self.transfer_functions_lumen)
+            }
+        };
+        let to_test = |d: DescriptionConfig| match self.linear_content {
+            true => d.to_test().linear(),
+            false => d.to_test(),
+        };
+        let mut description = to_test(self.description);
+        let mut render_description = self.decouple.then(|| to_test(self.render_description));
+        if self.alternate && self.alternate_second {
+            // The content stays the same, only the attached description changes.
+            render_description = Some(render_description.unwrap_or(description));
+            description = to_test(self.alternate_description);
+        }
+        if let Some(stress) = self.stress_description {
+            description = stress;
+        }
+        TestConfig {
+            description,
+            render_description,
+            blend_description: self
+                .separate_blend_description
+                .then(|| to_test(self.blend_description)),
+            keep_buffer: self.keep_buffer,
+            verify_feedback: self.verify_feedback,
+            // The Vulkan implementation describes the content of other color spaces
+            // with its own color management surface.
+            color_management_surface: self.color_management_surface
+                && self.color_space == SwapchainColorSpace::PassThrough,
+            color_space: self.color_space,
+            scene,
+            clear: self.enable_clear.then_some(self.clear),
+            legend: self.enable_legend,
+            srgb_reference: self.srgb_reference,
+            overlay: self.enable_overlay.then_some(TestOverlay {
+                spacing: self.overlay_spacing,
+                color: self.overlay_color,
+            }),
+            viewport: self.enable_viewport.then_some(TestViewport {
+                source: self.viewport_source,
+                destination: self
+                    .enable_viewport_destination
+                    .then_some(self.viewport_destination),
+            }),
+            damage: self.enable_damage.then_some(TestDamage {
+                coordinates: self.damage_coordinates,
+                rect: self.damage_rect,
+            }),
+            peak_lumen: self.enable_peak.then_some(self.peak_lumen),
+            xyz_from_lms: match self.override_xyz_from_lms {
+                true => ColorMatrix::from_3x3(self.xyz_from_lms),
+                false => ColorMatrix::XYZ_FROM_LMS,
+            },
+            upload_mode: self.upload_mode,
+            alpha_mode: self.alpha_mode,
+            image_count: self.image_count,
+            present_mode: self.present_mode,
+            allow_tearing: self.allow_tearing,
+            representation: self.representation,
+            dither_bits: self.enable_dither.then_some(self.dither_bits),
+            gamut_mapping: self.gamut_mapping,
+            gradient_space: self.gradient_space,
+            output_clamp: self.output_clamp,
+            channel_adjustment: self.channel_adjustment,
+            measure_present_latency: self.measure_present_latency,
+            freeze: self.freeze,
+            pause: self.pause,
+            fullscreen: self.fullscreen,
+            fullscreen_output: self.fullscreen_output,
+        }
+    }
 }
 
 impl Default for ControlPaneConfig {
@@ -416,24 +1097,84 @@ impl Default for ControlPaneConfig {
         let default_chroma = 0.2;
         Self {
             view: Default::default(),
+            freeze: false,
+            pause: false,
+            fullscreen: false,
+            fullscreen_output: None,
+            eyedropper: false,
+            eyedropper_slot: 0,
+            histogram_scale: Default::default(),
+            diagram_background: [255; 3],
+            diagram_export_path: "cie-diagram.png".to_string(),
             max_lumen: 1000.0,
             max_chroma: 0.5,
-            cd_type: ColorDescriptionType::None,
-            named_primaries: NamedPrimaries::Srgb,
-            use_custom_primaries: false,
-            tf: TransferFunction::Named(NamedTransferFunction::Gamma22),
-            tf_power: 2.2,
-            enable_luminance: false,
-            luminance: Default::default(),
-            primaries: Primaries::SRGB,
+            xyy_entry: false,
+            chromaticity_epsilon: DescriptionDeltas::DEFAULT_CHROMATICITY_EPSILON,
+            upload_mode: Default::default(),
+            alpha_mode: Default::default(),
+            image_count: DEFAULT_IMAGE_COUNT,
+            present_mode: Default::default(),
+            allow_tearing: false,
+            representation: Default::default(),
+            enable_dither: false,
+            dither_bits: 8,
+            gamut_mapping: Default::default(),
+            gradient_space: Default::default(),
+            output_clamp: Default::default(),
+            channel_adjustment: Default::default(),
+            measure_present_latency: false,
+            enable_peak: false,
+            peak_lumen: 400.0,
+            override_xyz_from_lms: false,
+            xyz_from_lms: ColorMatrix::XYZ_FROM_LMS.to_3x3(),
+            enable_viewport: false,
+            viewport_source: (1600, 1200),
+            enable_viewport_destination: false,
+            viewport_destination: (800, 600),
+            enable_damage: false,
+            damage_coordinates: Default::default(),
+            damage_rect: (0, 0, 100, 100),
+            description: Default::default(),
+            decouple: false,
+            keep_buffer: false,
+            verify_feedback: false,
+            color_management_surface: true,
+            color_space: Default::default(),
+            linear_content: false,
+            render_description: Default::default(),
+            alternate: false,
+            alternate_description: Default::default(),
+            alternate_interval: 2.0,
+            alternate_second: false,
+            stress_seconds: 10.0,
+            invalid_luminance: Luminance {
+                min: F64(100.0),
+                max: F64(50.0),
+                white: F64(80.0),
+            },
+            stress_description: None,
+            separate_blend_description: false,
+            blend_description: Default::default(),
             scene: SelectedScene::FillFour,
+            enable_clear: false,
+            clear: Color::default(),
+            enable_overlay: false,
+            enable_legend: false,
+            srgb_reference: false,
+            overlay_spacing: 100,
+            overlay_color: Color {
+                lumen: default_lumen,
+                lightness: 1.0,
+                chroma: 0.0,
+                hue: 0.0,
+            },
             fill: Color {
                 lumen: default_lumen,
                 lightness: 1.0,
                 chroma: 0.0,
                 hue: 0.0,
             },
-            left_right: [
+            gradient: [
                 Color {
                     lumen: default_lumen,
                     lightness: default_lightness,
@@ -447,20 +1188,8 @@ impl Default for ControlPaneConfig {
                     hue: 180.0,
                 },
             ],
-            top_bottom: [
-                Color {
-                    lumen: default_lumen,
-                    lightness: default_lightness,
-                    chroma: default_chroma,
-                    hue: 90.0,
-                },
-                Color {
-                    lumen: default_lumen,
-                    lightness: default_lightness,
-                    chroma: default_chroma,
-                    hue: 270.0,
-                },
-            ],
+            gradient_angle: 0.0,
+            four_corners_alpha: [1.0; 4],
             four_corners: [
                 Color {
                     lumen: default_lumen,
@@ -518,76 +1247,603 @@ impl Default for ControlPaneConfig {
             ],
             grid_rows: 4,
             grid_cols: 4,
-            blend: [
+            color_grid: ColorSweep {
+                rows: 4,
+                cols: 12,
+                lumen: default_lumen,
+                chroma: default_chroma,
+                hue: (0.0, 360.0),
+                lightness: (0.9, 0.3),
+            },
+            crosshair: [
                 Color {
-                    lumen: default_lumen,
-                    lightness: default_lightness,
-                    chroma: default_chroma,
-                    hue: 40.0,
+                    lumen: 0.0,
+                    lightness: 0.0,
+                    chroma: 0.0,
+                    hue: 0.0,
                 },
                 Color {
                     lumen: default_lumen,
-                    lightness: default_lightness,
-                    chroma: default_chroma,
+                    lightness: 1.0,
+                    chroma: 0.0,
+                    hue: 0.0,
+                },
+            ],
+            patch: Color {
+                lumen: default_lumen,
+                lightness: 1.0,
+                chroma: 0.0,
+                hue: 0.0,
+            },
+            patch_size: 200,
+            patch_offset: (0, 0),
+            blend: [
+                Color {
+                    lumen: default_lumen,
+                    lightness: default_lightness,
+                    chroma: default_chroma,
+                    hue: 40.0,
+                },
+                Color {
+                    lumen: default_lumen,
+                    lightness: default_lightness,
+                    chroma: default_chroma,
                     hue: 140.0,
                 },
             ],
             blend_alpha: 0.5,
+            blend_banding: [
+                Color {
+                    lumen: default_lumen,
+                    lightness: 0.3,
+                    chroma: 0.0,
+                    hue: 0.0,
+                },
+                Color {
+                    lumen: default_lumen,
+                    lightness: 0.35,
+                    chroma: 0.0,
+                    hue: 0.0,
+                },
+            ],
+            blend_banding_alpha: [0.0, 0.1],
+            blend_layers_background: Color {
+                lumen: default_lumen,
+                lightness: default_lightness,
+                chroma: 0.0,
+                hue: 0.0,
+            },
+            blend_layers: BlendLayers {
+                count: 3,
+                colors: array::from_fn(|i| Color {
+                    lumen: default_lumen,
+                    lightness: default_lightness,
+                    chroma: default_chroma,
+                    hue: (40.0 + 100.0 * i as f32) % 360.0,
+                }),
+                alpha: [0.5; MAX_BLEND_LAYERS],
+                stacking: Default::default(),
+            },
+            primaries_white_lumen: default_lumen,
+            compare_lumen: default_lumen,
+            compare_reference: NamedTransferFunction::Linear,
+            compare_pq_luminance: Default::default(),
+            black_detail: CodeValueSteps {
+                start: 0,
+                step: 1,
+                count: 5,
+                bits: 8,
+            },
+            image_path: String::new(),
+            moving_bar: [
+                Color {
+                    lumen: 0.0,
+                    lightness: 0.0,
+                    chroma: 0.0,
+                    hue: 0.0,
+                },
+                Color {
+                    lumen: default_lumen,
+                    lightness: 1.0,
+                    chroma: 0.0,
+                    hue: 0.0,
+                },
+            ],
+            moving_bar_speed: 1.0,
+            transfer_functions_lumen: default_lumen,
+        }
+    }
+}
+
+/// Switches between the two alternating descriptions once the interval has passed.
+fn alternate_descriptions(ctx: &Context, ds: &mut DrawState) {
+    let config = &mut ds.config;
+    if !config.alternate {
+        config.alternate_second = false;
+        ds.next_alternation = None;
+        return;
+    }
+    let now = Instant::now();
+    let interval = Duration::from_secs_f32(config.alternate_interval);
+    let mut next = *ds.next_alternation.get_or_insert(now + interval);
+    if now >= next {
+        config.alternate_second = !config.alternate_second;
+        let name = match config.alternate_second {
+            true => "second",
+            false => "first",
+        };
+        log::debug!("alternating: attaching the {name} description");
+        next = now + interval;
+        ds.next_alternation = Some(next);
+    }
+    ctx.request_repaint_after(next - now);
+}
+
+/// Attaches a new random description in every frame while a stress run is active.
+fn stress_descriptions(ctx: &Context, test_pane: &TestPane, ds: &mut DrawState) {
+    ds.config.stress_description = None;
+    let Some(run) = &mut ds.stress else {
+        return;
+    };
+    if !run.is_running() {
+        return;
+    }
+    match run.next_description(&test_pane.caps) {
+        Ok(desc) => ds.config.stress_description = Some(desc),
+        Err(reason) => {
+            run.stop_reason = Some(reason);
+            run.end = Instant::now();
+        }
+    }
+    ctx.request_repaint();
+}
+
+fn draw_stress(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    let running = ds.stress.as_ref().is_some_and(|r| r.is_running());
+    let parametric = test_pane
+        .caps
+        .features
+        .contains(&WpColorManagerV1Feature::PARAMETRIC);
+    ui.horizontal(|ui| {
+        ui.add_enabled(
+            !running,
+            Slider::new(&mut ds.config.stress_seconds, 1.0..=60.0)
+                .prefix("Stress test: ")
+                .suffix(" s"),
+        );
+        match running {
+            true => {
+                if ui.button("Stop").clicked() {
+                    if let Some(run) = &mut ds.stress {
+                        run.end = Instant::now();
+                    }
+                }
+            }
+            false => {
+                let start = ui
+                    .add_enabled(parametric, Button::new("Start"))
+                    .on_hover_text(concat!(
+                        "Attaches a new random parametric description in every frame ",
+                        "and reports the errors sent by the compositor",
+                    ));
+                if start.clicked() {
+                    let duration = Duration::from_secs_f32(ds.config.stress_seconds);
+                    ds.stress = Some(StressRun::new(duration));
+                }
+            }
         }
+    });
+    let Some(run) = &ds.stress else {
+        return;
+    };
+    if let Some(reason) = run.stop_reason {
+        ui.colored_label(Color32::ORANGE, format!("Stopped because {reason}"));
+    }
+    ui.label(format!("Descriptions requested: {}", run.descriptions))
+        .on_hover_text("Descriptions that were superseded might never have become ready");
+    let live = test_pane.live_descriptions();
+    let text = format!("Description objects alive: {live}");
+    match live > 1 {
+        true => ui.colored_label(Color32::from_rgb(255, 128, 128), text),
+        false => ui.label(text),
     }
+    .on_hover_text("Descriptions that were superseded before they became ready must be destroyed");
+    if run.errors.is_empty() {
+        ui.label("No errors");
+    }
+    for (msg, count) in &run.errors {
+        ui.colored_label(Color32::from_rgb(255, 128, 128), format!("{count}x {msg}"));
+    }
+}
+
+/// Sends luminances that the protocol forbids. The description settings never produce
+/// such values.
+fn draw_invalid_luminance(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    ui.collapsing("Invalid luminances", |ui| {
+        ui.label(concat!(
+            "Creates a description on a separate connection with a max or white ",
+            "luminance that is not greater than the min luminance. The compositor must ",
+            "raise the invalid_luminance protocol error.",
+        ));
+        let l = &mut ds.config.invalid_luminance;
+        ui.horizontal(|ui| {
+            DragValue::new(&mut l.min.0)
+                .prefix("min: ")
+                .suffix(" cd/m²")
+                .range(0.0..=400_000.0)
+                .max_decimals(4)
+                .ui(ui);
+            DragValue::new(&mut l.max.0)
+                .prefix("max: ")
+                .suffix(" cd/m²")
+                .range(0.0..=u32::MAX as f64)
+                .max_decimals(0)
+                .ui(ui);
+            DragValue::new(&mut l.white.0)
+                .prefix("white: ")
+                .suffix(" cd/m²")
+                .range(0.0..=u32::MAX as f64)
+                .max_decimals(0)
+                .ui(ui);
+        });
+        let (min, max, white) = l.to_protocol();
+        let valid = max as u64 * 10_000 > min as u64 && white as u64 * 10_000 > min as u64;
+        if valid {
+            ui.colored_label(Color32::ORANGE, "These luminances are valid");
+        }
+        if ui.button("Send").clicked() {
+            ds.invalid_luminance = Some(probe_luminance(&test_pane.caps, *l));
+        }
+        let pass = Color32::from_rgb(128, 255, 128);
+        let fail = Color32::from_rgb(255, 128, 128);
+        match &ds.invalid_luminance {
+            Some(Ok(InvalidLuminanceOutcome::ProtocolError(e))) => {
+                ui.colored_label(pass, format!("Protocol error: {e}"))
+                    .on_hover_text("The message of the error is printed to stderr");
+            }
+            Some(Ok(InvalidLuminanceOutcome::Failed(msg))) => {
+                ui.colored_label(
+                    fail,
+                    format!("Failed event instead of a protocol error: {msg}"),
+                );
+            }
+            Some(Ok(InvalidLuminanceOutcome::Ready)) => {
+                ui.colored_label(fail, "The compositor accepted the description");
+            }
+            Some(Err(e)) => {
+                ui.colored_label(fail, e);
+            }
+            None => {}
+        }
+    });
 }
 
 fn draw_egui(ctx: &Context, test_pane: &TestPane, ds: &mut DrawState) {
+    alternate_descriptions(ctx, ds);
+    stress_descriptions(ctx, test_pane, ds);
     CentralPanel::default().show(ctx, |ui| {
-        ComboBox::from_label("View")
-            .selected_text(ds.config.view)
-            .show_ui(ui, |ui| {
-                for s in View::variants() {
-                    ui.selectable_value(&mut ds.config.view, s, s);
+        ui.horizontal(|ui| {
+            ComboBox::from_label("View")
+                .selected_text(ds.config.view)
+                .show_ui(ui, |ui| {
+                    for s in View::variants() {
+                        ui.selectable_value(&mut ds.config.view, s, s);
+                    }
+                });
+            ui.toggle_value(&mut ds.config.freeze, "Freeze")
+                .on_hover_text("Stops all rendering and protocol requests of the test pane");
+            ui.toggle_value(&mut ds.config.pause, "Pause")
+                .on_hover_text(concat!(
+                    "Stops rendering of the test pane. Changes are still applied to the ",
+                    "surface but only become visible with the next step.",
+                ));
+            if ds.config.pause {
+                let step = ui
+                    .add_enabled(!ds.config.freeze, Button::new("Step"))
+                    .on_hover_text("Renders a single frame and advances animations by 1/60 s");
+                if step.clicked() {
+                    test_pane.step();
                 }
-            });
+            }
+            ui.toggle_value(&mut ds.config.fullscreen, "Fullscreen")
+                .on_hover_text("Makes the test pane fullscreen (F11)");
+            draw_fullscreen_output(ui, ds);
+            let states = ds.toplevel_states;
+            let states = [
+                (states.maximized, "maximized"),
+                (states.fullscreen, "fullscreen"),
+                (states.activated, "activated"),
+            ];
+            let states = states.iter().filter(|s| s.0).map(|s| s.1).join(", ");
+            if !states.is_empty() {
+                ui.weak(format!("Test pane: {states}"));
+            }
+            if ds.updating {
+                ui.weak("updating…").on_hover_text(concat!(
+                    "The test pane does not show the current settings yet, for example ",
+                    "because the compositor has not finished creating the description",
+                ));
+            }
+        });
+        if ctx.input(|i| i.key_pressed(Key::F11)) {
+            ds.config.fullscreen = !ds.config.fullscreen;
+        }
+        if ds.config.freeze {
+            ui.colored_label(
+                Color32::from_rgb(255, 128, 128),
+                "Frozen: changes are not applied to the test pane.",
+            );
+        }
+        if ds.waiting_for_configure {
+            ui.colored_label(
+                Color32::ORANGE,
+                "Waiting for the compositor to configure the test pane…",
+            )
+            .on_hover_text(concat!(
+                "The test pane cannot render before the compositor has sent the first ",
+                "xdg_surface.configure event. The surface has been committed again.",
+            ));
+        }
+        if let Some(err) = test_pane.vulkan_error() {
+            ui.colored_label(
+                Color32::from_rgb(255, 128, 128),
+                format!("Vulkan is unavailable, the test pane cannot render: {err}"),
+            );
+        }
+        if let Some(err) = &ds.render_error_message {
+            ui.colored_label(
+                Color32::from_rgb(255, 128, 128),
+                format!("The test pane could not be rendered: {err}"),
+            );
+        }
         ui.add_space(10.0);
         match ds.config.view {
-            View::Scenes => draw_scenes(ui, ds),
-            View::Settings => draw_settings(ui, ds),
+            View::Scenes => draw_scenes(ui, test_pane, ds),
+            View::Settings => draw_settings(ui, test_pane, ds),
             View::ColorDescription => draw_color_description(ui, test_pane, ds),
-            View::Feedback => draw_feedback(ui, ds),
+            View::Feedback => draw_feedback(ui, test_pane, ds),
+            View::Output => draw_output(ui, ds),
         }
     });
 }
 
+/// Selects the output that the test pane is made fullscreen on. Selecting an output
+/// also makes the test pane fullscreen.
+fn draw_fullscreen_output(ui: &mut Ui, ds: &mut DrawState) {
+    let label = |o: &OutputInfo| {
+        let name = match &o.name {
+            Some(name) => name.clone(),
+            None => format!("output {}", o.global),
+        };
+        match &o.description {
+            Some(desc) => format!("{name} ({desc})"),
+            None => name,
+        }
+    };
+    let selected = ds
+        .config
+        .fullscreen_output
+        .and_then(|g| ds.outputs.iter().find(|o| o.global == g))
+        .map(label)
+        .unwrap_or_else(|| "any output".to_string());
+    ComboBox::from_id_salt("fullscreen_output")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut ds.config.fullscreen_output, None, "any output");
+            for o in &ds.outputs {
+                let res =
+                    ui.selectable_value(&mut ds.config.fullscreen_output, Some(o.global), label(o));
+                if res.clicked() {
+                    ds.config.fullscreen = true;
+                }
+            }
+        })
+        .response
+        .on_hover_text("The output that the test pane is made fullscreen on");
+}
+
 fn draw_color_description(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
-    ui.label("Changing these settings should not affect the output.");
+    if ds.config.decouple {
+        ui.colored_label(
+            Color32::from_rgb(255, 128, 128),
+            concat!(
+                "Intentional mismatch: the content is rendered for the actual color space ",
+                "but the declared color space is attached to the surface.",
+            ),
+        );
+    } else {
+        ui.label("Changing these settings should not affect the output.");
+    }
     ui.add_space(20.0);
     ui.horizontal_top(|ui| {
         ui.vertical(|ui| {
             ui.set_width(270.0);
-            draw_color_description_settings(ui, test_pane, ds);
+            let copy = ui
+                .button("Copy summary")
+                .on_hover_text("Copies the attached description in a form suited for bug reports");
+            if copy.clicked() {
+                let config = ds.config.test_config();
+                // Without a color management surface, the content is untagged sRGB.
+                let description = match config.color_management_surface {
+                    true => config.description,
+                    false => TestColorDescription::None,
+                };
+                let summary = description_summary(description);
+                ui.ctx().copy_text(summary);
+            }
+            ui.checkbox(&mut ds.config.decouple, "Decouple rendering");
+            ui.checkbox(
+                &mut ds.config.keep_buffer,
+                "Protocol test: commit without a new buffer",
+            )
+            .on_hover_text(concat!(
+                "Description changes are committed without attaching a new buffer. ",
+                "The compositor should re-transform the existing content.",
+            ));
+            ui.checkbox(&mut ds.config.alternate, "Alternate descriptions")
+                .on_hover_text(concat!(
+                    "Switches between the declared description and a second description ",
+                    "at a fixed interval. The content is not changed.",
+                ));
+            if ds.config.alternate {
+                Slider::new(&mut ds.config.alternate_interval, 0.1..=10.0)
+                    .prefix("Interval: ")
+                    .suffix(" s")
+                    .logarithmic(true)
+                    .ui(ui);
+            }
+            draw_stress(ui, test_pane, ds);
+            draw_invalid_luminance(ui, test_pane, ds);
+            // The luminances of the description are kept and usually differ from the
+            // defaults of ext_linear.
+            let caps = &test_pane.caps;
+            let linear = caps.tf.contains(&NamedTransferFunction::Linear.wayland())
+                && caps
+                    .features
+                    .contains(&WpColorManagerV1Feature::SET_LUMINANCES);
+            ds.config.linear_content &= linear;
+            ui.add_enabled(
+                linear,
+                Checkbox::new(&mut ds.config.linear_content, "Client sends linear content"),
+            )
+            .on_hover_text(concat!(
+                "The transfer function of the attached description is replaced by ext_linear. ",
+                "The compositor performs all encoding.",
+            ))
+            .on_disabled_hover_text(
+                "The compositor does not support ext_linear and set_luminances",
+            );
+            ui.add_space(10.0);
+            if ds.config.decouple {
+                ui.heading("Declared");
+            }
+            ui.push_id("declared", |ui| {
+                draw_color_description_settings(
+                    ui,
+                    Some(&test_pane.caps),
+                    &mut ds.config.description,
+                );
+            });
             if let Some(err) = &ds.create_description_error_message {
                 ui.add_space(20.0);
                 ui.colored_label(Color32::from_rgb(255, 128, 128), err);
             }
+            if ds.config.decouple {
+                ui.add_space(20.0);
+                ui.heading("Actual");
+                ui.push_id("actual", |ui| {
+                    draw_color_description_settings(ui, None, &mut ds.config.render_description);
+                });
+            }
+            if ds.config.alternate {
+                ui.add_space(20.0);
+                ui.heading("Alternate");
+                ui.push_id("alternate", |ui| {
+                    draw_color_description_settings(
+                        ui,
+                        Some(&test_pane.caps),
+                        &mut ds.config.alternate_description,
+                    );
+                });
+            }
+            ui.add_space(20.0);
+            draw_eotf_plot(ui, ds.config.description.to_test());
         });
         ui.vertical(|ui| {
-            let primaries = match ds.config.cd_type {
-                ColorDescriptionType::None => Primaries::SRGB,
-                ColorDescriptionType::ScRgb => Primaries::SRGB,
-                ColorDescriptionType::Parametric => match ds.config.use_custom_primaries {
-                    true => ds.config.primaries,
-                    false => ds.config.named_primaries.primaries(),
-                },
-            };
+            let primaries = ds.config.description.primaries();
             draw_chromaticity_diagram(ui, ds, primaries);
         });
     });
 }
 
-fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
-    let supported_features = &test_pane.caps.features;
-    let supported_tf = &test_pane.caps.tf;
-    let supported_primaries = &test_pane.caps.primaries;
+/// Plots the luminance of each code value of the description. The curve is computed
+/// with the same inverse EOTFs as the test pane.
+fn draw_eotf_plot(ui: &mut Ui, description: TestColorDescription) {
+    const SAMPLES: usize = 256;
+    let params = description.render_params(ColorMatrix::XYZ_FROM_LMS);
+    let eotf = eotf_id(params.tf);
+    let (min, max) = description.code_value_range();
+    ui.label("EOTF:");
+    let (response, painter) =
+        ui.allocate_painter(vec2(ui.available_width(), 180.0), Sense::hover());
+    let text_color = ui.visuals().text_color();
+    let font = FontId::proportional(10.0);
+    let plot = Rect::from_min_max(
+        response.rect.min + vec2(50.0, 5.0),
+        response.rect.max - vec2(5.0, 15.0),
+    );
+    painter.rect_stroke(
+        plot,
+        0.0,
+        Stroke::new(1.0, Color32::GRAY),
+        StrokeKind::Inside,
+    );
+    // Samples are denser near black where most curves change quickly.
+    let points = (0..=SAMPLES)
+        .map(|i| {
+            let linear = (i as f32 / SAMPLES as f32).powi(3);
+            let code = trace::inv_eotf(eotf, &params.tf_args, linear).clamp(0.0, 1.0);
+            pos2(
+                plot.left() + code * plot.width(),
+                plot.bottom() - linear * plot.height(),
+            )
+        })
+        .collect();
+    painter.add(Shape::line(points, Stroke::new(1.5, text_color)));
+    let label = |pos, align, text: String| {
+        painter.text(pos, align, text, font.clone(), text_color);
+    };
+    label(
+        plot.left_bottom() + vec2(-3.0, 0.0),
+        Align2::RIGHT_BOTTOM,
+        format!("{min}"),
+    );
+    label(
+        plot.left_top() + vec2(-3.0, 0.0),
+        Align2::RIGHT_TOP,
+        format!("{max}"),
+    );
+    label(
+        plot.left_center() + vec2(-3.0, 0.0),
+        Align2::RIGHT_CENTER,
+        "cd/m²".to_string(),
+    );
+    label(
+        plot.left_bottom() + vec2(0.0, 2.0),
+        Align2::LEFT_TOP,
+        "0".to_string(),
+    );
+    label(
+        plot.right_bottom() + vec2(0.0, 2.0),
+        Align2::RIGHT_TOP,
+        "1".to_string(),
+    );
+    label(
+        plot.center_bottom() + vec2(0.0, 2.0),
+        Align2::CENTER_TOP,
+        "code value".to_string(),
+    );
+}
+
+/// Draws the settings of a color description. If `caps` is `None`, all options are shown.
+fn draw_color_description_settings(
+    ui: &mut Ui,
+    caps: Option<&Capablities>,
+    config: &mut DescriptionConfig,
+) {
+    let supports_feature =
+        |feature: WpColorManagerV1Feature| caps.is_none_or(|c| c.features.contains(&feature));
+    let supports_tf = |tf: NamedTransferFunction| caps.is_none_or(|c| c.tf.contains(&tf.wayland()));
+    let supports_primaries =
+        |p: NamedPrimaries| caps.is_none_or(|c| c.primaries.contains(&p.wayland()));
+    let any_tf = caps.is_none_or(|c| c.tf.is_not_empty());
+    let any_primaries = caps.is_none_or(|c| c.primaries.is_not_empty());
+    let windows_scrgb = supports_feature(WpColorManagerV1Feature::WINDOWS_SCRGB);
+    let parametric_scrgb = supports_feature(WpColorManagerV1Feature::PARAMETRIC)
+        && supports_feature(WpColorManagerV1Feature::SET_LUMINANCES)
+        && supports_tf(NamedTransferFunction::Linear)
+        && supports_primaries(NamedPrimaries::Srgb);
 
-    let config = &mut ds.config;
     ComboBox::from_label("Type")
         .selected_text(config.cd_type)
         .show_ui(ui, |ui| {
@@ -595,20 +1851,48 @@ fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut D
                 ui.selectable_value(&mut config.cd_type, ty, ty);
             };
             val(ColorDescriptionType::None);
-            if supported_features.contains(&WpColorManagerV1Feature::WINDOWS_SCRGB) {
+            if windows_scrgb || parametric_scrgb {
                 val(ColorDescriptionType::ScRgb);
             }
-            if supported_features.contains(&WpColorManagerV1Feature::PARAMETRIC)
-                && supported_primaries.is_not_empty()
-                && supported_tf.is_not_empty()
-            {
+            if supports_feature(WpColorManagerV1Feature::PARAMETRIC) && any_primaries && any_tf {
                 val(ColorDescriptionType::Parametric);
             }
         });
+    // Without this, the missing parametric type looks like a setting that was not
+    // enabled.
+    if supports_feature(WpColorManagerV1Feature::PARAMETRIC) && !(any_tf && any_primaries) {
+        let missing = match (any_tf, any_primaries) {
+            (false, false) => "transfer functions or primaries",
+            (false, true) => "transfer functions",
+            _ => "primaries",
+        };
+        ui.colored_label(
+            Color32::ORANGE,
+            format!(
+                "The compositor supports parametric descriptions but advertises no named \
+                 {missing}. Parametric descriptions cannot be created."
+            ),
+        );
+    }
+    if config.cd_type == ColorDescriptionType::ScRgb {
+        if !windows_scrgb {
+            config.scrgb_parametric = true;
+        } else if !parametric_scrgb {
+            config.scrgb_parametric = false;
+        }
+        ui.add_enabled_ui(windows_scrgb && parametric_scrgb, |ui| {
+            ui.checkbox(&mut config.scrgb_parametric, "Parametric approximation")
+                .on_hover_text(concat!(
+                    "Describes scRGB with sRGB primaries, the extended linear transfer ",
+                    "function, and the luminances of Windows-scRGB instead of using ",
+                    "create_windows_scrgb. Both should look the same.",
+                ));
+        });
+    }
     ui.add_space(20.0);
     let mut primaries;
     if config.cd_type == ColorDescriptionType::Parametric {
-        if supported_features.contains(&WpColorManagerV1Feature::SET_PRIMARIES) {
+        if supports_feature(WpColorManagerV1Feature::SET_PRIMARIES) {
             ui.checkbox(&mut config.use_custom_primaries, "Custom primaries");
         }
         ui.add_enabled_ui(!config.use_custom_primaries, |ui| {
@@ -616,7 +1900,7 @@ fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut D
                 .selected_text(config.named_primaries)
                 .show_ui(ui, |ui| {
                     for primary in NamedPrimaries::variants() {
-                        if supported_primaries.contains(&primary.wayland()) {
+                        if supports_primaries(primary) {
                             ui.selectable_value(&mut config.named_primaries, primary, primary);
                         }
                     }
@@ -637,11 +1921,34 @@ fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut D
                 ] {
                     let (x, y) = cp;
                     ui.label(name);
-                    DragValue::new(&mut x.0).speed(0.001).ui(ui);
-                    DragValue::new(&mut y.0).speed(0.001).ui(ui);
+                    // The protocol transmits 6 decimal places.
+                    DragValue::new(&mut x.0).speed(0.001).max_decimals(6).ui(ui);
+                    DragValue::new(&mut y.0).speed(0.001).max_decimals(6).ui(ui);
                     ui.end_row();
                 }
             });
+            let response = Slider::new(&mut config.white_point_cct, 2000.0..=15000.0)
+                .prefix("White point: ")
+                .suffix(" K")
+                .max_decimals(0)
+                .ui(ui);
+            if response.changed() {
+                primaries.wp = cct_to_xy(config.white_point_cct);
+            }
+            ui.horizontal(|ui| {
+                match primaries.white_point_inside() {
+                    true => ui.label("The white point is inside the primaries"),
+                    false => ui
+                        .colored_label(Color32::ORANGE, "The white point is outside the primaries"),
+                };
+                if ui
+                    .button("Snap to centroid")
+                    .on_hover_text("Moves the white point to the center of the triangle")
+                    .clicked()
+                {
+                    primaries.wp = primaries.centroid();
+                }
+            });
         });
         if config.use_custom_primaries {
             config.primaries = primaries;
@@ -650,11 +1957,11 @@ fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut D
             .selected_text(config.tf)
             .show_ui(ui, |ui| {
                 for tf in NamedTransferFunction::variants() {
-                    if supported_tf.contains(&tf.wayland()) {
+                    if supports_tf(tf) {
                         ui.selectable_value(&mut config.tf, TransferFunction::Named(tf), tf);
                     }
                 }
-                if supported_features.contains(&WpColorManagerV1Feature::SET_TF_POWER) {
+                if supports_feature(WpColorManagerV1Feature::SET_TF_POWER) {
                     ui.selectable_value(
                         &mut config.tf,
                         TransferFunction::Pow,
@@ -663,233 +1970,2013 @@ fn draw_color_description_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut D
                 }
             });
         if config.tf == TransferFunction::Pow {
-            Slider::new(&mut config.tf_power, 1.0..=10.0)
-                .prefix("Power: ")
-                .drag_value_speed(0.1)
-                .ui(ui);
+            // The protocol only allows exponents between 1 and 10 in steps of 0.0001.
+            ui.horizontal(|ui| {
+                ui.label("Power:");
+                DragValue::new(&mut config.tf_power)
+                    .range(1.0..=10.0)
+                    .speed(0.001)
+                    .max_decimals(4)
+                    .ui(ui);
+            });
+            config.tf_power = tf_power_from_protocol(tf_power_to_protocol(config.tf_power));
         }
-        if supported_features.contains(&WpColorManagerV1Feature::SET_LUMINANCES) {
-            ui.checkbox(&mut config.enable_luminance, "Luminance");
+        if supports_feature(WpColorManagerV1Feature::SET_LUMINANCES) {
+            ui.horizontal(|ui| {
+                ui.label("Luminance:");
+                ui.radio_value(&mut config.enable_luminance, false, "default")
+                    .on_hover_text("set_luminances is not sent");
+                ui.radio_value(&mut config.enable_luminance, true, "override");
+            });
             if config.enable_luminance {
-                Slider::new(&mut config.luminance.min.0, 0.0..=100.0)
-                    .prefix("Min: ")
-                    .drag_value_speed(1.0)
+                if ui
+                    .button("Transfer function defaults")
+                    .on_hover_text(concat!(
+                        "Sends the default luminances of the transfer function explicitly. ",
+                        "The result should look the same as without set_luminances.",
+                    ))
+                    .clicked()
+                {
+                    config.luminance = Luminance::default_for(config.tf);
+                }
+                let l = &mut config.luminance;
+                Grid::new("luminance").show(ui, |ui| {
+                    // All values are in cd/m². The protocol transmits the minimum in
+                    // units of 0.0001 cd/m², which is the smallest step of the slider.
+                    ui.label("Min:");
+                    Slider::new(&mut l.min.0, 0.0..=100.0)
+                        .logarithmic(true)
+                        .smallest_positive(0.0001)
+                        .max_decimals(4)
+                        .suffix(" cd/m²")
+                        .ui(ui);
+                    ui.end_row();
+                    ui.label("White:");
+                    Slider::new(&mut l.white.0, 1.0..=10000.0)
+                        .logarithmic(true)
+                        .max_decimals(0)
+                        .suffix(" cd/m²")
+                        .ui(ui);
+                    ui.end_row();
+                    ui.label("Max:");
+                    Slider::new(&mut l.max.0, 1.0..=10000.0)
+                        .logarithmic(true)
+                        .max_decimals(0)
+                        .suffix(" cd/m²")
+                        .ui(ui);
+                    ui.end_row();
+                });
+                validate_luminance(l);
+            }
+        }
+        draw_mastering_settings(
+            ui,
+            supports_feature(WpColorManagerV1Feature::SET_MASTERING_DISPLAY_PRIMARIES),
+            supports_feature(WpColorManagerV1Feature::EXTENDED_TARGET_VOLUME),
+            config,
+        );
+    }
+}
+
+/// Draws the HDR10 static metadata settings. The mastering display requests require
+/// the set_mastering_display_primaries feature, max_cll and max_fall are always
+/// available. Mastering displays outside of the primary color volume additionally
+/// require the extended_target_volume feature.
+fn draw_mastering_settings(
+    ui: &mut Ui,
+    supports_mastering: bool,
+    extended_target_volume: bool,
+    config: &mut DescriptionConfig,
+) {
+    ui.checkbox(&mut config.enable_mastering, "HDR10 static metadata")
+        .on_hover_text(concat!(
+            "Sends the mastering display and content light levels. Otherwise max_cll ",
+            "and max_fall are set to the white luminance.",
+        ));
+    if !config.enable_mastering {
+        return;
+    }
+    let m = &mut config.mastering;
+    if !supports_mastering {
+        m.primaries = None;
+        m.luminance = None;
+        ui.colored_label(
+            Color32::ORANGE,
+            "The compositor does not support set_mastering_display_primaries",
+        );
+    }
+    let toggle = |ui: &mut Ui, enabled: bool, label: &str| {
+        let mut on = enabled;
+        ui.add_enabled(supports_mastering, Checkbox::new(&mut on, label));
+        on
+    };
+    ui.indent("mastering", |ui| {
+        let on = toggle(ui, m.primaries.is_some(), "Mastering display primaries");
+        match (on, &mut m.primaries) {
+            (true, None) => m.primaries = Some(NamedPrimaries::DisplayP3.primaries()),
+            (false, Some(_)) => m.primaries = None,
+            _ => {}
+        }
+        if let Some(p) = &mut m.primaries {
+            Grid::new("mastering primaries").show(ui, |ui| {
+                for (name, (x, y)) in [
+                    ("r", &mut p.r),
+                    ("g", &mut p.g),
+                    ("b", &mut p.b),
+                    ("wp", &mut p.wp),
+                ] {
+                    ui.label(name);
+                    DragValue::new(&mut x.0).speed(0.001).max_decimals(6).ui(ui);
+                    DragValue::new(&mut y.0).speed(0.001).max_decimals(6).ui(ui);
+                    ui.end_row();
+                }
+            });
+        }
+        let on = toggle(ui, m.luminance.is_some(), "Mastering luminance");
+        match (on, &mut m.luminance) {
+            (true, None) => m.luminance = Some((F64(0.005), F64(1000.0))),
+            (false, Some(_)) => m.luminance = None,
+            _ => {}
+        }
+        if let Some((min, max)) = &mut m.luminance {
+            Grid::new("mastering luminance").show(ui, |ui| {
+                ui.label("Min:");
+                Slider::new(&mut min.0, 0.0..=100.0)
+                    .logarithmic(true)
+                    .smallest_positive(0.0001)
+                    .max_decimals(4)
+                    .suffix(" cd/m²")
+                    .ui(ui);
+                ui.end_row();
+                ui.label("Max:");
+                Slider::new(&mut max.0, 1.0..=10000.0)
+                    .logarithmic(true)
+                    .max_decimals(0)
+                    .suffix(" cd/m²")
                     .ui(ui);
-                let min = config.luminance.min.0 + 1.0;
-                Slider::new(&mut config.luminance.max.0, min..=10000.0)
-                    .prefix("Max: ")
-                    .drag_value_speed(1.0)
+                ui.end_row();
+            });
+            // The protocol requires max > min.
+            max.0 = max.0.round().max(min.0.floor() + 1.0);
+        }
+        for (label, value, default) in [
+            ("MaxCLL", &mut m.max_cll, 1000),
+            ("MaxFALL", &mut m.max_fall, 400),
+        ] {
+            ui.horizontal(|ui| {
+                let mut on = value.is_some();
+                ui.checkbox(&mut on, label);
+                match (on, &mut *value) {
+                    (true, None) => *value = Some(default),
+                    (false, Some(_)) => *value = None,
+                    _ => {}
+                }
+                if let Some(v) = value {
+                    DragValue::new(v).range(1..=10000).suffix(" cd/m²").ui(ui);
+                }
+            });
+        }
+        if let (Some(cll), Some(fall)) = (m.max_cll, m.max_fall) {
+            if fall > cll {
+                ui.colored_label(Color32::ORANGE, "MaxFALL must not exceed MaxCLL");
+            }
+        }
+    });
+    if !extended_target_volume {
+        let primaries = config.primaries();
+        let (min, max) = config.to_test().code_value_range();
+        let m = &config.mastering;
+        let outside_primaries = m
+            .primaries
+            .is_some_and(|p| [p.r, p.g, p.b].into_iter().any(|c| !primaries.contains(c)));
+        let outside_luminance = m.luminance.is_some_and(|(lo, hi)| lo.0 < min || hi.0 > max);
+        if outside_primaries || outside_luminance {
+            ui.colored_label(
+                Color32::ORANGE,
+                "The mastering display exceeds the primary color volume. The compositor \
+                 does not support extended_target_volume, so the result is implementation \
+                 defined.",
+            );
+        }
+    }
+}
+
+/// Formats the description with the names used by the protocol.
+fn description_summary(description: TestColorDescription) -> String {
+    let compositor = env::var("XDG_CURRENT_DESKTOP").unwrap_or_else(|_| "unknown".to_string());
+    let mut lines = vec![format!("compositor: {compositor}")];
+    match description {
+        TestColorDescription::None => lines.push("description: none".to_string()),
+        TestColorDescription::ScRgb => lines.push("description: windows_scrgb".to_string()),
+        TestColorDescription::Parametric {
+            primaries,
+            transfer_function,
+            luminance,
+            mastering,
+        } => {
+            let primaries = match primaries {
+                TestPrimaries::Named(n) => WidgetText::from(n).text().to_string(),
+                TestPrimaries::Custom(p) => {
+                    let xy = |(x, y): (F64, F64)| format!("{:.6}, {:.6}", x.0, y.0);
+                    format!("r {} g {} b {} w {}", xy(p.r), xy(p.g), xy(p.b), xy(p.wp))
+                }
+            };
+            let tf = match transfer_function.tf {
+                TransferFunction::Pow => format!("power {}", transfer_function.pow),
+                tf => WidgetText::from(tf).text().to_string(),
+            };
+            let (l, source) = match luminance {
+                Some(l) => (l, "set_luminances"),
+                None => (Luminance::default_for(transfer_function.tf), "default"),
+            };
+            lines.push("description: parametric".to_string());
+            lines.push(format!("primaries: {primaries}"));
+            lines.push(format!("transfer function: {tf}"));
+            lines.push(format!(
+                "luminances: min {} max {} white {} cd/m² ({source})",
+                l.min.0, l.max.0, l.white.0,
+            ));
+            if let Some(m) = mastering {
+                if let Some(p) = m.primaries {
+                    let xy = |(x, y): (F64, F64)| format!("{:.6}, {:.6}", x.0, y.0);
+                    lines.push(format!(
+                        "mastering primaries: r {} g {} b {} w {}",
+                        xy(p.r),
+                        xy(p.g),
+                        xy(p.b),
+                        xy(p.wp),
+                    ));
+                }
+                if let Some((min, max)) = m.luminance {
+                    lines.push(format!(
+                        "mastering luminance: min {} max {} cd/m²",
+                        min.0, max.0
+                    ));
+                }
+                if let Some(max_cll) = m.max_cll {
+                    lines.push(format!("max_cll: {max_cll} cd/m²"));
+                }
+                if let Some(max_fall) = m.max_fall {
+                    lines.push(format!("max_fall: {max_fall} cd/m²"));
+                }
+            }
+        }
+    }
+    lines.push("render intent: perceptual".to_string());
+    lines.join("\n")
+}
+
+/// Ensures that `min < white <= max`. The white and max luminances are integers on the
+/// wire.
+fn validate_luminance(l: &mut Luminance) {
+    l.white.0 = l.white.0.round().max(l.min.0.floor() + 1.0);
+    l.max.0 = l.max.0.round().max(l.white.0);
+}
+
+fn draw_feedback(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    ui.checkbox(
+        &mut ds.config.verify_feedback,
+        "Compare with the attached description",
+    )
+    .on_hover_text(concat!(
+        "Requests the preferred description whenever a parametric description has ",
+        "been attached and compares it with the attached description.\n",
+        "\n",
+        "Descriptions created by the client cannot be read back, so this only passes ",
+        "if the compositor prefers the description of the content, for example for ",
+        "direct scanout.",
+    ));
+    ui.add_space(10.0);
+    if let Some(err) = &ds.preferred_description_error_message {
+        ui.colored_label(Color32::from_rgb(255, 128, 128), err);
+        return;
+    }
+    let Some(data) = ds.preferred_description_data else {
+        return;
+    };
+    if let Some(id) = ds.preferred_identity {
+        draw_identity(ui, id);
+        ui.add_space(10.0);
+    }
+    ui.horizontal(|ui| {
+        for (slot, name) in ds.feedback_snapshots.iter_mut().zip(["A", "B"]) {
+            if ui.button(format!("Capture {name}")).clicked() {
+                *slot = Some(data);
+            }
+        }
+        let adopt = ui.button("Match preferred").on_hover_text(concat!(
+            "Attaches a parametric description with the parameters of the preferred ",
+            "description. The compositor should then pass the content through nearly ",
+            "unchanged.",
+        ));
+        if adopt.clicked() {
+            let config = &mut ds.config;
+            ds.match_preferred_dropped = config.description.set_from_data(data, &test_pane.caps);
+            config.color_management_surface = true;
+            config.color_space = SwapchainColorSpace::PassThrough;
+        }
+    });
+    if !ds.match_preferred_dropped.is_empty() {
+        ui.colored_label(
+            Color32::ORANGE,
+            format!(
+                "Not matched because the compositor does not accept them: {}",
+                ds.match_preferred_dropped.join(", "),
+            ),
+        );
+    }
+    ui.add_space(10.0);
+    if ds.config.verify_feedback {
+        let description = ds.config.test_config().description;
+        draw_verification(ui, description, &data, ds.config.chromaticity_epsilon);
+        ui.add_space(10.0);
+    }
+    draw_description_data(ui, ds, data);
+    if ds.feedback_snapshots.iter().any(|s| s.is_some()) {
+        ui.add_space(20.0);
+        draw_snapshot_diff(ui, &ds.feedback_snapshots);
+    }
+}
+
+/// Shows whether the preferred description matches the attached description.
+fn draw_verification(
+    ui: &mut Ui,
+    description: TestColorDescription,
+    data: &DescriptionData,
+    chromaticity_epsilon: f64,
+) {
+    let Some(deltas) = compare_description(description, data, chromaticity_epsilon) else {
+        ui.label("Only parametric descriptions can be compared");
+        return;
+    };
+    match deltas.pass() {
+        true => ui.colored_label(Color32::from_rgb(128, 255, 128), "PASS"),
+        false => ui.colored_label(Color32::from_rgb(255, 128, 128), "FAIL"),
+    };
+    Grid::new("verification").show(ui, |ui| {
+        ui.label("Primaries:");
+        ui.label(format!(
+            "{:.6} (max {:e})",
+            deltas.primaries, deltas.chromaticity_epsilon
+        ));
+        ui.end_row();
+        ui.label("Transfer function:");
+        ui.label(match deltas.tf {
+            true => "matches",
+            false => "differs",
+        });
+        ui.end_row();
+        ui.label("Luminance:");
+        match deltas.luminance {
+            Some(l) => ui.label(format!(
+                "{:.2}% (max {}%)",
+                l * 100.0,
+                DescriptionDeltas::MAX_LUMINANCE * 100.0,
+            )),
+            None => ui.label("not sent"),
+        };
+        ui.end_row();
+    });
+    if let TestColorDescription::Parametric {
+        mastering: Some(mastering),
+        ..
+    } = description
+    {
+        ui.add_space(10.0);
+        draw_mastering_verification(ui, description, mastering, data, chromaticity_epsilon);
+    }
+}
+
+/// Shows whether the compositor reported the HDR10 static metadata that was sent.
+fn draw_mastering_verification(
+    ui: &mut Ui,
+    description: TestColorDescription,
+    mastering: Mastering,
+    data: &DescriptionData,
+    chromaticity_epsilon: f64,
+) {
+    let Some(matches) = compare_mastering(description, data, chromaticity_epsilon) else {
+        return;
+    };
+    let xy = |(x, y): (F64, F64)| format!("{:.4}, {:.4}", x.0, y.0);
+    let primaries = |p: Primaries| format!("r {} g {} b {}", xy(p.r), xy(p.g), xy(p.b));
+    let luminance = |(min, max): (F64, F64)| format!("{} - {} cd/m²", min.0, max.0);
+    let level = |l: u32| format!("{l} cd/m²");
+    let rows = [
+        (
+            "Mastering primaries",
+            matches.primaries,
+            mastering.primaries.map(primaries),
+            data.target_primaries.map(primaries),
+        ),
+        (
+            "Mastering luminance",
+            matches.luminance,
+            mastering.luminance.map(luminance),
+            data.target_luminance.map(luminance),
+        ),
+        (
+            "MaxCLL",
+            matches.max_cll,
+            mastering.max_cll.map(level),
+            data.target_max_cll.map(level),
+        ),
+        (
+            "MaxFALL",
+            matches.max_fall,
+            mastering.max_fall.map(level),
+            data.target_max_fall.map(level),
+        ),
+    ];
+    Grid::new("mastering verification")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("HDR10 metadata");
+            ui.strong("Sent");
+            ui.strong("Reported");
+            ui.strong("Result");
+            ui.end_row();
+            for (name, result, sent, reported) in rows {
+                let (Some(result), Some(sent)) = (result, sent) else {
+                    continue;
+                };
+                ui.label(name);
+                ui.label(sent);
+                ui.label(reported.unwrap_or_else(|| "-".to_string()));
+                match result {
+                    MetadataMatch::Match => {
+                        ui.colored_label(Color32::from_rgb(128, 255, 128), "match");
+                    }
+                    MetadataMatch::Mismatch => {
+                        ui.colored_label(Color32::from_rgb(255, 128, 128), "mismatch");
+                    }
+                    MetadataMatch::NotReported => {
+                        ui.label("not reported");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    if !matches.pass() {
+        ui.colored_label(
+            Color32::from_rgb(255, 128, 128),
+            "The preferred description has different HDR10 metadata. This is expected \
+             unless the compositor prefers the description of the content.",
+        );
+    }
+}
+
+fn draw_identity(ui: &mut Ui, id: DescriptionIdentity) {
+    Grid::new("identity").show(ui, |ui| {
+        ui.label("Identity:");
+        ui.label(id.identity.to_string());
+        ui.end_row();
+        ui.label("Announced identity:");
+        match id.announced {
+            Some(a) if a != id.identity => {
+                ui.colored_label(Color32::ORANGE, a.to_string());
+            }
+            Some(a) => {
+                ui.label(a.to_string());
+            }
+            None => {
+                ui.label("-");
+            }
+        }
+        ui.end_row();
+    });
+    if id.unchanged_content {
+        ui.colored_label(
+            Color32::ORANGE,
+            "The identity changed but the description did not",
+        );
+    }
+}
+
+/// The row labels of `description_rows`.
+const DESCRIPTION_ROWS: [&str; 11] = [
+    "Primaries",
+    "r",
+    "g",
+    "b",
+    "wp",
+    "Transfer function",
+    "Min luminance",
+    "Max luminance",
+    "White luminance",
+    "Target min luminance",
+    "Target max luminance",
+];
+
+/// Formats a description as the rows of the snapshot comparison.
+fn description_rows(data: &DescriptionData) -> [String; 11] {
+    let primaries = match data.primaries {
+        TestPrimaries::Named(p) => p.primaries(),
+        TestPrimaries::Custom(p) => p,
+    };
+    let xy = |(x, y): (F64, F64)| format!("{}, {}", x.0, y.0);
+    let opt = |v: Option<F64>| v.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string());
+    [
+        match data.primaries {
+            TestPrimaries::Named(p) => WidgetText::from(p).text().to_string(),
+            TestPrimaries::Custom(_) => "custom".to_string(),
+        },
+        xy(primaries.r),
+        xy(primaries.g),
+        xy(primaries.b),
+        xy(primaries.wp),
+        match data.tf {
+            TransferFunction::Named(n) => WidgetText::from(n).text().to_string(),
+            TransferFunction::Pow => format!("pow({})", data.tf_power),
+        },
+        opt(data.luminance.map(|l| l.min)),
+        opt(data.luminance.map(|l| l.max)),
+        opt(data.luminance.map(|l| l.white)),
+        opt(data.target_luminance.map(|l| l.0)),
+        opt(data.target_luminance.map(|l| l.1)),
+    ]
+}
+
+/// Shows the two feedback snapshots side by side. Rows that differ are highlighted.
+fn draw_snapshot_diff(ui: &mut Ui, snapshots: &[Option<DescriptionData>; 2]) {
+    let rows = snapshots.map(|s| s.map(|d| description_rows(&d)));
+    Grid::new("feedback snapshots")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.label("A");
+            ui.label("B");
+            ui.end_row();
+            for (idx, name) in DESCRIPTION_ROWS.iter().enumerate() {
+                let [a, b] = rows.each_ref().map(|r| r.as_ref().map(|r| r[idx].as_str()));
+                ui.label(*name);
+                for value in [a, b] {
+                    let text = value.unwrap_or("-");
+                    if a != b {
+                        ui.colored_label(Color32::from_rgb(255, 200, 100), text);
+                    } else {
+                        ui.label(text);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn draw_output(ui: &mut Ui, ds: &mut DrawState) {
+    if let Some(err) = &ds.output_error_message {
+        ui.colored_label(Color32::from_rgb(255, 128, 128), err);
+        return;
+    }
+    let Some(data) = &ds.output_data else {
+        return;
+    };
+    if let Some(name) = &data.name {
+        ui.label(format!("Output: {name}"));
+        ui.add_space(10.0);
+    }
+    draw_description_data(ui, ds, data.description);
+}
+
+fn draw_description_data(ui: &mut Ui, ds: &mut DrawState, data: DescriptionData) {
+    let primaries = match data.primaries {
+        TestPrimaries::Named(p) => p.primaries(),
+        TestPrimaries::Custom(p) => p,
+    };
+    ui.horizontal_top(|ui| {
+        ui.vertical(|ui| {
+            ui.set_width(270.0);
+            ui.horizontal_top(|ui| {
+                ui.label("Primaries:");
+                if let TestPrimaries::Named(p) = data.primaries {
+                    ui.label(p);
+                };
+            });
+            ui.indent("primaries", |ui| {
+                Grid::new("primaries").show(ui, |ui| {
+                    for (name, cp) in [
+                        ("r", primaries.r),
+                        ("g", primaries.g),
+                        ("b", primaries.b),
+                        ("wp", primaries.wp),
+                    ] {
+                        let (x, y) = cp;
+                        ui.label(name);
+                        ui.label(x.0.to_string());
+                        ui.label(y.0.to_string());
+                        ui.end_row();
+                    }
+                });
+            });
+            ui.add_space(10.0);
+            ui.horizontal_top(|ui| {
+                ui.label("Transfer function:");
+                match data.tf {
+                    TransferFunction::Named(n) => {
+                        ui.label(n);
+                    }
+                    TransferFunction::Pow => {
+                        ui.label(format!("pow({})", data.tf_power));
+                    }
+                }
+            });
+            ui.add_space(10.0);
+            if let Some(lum) = data.luminance {
+                ui.label("Luminance:");
+                ui.indent("luminance", |ui| {
+                    Grid::new("luminance").show(ui, |ui| {
+                        ui.label("Min");
+                        ui.label(lum.min.to_string());
+                        ui.end_row();
+                        ui.label("Max");
+                        ui.label(lum.max.to_string());
+                        ui.end_row();
+                        ui.label("White");
+                        ui.label(lum.white.to_string());
+                        ui.end_row();
+                    });
+                });
+            }
+            if let Some((min, max)) = data.target_luminance {
+                ui.add_space(10.0);
+                ui.label("Target luminance:");
+                ui.indent("target luminance", |ui| {
+                    Grid::new("target luminance").show(ui, |ui| {
+                        ui.label("Min");
+                        ui.label(min.to_string());
+                        ui.end_row();
+                        ui.label("Max");
+                        ui.label(max.to_string());
+                        ui.end_row();
+                    });
+                });
+            }
+        });
+        ui.vertical(|ui| {
+            draw_chromaticity_diagram(ui, ds, primaries);
+        });
+    });
+}
+
+/// Draws the present mode and the tearing hint. Tearing needs the immediate present
+/// mode or the hint.
+fn draw_presentation(ui: &mut Ui, test_pane: &TestPane, config: &mut ControlPaneConfig) {
+    let modes = test_pane.present_modes();
+    ComboBox::from_label("Present mode")
+        .selected_text(config.present_mode)
+        .show_ui(ui, |ui| {
+            for mode in modes {
+                ui.selectable_value(&mut config.present_mode, mode, mode);
+            }
+        })
+        .response
+        .on_hover_text("Modes that the surface does not support fall back to fifo");
+    let immediate = config.present_mode == vulkan::PresentMode::Immediate;
+    ui.add_enabled(
+        test_pane.has_tearing_control() && !immediate,
+        Checkbox::new(&mut config.allow_tearing, "Allow tearing"),
+    )
+    .on_hover_text("Asks the compositor to present frames without waiting for vblank")
+    .on_disabled_hover_text(match immediate {
+        true => "With the immediate present mode, the Vulkan driver sets the hint itself",
+        false => "The compositor does not support wp_tearing_control_v1",
+    });
+}
+
+/// Draws the metadata sent with wp_color_representation_v1. Only the values advertised
+/// by the compositor are offered.
+fn draw_color_representation(ui: &mut Ui, test_pane: &TestPane, config: &mut ControlPaneConfig) {
+    if !test_pane.has_color_representation() {
+        ui.label("The compositor does not support wp_color_representation_v1");
+        return;
+    }
+    let caps = &test_pane.caps;
+    let r = &mut config.representation;
+    let alpha_mode_text = match r.alpha_mode {
+        Some(mode) => mode.into(),
+        None => WidgetText::from("unset"),
+    };
+    ComboBox::from_label("Declared alpha mode")
+        .selected_text(alpha_mode_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut r.alpha_mode, None, "unset");
+            for &mode in caps.alpha_modes.iter().sorted() {
+                ui.selectable_value(&mut r.alpha_mode, Some(mode), mode);
+            }
+        })
+        .response
+        .on_hover_text(
+            "The alpha mode declared with wp_color_representation_v1. The content is \
+             still rendered as selected above. Straight content is declared as straight \
+             and premultiplied content as premultiplied electrical. Other combinations \
+             show how the compositor's interpretation changes the blend scene.",
+        );
+    let selected = match r.coefficients {
+        Some((c, range)) => coefficients_text(c, range),
+        None => "unset".to_string(),
+    };
+    ComboBox::from_label("Coefficients")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut r.coefficients, None, "unset");
+            for &(c, range) in caps.coefficients.iter().sorted() {
+                let value = Some((c, range));
+                let label =
+                    SelectableLabel::new(r.coefficients == value, coefficients_text(c, range));
+                let response = ui
+                    .add_enabled(
+                        c == WpColorRepresentationSurfaceV1Coefficients::IDENTITY,
+                        label,
+                    )
+                    .on_disabled_hover_text(
+                        "Requires a YCbCr buffer but the test pane renders RGB",
+                    );
+                if response.clicked() {
+                    r.coefficients = value;
+                }
+            }
+        })
+        .response
+        .on_hover_text(
+            "The matrix coefficients and range declared with wp_color_representation_v1. \
+             The content is always rendered with full range.",
+        );
+}
+
+fn draw_settings(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    let config = &mut ds.config;
+    Slider::new(&mut config.max_lumen, 0.0..=10000.0)
+        .prefix("Max lumen: ")
+        .logarithmic(true)
+        .smallest_positive(1.0)
+        .drag_value_speed(10.0)
+        .ui(ui);
+    Slider::new(&mut config.max_chroma, 0.0..=10.0)
+        .prefix("Max chroma: ")
+        .drag_value_speed(0.1)
+        .ui(ui);
+    Slider::new(&mut config.chromaticity_epsilon, 1e-7..=1e-1)
+        .prefix("Chromaticity epsilon: ")
+        .logarithmic(true)
+        .custom_formatter(|v, _| format!("{v:e}"))
+        .ui(ui)
+        .on_hover_text(concat!(
+            "The largest difference of chromaticity coordinates that counts as a match ",
+            "when comparing feedback with the attached description. The protocol ",
+            "transmits chromaticities with 6 decimal places.\n",
+            "\n",
+            "Luminances and the power of the transfer function have fixed tolerances.",
+        ));
+    ui.horizontal(|ui| {
+        ui.color_edit_button_srgb(&mut config.diagram_background);
+        ui.label("CIE diagram background");
+    });
+    if test_pane.has_transfer_queue() {
+        ComboBox::from_label("Upload queue")
+            .selected_text(config.upload_mode)
+            .show_ui(ui, |ui| {
+                for mode in UploadMode::variants() {
+                    ui.selectable_value(&mut config.upload_mode, mode, mode);
+                }
+            });
+    }
+    ComboBox::from_label("Alpha")
+        .selected_text(config.alpha_mode)
+        .show_ui(ui, |ui| {
+            for mode in AlphaMode::variants() {
+                ui.selectable_value(&mut config.alpha_mode, mode, mode);
+            }
+        })
+        .response
+        .on_hover_text(
+            "How the content encodes translucency and which interpretation the surface \
+             requests from the compositor. Premultiplied colors are added to the \
+             background while straight colors are multiplied by alpha first. The blend \
+             scene shows the expected result next to the compositor's.",
+        );
+    if config.alpha_mode == AlphaMode::Straight && test_pane.post_multiplied_alpha() == Some(false)
+    {
+        ui.colored_label(
+            Color32::ORANGE,
+            "The surface does not support post-multiplied composite alpha. The swapchain \
+             uses pre-multiplied composite alpha instead.",
+        );
+    }
+    draw_color_representation(ui, test_pane, config);
+    if let Some((min, max)) = test_pane.image_count_range() {
+        // The maximum is usually unlimited.
+        let max = max.unwrap_or(min + 6).max(min);
+        Slider::new(&mut config.image_count, min..=max)
+            .prefix("Swapchain images: ")
+            .ui(ui)
+            .on_hover_text("The minimum number of images requested when creating the swapchain");
+        config.image_count = config.image_count.clamp(min, max);
+    }
+    draw_extent(ui, test_pane);
+    draw_presentation(ui, test_pane, config);
+    draw_present_latency(ui, test_pane, config);
+    draw_memory_stats(ui, test_pane);
+    draw_uploaded_params(ui, test_pane);
+    draw_color_space(ui, test_pane, config);
+    ui.add_enabled(
+        config.color_space == SwapchainColorSpace::PassThrough,
+        Checkbox::new(
+            &mut config.color_management_surface,
+            "Attach color-management surface",
+        ),
+    )
+    .on_hover_text("Without it, the compositor treats the test pane as an untagged sRGB surface");
+    ui.checkbox(&mut config.enable_peak, "Clamp to peak luminance")
+        .on_hover_text("Simulates a display that clips at the given luminance");
+    if config.enable_peak {
+        Slider::new(&mut config.peak_lumen, 1.0..=10000.0)
+            .prefix("Peak: ")
+            .suffix(" lumen")
+            .logarithmic(true)
+            .ui(ui);
+    }
+    ui.checkbox(
+        &mut config.override_xyz_from_lms,
+        "Override XYZ from LMS matrix",
+    )
+    .on_hover_text("Replaces the matrix that converts the LMS space of Oklab to XYZ");
+    if config.override_xyz_from_lms {
+        Grid::new("xyz_from_lms").show(ui, |ui| {
+            for row in &mut config.xyz_from_lms {
+                for v in row {
+                    DragValue::new(v).speed(0.001).max_decimals(6).ui(ui);
+                }
+                ui.end_row();
+            }
+        });
+        if ColorMatrix::<Xyz, Lms>::from_3x3(config.xyz_from_lms)
+            .inverse()
+            .is_none()
+        {
+            ui.colored_label(
+                Color32::from_rgb(255, 128, 128),
+                "The matrix is not invertible. The last invertible matrix is used.",
+            );
+        }
+        if ui.button("Reset").clicked() {
+            config.xyz_from_lms = ColorMatrix::XYZ_FROM_LMS.to_3x3();
+        }
+    }
+    ui.checkbox(&mut config.enable_dither, "Dither")
+        .on_hover_text("Applies an 8x8 ordered dither to the encoded colors");
+    if config.enable_dither {
+        Slider::new(&mut config.dither_bits, 4..=12)
+            .prefix("Target depth: ")
+            .suffix(" bits")
+            .ui(ui)
+            .on_hover_text("The amplitude of the dither is one step at this bit depth");
+    }
+    ComboBox::from_label("Gamut mapping")
+        .selected_text(config.gamut_mapping)
+        .show_ui(ui, |ui| {
+            for mapping in GamutMapping::variants() {
+                ui.selectable_value(&mut config.gamut_mapping, mapping, mapping);
+            }
+        })
+        .response
+        .on_hover_text(
+            "How colors outside of the target primaries are rendered. Clipping leaves \
+             negative channels to the encoding and the compositor. Compression reduces \
+             the chroma at constant lightness and hue until the color is inside the gamut.",
+        );
+    ComboBox::from_label("Gradient space")
+        .selected_text(config.gradient_space)
+        .show_ui(ui, |ui| {
+            for space in GradientSpace::variants() {
+                ui.selectable_value(&mut config.gradient_space, space, space);
+            }
+        })
+        .response
+        .on_hover_text(
+            "The space in which the colors of gradients are interpolated. ICtCp \
+             interpolates PQ-encoded LMS, which is closer to perceptually uniform at high \
+             luminances.",
+        );
+    ComboBox::from_label("Output clamp")
+        .selected_text(config.output_clamp)
+        .show_ui(ui, |ui| {
+            for clamp in OutputClamp::variants() {
+                ui.selectable_value(&mut config.output_clamp, clamp, clamp);
+            }
+        })
+        .response
+        .on_hover_text(
+            "How the encoded values are clamped before they are passed to the compositor. \
+             scRGB uses negative values and values above 1 for colors outside of sRGB.",
+        );
+    draw_channel_adjustment(ui, &mut config.channel_adjustment);
+    if test_pane.has_viewporter() {
+        ui.checkbox(&mut config.enable_viewport, "Viewport");
+        if config.enable_viewport {
+            let size = |ui: &mut Ui, name: &str, (width, height): &mut (i32, i32)| {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    DragValue::new(width).range(2..=8192).suffix(" px").ui(ui);
+                    ui.label("x");
+                    DragValue::new(height).range(2..=8192).suffix(" px").ui(ui);
+                });
+            };
+            size(ui, "Source: ", &mut config.viewport_source);
+            ui.checkbox(
+                &mut config.enable_viewport_destination,
+                "Custom destination",
+            );
+            if config.enable_viewport_destination {
+                size(ui, "Destination: ", &mut config.viewport_destination);
+            }
+        }
+    }
+    ui.checkbox(&mut config.enable_damage, "Explicit damage")
+        .on_hover_text(concat!(
+            "Damages this region before every commit of the test pane. ",
+            "The presentation engine adds its own damage when a new frame is presented, ",
+            "so the region is most visible in commits without a new buffer.",
+        ));
+    if config.enable_damage {
+        ComboBox::from_label("Damage coordinates")
+            .selected_text(config.damage_coordinates)
+            .show_ui(ui, |ui| {
+                for c in DamageCoordinates::variants() {
+                    if c == DamageCoordinates::Buffer && !test_pane.supports_damage_buffer() {
+                        continue;
+                    }
+                    ui.selectable_value(&mut config.damage_coordinates, c, c);
+                }
+            });
+        let (x, y, width, height) = &mut config.damage_rect;
+        ui.horizontal(|ui| {
+            ui.label("Position: ");
+            DragValue::new(x).range(0..=8192).ui(ui);
+            ui.label(",");
+            DragValue::new(y).range(0..=8192).ui(ui);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Size: ");
+            DragValue::new(width).range(1..=8192).suffix(" px").ui(ui);
+            ui.label("x");
+            DragValue::new(height).range(1..=8192).suffix(" px").ui(ui);
+        });
+    }
+    if ui
+        .button("Recreate test pane")
+        .on_hover_text(concat!(
+            "Destroys the surfaces and swapchains of the test pane and creates them again. ",
+            "The current settings are applied to the new surfaces.",
+        ))
+        .clicked()
+    {
+        ds.recreate_test_pane = true;
+    }
+    ui.add_space(20.0);
+    ui.heading("Compositor capabilities");
+    let caps = &test_pane.caps;
+    Grid::new("capabilities")
+        .spacing([20.0, 10.0])
+        .show(ui, |ui| {
+            ui.label("Features:");
+            ui.vertical(|ui| {
+                for &feature in caps.features.iter().sorted() {
+                    ui.label(feature);
+                }
+            });
+            ui.end_row();
+            ui.label("Transfer functions:");
+            ui.vertical(|ui| {
+                for &tf in caps.tf.iter().sorted() {
+                    match NamedTransferFunction::variants().find(|n| n.wayland() == tf) {
+                        Some(n) => ui.label(n),
+                        None => ui.label(format!("unknown ({tf:?})")),
+                    };
+                }
+            });
+            ui.end_row();
+            ui.label("Primaries:");
+            ui.vertical(|ui| {
+                for &primaries in caps.primaries.iter().sorted() {
+                    match NamedPrimaries::variants().find(|n| n.wayland() == primaries) {
+                        Some(n) => ui.label(n),
+                        None => ui.label(format!("unknown ({primaries:?})")),
+                    };
+                }
+            });
+            ui.end_row();
+            if test_pane.has_color_representation() {
+                ui.label("Alpha modes:");
+                ui.vertical(|ui| {
+                    for &mode in caps.alpha_modes.iter().sorted() {
+                        ui.label(mode);
+                    }
+                });
+                ui.end_row();
+                ui.label("Coefficients:");
+                ui.vertical(|ui| {
+                    for &(c, range) in caps.coefficients.iter().sorted() {
+                        ui.label(coefficients_text(c, range));
+                    }
+                });
+                ui.end_row();
+            }
+        });
+}
+
+/// A slider for a color component. While the slider is focused, the left and right
+/// arrow keys nudge the value by `step` and by ten times `step` if shift is held.
+fn color_slider(
+    ui: &mut Ui,
+    value: &mut f32,
+    range: RangeInclusive<f32>,
+    prefix: &str,
+    speed: f64,
+    step: f32,
+) -> Response {
+    let prev = *value;
+    let mut response = Slider::new(value, range.clone())
+        .prefix(prefix)
+        .drag_value_speed(speed)
+        .ui(ui);
+    if response.has_focus() && !ui.ctx().wants_keyboard_input() {
+        let (presses, shift) = ui.input(|i| {
+            let presses =
+                i.num_presses(Key::ArrowRight) as f32 - i.num_presses(Key::ArrowLeft) as f32;
+            (presses, i.modifiers.shift)
+        });
+        if presses != 0.0 {
+            let step = match shift {
+                true => 10.0 * step,
+                false => step,
+            };
+            *value = (prev + presses * step).clamp(*range.start(), *range.end());
+            response.mark_changed();
+        }
+    }
+    response
+}
+
+const MIN_XYY_Y: f64 = 0.0001;
+
+/// Edits the color as CIE xyY. Black has no chromaticity and starts at the D65 white
+/// point. y = 0 does not determine a color and cannot be entered.
+fn draw_xyy_entry(ui: &mut Ui, c: &mut Color, max_lumen: f32) {
+    let (mut x, mut y, mut big_y) = c.to_xyy().unwrap_or((0.3127, 0.3290, 0.0));
+    let mut changed = false;
+    changed |= Slider::new(&mut x, 0.0..=1.0)
+        .prefix("x: ")
+        .drag_value_speed(0.001)
+        .max_decimals(4)
+        .ui(ui)
+        .changed();
+    changed |= Slider::new(&mut y, MIN_XYY_Y..=1.0)
+        .prefix("y: ")
+        .drag_value_speed(0.001)
+        .max_decimals(4)
+        .ui(ui)
+        .changed();
+    changed |= Slider::new(&mut big_y, 0.0..=max_lumen as f64)
+        .prefix("Y: ")
+        .suffix(" cd/m²")
+        .drag_value_speed(1.0)
+        .ui(ui)
+        .changed();
+    if !changed {
+        return;
+    }
+    if let Some(color) = Color::from_xyy(x, y.max(MIN_XYY_Y), big_y) {
+        *c = color;
+    }
+}
+
+fn draw_blend_delta(
+    ui: &mut Ui,
+    test_pane: &TestPane,
+    delta: &mut Option<Result<BlendDelta, String>>,
+) {
+    if ui.button("Measure blend difference").clicked() {
+        *delta = Some(test_pane.blend_delta());
+    }
+    ui.label(concat!(
+        "The output of the compositor cannot be read back. Instead, the bottom ",
+        "quadrants are read back and the blend of the client is compared with the ",
+        "sub-surface blended in linear light and in encoded values.\n",
+        "\n",
+        "Differences are in Oklab. A difference of 0.02 is about one just noticeable ",
+        "difference."
+    ));
+    let delta = match delta {
+        Some(Ok(d)) => d,
+        Some(Err(e)) => {
+            ui.colored_label(Color32::from_rgb(255, 128, 128), &*e);
+            return;
+        }
+        None => return,
+    };
+    Grid::new("blend delta").striped(true).show(ui, |ui| {
+        ui.label("");
+        ui.label("Max");
+        ui.label("Mean");
+        ui.end_row();
+        ui.label("Linear light");
+        ui.label(format!("{:.4}", delta.linear.max));
+        ui.label(format!("{:.4}", delta.linear.mean));
+        ui.end_row();
+        ui.label("Encoded values");
+        match delta.encoded {
+            Some(d) => {
+                ui.label(format!("{:.4}", d.max));
+                ui.label(format!("{:.4}", d.mean));
+            }
+            None => {
+                ui.label("different descriptions");
+            }
+        }
+        ui.end_row();
+    });
+}
+
+fn draw_scenes(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    let config = &mut ds.config;
+    ComboBox::from_label("Scene")
+        .selected_text(config.scene)
+        .show_ui(ui, |ui| {
+            for s in SelectedScene::variants() {
+                ui.selectable_value(&mut config.scene, s, s);
+            }
+        });
+    draw_scene_saves(ui, &mut ds.scene_saves, &mut ds.scene_save_name, config);
+    ui.add_space(20.0);
+    ui.horizontal(|ui| {
+        ui.label("Color entry:");
+        ui.selectable_value(&mut config.xyy_entry, false, "LCh");
+        ui.selectable_value(&mut config.xyy_entry, true, "CIE xyY");
+    });
+    let max_lumen = config.max_lumen;
+    let max_chroma = config.max_chroma;
+    let xyy_entry = config.xyy_entry;
+    let mut idx = 0;
+    let mut colors = |ui: &mut Ui, colors: &mut [(&str, &mut Color)]| {
+        let salt = format!("c{}", idx);
+        idx += 1;
+        Grid::new(salt).spacing([20.0, 20.0]).show(ui, |ui| {
+            for (name, c) in colors {
+                ui.label(*name);
+                ui.vertical(|ui| {
+                    if xyy_entry {
+                        draw_xyy_entry(ui, c, max_lumen);
+                    } else {
+                        color_slider(ui, &mut c.lumen, 0.0..=max_lumen, "Lumen: ", 1.0, 1.0);
+                        color_slider(ui, &mut c.lightness, 0.0..=1.0, "Lightness: ", 0.01, 0.001);
+                        color_slider(ui, &mut c.chroma, 0.0..=max_chroma, "Chroma: ", 0.01, 0.001);
+                        color_slider(ui, &mut c.hue, 0.0..=360.0, "Hue: ", 0.1, 0.1);
+                    }
+                    let trace = ui
+                        .small_button("Trace")
+                        .on_hover_text("Print the color pipeline as JSON and copy it");
+                    if trace.clicked() {
+                        let json = test_pane.trace_color(**c);
+                        println!("{json}");
+                        ui.ctx().copy_text(json);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+    };
+    ui.checkbox(&mut config.enable_clear, "Clear color");
+    if config.enable_clear {
+        colors(ui, &mut [("clear: ", &mut config.clear)]);
+    }
+    ui.checkbox(&mut config.enable_legend, "Legend")
+        .on_hover_text("Describes the scene and the color description in the top-left corner");
+    ui.checkbox(&mut config.srgb_reference, "sRGB reference")
+        .on_hover_text(concat!(
+            "Draws the right half on a sub-surface without a color description. ",
+            "Not available in scenes that use sub-surfaces.",
+        ));
+    ui.checkbox(&mut config.enable_overlay, "Grid overlay");
+    if config.enable_overlay {
+        Slider::new(&mut config.overlay_spacing, 2..=500)
+            .prefix("Spacing: ")
+            .suffix(" px")
+            .ui(ui);
+        colors(ui, &mut [("overlay: ", &mut config.overlay_color)]);
+    }
+    ui.add_space(20.0);
+    match config.scene {
+        SelectedScene::Fill => {
+            colors(ui, &mut [("color: ", &mut config.fill)]);
+        }
+        SelectedScene::LinearGradient => {
+            Slider::new(&mut config.gradient_angle, 0.0..=360.0)
+                .prefix("Angle: ")
+                .suffix("°")
+                .ui(ui)
+                .on_hover_text("Counterclockwise, 0° goes from left to right");
+            let [from, to] = &mut config.gradient;
+            ui.horizontal_top(|ui| {
+                colors(ui, &mut [("from: ", from)]);
+                colors(ui, &mut [("to: ", to)]);
+            });
+        }
+        SelectedScene::FillFour => {
+            let [top_right, top_left, bottom_right, bottom_left] = &mut config.four_corners;
+            ui.horizontal_top(|ui| {
+                colors(
+                    ui,
+                    &mut [("top left: ", top_left), ("bottom left: ", bottom_left)],
+                );
+                colors(
+                    ui,
+                    &mut [("top right: ", top_right), ("bottom right: ", bottom_right)],
+                );
+            });
+            ui.add_space(10.0);
+            let [top_right, top_left, bottom_right, bottom_left] = &mut config.four_corners_alpha;
+            let alpha = |ui: &mut Ui, name: &str, alpha: &mut f32| {
+                Slider::new(alpha, 0.0..=1.0).prefix(name).ui(ui);
+            };
+            Grid::new("four_corners_alpha").show(ui, |ui| {
+                alpha(ui, "Alpha top left: ", top_left);
+                alpha(ui, "Alpha top right: ", top_right);
+                ui.end_row();
+                alpha(ui, "Alpha bottom left: ", bottom_left);
+                alpha(ui, "Alpha bottom right: ", bottom_right);
+                ui.end_row();
+            });
+        }
+        SelectedScene::CenterBox => {
+            let [bg, fg] = &mut config.center_box;
+            Slider::new(&mut config.center_box_size, 0.0..=100.0)
+                .prefix("Size: ")
+                .drag_value_speed(1.0)
+                .ui(ui);
+            ui.horizontal_top(|ui| {
+                colors(ui, &mut [("background: ", bg)]);
+                colors(ui, &mut [("foreground: ", fg)]);
+            });
+        }
+        SelectedScene::Grid => {
+            let [bg, fg] = &mut config.grid;
+            ui.horizontal_top(|ui| {
+                Slider::new(&mut config.grid_rows, 1..=25)
+                    .prefix("Rows: ")
+                    .ui(ui);
+                Slider::new(&mut config.grid_cols, 1..=25)
+                    .prefix("Columns: ")
+                    .ui(ui);
+            });
+            ui.horizontal_top(|ui| {
+                colors(ui, &mut [("background: ", bg)]);
+                colors(ui, &mut [("foreground: ", fg)]);
+            });
+        }
+        SelectedScene::ColorGrid => {
+            let sweep = &mut config.color_grid;
+            ui.horizontal_top(|ui| {
+                Slider::new(&mut sweep.rows, 1..=25).prefix("Rows: ").ui(ui);
+                Slider::new(&mut sweep.cols, 1..=25)
+                    .prefix("Columns: ")
+                    .ui(ui);
+            });
+            Slider::new(&mut sweep.lumen, 0.0..=max_lumen)
+                .prefix("Lumen: ")
+                .drag_value_speed(1.0)
+                .ui(ui);
+            Slider::new(&mut sweep.chroma, 0.0..=max_chroma)
+                .prefix("Chroma: ")
+                .drag_value_speed(0.01)
+                .ui(ui);
+            Grid::new("color grid sweep").show(ui, |ui| {
+                ui.label("Hue (columns):");
+                DragValue::new(&mut sweep.hue.0)
+                    .range(0.0..=360.0)
+                    .prefix("from ")
+                    .ui(ui);
+                DragValue::new(&mut sweep.hue.1)
+                    .range(0.0..=720.0)
+                    .prefix("to ")
+                    .ui(ui);
+                ui.end_row();
+                ui.label("Lightness (rows):");
+                DragValue::new(&mut sweep.lightness.0)
+                    .range(0.0..=1.0)
+                    .speed(0.01)
+                    .prefix("from ")
+                    .ui(ui);
+                DragValue::new(&mut sweep.lightness.1)
+                    .range(0.0..=1.0)
+                    .speed(0.01)
+                    .prefix("to ")
+                    .ui(ui);
+                ui.end_row();
+            });
+        }
+        SelectedScene::Crosshair => {
+            ui.label(concat!(
+                "A one pixel wide line through the center of the surface and markers in ",
+                "the corners.\n",
+                "If a dimension is even, the center falls between two pixels and the ",
+                "line is two pixels wide.",
+            ));
+            ui.add_space(10.0);
+            let [bg, fg] = &mut config.crosshair;
+            ui.horizontal_top(|ui| {
+                colors(ui, &mut [("background: ", bg)]);
+                colors(ui, &mut [("lines: ", fg)]);
+            });
+        }
+        SelectedScene::Patch => {
+            ui.label(concat!(
+                "A square patch for a colorimeter over a surround at code value 0. Sizes ",
+                "and offsets are in buffer pixels.",
+            ));
+            ui.add_space(10.0);
+            Slider::new(&mut config.patch_size, 1..=2000)
+                .prefix("Size: ")
+                .suffix(" px")
+                .logarithmic(true)
+                .ui(ui);
+            let (dx, dy) = &mut config.patch_offset;
+            ui.horizontal(|ui| {
+                ui.label("Offset:");
+                DragValue::new(dx).prefix("x: ").suffix(" px").ui(ui);
+                DragValue::new(dy).prefix("y: ").suffix(" px").ui(ui);
+                if ui.small_button("Center").clicked() {
+                    *dx = 0;
+                    *dy = 0;
+                }
+            });
+            colors(ui, &mut [("patch: ", &mut config.patch)]);
+        }
+        SelectedScene::Blend => {
+            ui.label(concat!(
+                "Top left shows the background color.\n",
+                "Top right shows the foreground color.\n",
+                "Bottom left blends with a sub-surface.\n",
+                "Bottom right blends in the client.\n",
+                "\n",
+                "Bottom left and bottom right are not expect to be identical due to the ",
+                "different blend space."
+            ));
+            ui.add_space(10.0);
+            let [bg, fg] = &mut config.blend;
+            Slider::new(&mut config.blend_alpha, 0.0..=1.0)
+                .prefix("Alpha: ")
+                .ui(ui);
+            colors(ui, &mut [("background: ", bg), ("foreground: ", fg)]);
+            ui.add_space(10.0);
+            draw_blend_description(ui, test_pane, config);
+            ui.add_space(10.0);
+            draw_blend_delta(ui, test_pane, &mut ds.blend_delta);
+        }
+        SelectedScene::BlendLayers => {
+            ui.label(concat!(
+                "Top left shows the background color.\n",
+                "Top right shows the layers without alpha from bottom to top.\n",
+                "Bottom left stacks the layers as sub-surfaces.\n",
+                "Bottom right blends the layers in the client.",
+            ));
+            ui.add_space(10.0);
+            let layers = &mut config.blend_layers;
+            Slider::new(&mut layers.count, 1..=MAX_BLEND_LAYERS)
+                .prefix("Layers: ")
+                .ui(ui);
+            ComboBox::from_label("Stacking")
+                .selected_text(layers.stacking)
+                .show_ui(ui, |ui| {
+                    for s in LayerStacking::variants() {
+                        ui.selectable_value(&mut layers.stacking, s, s);
+                    }
+                })
+                .response
+                .on_hover_text("The requests that establish the order of the sub-surfaces");
+            let alpha = BLEND_LAYER_NAMES.iter().zip(&mut layers.alpha);
+            for (name, alpha) in alpha.take(layers.count) {
+                Slider::new(alpha, 0.0..=1.0)
+                    .prefix(format!("Alpha of {name}: "))
+                    .ui(ui);
+            }
+            let names = BLEND_LAYER_NAMES.map(|n| format!("{n}: "));
+            let mut list = vec![("background: ", &mut config.blend_layers_background)];
+            list.extend(names.iter().map(|n| n.as_str()).zip(&mut layers.colors));
+            list.truncate(1 + layers.count);
+            colors(ui, &mut list);
+            ui.add_space(10.0);
+            draw_blend_description(ui, test_pane, config);
+        }
+        SelectedScene::BlendBanding => {
+            ui.label(concat!(
+                "Top: a sub-surface with a gradient of the foreground alpha over the ",
+                "background.\n",
+                "Bottom: the same gradient blended in the client.\n",
+                "\n",
+                "If the compositor blends with 8 bits per channel, the top shows steps ",
+                "that are not present in the bottom.",
+            ));
+            ui.add_space(10.0);
+            let [from, to] = &mut config.blend_banding_alpha;
+            Slider::new(from, 0.0..=1.0).prefix("Alpha from: ").ui(ui);
+            Slider::new(to, 0.0..=1.0).prefix("Alpha to: ").ui(ui);
+            let [bg, fg] = &mut config.blend_banding;
+            colors(ui, &mut [("background: ", bg), ("foreground: ", fg)]);
+        }
+        SelectedScene::BlackDetail => {
+            ui.label(concat!(
+                "Patches at the given code values over a surround at code value 0.\n",
+                "The values are encoded directly and bypass the color pipeline.\n",
+                "\n",
+                "Each patch that can be told apart from the surround is preserved.",
+            ));
+            ui.add_space(10.0);
+            let steps = &mut config.black_detail;
+            Slider::new(&mut steps.bits, 8..=12)
+                .prefix("Bit depth: ")
+                .ui(ui);
+            let max = (1 << steps.bits) - 1;
+            Slider::new(&mut steps.start, 0..=max)
+                .prefix("First code value: ")
+                .ui(ui);
+            Slider::new(&mut steps.step, 1..=16).prefix("Step: ").ui(ui);
+            Slider::new(&mut steps.count, 1..=16)
+                .prefix("Patches: ")
+                .ui(ui);
+            let values = (0..steps.count).map(|i| steps.start + i * steps.step);
+            ui.label(format!("Code values: {}", values.format(", ")));
+        }
+        SelectedScene::Image => {
+            ui.label(concat!(
+                "A PNG or OpenEXR file scaled to fit the surface.\n",
+                "The color space of PNG files is taken from the cICP chunk. Files without ",
+                "it are assumed to be sRGB. PQ luminances are relative to a reference ",
+                "white of 203 cd/m².\n",
+                "OpenEXR files are linear with 1.0 at the reference white. The primaries ",
+                "are taken from the chromaticities attribute and default to sRGB.\n",
+                "\n",
+                "Drop a file on this window, browse, or enter its path.",
+            ));
+            ui.add_space(10.0);
+            let mut picked = ui
+                .ctx()
+                .input(|i| i.raw.dropped_files.first().and_then(|f| f.path.clone()));
+            let load = ui
+                .horizontal(|ui| {
+                    ui.label("Path:");
+                    ui.text_edit_singleline(&mut config.image_path);
+                    if ui.button("Browse").clicked() {
+                        ds.image_picker = match ds.image_picker {
+                            Some(_) => None,
+                            None => Some(FilePicker::new(
+                                Path::new(&config.image_path),
+                                &["png", "exr"],
+                            )),
+                        };
+                    }
+                    ui.button("Load").clicked()
+                })
+                .inner;
+            if let Some(picker) = &mut ds.image_picker {
+                if let Some(path) = picker.show(ui) {
+                    picked = Some(path);
+                    ds.image_picker = None;
+                }
+            }
+            if let Some(path) = &picked {
+                config.image_path = path.display().to_string();
+            }
+            if load || picked.is_some() {
+                ds.image = Some(load_image(test_pane, &config.image_path));
+            }
+            match &ds.image {
+                Some(Ok(desc)) => {
+                    ui.label(desc);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(Color32::from_rgb(255, 128, 128), e);
+                }
+                None => {}
+            }
+        }
+        SelectedScene::MovingBar => {
+            ui.label(concat!(
+                "A vertical bar that moves across the surface.\n",
+                "A frame is rendered for every frame callback.\n",
+                "\n",
+                "With the immediate present mode or tearing allowed, the bar is split ",
+                "horizontally where the compositor switched buffers during scanout.",
+            ));
+            ui.add_space(10.0);
+            Slider::new(&mut config.moving_bar_speed, 0.1..=10.0)
+                .prefix("Speed: ")
+                .suffix(" widths/s")
+                .logarithmic(true)
+                .ui(ui);
+            draw_presentation(ui, test_pane, config);
+            let [bg, fg] = &mut config.moving_bar;
+            colors(ui, &mut [("background: ", bg), ("bar: ", fg)]);
+        }
+        SelectedScene::CompareEotf => {
+            ui.label(concat!(
+                "The top half uses the transfer function of the color description.\n",
+                "The bottom half uses the reference transfer function.\n",
+                "\n",
+                "Both halves should be identical.",
+            ));
+            ui.add_space(10.0);
+            ComboBox::from_label("Reference")
+                .selected_text(config.compare_reference)
+                .show_ui(ui, |ui| {
+                    for tf in NamedTransferFunction::variants() {
+                        if test_pane.caps.tf.contains(&tf.wayland()) {
+                            ui.selectable_value(&mut config.compare_reference, tf, tf);
+                        }
+                    }
+                });
+            Slider::new(&mut config.compare_lumen, 0.0..=config.max_lumen)
+                .prefix("Lumen: ")
+                .drag_value_speed(1.0)
+                .ui(ui);
+            ComboBox::from_label("PQ luminance")
+                .selected_text(config.compare_pq_luminance)
+                .show_ui(ui, |ui| {
+                    for mode in PqLuminance::variants() {
+                        ui.selectable_value(&mut config.compare_pq_luminance, mode, mode);
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Whether PQ-encoded halves scale the ramp to the reference white of the \
+                     description or encode the lumen values as absolute luminance.",
+                );
+        }
+        SelectedScene::TransferFunctions => {
+            ui.label(concat!(
+                "Each row shows the same ramp from black to the selected luminance, linear ",
+                "in light, encoded by the shader with one of the transfer functions that ",
+                "the compositor supports.\n",
+                "\n",
+                "The encoded values are written unchanged, so the rows are best viewed with ",
+                "a linear description such as scRGB, which shows the shape of each ",
+                "transfer function.",
+            ));
+            ui.add_space(10.0);
+            Slider::new(&mut config.transfer_functions_lumen, 0.0..=config.max_lumen)
+                .prefix("Lumen: ")
+                .drag_value_speed(1.0)
+                .ui(ui);
+        }
+        SelectedScene::Primaries => {
+            ui.label("Shows the red, green, and blue primaries of the target color space.");
+            ui.add_space(10.0);
+            Slider::new(&mut config.primaries_white_lumen, 0.0..=config.max_lumen)
+                .prefix("White: ")
+                .suffix(" lumen")
+                .drag_value_speed(1.0)
+                .ui(ui)
+                .on_hover_text(concat!(
+                    "Each bar has the channel value of a white of this luminance. ",
+                    "The bar itself is darker.",
+                ));
+        }
+    }
+    ui.add_space(20.0);
+    draw_histogram(ui, test_pane, ds);
+}
+
+impl From<BinScale> for WidgetText {
+    fn from(val: BinScale) -> Self {
+        let txt = match val {
+            BinScale::Linear => "linear",
+            BinScale::Log => "logarithmic",
+        };
+        txt.into()
+    }
+}
+
+/// Shows the luminance histogram of the last frame of the test pane.
+fn draw_histogram(ui: &mut Ui, test_pane: &TestPane, ds: &mut DrawState) {
+    ui.horizontal(|ui| {
+        let capture = ui
+            .button("Capture histogram")
+            .on_hover_text("Reads back the last frame and counts the luminance of each pixel");
+        ComboBox::from_label("Bins")
+            .selected_text(ds.config.histogram_scale)
+            .show_ui(ui, |ui| {
+                for s in BinScale::variants() {
+                    ui.selectable_value(&mut ds.config.histogram_scale, s, s);
+                }
+            });
+        if capture.clicked() {
+            ds.histogram = Some(test_pane.luminance_histogram(ds.config.histogram_scale));
+        }
+    });
+    let histogram = match &ds.histogram {
+        Some(Ok(h)) => h,
+        Some(Err(e)) => {
+            ui.colored_label(Color32::from_rgb(255, 128, 128), e);
+            return;
+        }
+        None => return,
+    };
+    let (response, painter) =
+        ui.allocate_painter(vec2(ui.available_width(), 120.0), Sense::hover());
+    let text_color = ui.visuals().text_color();
+    let font = FontId::proportional(10.0);
+    let plot = Rect::from_min_max(
+        response.rect.min + vec2(5.0, 5.0),
+        response.rect.max - vec2(5.0, 15.0),
+    );
+    painter.rect_stroke(
+        plot,
+        0.0,
+        Stroke::new(1.0, Color32::GRAY),
+        StrokeKind::Inside,
+    );
+    let max_count = histogram.bins.iter().copied().max().unwrap_or(0).max(1);
+    let bin_width = plot.width() / BINS as f32;
+    for (i, count) in histogram.bins.iter().enumerate() {
+        let height = *count as f32 / max_count as f32 * plot.height();
+        let left = plot.left() + i as f32 * bin_width;
+        painter.rect_filled(
+            Rect::from_min_max(
+                pos2(left, plot.bottom() - height),
+                pos2(left + bin_width, plot.bottom()),
+            ),
+            0.0,
+            text_color,
+        );
+    }
+    let (min, max) = histogram.range;
+    painter.text(
+        plot.left_bottom() + vec2(0.0, 2.0),
+        Align2::LEFT_TOP,
+        format!("{min:.4} cd/m²"),
+        font.clone(),
+        text_color,
+    );
+    painter.text(
+        plot.right_bottom() + vec2(0.0, 2.0),
+        Align2::RIGHT_TOP,
+        format!("{max:.4} cd/m²"),
+        font,
+        text_color,
+    );
+    ui.label(format!(
+        "Luminance of the pixels: {:.4} to {:.4} cd/m²",
+        histogram.min, histogram.max,
+    ));
+}
+
+const BLEND_LAYER_NAMES: [&str; MAX_BLEND_LAYERS] = [
+    "layer 1", "layer 2", "layer 3", "layer 4", "layer 5", "layer 6", "layer 7", "layer 8",
+];
+
+/// Shows the requested size of the test pane next to the size of its swapchain.
+fn draw_extent(ui: &mut Ui, test_pane: &TestPane) {
+    let size = |(width, height): (u32, u32)| format!("{width} x {height}");
+    Grid::new("extent").show(ui, |ui| {
+        let ((width, height), scale) = test_pane.toplevel_size();
+        ui.label("Toplevel:");
+        ui.label(format!("{width} x {height} (scale {scale})"));
+        ui.end_row();
+        let Some(extent) = test_pane.surface_extent() else {
+            return;
+        };
+        ui.label("Swapchain:");
+        ui.label(
+            extent
+                .swapchain
+                .map(size)
+                .unwrap_or_else(|| "none".to_string()),
+        );
+        ui.end_row();
+        ui.label("Surface limits:");
+        ui.label(format!("{} to {}", size(extent.min), size(extent.max)));
+        ui.end_row();
+    });
+}
+
+/// Loads the image of the image scene and returns a description of it.
+fn load_image(test_pane: &TestPane, path: &str) -> Result<String, String> {
+    let image =
+        image_file::load(Path::new(path)).map_err(|e| format!("{path}: {}", error_chain(&e)))?;
+    test_pane.set_image(&image)?;
+    let source = match image.tagged {
+        true => "from the file",
+        false => "assumed",
+    };
+    Ok(format!(
+        "{} x {}, primaries: {}, transfer function: {} ({source})",
+        image.width,
+        image.height,
+        WidgetText::from(image.primaries).text(),
+        WidgetText::from(image.transfer_function).text(),
+    ))
+}
+
+/// Draws the gain and offset that are applied to the encoded channels.
+fn draw_channel_adjustment(ui: &mut Ui, adjustment: &mut ChannelAdjustment) {
+    ui.collapsing("Channel adjustment", |ui| {
+        ui.label(concat!(
+            "Applied to the encoded values as value * gain + offset before the output ",
+            "clamp, after all color conversions. Emulates a miscalibrated display.",
+        ));
+        Grid::new("channel adjustment").show(ui, |ui| {
+            ui.label("");
+            ui.label("Gain");
+            ui.label("Offset");
+            ui.end_row();
+            for (i, name) in ["R", "G", "B"].into_iter().enumerate() {
+                ui.label(name);
+                DragValue::new(&mut adjustment.gain[i])
+                    .speed(0.001)
+                    .max_decimals(4)
                     .ui(ui);
-                Slider::new(&mut config.luminance.white.0, min..=config.luminance.max.0)
-                    .prefix("White: ")
-                    .drag_value_speed(1.0)
+                DragValue::new(&mut adjustment.offset[i])
+                    .speed(0.001)
+                    .max_decimals(4)
                     .ui(ui);
+                ui.end_row();
             }
+        });
+        if ui.button("Reset").clicked() {
+            *adjustment = ChannelAdjustment::default();
         }
-    }
+    });
 }
 
-fn draw_feedback(ui: &mut Ui, ds: &mut DrawState) {
-    if let Some(err) = &ds.preferred_description_error_message {
-        ui.colored_label(Color32::from_rgb(255, 128, 128), err);
+fn draw_present_latency(ui: &mut Ui, test_pane: &TestPane, config: &mut ControlPaneConfig) {
+    ui.horizontal(|ui| {
+        ui.checkbox(
+            &mut config.measure_present_latency,
+            "Measure present latency",
+        )
+        .on_hover_text(concat!(
+            "Measures the time between presenting a frame and the signaling of its ",
+            "present fence. Fences are only checked when the next frame is rendered, ",
+            "so the value is only meaningful for animated scenes.",
+        ));
+        if !config.measure_present_latency {
+            return;
+        }
+        match test_pane.present_latency() {
+            Some((latency, samples)) => {
+                ui.label(format!(
+                    "{:.2} ms (average of {samples} frames)",
+                    latency.as_secs_f64() * 1000.0,
+                ));
+            }
+            None => {
+                ui.weak("no samples");
+            }
+        }
+    });
+}
+
+fn draw_memory_stats(ui: &mut Ui, test_pane: &TestPane) {
+    let Some((stats, pooled_fill_buffers)) = test_pane.memory_stats() else {
         return;
+    };
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "GPU memory: {:.1} KiB in {} allocations, {} pooled fill buffers",
+            stats.allocated as f64 / 1024.0,
+            stats.allocations,
+            pooled_fill_buffers,
+        ));
+        if ui
+            .button("Trim")
+            .on_hover_text("Drops excess fill buffers and returns unused memory to the driver")
+            .clicked()
+        {
+            test_pane.trim();
+        }
+    });
+}
+
+/// Draws the color space of the swapchain of the test pane.
+fn draw_color_space(ui: &mut Ui, test_pane: &TestPane, config: &mut ControlPaneConfig) {
+    let spaces = test_pane.color_spaces();
+    ComboBox::from_label("Swapchain color space")
+        .selected_text(config.color_space)
+        .show_ui(ui, |ui| {
+            for space in spaces {
+                ui.selectable_value(&mut config.color_space, space, space);
+            }
+        })
+        .response
+        .on_hover_text(concat!(
+            "With color spaces other than PASS_THROUGH_EXT, the Vulkan implementation ",
+            "describes the content to the compositor and the test pane renders for that ",
+            "description. The test pane then has no color-management surface of its own ",
+            "and sub-surfaces keep using PASS_THROUGH_EXT.\n",
+            "\n",
+            "Only color spaces that the surface supports with the ",
+            "R16G16B16A16_SFLOAT format are listed.",
+        ));
+    if let Some(description) = wsi_description(config.color_space) {
+        ui.weak(description.legend());
     }
-    let Some(data) = ds.preferred_description_data else {
+}
+
+/// Shows the values that the shader received for the last frame.
+fn draw_uploaded_params(ui: &mut Ui, test_pane: &TestPane) {
+    let Some(params) = test_pane.uploaded_params() else {
         return;
     };
-    let primaries = match data.primaries {
-        TestPrimaries::Named(p) => p.primaries(),
-        TestPrimaries::Custom(p) => p,
-    };
-    ui.horizontal_top(|ui| {
-        ui.vertical(|ui| {
-            ui.set_width(270.0);
-            ui.horizontal_top(|ui| {
-                ui.label("Primaries:");
-                if let TestPrimaries::Named(p) = data.primaries {
-                    ui.label(p);
-                };
-            });
-            ui.indent("primaries", |ui| {
-                Grid::new("primaries").show(ui, |ui| {
-                    for (name, cp) in [
-                        ("r", primaries.r),
-                        ("g", primaries.g),
-                        ("b", primaries.b),
-                        ("wp", primaries.wp),
-                    ] {
-                        let (x, y) = cp;
-                        ui.label(name);
-                        ui.label(x.0.to_string());
-                        ui.label(y.0.to_string());
-                        ui.end_row();
-                    }
-                });
-            });
-            ui.add_space(10.0);
-            ui.horizontal_top(|ui| {
-                ui.label("Transfer function:");
-                match data.tf {
-                    TransferFunction::Named(n) => {
-                        ui.label(n);
-                    }
-                    TransferFunction::Pow => {
-                        ui.label(format!("pow({})", data.tf_power));
-                    }
+    ui.collapsing("Shader parameters of the last frame", |ui| {
+        Grid::new("uploaded params").show(ui, |ui| {
+            ui.label("eotf:");
+            ui.monospace(params.eotf.to_string());
+            ui.end_row();
+            ui.label("eotf_args:");
+            ui.monospace(format!("{:?}", params.eotf_args));
+            ui.end_row();
+            ui.label("lms_to_local:");
+            ui.vertical(|ui| {
+                for row in &params.lms_to_local[..3] {
+                    ui.monospace(
+                        row.iter()
+                            .map(|v| format!("{v:>10.6}"))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    );
                 }
             });
-            ui.add_space(10.0);
-            if let Some(lum) = data.luminance {
-                ui.label("Luminance:");
-                ui.indent("luminance", |ui| {
-                    Grid::new("luminance").show(ui, |ui| {
-                        ui.label("Min");
-                        ui.label(lum.min.to_string());
-                        ui.end_row();
-                        ui.label("Max");
-                        ui.label(lum.max.to_string());
-                        ui.end_row();
-                        ui.label("White");
-                        ui.label(lum.white.to_string());
-                        ui.end_row();
-                    });
-                });
-            }
-        });
-        ui.vertical(|ui| {
-            draw_chromaticity_diagram(ui, ds, primaries);
+            ui.end_row();
         });
     });
 }
 
-fn draw_settings(ui: &mut Ui, ds: &mut DrawState) {
-    let config = &mut ds.config;
-    Slider::new(&mut config.max_lumen, 0.0..=10000.0)
-        .prefix("Max lumen: ")
-        .drag_value_speed(10.0)
-        .ui(ui);
-    Slider::new(&mut config.max_chroma, 0.0..=10.0)
-        .prefix("Max chroma: ")
-        .drag_value_speed(0.1)
-        .ui(ui);
+fn draw_blend_description(ui: &mut Ui, test_pane: &TestPane, config: &mut ControlPaneConfig) {
+    ui.checkbox(
+        &mut config.separate_blend_description,
+        "Separate sub-surface description",
+    )
+    .on_hover_text("The sub-surfaces use a different color description than the parent");
+    if config.separate_blend_description {
+        ui.push_id("blend description", |ui| {
+            draw_color_description_settings(
+                ui,
+                Some(&test_pane.caps),
+                &mut config.blend_description,
+            );
+        });
+    }
 }
 
-fn draw_scenes(ui: &mut Ui, ds: &mut DrawState) {
-    let config = &mut ds.config;
-    ComboBox::from_label("Scene")
-        .selected_text(config.scene)
-        .show_ui(ui, |ui| {
-            for s in SelectedScene::variants() {
-                ui.selectable_value(&mut config.scene, s, s);
+fn draw_scene_saves(
+    ui: &mut Ui,
+    saves: &mut SceneSaves,
+    save_name: &mut String,
+    config: &mut ControlPaneConfig,
+) {
+    if scene_colors(config).is_empty() {
+        return;
+    }
+    let scene = config.scene.name();
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(save_name);
+        let save = ui
+            .add_enabled(!save_name.trim().is_empty(), Button::new("Save colors"))
+            .on_hover_text("Saves the colors of this scene to disk under this name");
+        if save.clicked() {
+            let colors = scene_colors(config)
+                .into_iter()
+                .map(|(name, c)| (name.to_string(), *c))
+                .collect();
+            saves.insert(scene, save_name.trim(), colors);
+        }
+    });
+    let names = saves.names(scene);
+    if names.is_empty() {
+        return;
+    }
+    Grid::new("scene saves").show(ui, |ui| {
+        for name in names {
+            ui.label(&name);
+            if ui.small_button("Load").clicked() {
+                if let Some(saved) = saves.get(scene, &name) {
+                    for (color_name, c) in scene_colors(config) {
+                        if let Some((_, saved)) = saved.iter().find(|s| s.0 == color_name) {
+                            *c = *saved;
+                        }
+                    }
+                }
+                *save_name = name.clone();
             }
-        });
-    ui.add_space(20.0);
-    let max_lumen = config.max_lumen;
-    let max_chroma = config.max_chroma;
-    let mut idx = 0;
-    let mut colors = |ui: &mut Ui, colors: &mut [(&str, &mut Color)]| {
-        let salt = format!("c{}", idx);
-        idx += 1;
-        Grid::new(salt).spacing([20.0, 20.0]).show(ui, |ui| {
-            for (name, c) in colors {
-                ui.label(*name);
-                ui.vertical(|ui| {
-                    Slider::new(&mut c.lumen, 0.0..=max_lumen)
-                        .prefix("Lumen: ")
-                        .drag_value_speed(1.0)
-                        .ui(ui);
-                    Slider::new(&mut c.lightness, 0.0..=1.0)
-                        .prefix("Lightness: ")
-                        .drag_value_speed(0.01)
-                        .ui(ui);
-                    Slider::new(&mut c.chroma, 0.0..=max_chroma)
-                        .prefix("Chroma: ")
-                        .drag_value_speed(0.01)
-                        .ui(ui);
-                    Slider::new(&mut c.hue, 0.0..=360.0)
-                        .prefix("Hue: ")
-                        .drag_value_speed(0.1)
-                        .ui(ui);
-                });
-                ui.end_row();
+            if ui.small_button("Delete").clicked() {
+                saves.remove(scene, &name);
             }
-        });
-    };
-    match config.scene {
-        SelectedScene::Fill => {
-            colors(ui, &mut [("color: ", &mut config.fill)]);
-        }
-        SelectedScene::FillLeftRight => {
-            let [left, right] = &mut config.left_right;
-            ui.horizontal_top(|ui| {
-                colors(ui, &mut [("left: ", left)]);
-                colors(ui, &mut [("right: ", right)]);
-            });
+            ui.end_row();
         }
-        SelectedScene::FillTopBottom => {
-            let [top, bottom] = &mut config.top_bottom;
-            colors(ui, &mut [("top: ", top), ("bottom: ", bottom)]);
+    });
+}
+
+/// Returns the named colors of the selected scene.
+fn scene_colors(config: &mut ControlPaneConfig) -> Vec<(&'static str, &mut Color)> {
+    match config.scene {
+        SelectedScene::Fill => vec![("color", &mut config.fill)],
+        SelectedScene::LinearGradient => {
+            let [from, to] = &mut config.gradient;
+            vec![("from", from), ("to", to)]
         }
         SelectedScene::FillFour => {
             let [top_right, top_left, bottom_right, bottom_left] = &mut config.four_corners;
-            ui.horizontal_top(|ui| {
-                colors(
-                    ui,
-                    &mut [("top left: ", top_left), ("bottom left: ", bottom_left)],
-                );
-                colors(
-                    ui,
-                    &mut [("top right: ", top_right), ("bottom right: ", bottom_right)],
-                );
-            });
+            vec![
+                ("top left", top_left),
+                ("top right", top_right),
+                ("bottom left", bottom_left),
+                ("bottom right", bottom_right),
+            ]
         }
         SelectedScene::CenterBox => {
             let [bg, fg] = &mut config.center_box;
-            Slider::new(&mut config.center_box_size, 0.0..=100.0)
-                .prefix("Size: ")
-                .drag_value_speed(1.0)
-                .ui(ui);
-            ui.horizontal_top(|ui| {
-                colors(ui, &mut [("background: ", bg)]);
-                colors(ui, &mut [("foreground: ", fg)]);
-            });
+            vec![("background", bg), ("foreground", fg)]
         }
         SelectedScene::Grid => {
             let [bg, fg] = &mut config.grid;
-            ui.horizontal_top(|ui| {
-                Slider::new(&mut config.grid_rows, 1..=25)
-                    .prefix("Rows: ")
-                    .ui(ui);
-                Slider::new(&mut config.grid_cols, 1..=25)
-                    .prefix("Columns: ")
-                    .ui(ui);
-            });
-            ui.horizontal_top(|ui| {
-                colors(ui, &mut [("background: ", bg)]);
-                colors(ui, &mut [("foreground: ", fg)]);
-            });
+            vec![("background", bg), ("foreground", fg)]
+        }
+        SelectedScene::Crosshair => {
+            let [bg, fg] = &mut config.crosshair;
+            vec![("background", bg), ("lines", fg)]
+        }
+        SelectedScene::Patch => vec![("patch", &mut config.patch)],
+        SelectedScene::MovingBar => {
+            let [bg, fg] = &mut config.moving_bar;
+            vec![("background", bg), ("bar", fg)]
         }
         SelectedScene::Blend => {
-            ui.label(concat!(
-                "Top left shows the background color.\n",
-                "Top right shows the foreground color.\n",
-                "Bottom left blends with a sub-surface.\n",
-                "Bottom right blends in the client.\n",
-                "\n",
-                "Bottom left and bottom right are not expect to be identical due to the ",
-                "different blend space."
-            ));
-            ui.add_space(10.0);
             let [bg, fg] = &mut config.blend;
-            Slider::new(&mut config.blend_alpha, 0.0..=1.0)
-                .prefix("Alpha: ")
-                .ui(ui);
-            colors(ui, &mut [("background: ", bg), ("foreground: ", fg)]);
+            vec![("background", bg), ("foreground", fg)]
+        }
+        SelectedScene::BlendBanding => {
+            let [bg, fg] = &mut config.blend_banding;
+            vec![("background", bg), ("foreground", fg)]
         }
+        SelectedScene::BlendLayers => {
+            let layers = &mut config.blend_layers;
+            let mut colors = vec![("background", &mut config.blend_layers_background)];
+            colors.extend(BLEND_LAYER_NAMES.into_iter().zip(&mut layers.colors));
+            colors.truncate(1 + layers.count);
+            colors
+        }
+        SelectedScene::ColorGrid
+        | SelectedScene::Primaries
+        | SelectedScene::CompareEotf
+        | SelectedScene::BlackDetail
+        | SelectedScene::Image
+        | SelectedScene::TransferFunctions => vec![],
     }
 }
 
+fn draw_eyedropper_settings(ui: &mut Ui, config: &mut ControlPaneConfig) {
+    ui.horizontal(|ui| {
+        ui.toggle_value(&mut config.eyedropper, "Eyedropper")
+            .on_hover_text("Click the diagram to set the chromaticity of a scene color");
+        if !config.eyedropper {
+            return;
+        }
+        let mut slot = config.eyedropper_slot;
+        let names: Vec<_> = scene_colors(config).iter().map(|c| c.0).collect();
+        if names.is_empty() {
+            ui.label("The scene has no colors");
+            return;
+        }
+        slot = slot.min(names.len() - 1);
+        ComboBox::from_id_salt("eyedropper slot")
+            .selected_text(names[slot])
+            .show_ui(ui, |ui| {
+                for (idx, name) in names.iter().enumerate() {
+                    ui.selectable_value(&mut slot, idx, *name);
+                }
+            });
+        config.eyedropper_slot = slot;
+    });
+}
+
 fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primaries) {
+    draw_eyedropper_settings(ui, &mut ds.config);
+    let export = ui
+        .horizontal(|ui| {
+            ui.text_edit_singleline(&mut ds.config.diagram_export_path);
+            ui.button("Save PNG")
+                .on_hover_text("Saves the diagram as shown, at its size in physical pixels")
+                .clicked()
+        })
+        .inner;
+    match &ds.diagram_export {
+        Some(Ok(msg)) => {
+            ui.label(msg);
+        }
+        Some(Err(e)) => {
+            ui.colored_label(Color32::from_rgb(255, 128, 128), e);
+        }
+        None => {}
+    }
     let available = ui.available_size();
     let available = available.x.min(available.y).round();
     let size = (ui.pixels_per_point() * available).round() as u32;
@@ -920,22 +4007,6 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
             let horseshoe_view = horseshoe_tex.create_view(&TextureViewDescriptor {
                 ..Default::default()
             });
-            let mut encoder = ds
-                .renderer
-                .device
-                .create_command_encoder(&Default::default());
-            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &horseshoe_view,
-                    resolve_target: None,
-                    ops: Default::default(),
-                })],
-                ..Default::default()
-            });
-            pass.set_pipeline(&ds.horseshoe_pipeline);
-            pass.draw(0..4, 0..1);
-            drop(pass);
-            ds.renderer.queue.submit([encoder.finish()]);
             let tex = ds.renderer.device.create_texture(&TextureDescriptor {
                 label: None,
                 size: Extent3d {
@@ -949,7 +4020,8 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
                 format: TextureFormat::Rgba8UnormSrgb,
                 usage: TextureUsages::RENDER_ATTACHMENT
                     | TextureUsages::TEXTURE_BINDING
-                    | TextureUsages::COPY_DST,
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC,
                 view_formats: &[TextureFormat::Rgba8UnormSrgb],
             });
             let view = tex.create_view(&TextureViewDescriptor {
@@ -962,7 +4034,8 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
             );
             ds.cie_diagram.insert(CieDiagram {
                 horseshoe_tex,
-                _horseshoe_view: horseshoe_view,
+                horseshoe_view,
+                horseshoe_data: None,
                 tex,
                 view,
                 id: tex_id,
@@ -970,10 +4043,35 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
             })
         }
     };
+    let cie_view = ds.cie_view;
+    let background = ds.config.diagram_background;
+    let horseshoe_data = HorseshoeData {
+        view: cie_view,
+        background: [
+            background[0] as f32 / 255.0,
+            background[1] as f32 / 255.0,
+            background[2] as f32 / 255.0,
+            1.0,
+        ],
+    };
     let mut encoder = ds
         .renderer
         .device
         .create_command_encoder(&Default::default());
+    if cie.horseshoe_data != Some(horseshoe_data) {
+        cie.horseshoe_data = Some(horseshoe_data);
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &cie.horseshoe_view,
+                resolve_target: None,
+                ops: Default::default(),
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&ds.horseshoe_pipeline);
+        pass.set_push_constants(ShaderStages::FRAGMENT, 0, bytes_of(&horseshoe_data));
+        pass.draw(0..4, 0..1);
+    }
     encoder.copy_texture_to_texture(
         TexelCopyTextureInfo {
             texture: &cie.horseshoe_tex,
@@ -1012,6 +4110,8 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
         g: [f32; 2],
         b: [f32; 2],
         wp: [f32; 2],
+        view: CieView,
+        line: [f32; 4],
     }
     let map = |f: (F64, F64)| [f.0 .0 as f32, f.1 .0 as f32];
     let data = Data {
@@ -1019,13 +4119,115 @@ fn draw_chromaticity_diagram(ui: &mut Ui, ds: &mut DrawState, primaries: Primari
         g: map(primaries.g),
         b: map(primaries.b),
         wp: map(primaries.wp),
+        view: cie_view,
+        // Keep the triangle visible on dark backgrounds.
+        line: match background.iter().map(|&c| c as u32).sum::<u32>() < 3 * 128 {
+            true => [1.0; 4],
+            false => [0.0, 0.0, 0.0, 1.0],
+        },
     };
     pass.set_push_constants(ShaderStages::FRAGMENT, 0, bytes_of(&data));
     pass.draw(0..4, 0..1);
     drop(pass);
     ds.renderer.queue.submit([encoder.finish()]);
+    if export {
+        let path = &ds.config.diagram_export_path;
+        ds.diagram_export = Some(read_back_diagram(&ds.renderer, cie).and_then(|pixels| {
+            image_file::save_srgb(Path::new(path), size, size, &pixels)
+                .map(|()| format!("Saved {path}"))
+                .map_err(|e| format!("{path}: {}", error_chain(&e)))
+        }));
+    }
     let image = Image::from_texture((cie.id, vec2(available as _, available as _)));
-    image.ui(ui);
+    let response = image.sense(Sense::click_and_drag()).ui(ui);
+    if ds.config.eyedropper && response.clicked() {
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let view = &ds.cie_view;
+            let uv = (pointer - response.rect.min) / available;
+            let x = view.offset[0] + uv.x * view.scale;
+            let y = view.offset[1] + (1.0 - uv.y) * view.scale;
+            let xy = clamp_to_spectral_locus((F64(x as f64), F64(y as f64)));
+            let slot = ds.config.eyedropper_slot;
+            if let Some((_, c)) = scene_colors(&mut ds.config).into_iter().nth(slot) {
+                *c = Color::from_xy(xy, c.lumen);
+            }
+        }
+    }
+    let view = &mut ds.cie_view;
+    if response.double_clicked() {
+        *view = CieView::default();
+    }
+    let delta = response.drag_delta() / available * view.scale;
+    view.offset[0] -= delta.x;
+    view.offset[1] += delta.y;
+    if let Some(pointer) = response.hover_pos() {
+        let scroll = ui.input_mut(|i| mem::take(&mut i.smooth_scroll_delta.y));
+        if scroll != 0.0 {
+            // Keep the point under the cursor fixed.
+            let uv = (pointer - response.rect.min) / available;
+            let scale = (view.scale * (-scroll / 200.0).exp()).clamp(0.01, 2.0);
+            view.offset[0] += uv.x * (view.scale - scale);
+            view.offset[1] += (1.0 - uv.y) * (view.scale - scale);
+            view.scale = scale;
+        }
+    }
+}
+
+/// Reads the pixels of the CIE diagram back from the GPU. The texture stores
+/// sRGB-encoded values, so the bytes can be written as they are.
+fn read_back_diagram(renderer: &RenderState, cie: &CieDiagram) -> Result<Vec<u8>, String> {
+    let row = cie.size * 4;
+    let padded_row = row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = renderer.device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: padded_row as u64 * cie.size as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = renderer.device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+            texture: &cie.tex,
+            mip_level: 0,
+            origin: Default::default(),
+            aspect: Default::default(),
+        },
+        TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: cie.size,
+            height: cie.size,
+            depth_or_array_layers: 1,
+        },
+    );
+    let submission = renderer.queue.submit([encoder.finish()]);
+    let slice = buffer.slice(..);
+    let (send, recv) = mpsc::channel();
+    slice.map_async(MapMode::Read, move |res| {
+        let _ = send.send(res);
+    });
+    // The result only tells whether other submissions are still in flight. Whether
+    // the buffer was mapped is reported through the channel.
+    let _ = renderer.device.poll(Maintain::wait_for(submission));
+    match recv.try_recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return Err(format!("Could not map the read-back buffer: {e}")),
+        Err(_) => return Err("The read-back buffer was not mapped".to_string()),
+    }
+    let pixels = slice
+        .get_mapped_range()
+        .chunks_exact(padded_row as usize)
+        .flat_map(|r| &r[..row as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+    Ok(pixels)
 }
 
 fn init_wgpu(painter: &Painter, test_pane: &TestPane) -> DrawState {
@@ -1040,7 +4242,17 @@ fn init_wgpu(painter: &Painter, test_pane: &TestPane) -> DrawState {
         .device
         .create_render_pipeline(&RenderPipelineDescriptor {
             label: None,
-            layout: None,
+            layout: Some(
+                &renderer
+                    .device
+                    .create_pipeline_layout(&PipelineLayoutDescriptor {
+                        push_constant_ranges: &[PushConstantRange {
+                            stages: ShaderStages::FRAGMENT,
+                            range: 0..32,
+                        }],
+                        ..Default::default()
+                    }),
+            ),
             vertex: VertexState {
                 module: &horseshoe_module,
                 entry_point: None,
@@ -1083,7 +4295,7 @@ fn init_wgpu(painter: &Painter, test_pane: &TestPane) -> DrawState {
                     .create_pipeline_layout(&PipelineLayoutDescriptor {
                         push_constant_ranges: &[PushConstantRange {
                             stages: ShaderStages::FRAGMENT,
-                            range: 0..32,
+                            range: 0..64,
                         }],
                         ..Default::default()
                     }),
@@ -1121,19 +4333,48 @@ fn init_wgpu(painter: &Painter, test_pane: &TestPane) -> DrawState {
     let mut config = ControlPaneConfig::default();
     for tf in NamedTransferFunction::variants() {
         if test_pane.caps.tf.contains(&tf.wayland()) {
-            config.tf = TransferFunction::Named(tf);
+            config.description.tf = TransferFunction::Named(tf);
+            config.render_description.tf = TransferFunction::Named(tf);
             break;
         }
     }
+    let linear = NamedTransferFunction::Linear.wayland();
+    if let TransferFunction::Named(tf) = config.description.tf {
+        if test_pane.caps.tf.not_contains(&linear) {
+            config.compare_reference = tf;
+        }
+    }
     DrawState {
         renderer,
         max_size: limits.max_texture_dimension_2d,
         config,
         cie_diagram: None,
+        cie_view: Default::default(),
+        diagram_export: None,
         horseshoe_pipeline,
         triangle_pipeline,
         create_description_error_message: None,
         preferred_description_error_message: None,
         preferred_description_data: None,
+        preferred_identity: None,
+        feedback_snapshots: [None; 2],
+        match_preferred_dropped: vec![],
+        output_error_message: None,
+        render_error_message: None,
+        output_data: None,
+        toplevel_states: Default::default(),
+        outputs: vec![],
+        histogram: None,
+        image: None,
+        image_picker: None,
+        blend_delta: None,
+        scene_saves: Default::default(),
+        scene_save_name: Default::default(),
+        recreate_test_pane: false,
+        next_alternation: None,
+        stress: None,
+        invalid_luminance: None,
+        updating: false,
+        waiting_for_configure: false,
     }
 }
@@ -0,0 +1,102 @@
+use {
+    egui::{ScrollArea, Ui},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+    },
+};
+
+struct Entry {
+    name: String,
+    dir: bool,
+}
+
+/// Lists the directories and the files with one of the extensions in a directory.
+pub struct FilePicker {
+    dir: PathBuf,
+    extensions: &'static [&'static str],
+    entries: Result<Vec<Entry>, String>,
+}
+
+impl FilePicker {
+    /// Starts in the directory of `path` if it exists, otherwise in the home directory.
+    pub fn new(path: &Path, extensions: &'static [&'static str]) -> Self {
+        let dir = path
+            .parent()
+            .filter(|p| p.is_dir())
+            .map(|p| p.to_path_buf())
+            .or_else(|| env::var_os("HOME").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("/"));
+        let mut picker = Self {
+            dir,
+            extensions,
+            entries: Ok(vec![]),
+        };
+        picker.read_dir();
+        picker
+    }
+
+    fn read_dir(&mut self) {
+        let matches = |name: &str| {
+            let ext = Path::new(name).extension().unwrap_or_default();
+            self.extensions.iter().any(|e| ext.eq_ignore_ascii_case(e))
+        };
+        self.entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("{}: {e}", self.dir.display()))
+            .map(|entries| {
+                let mut entries: Vec<_> = entries
+                    .flatten()
+                    .map(|e| Entry {
+                        name: e.file_name().to_string_lossy().into_owned(),
+                        // Follows symlinks.
+                        dir: e.path().is_dir(),
+                    })
+                    .filter(|e| !e.name.starts_with('.') && (e.dir || matches(&e.name)))
+                    .collect();
+                entries.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+                entries
+            });
+    }
+
+    /// Draws the entries of the directory. Returns the file that was clicked, if any.
+    pub fn show(&mut self, ui: &mut Ui) -> Option<PathBuf> {
+        ui.label(self.dir.display().to_string());
+        let mut cd = None;
+        let mut selected = None;
+        ScrollArea::vertical()
+            .max_height(200.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                if let Some(parent) = self.dir.parent() {
+                    if ui.selectable_label(false, "..").clicked() {
+                        cd = Some(parent.to_path_buf());
+                    }
+                }
+                let entries = match &self.entries {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        ui.label(e);
+                        return;
+                    }
+                };
+                for entry in entries {
+                    let label = match entry.dir {
+                        true => format!("{}/", entry.name),
+                        false => entry.name.clone(),
+                    };
+                    if ui.selectable_label(false, label).clicked() {
+                        let path = self.dir.join(&entry.name);
+                        match entry.dir {
+                            true => cd = Some(path),
+                            false => selected = Some(path),
+                        }
+                    }
+                }
+            });
+        if let Some(dir) = cd {
+            self.dir = dir;
+            self.read_dir();
+        }
+        selected
+    }
+}
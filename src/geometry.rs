@@ -0,0 +1,99 @@
+use std::{cell::Cell, env, fs, io, path::PathBuf};
+
+/// The window sizes that are persisted across launches.
+///
+/// Wayland clients can neither observe nor choose the position of their windows, so only
+/// the sizes are persisted.
+#[derive(Default)]
+pub struct Geometry {
+    pub control_pane: Cell<Option<(f32, f32)>>,
+    pub test_pane: Cell<Option<(i32, i32)>>,
+}
+
+impl Geometry {
+    pub fn load() -> Self {
+        let slf = Self::default();
+        let Some(contents) = path().and_then(|p| fs::read_to_string(p).ok()) else {
+            return slf;
+        };
+        for line in contents.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(name), Some(width), Some(height)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            match name {
+                "control-pane" => {
+                    if let (Ok(w), Ok(h)) = (width.parse::<f32>(), height.parse::<f32>()) {
+                        let valid = |v: f32| v.is_finite() && v > 0.0;
+                        if valid(w) && valid(h) {
+                            slf.control_pane.set(Some((w, h)));
+                        }
+                    }
+                }
+                "test-pane" => {
+                    if let (Ok(w), Ok(h)) = (width.parse::<i32>(), height.parse::<i32>()) {
+                        if w > 0 && h > 0 {
+                            slf.test_pane.set(Some((w, h)));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        slf
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = self.try_save() {
+            log::warn!("could not save the window geometry: {e}");
+        }
+    }
+
+    fn try_save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        let mut contents = String::new();
+        if let Some((w, h)) = self.control_pane.get() {
+            contents.push_str(&format!("control-pane {w} {h}\n"));
+        }
+        if let Some((w, h)) = self.test_pane.get() {
+            contents.push_str(&format!("test-pane {w} {h}\n"));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    /// Shrinks the saved sizes so that the windows fit on an output of the given logical
+    /// size. This handles sizes that were saved while a larger output was connected.
+    pub fn clamp(&self, max_width: f64, max_height: f64) {
+        if let Some((w, h)) = self.control_pane.get() {
+            let w = w.min(max_width as f32);
+            let h = h.min(max_height as f32);
+            self.control_pane.set(Some((w, h)));
+        }
+        if let Some((w, h)) = self.test_pane.get() {
+            let w = w.min(max_width as i32);
+            let h = h.min(max_height as i32);
+            self.test_pane.set(Some((w, h)));
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    state_file("geometry")
+}
+
+/// Returns the path of the file with the given name in the state directory of the
+/// application.
+pub fn state_file(name: &str) -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(dir.join("wayland-color-test").join(name))
+}
@@ -0,0 +1,91 @@
+//! Luminance histograms of frames read back from the GPU.
+
+use {
+    crate::{cmm::ColorMatrix, test_pane::TestColorDescription, trace, vulkan::eotf_id},
+    linearize::Linearize,
+    std::collections::HashMap,
+};
+
+pub const BINS: usize = 64;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Linearize)]
+pub enum BinScale {
+    #[default]
+    Linear,
+    Log,
+}
+
+pub struct Histogram {
+    /// The luminances in cd/m² of the lower edge of the first bin and the upper edge of
+    /// the last bin.
+    pub range: (f64, f64),
+    pub bins: [u32; BINS],
+    /// The smallest and largest luminance of any pixel.
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Computes the histogram of the luminances of encoded pixels. The pixels are decoded
+/// as the compositor would with `description`. Values outside of the range of the
+/// description are counted in the first or last bin.
+pub fn compute(
+    pixels: &[[f32; 4]],
+    description: TestColorDescription,
+    scale: BinScale,
+) -> Histogram {
+    let params = description.render_params(ColorMatrix::XYZ_FROM_LMS);
+    let eotf = eotf_id(params.tf);
+    let (min_lum, max_lum) = description.code_value_range();
+    let (xyz_from_local, _) = description.primaries().matrices();
+    let y = xyz_from_local.0[1].map(|c| c.0);
+    let range = match scale {
+        BinScale::Linear => (min_lum, max_lum),
+        BinScale::Log => (min_lum.max(max_lum * 1e-5), max_lum),
+    };
+    let position = |lum: f64| match scale {
+        BinScale::Linear => (lum - range.0) / (range.1 - range.0),
+        BinScale::Log => (lum.max(range.0) / range.0).ln() / (range.1 / range.0).ln(),
+    };
+    let mut decoded = HashMap::new();
+    let mut decode_cached = |c: f32| {
+        *decoded
+            .entry(c.to_bits())
+            .or_insert_with(|| decode(eotf, &params.tf_args, c))
+    };
+    let mut histogram = Histogram {
+        range,
+        bins: [0; BINS],
+        min: f64::INFINITY,
+        max: f64::NEG_INFINITY,
+    };
+    for px in pixels {
+        let [r, g, b] = [px[0], px[1], px[2]].map(&mut decode_cached);
+        let lum = min_lum + (y[0] * r + y[1] * g + y[2] * b) * (max_lum - min_lum);
+        histogram.min = histogram.min.min(lum);
+        histogram.max = histogram.max.max(lum);
+        let bin = (position(lum) * BINS as f64).clamp(0.0, (BINS - 1) as f64);
+        histogram.bins[bin as usize] += 1;
+    }
+    histogram
+}
+
+/// Inverts `trace::inv_eotf` by bisection. Negative values are decoded as 0.
+pub fn decode(eotf: u32, args: &[f32; 4], c: f32) -> f64 {
+    let encode = |l: f64| trace::inv_eotf(eotf, args, l as f32);
+    if c <= encode(0.0) {
+        return 0.0;
+    }
+    let mut hi = 1.0;
+    while encode(hi) < c && hi < 1e6 {
+        hi *= 2.0;
+    }
+    let mut lo = 0.0;
+    for _ in 0..48 {
+        let mid = (lo + hi) / 2.0;
+        match encode(mid) < c {
+            true => lo = mid,
+            false => hi = mid,
+        }
+    }
+    (lo + hi) / 2.0
+}
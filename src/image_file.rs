@@ -0,0 +1,279 @@
+//! Decodes PNG and OpenEXR files for the image scene and encodes exported images.
+
+use {
+    crate::{
+        cmm::{ColorMatrix, NamedPrimaries, NamedTransferFunction},
+        ordered_float::F64,
+        vulkan::lms_to_lab,
+    },
+    exr::{
+        meta::{attribute::Chromaticities, MetaData},
+        prelude::read_first_rgba_layer_from_file,
+    },
+    linearize::LinearizeExt,
+    png::{BitDepth, ColorType, Decoder, Encoder, SrgbRenderingIntent, Transformations},
+    std::{
+        fs::File,
+        io::{self, BufReader, BufWriter},
+        path::Path,
+    },
+    thiserror::Error,
+};
+
+const MAX_PIXELS: u64 = 8192 * 8192;
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("could not open the file")]
+    Open(#[source] io::Error),
+    #[error("could not decode the PNG")]
+    Decode(#[source] png::DecodingError),
+    #[error("could not decode the OpenEXR file")]
+    DecodeExr(#[source] exr::error::Error),
+    #[error("could not create the file")]
+    Create(#[source] io::Error),
+    #[error("could not encode the PNG")]
+    Encode(#[source] png::EncodingError),
+    #[error("the image has {0} pixels but at most {MAX_PIXELS} are supported")]
+    TooLarge(u64),
+    #[error("the cICP color primaries {0} are not supported")]
+    UnsupportedPrimaries(u8),
+    #[error("the cICP transfer characteristics {0} are not supported")]
+    UnsupportedTransferFunction(u8),
+    #[error("the chromaticities do not match any named primaries")]
+    UnsupportedChromaticities,
+}
+
+pub struct ImageFile {
+    pub width: u32,
+    pub height: u32,
+    /// The pixels in Oklab with straight alpha, row by row. A luminance of 1 is the
+    /// reference white of 203 cd/m².
+    pub pixels: Vec<[f32; 4]>,
+    pub primaries: NamedPrimaries,
+    pub transfer_function: NamedTransferFunction,
+    /// Whether the color space is given by the file. Otherwise it is assumed to be sRGB
+    /// or, for OpenEXR, linear with sRGB primaries.
+    pub tagged: bool,
+}
+
+/// Loads a PNG or, if the extension is `exr`, an OpenEXR file.
+pub fn load(path: &Path) -> Result<ImageFile, ImageError> {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("exr") => load_exr(path),
+        _ => load_png(path),
+    }
+}
+
+/// Loads a PNG file with 8 or 16 bits per channel.
+///
+/// The color space is taken from the cICP chunk, which is how HDR content is stored
+/// in PNG files. Files without it are assumed to be sRGB. Absolute luminances of PQ
+/// are relative to the reference white of 203 cd/m².
+fn load_png(path: &Path) -> Result<ImageFile, ImageError> {
+    let file = File::open(path).map_err(ImageError::Open)?;
+    let mut decoder = Decoder::new(BufReader::new(file));
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(ImageError::Decode)?;
+    let info = reader.info();
+    let pixels = info.width as u64 * info.height as u64;
+    if pixels > MAX_PIXELS {
+        return Err(ImageError::TooLarge(pixels));
+    }
+    let mut primaries = NamedPrimaries::Srgb;
+    let mut transfer_function = NamedTransferFunction::Srgb;
+    let mut full_range = true;
+    let cicp = info.coding_independent_code_points;
+    if let Some(cicp) = cicp {
+        primaries = match cicp.color_primaries {
+            1 => NamedPrimaries::Srgb,
+            9 => NamedPrimaries::Bt2020,
+            11 => NamedPrimaries::DciP3,
+            12 => NamedPrimaries::DisplayP3,
+            n => return Err(ImageError::UnsupportedPrimaries(n)),
+        };
+        transfer_function = match cicp.transfer_function {
+            8 => NamedTransferFunction::Linear,
+            13 => NamedTransferFunction::Srgb,
+            16 => NamedTransferFunction::St2084Pq,
+            n => return Err(ImageError::UnsupportedTransferFunction(n)),
+        };
+        full_range = cicp.is_video_full_range_image;
+    }
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).map_err(ImageError::Decode)?;
+    let bytes = &buf[..frame.buffer_size()];
+    let samples: Vec<u16> = match frame.bit_depth {
+        BitDepth::Sixteen => bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect(),
+        _ => bytes.iter().map(|&b| b as u16).collect(),
+    };
+    let bits = match frame.bit_depth {
+        BitDepth::Sixteen => 16,
+        _ => 8,
+    };
+    let max = (1u32 << bits) - 1;
+    // Narrow range maps 16 to black and 235 to white at 8 bits.
+    let (black, white) = match full_range {
+        true => (0.0, max as f64),
+        false => ((16 << (bits - 8)) as f64, (235 << (bits - 8)) as f64),
+    };
+    let decoded: Vec<f64> = (0..=max)
+        .map(|v| eotf(transfer_function, (v as f64 - black) / (white - black)))
+        .collect();
+    let (xyz_from_local, _) = primaries.primaries().matrices();
+    let lms_from_local = ColorMatrix::XYZ_FROM_LMS.inverse().unwrap() * xyz_from_local;
+    let pixels = samples
+        .chunks_exact(frame.color_type.samples())
+        .map(|px| {
+            let (rgb, alpha) = match *px {
+                [l] => ([l; 3], max as u16),
+                [l, a] => ([l; 3], a),
+                [r, g, b] => ([r, g, b], max as u16),
+                [r, g, b, a, ..] => ([r, g, b], a),
+                [] => unreachable!(),
+            };
+            let mut lab = lms_to_lab(lms_from_local * rgb.map(|c| decoded[c as usize]));
+            lab[3] = alpha as f32 / max as f32;
+            lab
+        })
+        .collect();
+    Ok(ImageFile {
+        width: frame.width,
+        height: frame.height,
+        pixels,
+        primaries,
+        transfer_function,
+        tagged: cicp.is_some(),
+    })
+}
+
+/// Loads the RGB(A) channels of the first layer of an OpenEXR file.
+///
+/// The values are linear and 1 is the reference white of 203 cd/m². The primaries are
+/// taken from the chromaticities attribute and default to sRGB. Alpha is stored
+/// premultiplied in OpenEXR files.
+fn load_exr(path: &Path) -> Result<ImageFile, ImageError> {
+    let meta = MetaData::read_from_file(path, false).map_err(ImageError::DecodeExr)?;
+    if let Some(header) = meta.headers.first() {
+        let pixels = header.layer_size.area() as u64;
+        if pixels > MAX_PIXELS {
+            return Err(ImageError::TooLarge(pixels));
+        }
+    }
+    let image = read_first_rgba_layer_from_file(
+        path,
+        |size, _| (size.width(), vec![[0.0; 4]; size.area()]),
+        |(width, pixels): &mut (usize, Vec<[f32; 4]>), pos, (r, g, b, a): (f32, f32, f32, f32)| {
+            pixels[pos.y() * *width + pos.x()] = [r, g, b, a];
+        },
+    )
+    .map_err(ImageError::DecodeExr)?;
+    let chromaticities = image.attributes.chromaticities;
+    let primaries = match chromaticities {
+        Some(c) => named_primaries(c).ok_or(ImageError::UnsupportedChromaticities)?,
+        None => NamedPrimaries::Srgb,
+    };
+    let (xyz_from_local, _) = primaries.primaries().matrices();
+    let lms_from_local = ColorMatrix::XYZ_FROM_LMS.inverse().unwrap() * xyz_from_local;
+    let layer = image.layer_data;
+    let pixels = layer
+        .channel_data
+        .pixels
+        .1
+        .into_iter()
+        .map(|[r, g, b, a]| {
+            let straight = |c: f32| match a > 0.0 {
+                true => c as f64 / a as f64,
+                false => 0.0,
+            };
+            let mut lab = lms_to_lab(lms_from_local * [r, g, b].map(straight));
+            lab[3] = a.clamp(0.0, 1.0);
+            lab
+        })
+        .collect();
+    Ok(ImageFile {
+        width: layer.size.width() as u32,
+        height: layer.size.height() as u32,
+        pixels,
+        primaries,
+        transfer_function: NamedTransferFunction::Linear,
+        tagged: chromaticities.is_some(),
+    })
+}
+
+/// Returns the named primaries whose chromaticities match `c`.
+fn named_primaries(c: Chromaticities) -> Option<NamedPrimaries> {
+    let close = |(x, y): (F64, F64), v: exr::math::Vec2<f32>| {
+        (x.0 - v.0 as f64).abs() < 1e-3 && (y.0 - v.1 as f64).abs() < 1e-3
+    };
+    NamedPrimaries::variants().find(|n| {
+        let p = n.primaries();
+        close(p.r, c.red) && close(p.g, c.green) && close(p.b, c.blue) && close(p.wp, c.white)
+    })
+}
+
+/// Decodes an encoded value. Only the transfer functions supported by `load_png` are
+/// handled.
+fn eotf(tf: NamedTransferFunction, c: f64) -> f64 {
+    match tf {
+        NamedTransferFunction::Srgb => {
+            let a = c.abs();
+            let l = match a <= 0.04045 {
+                true => a / 12.92,
+                false => ((a + 0.055) / 1.055).powf(2.4),
+            };
+            l.copysign(c)
+        }
+        NamedTransferFunction::St2084Pq => {
+            let p = c.clamp(0.0, 1.0).powf(1.0 / 78.84375);
+            let num = (p - 0.8359375).max(0.0);
+            let den = 18.8515625 - 18.6875 * p;
+            10000.0 / 203.0 * (num / den).powf(1.0 / 0.1593017578125)
+        }
+        _ => c,
+    }
+}
+
+/// Saves 8-bit RGBA pixels that are already encoded with the sRGB transfer function,
+/// row by row, as an sRGB-tagged PNG.
+pub fn save_srgb(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), ImageError> {
+    let file = File::create(path).map_err(ImageError::Create)?;
+    let mut encoder = Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_source_srgb(SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().map_err(ImageError::Encode)?;
+    writer
+        .write_image_data(pixels)
+        .map_err(ImageError::Encode)?;
+    writer.finish().map_err(ImageError::Encode)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, exr::prelude::write_rgba_file, std::fs};
+
+    #[test]
+    fn load_exr_file() {
+        let path = std::env::temp_dir().join(format!("image-file-{}.exr", std::process::id()));
+        write_rgba_file(&path, 2, 1, |x, _| match x {
+            0 => (1.0f32, 1.0f32, 1.0f32, 1.0f32),
+            _ => (0.25, 0.25, 0.25, 0.5),
+        })
+        .unwrap();
+        let image = load(&path);
+        fs::remove_file(&path).unwrap();
+        let image = image.unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert!(!image.tagged);
+        assert_eq!(image.transfer_function, NamedTransferFunction::Linear);
+        let [white, gray] = [image.pixels[0], image.pixels[1]];
+        assert!((white[0] - 1.0).abs() < 1e-2, "{white:?}");
+        assert_eq!(gray[3], 0.5);
+        // The premultiplied gray is 0.5 after dividing by alpha.
+        assert!((gray[0] - 0.5f32.cbrt()).abs() < 1e-2, "{gray:?}");
+    }
+}
@@ -1,65 +1,182 @@
 use {
-    crate::{control_pane::ControlPane, test_pane::TestPane},
+    crate::{
+        control_pane::ControlPane,
+        geometry::Geometry,
+        test_pane::{TestConfig, TestPane},
+    },
     async_io::{Async, Timer},
     egui_winit::winit::{
         application::ApplicationHandler,
+        dpi::LogicalSize,
         event::WindowEvent,
         event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
         platform::pump_events::EventLoopExtPumpEvents,
         window::WindowId,
     },
     futures_util::{select, FutureExt},
-    std::{future::pending, os::fd::AsFd, time::Duration},
+    std::{future::pending, mem, os::fd::AsFd, rc::Rc, time::Duration},
 };
 
+mod bench;
+mod blend_delta;
 mod cmm;
 mod control_pane;
+mod file_picker;
+mod font;
+mod geometry;
+mod histogram;
+mod image_file;
 mod ordered_float;
 mod protocols;
+mod render_hash;
+mod scene_saves;
+mod selftest;
 mod singletons;
+mod stress;
 mod test_pane;
+mod trace;
 mod vulkan;
 
 struct WinitApp {
+    geometry: Rc<Geometry>,
     test_pane: TestPane,
     control_pane: Option<ControlPane>,
+    /// If set, no control pane is created.
+    no_control_pane: bool,
 }
 
 fn main() {
+    // Vulkan validation messages are logged as warnings and errors.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--vk-validation") {
+        vulkan::enable_validation();
+    }
+    if args.iter().any(|a| a == "--selftest") {
+        std::process::exit(selftest::run());
+    }
+    if args.iter().any(|a| a == "--render-hash") {
+        std::process::exit(render_hash::run());
+    }
+    if let Some(idx) = args.iter().position(|a| a == "--bench") {
+        let frames = args
+            .get(idx + 1)
+            .and_then(|a| a.parse().ok())
+            .unwrap_or(bench::DEFAULT_FRAMES);
+        std::process::exit(bench::run(frames));
+    }
+    let cli_config = match args.iter().any(|a| a == "--no-control-pane") {
+        true => match control_pane::cli_test_config(&args) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        },
+        false => None,
+    };
     async_io::block_on(async {
-        async_main().await;
+        async_main(cli_config).await;
     });
 }
 
-async fn async_main() {
+/// Forwards the state of the test pane to the control pane.
+fn update_control_pane(control_pane: &mut ControlPane, test_pane: &TestPane) {
+    if let Some(error_message) = test_pane.create_description_error_message() {
+        control_pane
+            .draw_state
+            .set_create_description_error_message(error_message);
+        control_pane.need_repaint = true;
+    }
+    if let Some(error_message) = test_pane.preferred_description_error_message() {
+        control_pane.draw_state.preferred_description_error_message = error_message;
+        control_pane.need_repaint = true;
+    }
+    if let Some(data) = test_pane.preferred_description_data() {
+        control_pane.draw_state.set_preferred_description_data(data);
+        control_pane.need_repaint = true;
+    }
+    if let Some(states) = test_pane.toplevel_states() {
+        control_pane.draw_state.set_toplevel_states(states);
+        control_pane.need_repaint = true;
+    }
+    if let Some(identity) = test_pane.preferred_identity() {
+        control_pane.draw_state.preferred_identity = Some(identity);
+        control_pane.need_repaint = true;
+    }
+    if let Some(error_message) = test_pane.output_error_message() {
+        control_pane.draw_state.output_error_message = error_message;
+        control_pane.need_repaint = true;
+    }
+    if let Some(data) = test_pane.output_data() {
+        control_pane.draw_state.output_data = Some(data);
+        control_pane.need_repaint = true;
+    }
+    if let Some(error_message) = test_pane.render_error_message() {
+        control_pane.draw_state.render_error_message = error_message;
+        control_pane.need_repaint = true;
+    }
+    if let Some(outputs) = test_pane.output_list() {
+        control_pane.draw_state.set_outputs(outputs);
+        control_pane.need_repaint = true;
+    }
+    let waiting = test_pane.waiting_for_configure();
+    if control_pane.draw_state.waiting_for_configure != waiting {
+        control_pane.draw_state.waiting_for_configure = waiting;
+        control_pane.need_repaint = true;
+    }
+    let updating = test_pane.is_updating();
+    if control_pane.draw_state.updating != updating {
+        control_pane.draw_state.updating = updating;
+        control_pane.need_repaint = true;
+    }
+    if control_pane.need_repaint {
+        control_pane.maybe_run(test_pane);
+    }
+}
+
+/// If `cli_config` is set, the test pane is configured once and no control pane is
+/// created.
+async fn async_main(cli_config: Option<TestConfig>) {
     let mut event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
+    let geometry = Rc::new(Geometry::load());
     let mut app = WinitApp {
-        test_pane: TestPane::new(&event_loop).await,
+        test_pane: TestPane::new(&event_loop, geometry.clone()).await,
+        geometry,
         control_pane: None,
+        no_control_pane: cli_config.is_some(),
     };
+    if let Some(config) = &cli_config {
+        app.test_pane.apply_config(config);
+    }
     let fd = event_loop.as_fd().try_clone_to_owned().unwrap();
     let fd = Async::new_nonblocking(fd).unwrap();
     loop {
         event_loop.pump_app_events(Some(Duration::ZERO), &mut app);
         app.test_pane.dispatch();
-        let control_pane = app.control_pane.as_mut().unwrap();
-        if let Some(error_message) = app.test_pane.create_description_error_message() {
-            control_pane.draw_state.create_description_error_message = error_message;
-            control_pane.need_repaint = true;
-        }
-        if let Some(error_message) = app.test_pane.preferred_description_error_message() {
-            control_pane.draw_state.preferred_description_error_message = error_message;
-            control_pane.need_repaint = true;
+        if let Some(control_pane) = &mut app.control_pane {
+            update_control_pane(control_pane, &app.test_pane);
         }
-        if let Some(data) = app.test_pane.preferred_description_data() {
-            control_pane.draw_state.preferred_description_data = Some(data);
-            control_pane.need_repaint = true;
-        }
-        if control_pane.need_repaint {
-            control_pane.maybe_run(&app.test_pane);
+        let recreate = app
+            .control_pane
+            .as_mut()
+            .is_some_and(|c| mem::take(&mut c.draw_state.recreate_test_pane));
+        if recreate {
+            app.test_pane = TestPane::new(&event_loop, app.geometry.clone()).await;
+            if let Some(control_pane) = &mut app.control_pane {
+                // The new test pane starts from the defaults. Running the control pane
+                // applies the current settings to it.
+                control_pane.draw_state.test_pane_recreated(&app.test_pane);
+                control_pane.need_repaint = true;
+                control_pane.maybe_run(&app.test_pane);
+            }
         }
-        let timer = control_pane.repaint_after.map(Timer::at);
+        let timer = app
+            .control_pane
+            .as_ref()
+            .and_then(|c| c.repaint_after)
+            .map(Timer::at);
         let fut = async move {
             if let Some(timer) = timer {
                 timer.await;
@@ -67,15 +184,45 @@ async fn async_main() {
                 pending().await
             }
         };
+        let trim = app.test_pane.trim_deadline().map(Timer::at);
+        let trim = async move {
+            if let Some(timer) = trim {
+                timer.await;
+            } else {
+                pending().await
+            }
+        };
+        let configure = app.test_pane.configure_deadline().map(Timer::at);
+        let configure = async move {
+            if let Some(timer) = configure {
+                timer.await;
+            } else {
+                pending().await
+            }
+        };
         select! {
             _ = app.test_pane.wait_for_events().fuse() => { },
+            _ = trim.fuse() => {
+                app.test_pane.trim();
+                if let Some(control_pane) = &mut app.control_pane {
+                    // The memory statistics have changed.
+                    control_pane.need_repaint = true;
+                    control_pane.maybe_run(&app.test_pane);
+                }
+            },
+            _ = configure.fuse() => {
+                // The state is forwarded to the control pane in the next iteration.
+                app.test_pane.configure_timeout();
+            },
             res = fd.readable().fuse() => {
                 res.unwrap();
             },
             _ = fut.fuse() => {
-                control_pane.repaint_after = None;
-                control_pane.need_repaint = true;
-                control_pane.maybe_run(&app.test_pane);
+                if let Some(control_pane) = &mut app.control_pane {
+                    control_pane.repaint_after = None;
+                    control_pane.need_repaint = true;
+                    control_pane.maybe_run(&app.test_pane);
+                }
             }
         }
     }
@@ -83,8 +230,19 @@ async fn async_main() {
 
 impl ApplicationHandler for WinitApp {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.control_pane.is_none() {
-            self.control_pane = Some(ControlPane::new(event_loop, &self.test_pane));
+        if self.control_pane.is_none() && !self.no_control_pane {
+            let max_size = event_loop
+                .available_monitors()
+                .map(|m| m.size().to_logical::<f64>(m.scale_factor()))
+                .reduce(|a, b| LogicalSize::new(a.width.max(b.width), a.height.max(b.height)));
+            if let Some(size) = max_size {
+                self.geometry.clamp(size.width, size.height);
+            }
+            self.control_pane = Some(ControlPane::new(
+                event_loop,
+                &self.test_pane,
+                self.geometry.clone(),
+            ));
         }
     }
 
@@ -94,9 +252,8 @@ impl ApplicationHandler for WinitApp {
         window_id: WindowId,
         event: WindowEvent,
     ) {
-        self.control_pane
-            .as_mut()
-            .unwrap()
-            .handle_event(window_id, event, &self.test_pane);
+        if let Some(control_pane) = &mut self.control_pane {
+            control_pane.handle_event(window_id, event, &self.test_pane);
+        }
     }
 }
@@ -0,0 +1,91 @@
+//! Renders a fixed scene into an offscreen image and hashes the pixels. The hash
+//! changes whenever the output of the shader or the color pipeline changes.
+
+use {
+    crate::{
+        cmm::ColorMatrix,
+        test_pane::{Color, TestColorDescription},
+        vulkan::{self, Legend, Overlay, Scene, VulkanDevice},
+    },
+    std::rc::Rc,
+};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+/// Renders the scene, prints the hash, and returns the exit code of the process.
+pub fn run() -> i32 {
+    let res = VulkanDevice::create().and_then(|device| render_hash(&device));
+    match res {
+        Ok(hash) => {
+            println!("{hash:016x}");
+            0
+        }
+        Err(e) => {
+            eprintln!("Could not render the scene: {e}");
+            1
+        }
+    }
+}
+
+/// Returns the FNV-1a hash of the pixels of the scene.
+pub fn render_hash(device: &Rc<VulkanDevice>) -> Result<u64, vulkan::Error> {
+    let params = TestColorDescription::None.render_params(ColorMatrix::XYZ_FROM_LMS);
+    let color = |lumen, lightness, chroma, hue| {
+        Color {
+            lumen,
+            lightness,
+            chroma,
+            hue,
+        }
+        .to_lab()
+    };
+    let scene = Scene::LinearGradient(
+        [color(203.0, 0.2, 0.1, 30.0), color(203.0, 0.9, 0.15, 250.0)],
+        30.0,
+    );
+    let overlay = Overlay {
+        grid: Some(vulkan::Grid {
+            spacing: 16,
+            color: color(203.0, 0.5, 0.0, 0.0),
+        }),
+        legend: Some(Legend {
+            text: "Hash".to_string(),
+            scale: 1,
+            color: color(203.0, 1.0, 0.0, 0.0),
+            background: color(203.0, 0.0, 0.0, 0.0),
+        }),
+    };
+    let pixels = device.render_offscreen(WIDTH, HEIGHT, scene, None, &overlay, &params)?;
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in pixels.iter().flatten().flat_map(|c| c.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/render_hash.golden");
+
+    /// Compares the hash with the value stored in `render_hash.golden`. After intended
+    /// changes to the rendering, run the test with `UPDATE_RENDER_HASH=1` to store the
+    /// current hash.
+    #[test]
+    #[ignore = "requires a vulkan device"]
+    fn golden() {
+        let device = VulkanDevice::create().unwrap();
+        let hash = format!("{:016x}", render_hash(&device).unwrap());
+        if std::env::var_os("UPDATE_RENDER_HASH").is_some() {
+            fs::write(GOLDEN, format!("{hash}\n")).unwrap();
+            return;
+        }
+        let golden = fs::read_to_string(GOLDEN).unwrap_or_else(|e| {
+            panic!("could not read {GOLDEN}: {e}. Run with UPDATE_RENDER_HASH=1")
+        });
+        assert_eq!(hash, golden.trim());
+    }
+}
@@ -0,0 +1,160 @@
+use {
+    crate::{geometry::state_file, test_pane::Color},
+    std::{collections::BTreeMap, fs, io, path::PathBuf},
+};
+
+/// The colors of a single save, keyed by the name of the color within the scene.
+pub type SavedColors = Vec<(String, Color)>;
+
+/// Named snapshots of the colors of individual scenes that are persisted across launches.
+///
+/// The file is only read when the saves are first accessed.
+#[derive(Default)]
+pub struct SceneSaves {
+    saves: Option<BTreeMap<String, BTreeMap<String, SavedColors>>>,
+}
+
+impl SceneSaves {
+    fn saves(&mut self) -> &mut BTreeMap<String, BTreeMap<String, SavedColors>> {
+        self.saves.get_or_insert_with(|| {
+            path()
+                .and_then(|p| fs::read_to_string(p).ok())
+                .map(|c| parse(&c))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the names of the saves of the scene.
+    pub fn names(&mut self, scene: &str) -> Vec<String> {
+        match self.saves().get(scene) {
+            Some(saves) => saves.keys().cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn get(&mut self, scene: &str, name: &str) -> Option<&SavedColors> {
+        self.saves().get(scene)?.get(name)
+    }
+
+    /// Adds or replaces a save and writes all saves to disk.
+    pub fn insert(&mut self, scene: &str, name: &str, colors: SavedColors) {
+        self.saves()
+            .entry(scene.to_string())
+            .or_default()
+            .insert(sanitize(name), colors);
+        self.save();
+    }
+
+    /// Removes a save and writes all saves to disk.
+    pub fn remove(&mut self, scene: &str, name: &str) {
+        let saves = self.saves();
+        if let Some(scene_saves) = saves.get_mut(scene) {
+            scene_saves.remove(name);
+            if scene_saves.is_empty() {
+                saves.remove(scene);
+            }
+        }
+        self.save();
+    }
+
+    fn save(&mut self) {
+        if let Err(e) = self.try_save() {
+            log::warn!("could not save the scene saves: {e}");
+        }
+    }
+
+    fn try_save(&mut self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        let contents = serialize(self.saves());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+}
+
+/// Names are stored tab-separated on a single line.
+fn sanitize(name: &str) -> String {
+    name.replace(['\t', '\n', '\r'], " ")
+}
+
+fn serialize(saves: &BTreeMap<String, BTreeMap<String, SavedColors>>) -> String {
+    let mut contents = String::new();
+    for (scene, saves) in saves {
+        for (name, colors) in saves {
+            for (color_name, c) in colors {
+                contents.push_str(&format!(
+                    "{scene}\t{name}\t{color_name}\t{} {} {} {}\n",
+                    c.lumen, c.lightness, c.chroma, c.hue,
+                ));
+            }
+        }
+    }
+    contents
+}
+
+fn parse(contents: &str) -> BTreeMap<String, BTreeMap<String, SavedColors>> {
+    let mut saves = BTreeMap::<_, BTreeMap<_, SavedColors>>::new();
+    for line in contents.lines() {
+        let mut parts = line.split('\t');
+        let (Some(scene), Some(name), Some(color_name), Some(values)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let values: Vec<f32> = match values.split_whitespace().map(|v| v.parse()).collect() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let &[lumen, lightness, chroma, hue] = &values[..] else {
+            continue;
+        };
+        let color = Color {
+            lumen,
+            lightness,
+            chroma,
+            hue,
+        };
+        saves
+            .entry(scene.to_string())
+            .or_default()
+            .entry(name.to_string())
+            .or_default()
+            .push((color_name.to_string(), color));
+    }
+    saves
+}
+
+fn path() -> Option<PathBuf> {
+    state_file("scene-saves")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let color = Color {
+            lumen: 203.0,
+            lightness: 0.5,
+            chroma: 0.125,
+            hue: 90.0,
+        };
+        let mut saves = BTreeMap::<_, BTreeMap<_, _>>::new();
+        saves.entry("center box".to_string()).or_default().insert(
+            sanitize("dark\tbox"),
+            vec![
+                ("background".to_string(), Color::default()),
+                ("foreground".to_string(), color),
+            ],
+        );
+        let parsed = parse(&serialize(&saves));
+        let colors = &parsed["center box"]["dark box"];
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[0].0, "background");
+        assert!(colors[1].1 == color);
+    }
+}
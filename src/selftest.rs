@@ -0,0 +1,116 @@
+//! A headless check of the rendering pipeline. Fills are rendered into an offscreen
+//! image and the pixels are compared with the values computed by `trace`.
+
+use {
+    crate::{
+        cmm::{
+            ColorMatrix, NamedPrimaries, NamedTransferFunction, TransferFunction,
+            TransferFunctionWithArgs,
+        },
+        test_pane::{Color, TestColorDescription, TestPrimaries},
+        trace,
+        vulkan::VulkanDevice,
+    },
+    linearize::LinearizeExt,
+};
+
+const SIZE: u32 = 4;
+
+/// Runs all checks and returns the exit code of the process.
+pub fn run() -> i32 {
+    let device = match VulkanDevice::create() {
+        Ok(d) => d,
+        Err(e) => {
+            println!("FAIL: could not create the vulkan device: {e}");
+            return 2;
+        }
+    };
+    let mut total = 0;
+    let mut failed = 0;
+    for (name, description) in descriptions() {
+        let params = description.render_params(ColorMatrix::XYZ_FROM_LMS);
+        for color in colors() {
+            total += 1;
+            let lch = color.to_lab();
+            let expected = trace::encode(lch, &params, &Default::default());
+            let label = format!(
+                "{name}, {} cd/m², lightness {}, chroma {}, hue {}",
+                color.lumen, color.lightness, color.chroma, color.hue,
+            );
+            let res = device.read_back_fill(SIZE, SIZE, lch, &params);
+            let mismatch = match &res {
+                Ok(pixels) => pixels.iter().find(|px| !matches(px, &expected)),
+                Err(e) => {
+                    failed += 1;
+                    println!("FAIL: {label}: {e}");
+                    continue;
+                }
+            };
+            match mismatch {
+                None => println!("PASS: {label}"),
+                Some(px) => {
+                    failed += 1;
+                    println!("FAIL: {label}: expected {expected:?}, got {px:?}");
+                }
+            }
+        }
+    }
+    println!("{} of {total} checks passed", total - failed);
+    (failed > 0) as i32
+}
+
+/// The tolerance accounts for the precision of the F16 format and of the GPU.
+fn matches(actual: &[f32; 4], expected: &[f32; 4]) -> bool {
+    actual
+        .iter()
+        .zip(expected)
+        .all(|(a, e)| (a - e).abs() <= 1e-3 + 2e-3 * e.abs())
+}
+
+fn descriptions() -> Vec<(String, TestColorDescription)> {
+    let mut res = vec![
+        ("none".to_string(), TestColorDescription::None),
+        ("scRGB".to_string(), TestColorDescription::ScRgb),
+    ];
+    let parametric = |primaries, tf, pow| TestColorDescription::Parametric {
+        primaries: TestPrimaries::Named(primaries),
+        transfer_function: TransferFunctionWithArgs { tf, pow },
+        luminance: None,
+        mastering: None,
+    };
+    for tf in NamedTransferFunction::variants() {
+        res.push((
+            format!("{tf:?}"),
+            parametric(NamedPrimaries::Srgb, TransferFunction::Named(tf), 1.0),
+        ));
+    }
+    res.push((
+        "Pow 2.4".to_string(),
+        parametric(NamedPrimaries::Srgb, TransferFunction::Pow, 2.4),
+    ));
+    res.push((
+        "BT.2020 St2084Pq".to_string(),
+        parametric(
+            NamedPrimaries::Bt2020,
+            TransferFunction::Named(NamedTransferFunction::St2084Pq),
+            1.0,
+        ),
+    ));
+    res
+}
+
+fn colors() -> [Color; 5] {
+    let color = |lumen, lightness, chroma, hue| Color {
+        lumen,
+        lightness,
+        chroma,
+        hue,
+    };
+    [
+        color(0.0, 0.0, 0.0, 0.0),
+        color(203.0, 1.0, 0.0, 0.0),
+        color(203.0, 0.5, 0.0, 0.0),
+        color(203.0, 0.6, 0.1, 30.0),
+        color(1000.0, 0.8, 0.05, 250.0),
+    ]
+}
@@ -0,0 +1,208 @@
+use {
+    crate::{
+        cmm::{
+            Luminance, NamedPrimaries, NamedTransferFunction, TransferFunction,
+            TransferFunctionWithArgs,
+        },
+        ordered_float::F64,
+        protocols::{
+            color_management_v1::{
+                wp_color_manager_v1::{WpColorManagerV1, WpColorManagerV1Feature},
+                wp_image_description_v1::{
+                    WpImageDescriptionV1Cause, WpImageDescriptionV1EventHandler,
+                    WpImageDescriptionV1Ref,
+                },
+            },
+            wayland::wl_display::WlDisplay,
+        },
+        singletons::get_singletons,
+        test_pane::{Capablities, TestColorDescription, TestPrimaries},
+    },
+    linearize::LinearizeExt,
+    std::{
+        cell::RefCell,
+        rc::Rc,
+        time::{Duration, Instant, SystemTime},
+    },
+    wl_client::{proxy, Libwayland},
+};
+
+/// A run that attaches a new parametric description in every frame.
+pub struct StressRun {
+    pub end: Instant,
+    /// The number of descriptions that were requested. Some of them might not have
+    /// become ready.
+    pub descriptions: u64,
+    /// Why the run ended early, if it did.
+    pub stop_reason: Option<&'static str>,
+    /// The distinct errors sent by the compositor and how often each occurred.
+    pub errors: Vec<(String, u64)>,
+    rng: u64,
+}
+
+impl StressRun {
+    pub fn new(duration: Duration) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self {
+            end: Instant::now() + duration,
+            descriptions: 0,
+            stop_reason: None,
+            errors: vec![],
+            rng: seed | 1,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        Instant::now() < self.end
+    }
+
+    pub fn record_error(&mut self, msg: &str) {
+        match self.errors.iter_mut().find(|e| e.0 == msg) {
+            Some(e) => e.1 += 1,
+            None => self.errors.push((msg.to_string(), 1)),
+        }
+    }
+
+    /// xorshift64
+    fn next(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> Option<T> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(values[(self.next() % values.len() as u64) as usize])
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random description that only uses features supported by the
+    /// compositor, or why no such description exists.
+    pub fn next_description(
+        &mut self,
+        caps: &Capablities,
+    ) -> Result<TestColorDescription, &'static str> {
+        if !caps.features.contains(&WpColorManagerV1Feature::PARAMETRIC) {
+            return Err("the compositor does not support parametric descriptions");
+        }
+        let primaries: Vec<_> = NamedPrimaries::variants()
+            .filter(|p| caps.primaries.contains(&p.wayland()))
+            .collect();
+        let tfs: Vec<_> = NamedTransferFunction::variants()
+            .filter(|tf| caps.tf.contains(&tf.wayland()))
+            .collect();
+        let primaries = self
+            .pick(&primaries)
+            .ok_or("the compositor supports no named primaries")?;
+        let tf = self
+            .pick(&tfs)
+            .ok_or("the compositor supports no named transfer functions")?;
+        let tf = TransferFunction::Named(tf);
+        let luminance = match caps
+            .features
+            .contains(&WpColorManagerV1Feature::SET_LUMINANCES)
+            && self.next() & 1 == 0
+        {
+            true => {
+                // Satisfies min < white <= max with the precision of the protocol.
+                let min = (self.unit() * 10_000.0).round() / 10_000.0;
+                let white = (min.floor() + 1.0 + self.unit() * 1000.0).round();
+                let max = (white + self.unit() * 9000.0).round();
+                Some(Luminance {
+                    min: F64(min),
+                    max: F64(max),
+                    white: F64(white),
+                })
+            }
+            false => None,
+        };
+        self.descriptions += 1;
+        Ok(TestColorDescription::Parametric {
+            primaries: TestPrimaries::Named(primaries),
+            transfer_function: TransferFunctionWithArgs { tf, pow: 1.0 },
+            luminance,
+            mastering: None,
+        })
+    }
+}
+
+/// The response of the compositor to luminances that violate `min < max` or
+/// `min < white`.
+#[derive(Clone, Debug)]
+pub enum InvalidLuminanceOutcome {
+    /// The compositor raised a protocol error as required by the protocol.
+    ProtocolError(String),
+    /// The compositor sent the failed event instead.
+    Failed(String),
+    /// The compositor accepted the description.
+    Ready,
+}
+
+/// Creates a parametric description with the given luminances without validating
+/// them.
+///
+/// The protocol error terminates the connection, so a separate connection is used.
+pub fn probe_luminance(
+    caps: &Capablities,
+    luminance: Luminance,
+) -> Result<InvalidLuminanceOutcome, String> {
+    let features = [
+        WpColorManagerV1Feature::PARAMETRIC,
+        WpColorManagerV1Feature::SET_LUMINANCES,
+    ];
+    if !features.iter().all(|f| caps.features.contains(f)) {
+        return Err("The compositor does not support setting luminances".to_string());
+    }
+    let primaries = NamedPrimaries::variants()
+        .find(|p| caps.primaries.contains(&p.wayland()))
+        .ok_or("The compositor supports no named primaries")?;
+    let tf = NamedTransferFunction::variants()
+        .find(|tf| caps.tf.contains(&tf.wayland()))
+        .ok_or("The compositor supports no named transfer functions")?;
+    let con = Libwayland::open()
+        .and_then(|l| l.connect_to_default_display())
+        .map_err(|e| format!("Could not connect to the compositor: {e}"))?;
+    let queue = con.create_local_queue(c"luminance-probe");
+    let singletons = get_singletons(&queue.display::<WlDisplay>());
+    let manager: WpColorManagerV1 = singletons.get(1, 1);
+    let c = manager.create_parametric_creator();
+    c.set_primaries_named(primaries.wayland());
+    c.set_tf_named(tf.wayland());
+    let (min, max, white) = luminance.to_protocol();
+    c.set_luminances(min, max, white);
+    let description = c.create();
+    struct Eh(Rc<RefCell<Option<InvalidLuminanceOutcome>>>);
+    impl WpImageDescriptionV1EventHandler for Eh {
+        fn failed(
+            &self,
+            _slf: &WpImageDescriptionV1Ref,
+            _cause: WpImageDescriptionV1Cause,
+            msg: &str,
+        ) {
+            *self.0.borrow_mut() = Some(InvalidLuminanceOutcome::Failed(msg.to_string()));
+        }
+
+        fn ready(&self, _slf: &WpImageDescriptionV1Ref, _identity: u32) {
+            *self.0.borrow_mut() = Some(InvalidLuminanceOutcome::Ready);
+        }
+    }
+    let outcome = Rc::new(RefCell::new(None));
+    proxy::set_event_handler_local(&description, Eh(outcome.clone()));
+    if let Err(e) = queue.dispatch_roundtrip_blocking() {
+        // libwayland prints the message of the protocol error.
+        return Ok(InvalidLuminanceOutcome::ProtocolError(e.to_string()));
+    }
+    proxy::destroy(&description);
+    proxy::destroy(&manager);
+    let outcome = outcome.borrow_mut().take();
+    outcome.ok_or_else(|| "The compositor did not respond".to_string())
+}
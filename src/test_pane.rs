@@ -1,12 +1,22 @@
 use {
     crate::{
+        blend_delta::{self, BlendDelta, Quadrant},
         cmm::{
-            matrix_from_lms, ColorMatrix, Lms, Local, Luminance, NamedPrimaries,
-            NamedTransferFunction, Primaries, TransferFunction, TransferFunctionWithArgs,
+            chromaticity_from_protocol, chromaticity_to_protocol, matrix_from_lms,
+            min_luminance_from_protocol, tf_power_from_protocol, tf_power_to_protocol, xyy_to_xyz,
+            xyz_to_xyy, ColorMatrix, Lms, Local, Luminance, NamedPrimaries, NamedTransferFunction,
+            Primaries, TransferFunction, TransferFunctionWithArgs, Xyz,
         },
+        geometry::Geometry,
+        histogram::{self, BinScale, Histogram},
+        image_file::ImageFile,
         ordered_float::F64,
         protocols::{
             color_management_v1::{
+                wp_color_management_output_v1::{
+                    WpColorManagementOutputV1, WpColorManagementOutputV1EventHandler,
+                    WpColorManagementOutputV1Ref,
+                },
                 wp_color_management_surface_feedback_v1::{
                     WpColorManagementSurfaceFeedbackV1,
                     WpColorManagementSurfaceFeedbackV1EventHandler,
@@ -27,37 +37,71 @@ use {
                     WpImageDescriptionV1EventHandler, WpImageDescriptionV1Ref,
                 },
             },
+            color_representation_v1::{
+                wp_color_representation_manager_v1::{
+                    WpColorRepresentationManagerV1, WpColorRepresentationManagerV1EventHandler,
+                    WpColorRepresentationManagerV1Ref,
+                },
+                wp_color_representation_surface_v1::{
+                    WpColorRepresentationSurfaceV1, WpColorRepresentationSurfaceV1AlphaMode,
+                    WpColorRepresentationSurfaceV1Coefficients,
+                    WpColorRepresentationSurfaceV1Range,
+                },
+            },
+            tearing_control_v1::{
+                wp_tearing_control_manager_v1::WpTearingControlManagerV1,
+                wp_tearing_control_v1::{WpTearingControlV1, WpTearingControlV1PresentationHint},
+            },
+            viewporter::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
             wayland::{
-                wl_compositor::WlCompositor, wl_display::WlDisplay,
-                wl_subcompositor::WlSubcompositor, wl_subsurface::WlSubsurface,
-                wl_surface::WlSurface,
+                wl_callback::WlCallback,
+                wl_compositor::WlCompositor,
+                wl_display::WlDisplay,
+                wl_output::{WlOutput, WlOutputEventHandler, WlOutputRef, WlOutputTransform},
+                wl_registry::{WlRegistry, WlRegistryEventHandler, WlRegistryRef},
+                wl_subcompositor::WlSubcompositor,
+                wl_subsurface::WlSubsurface,
+                wl_surface::{WlSurface, WlSurfaceEventHandler, WlSurfaceRef},
             },
             xdg_shell::{
                 xdg_surface::{XdgSurface, XdgSurfaceEventHandler, XdgSurfaceRef},
-                xdg_toplevel::{XdgToplevel, XdgToplevelEventHandler, XdgToplevelRef},
+                xdg_toplevel::{
+                    XdgToplevel, XdgToplevelEventHandler, XdgToplevelRef, XdgToplevelState,
+                },
                 xdg_wm_base::XdgWmBase,
             },
         },
         singletons::get_singletons,
-        vulkan::{Scene, VulkanDevice, VulkanSurface},
+        trace,
+        vulkan::{
+            self, lab_to_lms, lms_to_lab, AlphaMode, ChannelAdjustment, GamutMapping,
+            GradientSpace, Grid, Legend, MemoryStats, OutputClamp, Overlay, PresentMode,
+            RenderParams, Scene, SurfaceExtent, SwapchainColorSpace, Texture, UploadMode,
+            UploadedParams, VulkanDevice, VulkanSurface, DEFAULT_IMAGE_COUNT,
+        },
     },
     egui_winit::winit::{
         event_loop::{EventLoop, OwnedDisplayHandle},
         raw_window_handle::HasDisplayHandle,
     },
     isnt::std_1::collections::IsntHashSet2Ext,
+    linearize::{Linearize, LinearizeExt},
     raw_window_handle::RawDisplayHandle,
     std::{
+        array,
         cell::{Cell, RefCell},
         collections::HashSet,
+        error::Error,
         f32::consts::PI,
-        mem,
+        iter, mem,
         ptr::NonNull,
         rc::Rc,
+        time::{Duration, Instant},
     },
     wl_client::{
-        proxy::{self},
-        Libwayland, QueueOwner,
+        ffi,
+        proxy::{self, OwnedProxy},
+        Fixed, Libwayland, QueueOwner,
     },
 };
 
@@ -72,48 +116,584 @@ pub struct Capablities {
     pub features: HashSet<WpColorManagerV1Feature>,
     pub tf: HashSet<WpColorManagerV1TransferFunction>,
     pub primaries: HashSet<WpColorManagerV1Primaries>,
+    pub alpha_modes: HashSet<WpColorRepresentationSurfaceV1AlphaMode>,
+    pub coefficients: HashSet<(
+        WpColorRepresentationSurfaceV1Coefficients,
+        WpColorRepresentationSurfaceV1Range,
+    )>,
 }
 
 struct State {
     caps: Rc<Capablities>,
-    _xdg_wm_base: XdgWmBase,
-    _wl_compositor: WlCompositor,
+    geometry: Rc<Geometry>,
+    xdg_wm_base: XdgWmBase,
+    wl_display: NonNull<ffi::wl_display>,
+    wl_compositor: WlCompositor,
     wl_subcompositor: WlSubcompositor,
+    wp_viewporter: Option<WpViewporter>,
     wp_color_manager_v1: WpColorManagerV1,
     wl_surface: WlSurface,
     wl_blend_surface: WlSurface,
-    wp_color_management_surface_v1: WpColorManagementSurfaceV1,
+    wl_reference_surface: WlSurface,
     wp_color_management_surface_feedback_v1: WpColorManagementSurfaceFeedbackV1,
     wp_color_management_blend_surface_v1: WpColorManagementSurfaceV1,
+    wp_color_management_reference_surface_v1: WpColorManagementSurfaceV1,
+    wp_viewport: Option<WpViewport>,
+    wp_blend_viewport: Option<WpViewport>,
+    wp_tearing_control_manager_v1: Option<WpTearingControlManagerV1>,
+    wp_color_representation_manager_v1: Option<WpColorRepresentationManagerV1>,
     xdg_surface: XdgSurface,
-    _xdg_toplevel: XdgToplevel,
-    vulkan_surface: VulkanSurface,
-    vulkan_blend_surface: VulkanSurface,
+    xdg_toplevel: XdgToplevel,
+    /// The error message if Vulkan could not be initialized.
+    vulkan: RefCell<Result<Vulkan, String>>,
     mutable: RefCell<Mutable>,
+    last_frame: RefCell<Option<Frame>>,
     create_description_error_message: Cell<Option<Option<String>>>,
+    /// The number of descriptions created for the main surface whose objects have not
+    /// been destroyed yet.
+    live_descriptions: Cell<usize>,
     preferred_description_error_message: Cell<Option<Option<String>>>,
     preferred_description_data: Cell<Option<DescriptionData>>,
+    preferred_identity: Cell<Option<DescriptionIdentity>>,
+    /// The identity sent with the last `preferred_changed` event.
+    announced_identity: Cell<Option<u32>>,
+    /// The identity and contents of the last preferred description.
+    last_preferred: Cell<Option<(u32, DescriptionData)>>,
+    /// Incremented whenever the preferred description is requested. Results of older
+    /// requests are discarded.
+    feedback_generation: Cell<u64>,
+    /// Set when the set of entered outputs changes. The preferred description is
+    /// requested once after the events of the current dispatch have been handled.
+    outputs_changed: Cell<bool>,
+    /// Used to track outputs as they are added and removed.
+    wl_registry: WlRegistry,
+    outputs: RefCell<Vec<Rc<Output>>>,
+    /// Set when an output is added or removed or its name changes.
+    output_list: Cell<Option<Vec<OutputInfo>>>,
+    output_error_message: Cell<Option<Option<String>>>,
+    output_data: Cell<Option<OutputData>>,
+    /// Set when rendering starts or stops failing.
+    render_error_message: Cell<Option<Option<String>>>,
+    render_failed: Cell<bool>,
+    /// Set when the toplevel states change.
+    toplevel_states: Cell<Option<ToplevelStates>>,
+    /// Like `feedback_generation` but for the description of the output.
+    output_generation: Cell<u64>,
+    /// The time that animated scenes are relative to.
+    animation_start: Cell<Instant>,
+    /// The pending frame callback of the main surface. Animated scenes render the
+    /// next frame once it is done.
+    frame_callback: Rc<RefCell<Option<WlCallback>>>,
+    /// Set when the first `xdg_surface.configure` event has been received.
+    configured: Cell<bool>,
+    /// The time at which a missing initial configure is reported. Unset once it has
+    /// been reported or the surface has been configured.
+    configure_deadline: Cell<Option<Instant>>,
+    /// The time of the last successful render since the surfaces were last trimmed.
+    last_render: Cell<Option<Instant>>,
 }
 
-#[derive(Copy, Clone, Debug)]
+/// The surfaces are trimmed after this time without rendering.
+const IDLE_TRIM: Duration = Duration::from_secs(5);
+/// The time after which a missing initial configure is reported.
+const CONFIGURE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The inputs of the last frame rendered to the test pane.
+struct Frame {
+    width: u32,
+    height: u32,
+    scene: Scene,
+    clear: Option<[f32; 4]>,
+    overlay: Overlay,
+    params: RenderParams,
+    /// The description attached to the test pane.
+    description: TestColorDescription,
+}
+
+struct Vulkan {
+    device: Rc<VulkanDevice>,
+    surface: VulkanSurface,
+    blend_surface: VulkanSurface,
+    reference_surface: VulkanSurface,
+}
+
+impl Vulkan {
+    /// Returns the surfaces that exist for the lifetime of the test pane.
+    fn surfaces(&self) -> impl Iterator<Item = &VulkanSurface> {
+        [&self.surface, &self.blend_surface, &self.reference_surface].into_iter()
+    }
+}
+
+/// A sub-surface of `TestScene::BlendLayers`.
+struct BlendLayer {
+    wl_surface: WlSurface,
+    wl_subsurface: WlSubsurface,
+    wp_color_management_surface_v1: WpColorManagementSurfaceV1,
+    wp_viewport: Option<WpViewport>,
+    vulkan: VulkanSurface,
+}
+
+impl BlendLayer {
+    fn destroy(self) {
+        // The swapchain must be destroyed before the surface.
+        drop(self.vulkan);
+        if let Some(viewport) = &self.wp_viewport {
+            viewport.destroy();
+        }
+        self.wp_color_management_surface_v1.destroy();
+        self.wl_subsurface.destroy();
+        self.wl_surface.destroy();
+    }
+}
+
+struct Output {
+    /// The name of the `wl_output` global.
+    global: u32,
+    wl_output: WlOutput,
+    wp_color_management_output_v1: WpColorManagementOutputV1,
+    name: RefCell<Option<String>>,
+    description: RefCell<Option<String>>,
+}
+
+/// An output that the test pane can be made fullscreen on.
+#[derive(Clone, Debug)]
+pub struct OutputInfo {
+    /// The name of the `wl_output` global. It is never reused for another output.
+    pub global: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// The description of the output that the test pane is on.
+#[derive(Clone, Debug)]
+pub struct OutputData {
+    pub name: Option<String>,
+    pub description: DescriptionData,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct DescriptionData {
     pub primaries: TestPrimaries,
     pub tf: TransferFunction,
     pub tf_power: f32,
     pub luminance: Option<Luminance>,
+    /// The minimum and maximum luminance of the target display.
+    pub target_luminance: Option<(F64, F64)>,
+    pub target_primaries: Option<Primaries>,
+    pub target_max_cll: Option<u32>,
+    pub target_max_fall: Option<u32>,
+}
+
+/// HDR10 static metadata of a parametric description.
+///
+/// The mastering display primaries and luminance define the target color volume.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Mastering {
+    pub primaries: Option<Primaries>,
+    /// The minimum and maximum luminance of the mastering display.
+    pub luminance: Option<(F64, F64)>,
+    pub max_cll: Option<u32>,
+    pub max_fall: Option<u32>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MetadataMatch {
+    Match,
+    Mismatch,
+    /// The compositor did not send the value.
+    NotReported,
+}
+
+/// The results of comparing the sent HDR10 static metadata with the target color volume
+/// sent by the compositor. Fields are `None` if the value was not sent.
+#[derive(Copy, Clone, Debug)]
+pub struct MasteringMatches {
+    pub primaries: Option<MetadataMatch>,
+    pub luminance: Option<MetadataMatch>,
+    pub max_cll: Option<MetadataMatch>,
+    pub max_fall: Option<MetadataMatch>,
+}
+
+impl MasteringMatches {
+    /// Returns false if any reported value differs from the sent one. Values that were
+    /// not reported do not fail the comparison.
+    pub fn pass(&self) -> bool {
+        [self.primaries, self.luminance, self.max_cll, self.max_fall]
+            .into_iter()
+            .all(|m| m != Some(MetadataMatch::Mismatch))
+    }
+}
+
+/// Compares the HDR10 static metadata of a parametric description with the data sent
+/// by the compositor. Chromaticities match if they differ by at most
+/// `chromaticity_epsilon`, luminances if they are equal at the precision of the protocol.
+/// Returns `None` if the description has no such metadata.
+pub fn compare_mastering(
+    description: TestColorDescription,
+    data: &DescriptionData,
+    chromaticity_epsilon: f64,
+) -> Option<MasteringMatches> {
+    let TestColorDescription::Parametric {
+        mastering: Some(mastering),
+        ..
+    } = description
+    else {
+        return None;
+    };
+    fn compare<T: Copy>(
+        sent: Option<T>,
+        reported: Option<T>,
+        eq: impl Fn(T, T) -> bool,
+    ) -> Option<MetadataMatch> {
+        let sent = sent?;
+        Some(match reported {
+            None => MetadataMatch::NotReported,
+            Some(r) if eq(sent, r) => MetadataMatch::Match,
+            Some(_) => MetadataMatch::Mismatch,
+        })
+    }
+    let primaries = compare(mastering.primaries, data.target_primaries, |s, r| {
+        [(s.r, r.r), (s.g, r.g), (s.b, r.b), (s.wp, r.wp)]
+            .into_iter()
+            .flat_map(|(s, r)| [(s.0 .0 - r.0 .0).abs(), (s.1 .0 - r.1 .0).abs()])
+            .all(|d| d <= chromaticity_epsilon)
+    });
+    let luminance = compare(mastering.luminance, data.target_luminance, |s, r| {
+        (s.0 .0 - r.0 .0).abs() <= DescriptionDeltas::MIN_LUMINANCE_PRECISION
+            && (s.1 .0 - r.1 .0).abs() < 1.0
+    });
+    Some(MasteringMatches {
+        primaries,
+        luminance,
+        max_cll: compare(mastering.max_cll, data.target_max_cll, |s, r| s == r),
+        max_fall: compare(mastering.max_fall, data.target_max_fall, |s, r| s == r),
+    })
+}
+
+/// The differences between a parametric description and the data that the compositor
+/// sent for a description.
+#[derive(Copy, Clone, Debug)]
+pub struct DescriptionDeltas {
+    /// The largest difference of a chromaticity coordinate. Named primaries are
+    /// compared by their chromaticities.
+    pub primaries: f64,
+    /// The largest difference of a chromaticity coordinate that passes. It does not
+    /// apply to the luminances or the transfer function.
+    pub chromaticity_epsilon: f64,
+    pub tf: bool,
+    /// The largest relative difference of the min, max, and white luminance. Not set if
+    /// the compositor did not send luminances.
+    pub luminance: Option<f64>,
+}
+
+impl DescriptionDeltas {
+    /// The protocol transmits chromaticities with 6 decimal places.
+    pub const DEFAULT_CHROMATICITY_EPSILON: f64 = 1e-6;
+    /// The largest relative difference of luminances that passes. Unlike the
+    /// chromaticity epsilon, this is not configurable.
+    pub const MAX_LUMINANCE: f64 = 0.01;
+    /// The protocol transmits minimum luminances and the power of the transfer function
+    /// with 4 decimal places and maximum luminances as integers.
+    pub const MIN_LUMINANCE_PRECISION: f64 = 1e-4;
+    pub const TF_POWER_PRECISION: f32 = 1e-4;
+
+    pub fn pass(&self) -> bool {
+        self.primaries <= self.chromaticity_epsilon
+            && self.tf
+            && self.luminance.is_some_and(|l| l <= Self::MAX_LUMINANCE)
+    }
+}
+
+/// Compares a parametric description with the data sent by the compositor. Returns
+/// `None` for other descriptions.
+pub fn compare_description(
+    description: TestColorDescription,
+    data: &DescriptionData,
+    chromaticity_epsilon: f64,
+) -> Option<DescriptionDeltas> {
+    let TestColorDescription::Parametric {
+        transfer_function, ..
+    } = description
+    else {
+        return None;
+    };
+    let expected = description.primaries();
+    let actual = match data.primaries {
+        TestPrimaries::Named(n) => n.primaries(),
+        TestPrimaries::Custom(p) => p,
+    };
+    let primaries = [
+        (expected.r, actual.r),
+        (expected.g, actual.g),
+        (expected.b, actual.b),
+        (expected.wp, actual.wp),
+    ]
+    .into_iter()
+    .flat_map(|(e, a)| [(e.0 .0 - a.0 .0).abs(), (e.1 .0 - a.1 .0).abs()])
+    .fold(0.0, f64::max);
+    let tf = data.tf == transfer_function.tf
+        && (transfer_function.tf != TransferFunction::Pow
+            || (data.tf_power - transfer_function.pow).abs()
+                < DescriptionDeltas::TF_POWER_PRECISION);
+    let expected = description.luminance();
+    let luminance = data.luminance.map(|actual| {
+        let relative = |e: F64, a: F64| (e.0 - a.0).abs() / e.0.max(1e-4);
+        relative(expected.min, actual.min)
+            .max(relative(expected.max, actual.max))
+            .max(relative(expected.white, actual.white))
+    });
+    Some(DescriptionDeltas {
+        primaries,
+        chromaticity_epsilon,
+        tf,
+        luminance,
+    })
+}
+
+/// The identities sent by the compositor for the preferred description.
+#[derive(Copy, Clone, Debug)]
+pub struct DescriptionIdentity {
+    /// The identity sent with the last `preferred_changed` event.
+    pub announced: Option<u32>,
+    /// The identity sent with the `ready` event of the preferred description.
+    pub identity: u32,
+    /// Set if the identity differs from the previous description even though the
+    /// contents are the same.
+    pub unchanged_content: bool,
+}
+
+/// The `xdg_toplevel` states that the test pane handles. Other states are ignored.
+///
+/// While maximized or fullscreen, the size is chosen by the compositor and is not saved
+/// as the windowed size. The test pane renders static content, so activation is only
+/// displayed in the control pane.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ToplevelStates {
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub activated: bool,
+}
+
+impl ToplevelStates {
+    /// Parses the states array of the `xdg_toplevel.configure` event.
+    fn parse(states: &[u8]) -> Self {
+        let mut res = Self::default();
+        let states = states
+            .chunks_exact(4)
+            .map(|s| XdgToplevelState(u32::from_ne_bytes(s.try_into().unwrap())));
+        for state in states {
+            match state {
+                XdgToplevelState::MAXIMIZED => res.maximized = true,
+                XdgToplevelState::FULLSCREEN => res.fullscreen = true,
+                XdgToplevelState::ACTIVATED => res.activated = true,
+                _ => {}
+            }
+        }
+        res
+    }
 }
 
 struct Mutable {
     scene: TestScene,
+    clear: Option<Color>,
+    overlay: Option<TestOverlay>,
+    legend: bool,
+    viewport: Option<TestViewport>,
+    damage: Option<TestDamage>,
+    peak_lumen: Option<f32>,
+    xyz_from_lms: ColorMatrix<Xyz, Lms>,
+    alpha_mode: AlphaMode,
+    image_count: u32,
+    present_mode: PresentMode,
+    allow_tearing: bool,
+    /// Only exists while the present mode is not immediate. See
+    /// `State::update_tearing_control`.
+    wp_tearing_control_v1: Option<WpTearingControlV1>,
+    dither_bits: Option<u32>,
+    gamut_mapping: GamutMapping,
+    gradient_space: GradientSpace,
+    output_clamp: OutputClamp,
+    channel_adjustment: ChannelAdjustment,
+    measure_present_latency: bool,
+    color_space: SwapchainColorSpace,
+    verify_feedback: bool,
     width: i32,
     height: i32,
     description: TestColorDescription,
-    vulkan_tf_args: [f32; 4],
-    matrix: ColorMatrix<Local, Lms>,
+    /// The description attached to the surface by the last successful request.
+    attached_description: TestColorDescription,
+    /// Set if the last description could not be created. The content is rendered for
+    /// this description instead, which is the one still attached to the surface.
+    fallback_description: Option<TestColorDescription>,
+    render_description: Option<TestColorDescription>,
+    blend_description: Option<TestColorDescription>,
+    pending_blend_description: Option<WpImageDescriptionV1>,
     need_render: bool,
+    need_commit: bool,
+    frozen: bool,
+    /// If set, frames are only rendered by `TestPane::step`. Contains the time of
+    /// animated scenes.
+    paused: Option<Duration>,
+    /// Whether the next frame is rendered even though rendering is paused.
+    step: bool,
+    /// Whether fullscreen has been requested.
+    fullscreen: bool,
+    fullscreen_output: Option<u32>,
+    /// The states of the last `xdg_toplevel.configure` event.
+    toplevel_states: ToplevelStates,
     preferred_description: Option<WpImageDescriptionV1>,
     pending_description: Option<WpImageDescriptionV1>,
     blend_subsurface: Option<WlSubsurface>,
+    /// The layers of `TestScene::BlendLayers` from bottom to top.
+    blend_layers: Vec<BlendLayer>,
+    /// The texture of `TestScene::Image`.
+    image: Option<Rc<Texture>>,
+    /// The number and stacking of the layers that were last requested.
+    blend_layer_config: (usize, LayerStacking),
+    /// The description of the bottom half of the EOTF comparison or `None` for the
+    /// sRGB reference.
+    reference: Option<TestColorDescription>,
+    pending_reference: Option<WpImageDescriptionV1>,
+    reference_subsurface: Option<WlSubsurface>,
+    /// The color management surface of the test pane. If this is `None`, the compositor
+    /// treats the surface as an untagged sRGB surface.
+    wp_color_management_surface_v1: Option<WpColorManagementSurfaceV1>,
+    representation: TestRepresentation,
+    /// Only exists while some representation metadata is set.
+    wp_color_representation_surface_v1: Option<WpColorRepresentationSurfaceV1>,
+    /// The outputs that the test pane is on in the order in which they were entered.
+    entered_outputs: Vec<u32>,
+    /// The preferred buffer scale and transform of the surface.
+    buffer_scale: i32,
+    buffer_transform: WlOutputTransform,
+}
+
+impl Default for Mutable {
+    fn default() -> Self {
+        Self {
+            scene: TestScene::Fill(Color::default()),
+            clear: None,
+            overlay: None,
+            legend: false,
+            viewport: None,
+            damage: None,
+            peak_lumen: None,
+            xyz_from_lms: ColorMatrix::XYZ_FROM_LMS,
+            alpha_mode: Default::default(),
+            image_count: DEFAULT_IMAGE_COUNT,
+            present_mode: Default::default(),
+            allow_tearing: false,
+            wp_tearing_control_v1: None,
+            dither_bits: None,
+            verify_feedback: false,
+            gamut_mapping: Default::default(),
+            gradient_space: Default::default(),
+            output_clamp: Default::default(),
+            channel_adjustment: Default::default(),
+            measure_present_latency: false,
+            color_space: Default::default(),
+            width: 0,
+            height: 0,
+            description: TestColorDescription::None,
+            attached_description: TestColorDescription::None,
+            fallback_description: None,
+            render_description: None,
+            blend_description: None,
+            pending_blend_description: None,
+            need_render: false,
+            need_commit: false,
+            frozen: false,
+            paused: None,
+            step: false,
+            fullscreen: false,
+            fullscreen_output: None,
+            toplevel_states: Default::default(),
+            preferred_description: None,
+            pending_description: None,
+            blend_subsurface: None,
+            blend_layers: vec![],
+            image: None,
+            blend_layer_config: Default::default(),
+            reference: None,
+            pending_reference: None,
+            reference_subsurface: None,
+            wp_color_management_surface_v1: None,
+            representation: Default::default(),
+            wp_color_representation_surface_v1: None,
+            entered_outputs: vec![],
+            buffer_scale: 1,
+            buffer_transform: WlOutputTransform::NORMAL,
+        }
+    }
+}
+
+impl Mutable {
+    /// Returns the description that the content is rendered for.
+    fn content_description(&self) -> TestColorDescription {
+        wsi_description(self.color_space)
+            .or(self.render_description)
+            .or(self.fallback_description)
+            .unwrap_or(self.description)
+    }
+
+    /// Returns the render parameters of the description, limited to the peak
+    /// luminance if one is set.
+    fn render_params(&self, description: TestColorDescription) -> RenderParams {
+        let mut params = description.render_params(self.xyz_from_lms);
+        if let Some(peak) = self.peak_lumen {
+            params.peak = local_peak(params.lms_to_local, peak);
+        }
+        params
+    }
+
+    /// Returns the render parameters of the blend sub-surfaces.
+    fn blend_params(&self) -> RenderParams {
+        self.render_params(self.blend_description.unwrap_or(self.content_description()))
+    }
+
+    /// Records that `description` is now attached to the surface.
+    fn description_attached(&mut self, description: TestColorDescription) {
+        self.attached_description = description;
+        self.fallback_description = None;
+    }
+
+    /// Handles the failure of the pending description. The surface keeps its previous
+    /// description and the content is rendered for it until another description is
+    /// attached.
+    fn description_failed(&mut self) {
+        self.pending_description = None;
+        self.fallback_description = Some(self.attached_description);
+        self.need_render = true;
+    }
+}
+
+/// Returns the description that the Vulkan implementation sends for swapchains with
+/// this color space, or `None` for `PassThrough`.
+pub fn wsi_description(color_space: SwapchainColorSpace) -> Option<TestColorDescription> {
+    use {NamedPrimaries as P, NamedTransferFunction as T, SwapchainColorSpace as S};
+    let parametric = |primaries, tf, pow| TestColorDescription::Parametric {
+        primaries: TestPrimaries::Named(primaries),
+        transfer_function: TransferFunctionWithArgs { tf, pow },
+        luminance: None,
+        mastering: None,
+    };
+    let named = |primaries, tf| parametric(primaries, TransferFunction::Named(tf), 1.0);
+    let description = match color_space {
+        S::PassThrough => return None,
+        S::SrgbNonlinear => named(P::Srgb, T::Srgb),
+        S::ExtendedSrgbLinear => TestColorDescription::ScRgb,
+        S::ExtendedSrgbNonlinear => named(P::Srgb, T::ExtSrgb),
+        S::DisplayP3Nonlinear => named(P::DisplayP3, T::Srgb),
+        S::DisplayP3Linear => named(P::DisplayP3, T::Linear),
+        S::DciP3Nonlinear => parametric(P::DciP3, TransferFunction::Pow, 2.6),
+        S::Bt709Linear => named(P::Srgb, T::Linear),
+        S::Bt709Nonlinear => named(P::Srgb, T::Bt1886),
+        S::Bt2020Linear => named(P::Bt2020, T::Linear),
+        S::Hdr10St2084 => named(P::Bt2020, T::St2084Pq),
+        S::AdobeRgbLinear => named(P::AdobeRgb, T::Linear),
+        S::AdobeRgbNonlinear => named(P::AdobeRgb, T::Gamma22),
+    };
+    Some(description)
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -122,6 +702,8 @@ pub enum TestPrimaries {
     Custom(Primaries),
 }
 
+// Descriptions are copied around as part of `TestConfig`, boxing would prevent that.
+#[allow(clippy::large_enum_variant)]
 #[derive(Copy, Clone, PartialEq)]
 pub enum TestColorDescription {
     None,
@@ -130,18 +712,280 @@ pub enum TestColorDescription {
         primaries: TestPrimaries,
         transfer_function: TransferFunctionWithArgs,
         luminance: Option<Luminance>,
+        mastering: Option<Mastering>,
     },
 }
 
+#[derive(Copy, Clone, PartialEq)]
+pub struct TestConfig {
+    /// The description attached to the surfaces.
+    pub description: TestColorDescription,
+    /// If set, the content is rendered for this description instead of the attached one.
+    pub render_description: Option<TestColorDescription>,
+    /// If set, the blend subsurface uses this description instead of `description`.
+    pub blend_description: Option<TestColorDescription>,
+    /// If set, description changes are committed without attaching a new buffer.
+    pub keep_buffer: bool,
+    /// If set, the preferred description is requested again whenever a parametric
+    /// description has been attached.
+    pub verify_feedback: bool,
+    /// If not set, the test pane has no color management surface.
+    pub color_management_surface: bool,
+    pub scene: TestScene,
+    pub clear: Option<Color>,
+    pub overlay: Option<TestOverlay>,
+    /// If set, a description of the scene is drawn in the top-left corner.
+    pub legend: bool,
+    /// If set, the right half of the scene is drawn again on a sub-surface without a
+    /// color description. Ignored by scenes that use sub-surfaces.
+    pub srgb_reference: bool,
+    pub viewport: Option<TestViewport>,
+    /// If set, this region is damaged before every commit of the test pane.
+    pub damage: Option<TestDamage>,
+    /// If set, every channel is clamped to this luminance before encoding.
+    pub peak_lumen: Option<f32>,
+    /// The matrix used to convert from LMS to XYZ when rendering.
+    pub xyz_from_lms: ColorMatrix<Xyz, Lms>,
+    pub upload_mode: UploadMode,
+    pub alpha_mode: AlphaMode,
+    /// The minimum number of swapchain images.
+    pub image_count: u32,
+    pub present_mode: PresentMode,
+    /// If set, the surface asks the compositor to present frames asynchronously.
+    pub allow_tearing: bool,
+    /// Ignored if the compositor does not support wp_color_representation_v1.
+    pub representation: TestRepresentation,
+    /// If set, the encoded colors are dithered for this bit depth.
+    pub dither_bits: Option<u32>,
+    pub gamut_mapping: GamutMapping,
+    pub gradient_space: GradientSpace,
+    pub output_clamp: OutputClamp,
+    pub channel_adjustment: ChannelAdjustment,
+    /// Whether the time until the present fence of the main surface is released is
+    /// measured.
+    pub measure_present_latency: bool,
+    /// The color space of the swapchain of the main surface. Other color spaces than
+    /// `PassThrough` require that there is no color management surface.
+    pub color_space: SwapchainColorSpace,
+    /// If set, no frames are rendered and no requests are sent until it is unset.
+    pub freeze: bool,
+    /// If set, frames are only rendered by `TestPane::step`. Unlike `freeze`, changes
+    /// are still applied to the surface.
+    pub pause: bool,
+    /// If set, the test pane asks to be made fullscreen.
+    pub fullscreen: bool,
+    /// The global name of the output to be made fullscreen on. If not set, the
+    /// compositor chooses the output.
+    pub fullscreen_output: Option<u32>,
+}
+
+/// The metadata set with wp_color_representation_surface_v1. Unset fields are not sent.
+#[derive(Copy, Clone, Default, PartialEq)]
+pub struct TestRepresentation {
+    pub alpha_mode: Option<WpColorRepresentationSurfaceV1AlphaMode>,
+    pub coefficients: Option<(
+        WpColorRepresentationSurfaceV1Coefficients,
+        WpColorRepresentationSurfaceV1Range,
+    )>,
+}
+
+/// The buffer is rendered at the source size and scaled by the compositor to the
+/// destination size. Without a destination, the buffer is scaled to the toplevel size.
+#[derive(Copy, Clone, PartialEq)]
+pub struct TestViewport {
+    pub source: (i32, i32),
+    pub destination: Option<(i32, i32)>,
+}
+
+/// A damage rectangle. Buffer coordinates are sent with `damage_buffer`, surface
+/// coordinates with `damage`.
+#[derive(Copy, Clone, PartialEq)]
+pub struct TestDamage {
+    pub coordinates: DamageCoordinates,
+    pub rect: (i32, i32, i32, i32),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum DamageCoordinates {
+    #[default]
+    Buffer,
+    Surface,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct TestOverlay {
+    pub spacing: u32,
+    pub color: Color,
+}
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum TestScene {
     Fill(Color),
-    FillLeftRight([Color; 2]),
-    FillTopBottom([Color; 2]),
-    FillFour([Color; 4]),
+    /// A gradient between two colors. The angle is in degrees, counterclockwise, with 0
+    /// going from left to right.
+    LinearGradient([Color; 2], f32),
+    /// Colors and alpha values of the corners.
+    FillFour([Color; 4], [f32; 4]),
     CenterBox([Color; 2], f32),
     Grid([Color; 2], u32, u32),
+    /// A grid whose cells sweep the hue across the columns and the lightness across the
+    /// rows.
+    ColorGrid(ColorSweep),
+    /// Lines through the center and markers in the corners over a background.
+    Crosshair([Color; 2]),
+    /// A patch for colorimeters with the side length and the offset from the center in
+    /// buffer pixels over a black surround.
+    Patch(Color, u32, (i32, i32)),
     Blend([Color; 2], f32),
+    /// A sub-surface with a horizontal gradient of the alpha of the second color over
+    /// the first color. The alpha range is given by the array. Compositors that blend
+    /// with 8 bits per channel produce visible steps.
+    BlendBanding([Color; 2], [f32; 2]),
+    /// Stacked sub-surfaces over the background color in the bottom left. The bottom
+    /// right shows the layers blended by the client.
+    BlendLayers(Color, BlendLayers),
+    /// The bars of the primaries. Each channel has the value that it has in a white of
+    /// the given luminance.
+    Primaries(f32),
+    /// A luminance ramp up to the given luminance. The bottom half uses the given transfer
+    /// function instead of the one of the description.
+    CompareEotf(f32, NamedTransferFunction, PqLuminance),
+    /// Patches at near-black code values over a surround at code value 0.
+    BlackDetail(CodeValueSteps),
+    /// The image set with `TestPane::set_image`, scaled to fit the surface.
+    Image,
+    /// A vertical bar that crosses the surface at the given number of widths per
+    /// second. A frame is rendered for every frame callback.
+    MovingBar([Color; 2], f32),
+    /// One row per supported named transfer function. Each row encodes a ramp from
+    /// black to the given luminance with its transfer function, ignoring the transfer
+    /// function of the attached description.
+    TransferFunctions(f32),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorSweep {
+    pub rows: u32,
+    pub cols: u32,
+    pub lumen: f32,
+    pub chroma: f32,
+    /// The hue of the first column and the hue after the last column.
+    pub hue: (f32, f32),
+    /// The lightness of the first and the last row.
+    pub lightness: (f32, f32),
+}
+
+impl ColorSweep {
+    /// Returns the colors of the cells row by row.
+    fn colors(self) -> Vec<Color> {
+        let mut colors = vec![];
+        for row in 0..self.rows {
+            let t = match self.rows {
+                1 => 0.0,
+                n => row as f32 / (n - 1) as f32,
+            };
+            let lightness = self.lightness.0 + t * (self.lightness.1 - self.lightness.0);
+            for col in 0..self.cols {
+                let t = col as f32 / self.cols as f32;
+                colors.push(Color {
+                    lumen: self.lumen,
+                    lightness,
+                    chroma: self.chroma,
+                    hue: (self.hue.0 + t * (self.hue.1 - self.hue.0)).rem_euclid(360.0),
+                });
+            }
+        }
+        colors
+    }
+}
+
+pub const MAX_BLEND_LAYERS: usize = 8;
+
+/// The sub-surfaces of `TestScene::BlendLayers`.
+#[derive(Copy, Clone, PartialEq)]
+pub struct BlendLayers {
+    pub count: usize,
+    /// The colors and alpha values from the bottom to the top layer.
+    pub colors: [Color; MAX_BLEND_LAYERS],
+    pub alpha: [f32; MAX_BLEND_LAYERS],
+    pub stacking: LayerStacking,
+}
+
+/// The requests that establish the stacking order of the layers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum LayerStacking {
+    /// The layers are created from the bottom up and each is placed above the one
+    /// below it.
+    #[default]
+    PlaceAbove,
+    /// The layers are created from the top down and each is placed below the one
+    /// above it.
+    PlaceBelow,
+}
+
+/// The code values `start`, `start + step`, ... of `count` patches at the given bit depth.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CodeValueSteps {
+    pub start: u32,
+    pub step: u32,
+    pub count: u32,
+    pub bits: u32,
+}
+
+impl CodeValueSteps {
+    /// Returns the encoded values of the patches.
+    fn values(self) -> Vec<f32> {
+        let max = ((1u64 << self.bits) - 1) as f32;
+        (0..self.count)
+            .map(|i| ((self.start + i * self.step) as f32 / max).min(1.0))
+            .collect()
+    }
+}
+
+/// How the luminance of PQ-encoded content relates to the lumen values of the scene.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum PqLuminance {
+    /// The reference white of the scene maps to the reference white of the description.
+    #[default]
+    Relative,
+    /// Lumen values are encoded as cd/m², ignoring the reference white of the
+    /// description.
+    Absolute,
+}
+
+impl TestScene {
+    /// Returns whether the scene draws to sub-surfaces of its own.
+    fn has_subsurfaces(&self) -> bool {
+        matches!(
+            self,
+            TestScene::Blend(..)
+                | TestScene::BlendBanding(..)
+                | TestScene::BlendLayers(..)
+                | TestScene::CompareEotf(..)
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            TestScene::Fill(..) => "fill",
+            TestScene::LinearGradient(..) => "linear gradient",
+            TestScene::FillFour(..) => "four corners",
+            TestScene::CenterBox(..) => "center box",
+            TestScene::Grid(..) => "grid",
+            TestScene::ColorGrid(..) => "color grid",
+            TestScene::Crosshair(..) => "crosshair",
+            TestScene::Patch(..) => "colorimeter patch",
+            TestScene::Blend(..) => "blend",
+            TestScene::BlendBanding(..) => "blend banding",
+            TestScene::BlendLayers(..) => "blend layers",
+            TestScene::Primaries(..) => "primaries",
+            TestScene::CompareEotf(..) => "compare eotf",
+            TestScene::BlackDetail(..) => "black detail",
+            TestScene::Image => "image",
+            TestScene::MovingBar(..) => "moving bar",
+            TestScene::TransferFunctions(..) => "transfer functions",
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Default)]
@@ -152,8 +996,206 @@ pub struct Color {
     pub hue: f32,
 }
 
+impl TestColorDescription {
+    /// A parametric description that is equivalent to `ScRgb`.
+    pub const PARAMETRIC_SCRGB: Self = Self::Parametric {
+        primaries: TestPrimaries::Named(NamedPrimaries::Srgb),
+        transfer_function: TransferFunctionWithArgs {
+            tf: TransferFunction::Named(NamedTransferFunction::Linear),
+            pow: 1.0,
+        },
+        luminance: Some(Luminance::WINDOWS_SCRGB),
+        mastering: None,
+    };
+
+    /// Returns a description with the same primaries and luminances but a linear
+    /// transfer function.
+    pub fn linear(self) -> Self {
+        let lum = self.luminance();
+        match self {
+            TestColorDescription::ScRgb => self,
+            TestColorDescription::None | TestColorDescription::Parametric { .. } => {
+                let primaries = match self {
+                    TestColorDescription::Parametric { primaries, .. } => primaries,
+                    _ => TestPrimaries::Named(NamedPrimaries::Srgb),
+                };
+                TestColorDescription::Parametric {
+                    primaries,
+                    transfer_function: TransferFunctionWithArgs {
+                        tf: TransferFunction::Named(NamedTransferFunction::Linear),
+                        pow: 1.0,
+                    },
+                    // Only set the luminances if they differ from the defaults of ext_linear.
+                    luminance: Some(lum).filter(|l| *l != Luminance::SRGB),
+                    mastering: None,
+                }
+            }
+        }
+    }
+
+    /// Returns the luminances in cd/m² of the encoded values 0 and 1.
+    pub fn code_value_range(self) -> (f64, f64) {
+        match self {
+            // scRGB is not normalized to the luminance range.
+            TestColorDescription::ScRgb => (0.0, 80.0),
+            _ => {
+                let lum = self.luminance();
+                (lum.min.0, lum.max.0)
+            }
+        }
+    }
+
+    fn luminance(self) -> Luminance {
+        match self {
+            TestColorDescription::None => Luminance::SRGB,
+            TestColorDescription::ScRgb => Luminance::WINDOWS_SCRGB,
+            TestColorDescription::Parametric {
+                transfer_function,
+                luminance,
+                ..
+            } => {
+                let mut lum = Luminance::default_for(transfer_function.tf);
+                if let Some(l) = luminance {
+                    lum.min = l.min;
+                    lum.white = l.white;
+                    if transfer_function.tf
+                        == TransferFunction::Named(NamedTransferFunction::St2084Pq)
+                    {
+                        lum.max.0 = l.min.0 + 10000.0;
+                    } else {
+                        lum.max = l.max;
+                    }
+                }
+                lum
+            }
+        }
+    }
+
+    pub fn primaries(self) -> Primaries {
+        match self {
+            TestColorDescription::None | TestColorDescription::ScRgb => Primaries::SRGB,
+            TestColorDescription::Parametric { primaries, .. } => match primaries {
+                TestPrimaries::Named(n) => n.primaries(),
+                TestPrimaries::Custom(c) => c,
+            },
+        }
+    }
+
+    pub fn render_params(self, xyz_from_lms: ColorMatrix<Xyz, Lms>) -> RenderParams {
+        let lum = self.luminance();
+        match self {
+            TestColorDescription::None => RenderParams {
+                lms_to_local: matrix_from_lms(Primaries::SRGB, lum, xyz_from_lms),
+                tf: TransferFunction::Named(NamedTransferFunction::Gamma22),
+                tf_args: [0.0; 4],
+                peak: 0.0,
+            },
+            TestColorDescription::ScRgb => RenderParams {
+                lms_to_local: matrix_from_lms(Primaries::SRGB, lum, xyz_from_lms),
+                tf: TransferFunction::Named(NamedTransferFunction::Linear),
+                tf_args: [0.0; 4],
+                peak: 0.0,
+            },
+            TestColorDescription::Parametric {
+                transfer_function, ..
+            } => {
+                let mut tf_args = [0.0; 4];
+                match transfer_function.tf {
+                    TransferFunction::Named(n) => {
+                        if n == NamedTransferFunction::St2084Pq {
+                            tf_args[0] = 1.0;
+                        }
+                        if n == NamedTransferFunction::Bt1886 {
+                            let c = (lum.min.0 / lum.max.0) as f32;
+                            let gamma = 1.0 / 2.4;
+                            tf_args[0] = 1.0 / (1.0 - c.powf(gamma));
+                            tf_args[1] = 1.0 - c;
+                            tf_args[2] = c;
+                            tf_args[3] = c.powf(gamma);
+                        }
+                    }
+                    TransferFunction::Pow => {
+                        tf_args[0] = 1.0 / transfer_function.pow;
+                    }
+                }
+                RenderParams {
+                    lms_to_local: matrix_from_lms(self.primaries(), lum, xyz_from_lms),
+                    tf: transfer_function.tf,
+                    tf_args,
+                    peak: 0.0,
+                }
+            }
+        }
+    }
+}
+
+impl TestColorDescription {
+    /// Returns the factor that makes PQ-encoded lumen values absolute.
+    fn pq_absolute_scale(self) -> f32 {
+        (Luminance::ST2084_PQ.white / self.luminance().white).0 as f32
+    }
+
+    /// Describes the description in the on-screen legend.
+    pub fn legend(self) -> String {
+        let lum = self.luminance();
+        let details = match self {
+            TestColorDescription::None => "description: none".to_string(),
+            TestColorDescription::ScRgb => "description: scrgb".to_string(),
+            TestColorDescription::Parametric {
+                primaries,
+                transfer_function,
+                ..
+            } => {
+                let primaries = match primaries {
+                    TestPrimaries::Named(n) => format!("{n:?}"),
+                    TestPrimaries::Custom(p) => {
+                        let xy = |(x, y): (F64, F64)| format!("{:.3},{:.3}", x.0, y.0);
+                        format!("r {} g {} b {} w {}", xy(p.r), xy(p.g), xy(p.b), xy(p.wp),)
+                    }
+                };
+                let tf = match transfer_function.tf {
+                    TransferFunction::Named(n) => format!("{n:?}"),
+                    TransferFunction::Pow => format!("pow {}", transfer_function.pow),
+                };
+                format!("primaries: {primaries}\ntf: {tf}")
+            }
+        };
+        format!(
+            "{details}\nluminance: min {} white {} max {} cd/m²",
+            lum.min.0, lum.white.0, lum.max.0,
+        )
+    }
+
+    /// Returns a description with the same primaries but the given transfer function.
+    fn reference(self, tf: NamedTransferFunction) -> Self {
+        let primaries = match self {
+            TestColorDescription::Parametric { primaries, .. } => primaries,
+            _ => TestPrimaries::Named(NamedPrimaries::Srgb),
+        };
+        TestColorDescription::Parametric {
+            primaries,
+            transfer_function: TransferFunctionWithArgs {
+                tf: TransferFunction::Named(tf),
+                pow: 1.0,
+            },
+            luminance: None,
+            mastering: None,
+        }
+    }
+}
+
+/// Returns the value of a channel in the local color space that corresponds to the
+/// given luminance. Like [`Color::lumen`], 203 is the reference white.
+fn local_peak(lms_to_local: ColorMatrix<Local, Lms>, lumen: f32) -> f32 {
+    // The reference white is LMS (1, 1, 1).
+    let [_, g, _] = &lms_to_local.0;
+    let black = g[3].0;
+    let white = g[0].0 + g[1].0 + g[2].0 + black;
+    (black + (white - black) * lumen as f64 / 203.0) as f32
+}
+
 impl Color {
-    fn to_lab(self) -> [f32; 4] {
+    pub fn to_lab(self) -> [f32; 4] {
         self.to_lab_alpha(1.0)
     }
 
@@ -166,10 +1208,46 @@ impl Color {
             alpha,
         ]
     }
+
+    /// Returns the color with the chromaticity `xy` and the luminance `lumen`.
+    pub fn from_xy(xy: (F64, F64), lumen: f32) -> Self {
+        Self::from_xyy(xy.0 .0, xy.1 .0, lumen as f64).unwrap_or(Self {
+            lumen,
+            ..Self::default()
+        })
+    }
+
+    /// Returns the color with the CIE xyY coordinates where `big_y` is the luminance in
+    /// cd/m². Returns `None` if `y` is not positive.
+    pub fn from_xyy(x: f64, y: f64, big_y: f64) -> Option<Self> {
+        let xyz = xyy_to_xyz(x, y, 1.0)?;
+        let [l, a, b, _] = lms_to_lab(ColorMatrix::XYZ_FROM_LMS.inverse().unwrap() * xyz);
+        Some(Self {
+            lumen: big_y.max(0.0) as f32,
+            lightness: l,
+            chroma: a.hypot(b),
+            hue: (b.atan2(a) / PI * 180.0).rem_euclid(360.0),
+        })
+    }
+
+    /// Returns the CIE xyY coordinates of the color where Y is the luminance in cd/m².
+    /// Returns `None` for black.
+    pub fn to_xyy(self) -> Option<(f64, f64, f64)> {
+        let hue = self.hue as f64 / 180.0 * std::f64::consts::PI;
+        let chroma = self.chroma as f64;
+        let lab = [
+            self.lightness as f64,
+            chroma * hue.cos(),
+            chroma * hue.sin(),
+        ];
+        let xyz = ColorMatrix::XYZ_FROM_LMS * lab_to_lms(lab);
+        let (x, y, rel) = xyz_to_xyy(xyz)?;
+        Some((x, y, rel * self.lumen as f64))
+    }
 }
 
 impl TestPane {
-    pub async fn new<T>(event_loop: &EventLoop<T>) -> Self {
+    pub async fn new<T>(event_loop: &EventLoop<T>, geometry: Rc<Geometry>) -> Self {
         let display_handle = event_loop.owned_display_handle();
         let RawDisplayHandle::Wayland(wl) = *display_handle.display_handle().unwrap().as_ref()
         else {
@@ -186,14 +1264,19 @@ impl TestPane {
         let queue = con.create_local_queue(c"color-test");
         let display = queue.display::<WlDisplay>();
         let singletons = get_singletons(&display);
-        let wl_compositor: WlCompositor = singletons.get(1, 4);
+        let wl_compositor: WlCompositor = singletons.get(1, 6);
         let wl_subcompositor: WlSubcompositor = singletons.get(1, 1);
+        let wp_viewporter: Option<WpViewporter> = singletons.get_opt(1, 1);
         let xdg_wm_base: XdgWmBase = singletons.get(1, 1);
         proxy::set_event_handler(&xdg_wm_base, XdgWmBase::on_ping(|p, serial| p.pong(serial)));
         let wp_color_manager_v1: WpColorManagerV1 = singletons.get(1, 1);
         let supported_features = RefCell::new(HashSet::new());
         let supported_tf = RefCell::new(HashSet::new());
         let supported_primaries = RefCell::new(HashSet::new());
+        let wp_color_representation_manager_v1: Option<WpColorRepresentationManagerV1> =
+            singletons.get_opt(1, 1);
+        let supported_alpha_modes = RefCell::new(HashSet::new());
+        let supported_coefficients = RefCell::new(HashSet::new());
         queue
             .dispatch_scope_async(async |scope| {
                 scope.set_event_handler_local(
@@ -204,9 +1287,19 @@ impl TestPane {
                         primaries: &supported_primaries,
                     },
                 );
+                if let Some(manager) = &wp_color_representation_manager_v1 {
+                    scope.set_event_handler_local(
+                        manager,
+                        ColorRepresentationManagerEventHandler {
+                            alpha_modes: &supported_alpha_modes,
+                            coefficients: &supported_coefficients,
+                        },
+                    );
+                }
                 queue.dispatch_roundtrip_async().await.unwrap();
             })
             .await;
+        let wl_registry = display.get_registry();
         let wl_surface = wl_compositor.create_surface();
         let wp_color_management_surface_v1 = wp_color_manager_v1.get_surface(&wl_surface);
         let wp_color_management_surface_feedback_v1 =
@@ -218,50 +1311,91 @@ impl TestPane {
         let wl_blend_surface = wl_compositor.create_surface();
         let wp_color_management_blend_surface_v1 =
             wp_color_manager_v1.get_surface(&wl_blend_surface);
-        let vulkan_device = VulkanDevice::create().unwrap();
-        let vulkan_surface = vulkan_device
-            .create_surface(wl_display, &wl_surface)
-            .unwrap();
-        let vulkan_blend_surface = vulkan_device
-            .create_surface(wl_display, &wl_blend_surface)
-            .unwrap();
+        let wl_reference_surface = wl_compositor.create_surface();
+        let wp_color_management_reference_surface_v1 =
+            wp_color_manager_v1.get_surface(&wl_reference_surface);
+        let wp_viewport = wp_viewporter.as_ref().map(|v| v.get_viewport(&wl_surface));
+        let wp_blend_viewport = wp_viewporter
+            .as_ref()
+            .map(|v| v.get_viewport(&wl_blend_surface));
+        let wp_tearing_control_manager_v1: Option<WpTearingControlManagerV1> =
+            singletons.get_opt(1, 1);
+        let vulkan = VulkanDevice::create()
+            .and_then(|device| {
+                Ok(Vulkan {
+                    surface: device.create_surface(wl_display, &wl_surface)?,
+                    blend_surface: device.create_surface(wl_display, &wl_blend_surface)?,
+                    reference_surface: device.create_surface(wl_display, &wl_reference_surface)?,
+                    device,
+                })
+            })
+            .map_err(|e| {
+                let msg = error_chain(&e);
+                log::warn!("Could not initialize Vulkan: {msg}");
+                msg
+            });
         let caps = Rc::new(Capablities {
             features: supported_features.into_inner(),
             tf: supported_tf.into_inner(),
             primaries: supported_primaries.into_inner(),
+            alpha_modes: supported_alpha_modes.into_inner(),
+            coefficients: supported_coefficients.into_inner(),
         });
         let state = Rc::new(State {
             caps: caps.clone(),
-            _xdg_wm_base: xdg_wm_base,
-            _wl_compositor: wl_compositor,
+            geometry,
+            xdg_wm_base,
+            wl_display,
+            wl_compositor,
             wl_subcompositor,
+            wp_viewporter,
             wp_color_manager_v1,
             wl_surface,
             wl_blend_surface,
-            wp_color_management_surface_v1,
+            wl_reference_surface,
             wp_color_management_surface_feedback_v1,
             wp_color_management_blend_surface_v1,
+            wp_color_management_reference_surface_v1,
+            wp_viewport,
+            wp_blend_viewport,
+            wp_tearing_control_manager_v1,
+            wp_color_representation_manager_v1,
             xdg_surface: xdg_surface.clone(),
-            _xdg_toplevel: xdg_toplevel.clone(),
-            vulkan_surface,
-            vulkan_blend_surface,
+            xdg_toplevel: xdg_toplevel.clone(),
+            vulkan: RefCell::new(vulkan),
             mutable: RefCell::new(Mutable {
-                scene: TestScene::Fill(Color::default()),
-                width: 0,
-                height: 0,
-                description: TestColorDescription::None,
-                vulkan_tf_args: [0.0; 4],
-                matrix: matrix_from_lms(Primaries::SRGB, Luminance::SRGB),
-                need_render: false,
-                preferred_description: None,
-                pending_description: None,
-                blend_subsurface: None,
+                wp_color_management_surface_v1: Some(wp_color_management_surface_v1),
+                ..Default::default()
             }),
+            last_frame: Default::default(),
             create_description_error_message: Default::default(),
+            live_descriptions: Default::default(),
             preferred_description_error_message: Default::default(),
             preferred_description_data: Default::default(),
+            preferred_identity: Default::default(),
+            announced_identity: Default::default(),
+            last_preferred: Default::default(),
+            feedback_generation: Default::default(),
+            outputs_changed: Default::default(),
+            wl_registry,
+            outputs: Default::default(),
+            output_list: Default::default(),
+            output_error_message: Default::default(),
+            output_data: Default::default(),
+            render_error_message: Default::default(),
+            render_failed: Default::default(),
+            toplevel_states: Default::default(),
+            output_generation: Default::default(),
+            animation_start: Cell::new(Instant::now()),
+            frame_callback: Default::default(),
+            configured: Cell::new(false),
+            configure_deadline: Cell::new(Some(Instant::now() + CONFIGURE_TIMEOUT)),
+            last_render: Default::default(),
         });
         state.get_feedback();
+        proxy::set_event_handler_local(&state.wl_surface, state.clone());
+        // The outputs are bound when the registry announces them.
+        proxy::set_event_handler_local(&state.wl_registry, state.clone());
         proxy::set_event_handler_local(&xdg_surface, state.clone());
         proxy::set_event_handler_local(&xdg_toplevel, state.clone());
         proxy::set_event_handler_local(
@@ -280,6 +1414,12 @@ impl TestPane {
         self.state.create_description_error_message.take()
     }
 
+    /// Returns the number of descriptions created for the test pane that are still
+    /// alive. Only the pending description, if any, should be alive.
+    pub fn live_descriptions(&self) -> usize {
+        self.state.live_descriptions.get()
+    }
+
     pub fn preferred_description_error_message(&self) -> Option<Option<String>> {
         self.state.preferred_description_error_message.take()
     }
@@ -288,110 +1428,584 @@ impl TestPane {
         self.state.preferred_description_data.take()
     }
 
-    pub fn apply_config(&self, description: TestColorDescription, scene: TestScene) {
+    pub fn toplevel_states(&self) -> Option<ToplevelStates> {
+        self.state.toplevel_states.take()
+    }
+
+    pub fn preferred_identity(&self) -> Option<DescriptionIdentity> {
+        self.state.preferred_identity.take()
+    }
+
+    pub fn output_error_message(&self) -> Option<Option<String>> {
+        self.state.output_error_message.take()
+    }
+
+    pub fn output_data(&self) -> Option<OutputData> {
+        self.state.output_data.take()
+    }
+
+    pub fn render_error_message(&self) -> Option<Option<String>> {
+        self.state.render_error_message.take()
+    }
+
+    pub fn output_list(&self) -> Option<Vec<OutputInfo>> {
+        self.state.output_list.take()
+    }
+
+    pub fn has_transfer_queue(&self) -> bool {
+        self.state
+            .vulkan
+            .borrow()
+            .as_ref()
+            .is_ok_and(|v| v.device.has_transfer_queue())
+    }
+
+    pub fn vulkan_error(&self) -> Option<String> {
+        self.state.vulkan.borrow().as_ref().err().cloned()
+    }
+
+    /// Returns the range of swapchain image counts supported by the test pane.
+    pub fn image_count_range(&self) -> Option<(u32, Option<u32>)> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().ok()?;
+        vulkan.surface.image_count_range().ok()
+    }
+
+    /// Returns whether the swapchain of the test pane uses post-multiplied composite
+    /// alpha.
+    pub fn post_multiplied_alpha(&self) -> Option<bool> {
+        let vulkan = self.state.vulkan.borrow();
+        vulkan.as_ref().ok()?.surface.post_multiplied()
+    }
+
+    /// Returns the swapchain color spaces supported by the test pane.
+    pub fn color_spaces(&self) -> Vec<SwapchainColorSpace> {
+        let vulkan = self.state.vulkan.borrow();
+        let Ok(vulkan) = &*vulkan else {
+            return vec![];
+        };
+        vulkan.surface.color_spaces().unwrap_or_default()
+    }
+
+    /// Returns the swapchain extent and surface limits of the test pane.
+    pub fn surface_extent(&self) -> Option<SurfaceExtent> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().ok()?;
+        vulkan.surface.extent().ok()
+    }
+
+    /// Returns the EOTF id, EOTF arguments, and matrix that the shader received for the
+    /// last frame of the test pane.
+    pub fn uploaded_params(&self) -> Option<UploadedParams> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().ok()?;
+        vulkan.surface.uploaded_params()
+    }
+
+    /// Returns the average time between presenting a frame of the test pane and the
+    /// release of its present fence, and the number of samples.
+    pub fn present_latency(&self) -> Option<(Duration, usize)> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().ok()?;
+        vulkan.surface.present_latency()
+    }
+
+    /// Returns whether the content of the test pane does not yet reflect the last
+    /// configuration, for example because a description is not ready yet.
+    pub fn is_updating(&self) -> bool {
+        if self.state.vulkan.borrow().is_err() || self.state.render_failed.get() {
+            // Errors are shown instead.
+            return false;
+        }
+        let m = self.state.mutable.borrow();
+        !m.frozen
+            && m.paused.is_none()
+            && (m.need_render
+                || m.need_commit
+                || m.pending_description.is_some()
+                || m.pending_reference.is_some()
+                || m.pending_blend_description.is_some())
+    }
+
+    /// Returns the size of the toplevel in surface coordinates and the buffer scale.
+    pub fn toplevel_size(&self) -> ((i32, i32), i32) {
+        let m = self.state.mutable.borrow();
+        ((m.width, m.height), m.buffer_scale)
+    }
+
+    /// Returns whether the surface supports `wl_surface.damage_buffer`.
+    pub fn supports_damage_buffer(&self) -> bool {
+        proxy::version(&*self.state.wl_surface) >= 4
+    }
+
+    /// Returns the time at which `configure_timeout` should be called, if any.
+    pub fn configure_deadline(&self) -> Option<Instant> {
+        self.state.configure_deadline.get()
+    }
+
+    /// Called when the compositor has not configured the test pane in time. The
+    /// surface is committed again in case the initial commit got lost. Buffers cannot
+    /// be attached before the first configure.
+    pub fn configure_timeout(&self) {
+        if self.state.configure_deadline.take().is_some() && !self.state.configured.get() {
+            log::warn!("The compositor has not configured the test pane");
+            self.state.wl_surface.commit();
+        }
+    }
+
+    /// Returns whether the compositor has not configured the test pane within
+    /// `CONFIGURE_TIMEOUT`.
+    pub fn waiting_for_configure(&self) -> bool {
+        !self.state.configured.get() && self.state.configure_deadline.get().is_none()
+    }
+
+    /// Returns the time at which the surfaces should be trimmed, if any.
+    pub fn trim_deadline(&self) -> Option<Instant> {
+        self.state.last_render.get().map(|t| t + IDLE_TRIM)
+    }
+
+    /// Drops excess pooled fill buffers and returns unused memory to the driver.
+    pub fn trim(&self) {
+        self.state.last_render.take();
+        let vulkan = self.state.vulkan.borrow();
+        let Ok(vulkan) = &*vulkan else {
+            return;
+        };
+        let m = self.state.mutable.borrow();
+        for surface in vulkan
+            .surfaces()
+            .chain(m.blend_layers.iter().map(|l| &l.vulkan))
+        {
+            if let Err(e) = surface.trim() {
+                log::warn!("Could not trim a surface: {}", error_chain(&e));
+            }
+        }
+    }
+
+    /// Returns the memory allocated by the device and the number of fill buffers that
+    /// the surfaces keep for reuse.
+    pub fn memory_stats(&self) -> Option<(MemoryStats, usize)> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().ok()?;
+        let m = self.state.mutable.borrow();
+        let pooled_fill_buffers = vulkan
+            .surfaces()
+            .chain(m.blend_layers.iter().map(|l| &l.vulkan))
+            .map(|s| s.pooled_fill_buffers())
+            .sum();
+        Some((vulkan.device.memory_stats(), pooled_fill_buffers))
+    }
+
+    /// Uploads the pixels shown by `TestScene::Image`.
+    pub fn set_image(&self, image: &ImageFile) -> Result<(), String> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().map_err(|e| e.clone())?;
+        let texture = vulkan
+            .device
+            .create_texture(image.width, image.height, &image.pixels)
+            .map_err(|e| error_chain(&e))?;
         let m = &mut *self.state.mutable.borrow_mut();
-        if m.description != description {
-            self.state.create_description_error_message.set(Some(None));
-            m.description = description;
+        m.image = Some(texture);
+        if m.scene == TestScene::Image {
             m.need_render = true;
-            if let Some(prev) = m.pending_description.take() {
-                prev.destroy();
-            }
-            let s1 = &self.state.wp_color_management_surface_v1;
-            let s2 = &self.state.wp_color_management_blend_surface_v1;
-            match description {
+        }
+        Ok(())
+    }
+
+    pub fn has_tearing_control(&self) -> bool {
+        self.state.wp_tearing_control_manager_v1.is_some()
+    }
+
+    pub fn has_color_representation(&self) -> bool {
+        self.state.wp_color_representation_manager_v1.is_some()
+    }
+
+    /// Returns the present modes supported by the test pane.
+    pub fn present_modes(&self) -> Vec<PresentMode> {
+        let vulkan = self.state.vulkan.borrow();
+        let Ok(vulkan) = &*vulkan else {
+            return vec![];
+        };
+        vulkan.surface.present_modes().unwrap_or_default()
+    }
+
+    pub fn has_viewporter(&self) -> bool {
+        self.state.wp_viewport.is_some()
+    }
+
+    /// Returns the intermediate values of the color pipeline for the color as JSON.
+    pub fn trace_color(&self, color: Color) -> String {
+        let m = &*self.state.mutable.borrow();
+        let params = m.content_description().render_params(m.xyz_from_lms);
+        let options = trace::Options {
+            gamut_mapping: m.gamut_mapping,
+            output_clamp: m.output_clamp,
+            channel_adjustment: m.channel_adjustment,
+        };
+        trace::trace(color.to_lab(), &params, &options, m.xyz_from_lms)
+    }
+
+    /// Renders the last frame of the test pane into an offscreen image and computes the
+    /// histogram of its luminances. Subsurfaces are not included.
+    pub fn luminance_histogram(&self, scale: BinScale) -> Result<Histogram, String> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().map_err(|e| e.clone())?;
+        let frame = self.state.last_frame.borrow();
+        let Some(f) = &*frame else {
+            return Err("No frame has been rendered".to_string());
+        };
+        let pixels = vulkan
+            .device
+            .render_offscreen(
+                f.width,
+                f.height,
+                f.scene.clone(),
+                f.clear,
+                &f.overlay,
+                &f.params,
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(histogram::compute(&pixels, f.description, scale))
+    }
+
+    /// Reads back the bottom half of the last frame of the blend scene and the content
+    /// of its sub-surface, and measures how much blending them in other spaces differs
+    /// from the blend computed by the client.
+    pub fn blend_delta(&self) -> Result<BlendDelta, String> {
+        let vulkan = self.state.vulkan.borrow();
+        let vulkan = vulkan.as_ref().map_err(|e| e.clone())?;
+        let frame = self.state.last_frame.borrow();
+        let m = self.state.mutable.borrow();
+        let (Some(f), TestScene::Blend(colors, alpha)) = (&*frame, m.scene) else {
+            return Err("The blend scene has not been rendered".to_string());
+        };
+        if !matches!(f.scene, Scene::BlendRight(..)) {
+            return Err("The blend scene has not been rendered".to_string());
+        }
+        let blend_params = m.blend_params();
+        let (width, height) = (f.width as usize, f.height as usize);
+        let half = width / 2;
+        let render = |width, scene, clear, params| {
+            vulkan
+                .device
+                .render_offscreen(width, f.height, scene, clear, &Overlay::default(), params)
+                .map_err(|e| error_chain(&e))
+        };
+        let parent = render(f.width, f.scene.clone(), f.clear, &f.params)?;
+        let foreground = Scene::BlendLeft(colors[1].to_lab_alpha(alpha));
+        let subsurface = render(half as u32, foreground, None, &blend_params)?;
+        // The bottom halves of the left and right quadrants. The rows and columns on
+        // the boundaries are excluded if the size is odd.
+        let quadrant = |pixels: &[[f32; 4]], stride: usize, x: usize| -> Vec<[f32; 4]> {
+            (height - height / 2..height)
+                .flat_map(|y| pixels[y * stride + x..][..half].iter().copied())
+                .collect()
+        };
+        let background = quadrant(&parent, width, 0);
+        let client = quadrant(&parent, width, width - half);
+        let foreground = quadrant(&subsurface, half, 0);
+        blend_delta::compute(
+            Quadrant {
+                pixels: &background,
+                params: &f.params,
+            },
+            Quadrant {
+                pixels: &foreground,
+                params: &blend_params,
+            },
+            Quadrant {
+                pixels: &client,
+                params: &f.params,
+            },
+        )
+        .ok_or_else(|| "The color space matrix is not invertible".to_string())
+    }
+
+    pub fn apply_config(&self, config: &TestConfig) {
+        let unfrozen = {
+            let m = &mut *self.state.mutable.borrow_mut();
+            let unfrozen = m.frozen && !config.freeze;
+            m.frozen = config.freeze;
+            unfrozen
+        };
+        if unfrozen {
+            // Feedback events that arrived while frozen were ignored.
+            self.state.get_feedback();
+            self.state.get_output_feedback();
+        }
+        if config.freeze {
+            return;
+        }
+        let m = &mut *self.state.mutable.borrow_mut();
+        if m.paused.is_some() != config.pause {
+            let start = &self.state.animation_start;
+            match m.paused.take() {
+                // Continue animations where they were paused.
+                Some(time) => start.set(Instant::now() - time),
+                None => m.paused = Some(start.get().elapsed()),
+            }
+        }
+        let description = config.description;
+        let prev_blend_description = m.blend_description.unwrap_or(m.description);
+        let mut reapply_description = false;
+        if m.color_space != config.color_space
+            && config.color_space == SwapchainColorSpace::PassThrough
+        {
+            // The WSI owns the color management surface while the swapchain exists. It
+            // must be gone before one is created below.
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.surface.destroy_swapchain();
+            }
+        }
+        if m.wp_color_management_surface_v1.is_some() != config.color_management_surface {
+            match config.color_management_surface {
+                true => {
+                    m.wp_color_management_surface_v1 = Some(
+                        self.state
+                            .wp_color_manager_v1
+                            .get_surface(&self.state.wl_surface),
+                    );
+                    reapply_description = true;
+                }
+                false => {
+                    // Destroying the object also removes the description from the
+                    // surface.
+                    if let Some(s) = m.wp_color_management_surface_v1.take() {
+                        s.destroy();
+                    }
+                    m.attached_description = TestColorDescription::None;
+                }
+            }
+            match config.keep_buffer {
+                true => m.need_commit = true,
+                false => m.need_render = true,
+            }
+        }
+        if m.description != description || reapply_description {
+            self.state.create_description_error_message.set(Some(None));
+            m.description = description;
+            m.fallback_description = None;
+            match config.keep_buffer {
+                true => m.need_commit = true,
+                false => m.need_render = true,
+            }
+            if let Some(prev) = m.pending_description.take() {
+                prev.destroy();
+            }
+            let s1 = &m.wp_color_management_surface_v1;
+            match description {
                 TestColorDescription::None => {
-                    m.matrix = matrix_from_lms(Primaries::SRGB, Luminance::SRGB);
-                    s1.unset_image_description();
-                    s2.unset_image_description();
+                    if let Some(s1) = s1 {
+                        s1.unset_image_description();
+                    }
+                    m.description_attached(description);
                 }
                 TestColorDescription::ScRgb => {
-                    m.matrix = matrix_from_lms(Primaries::SRGB, Luminance::WINDOWS_SCRGB);
                     let scrgb = self.state.wp_color_manager_v1.create_windows_scrgb();
-                    s1.set_image_description(&scrgb, WpColorManagerV1RenderIntent::PERCEPTUAL);
-                    s2.set_image_description(&scrgb, WpColorManagerV1RenderIntent::PERCEPTUAL);
+                    if let Some(s1) = s1 {
+                        s1.set_image_description(&scrgb, WpColorManagerV1RenderIntent::PERCEPTUAL);
+                    }
                     scrgb.destroy();
+                    if s1.is_some() {
+                        m.description_attached(description);
+                    }
                 }
                 TestColorDescription::Parametric {
                     primaries,
                     transfer_function,
                     luminance,
+                    mastering,
                 } => {
-                    let mut lum = match transfer_function.tf {
-                        TransferFunction::Named(NamedTransferFunction::St2084Pq) => {
-                            Luminance::ST2084_PQ
-                        }
-                        TransferFunction::Named(NamedTransferFunction::Bt1886) => Luminance::BT1886,
-                        _ => Luminance::SRGB,
-                    };
-                    if let Some(l) = luminance {
-                        lum.min = l.min;
-                        lum.white = l.white;
-                        if transfer_function.tf
-                            == TransferFunction::Named(NamedTransferFunction::St2084Pq)
-                        {
-                            lum.max.0 = l.min.0 + 10000.0;
-                        } else {
-                            lum.max = l.max;
+                    let desc = self.state.create_parametric_description(
+                        primaries,
+                        transfer_function,
+                        luminance,
+                        mastering,
+                        description.luminance(),
+                    );
+                    struct Eh(WpImageDescriptionV1, Rc<State>, TestColorDescription);
+                    // The handler is dropped when the description is destroyed.
+                    impl Drop for Eh {
+                        fn drop(&mut self) {
+                            let live = &self.1.live_descriptions;
+                            live.set(live.get() - 1);
                         }
                     }
-                    {
-                        match transfer_function.tf {
-                            TransferFunction::Named(n) => {
-                                if n == NamedTransferFunction::Bt1886 {
-                                    let c = (lum.min.0 / lum.max.0) as f32;
-                                    let gamma = 1.0 / 2.4;
-                                    m.vulkan_tf_args[0] = 1.0 / (1.0 - c.powf(gamma));
-                                    m.vulkan_tf_args[1] = 1.0 - c;
-                                    m.vulkan_tf_args[2] = c;
-                                    m.vulkan_tf_args[3] = c.powf(gamma);
+                    impl WpImageDescriptionV1EventHandler for Eh {
+                        fn failed(
+                            &self,
+                            _slf: &WpImageDescriptionV1Ref,
+                            _cause: WpImageDescriptionV1Cause,
+                            msg: &str,
+                        ) {
+                            let m = &mut *self.1.mutable.borrow_mut();
+                            m.description_failed();
+                            self.1
+                                .create_description_error_message
+                                .set(Some(Some(msg.to_string())));
+                            self.0.destroy();
+                            self.1.render_frame(m);
+                        }
+
+                        fn ready(&self, slf: &WpImageDescriptionV1Ref, identity: u32) {
+                            log::info!("created main description: identity {identity}");
+                            let verify = {
+                                let m = &mut *self.1.mutable.borrow_mut();
+                                m.pending_description = None;
+                                self.1.create_description_error_message.set(Some(None));
+                                let attached = m.wp_color_management_surface_v1.is_some();
+                                if let Some(s) = &m.wp_color_management_surface_v1 {
+                                    s.set_image_description(
+                                        slf,
+                                        WpColorManagerV1RenderIntent::PERCEPTUAL,
+                                    );
+                                    m.description_attached(self.2);
                                 }
-                            }
-                            TransferFunction::Pow => {
-                                m.vulkan_tf_args[0] = 1.0 / transfer_function.pow;
+                                self.0.destroy();
+                                self.1.render_frame(m);
+                                attached && m.verify_feedback
+                            };
+                            // Requested after the commit so that the compositor knows
+                            // the new description.
+                            if verify {
+                                self.1.get_feedback();
                             }
                         }
-                        let primaries = match primaries {
-                            TestPrimaries::Named(n) => n.primaries(),
-                            TestPrimaries::Custom(c) => c,
-                        };
-                        m.matrix = matrix_from_lms(primaries, lum);
                     }
-                    let c = self.state.wp_color_manager_v1.create_parametric_creator();
-                    match primaries {
-                        TestPrimaries::Named(n) => c.set_primaries_named(n.wayland()),
-                        TestPrimaries::Custom(p) => {
-                            let map = |p: F64| (p.0 * 1_000_000.0) as i32;
-                            c.set_primaries(
-                                map(p.r.0),
-                                map(p.r.1),
-                                map(p.g.0),
-                                map(p.g.1),
-                                map(p.b.0),
-                                map(p.b.1),
-                                map(p.wp.0),
-                                map(p.wp.1),
-                            );
-                        }
+                    let live = &self.state.live_descriptions;
+                    live.set(live.get() + 1);
+                    proxy::set_event_handler_local(
+                        &desc,
+                        Eh(desc.clone(), self.state.clone(), description),
+                    );
+                    m.pending_description = Some(desc);
+                }
+            }
+        }
+        let blend_layer_config = match config.scene {
+            TestScene::BlendLayers(_, layers) => (layers.count, layers.stacking),
+            _ => Default::default(),
+        };
+        let blend_layers_changed = m.blend_layer_config != blend_layer_config;
+        if blend_layers_changed {
+            m.blend_layer_config = blend_layer_config;
+            self.state.create_blend_layers(m);
+            m.need_render = true;
+        }
+        m.blend_description = config.blend_description;
+        let blend_description = m.blend_description.unwrap_or(description);
+        // New layers do not have a description yet.
+        if prev_blend_description != blend_description || blend_layers_changed {
+            match config.keep_buffer {
+                true => m.need_commit = true,
+                false => m.need_render = true,
+            }
+            if let Some(prev) = m.pending_blend_description.take() {
+                prev.destroy();
+            }
+            match blend_description {
+                TestColorDescription::None => {
+                    for s2 in self.state.blend_cm_surfaces(m) {
+                        s2.unset_image_description();
+                    }
+                }
+                TestColorDescription::ScRgb => {
+                    let scrgb = self.state.wp_color_manager_v1.create_windows_scrgb();
+                    for s2 in self.state.blend_cm_surfaces(m) {
+                        s2.set_image_description(&scrgb, WpColorManagerV1RenderIntent::PERCEPTUAL);
                     }
-                    match transfer_function.tf {
-                        TransferFunction::Named(n) => {
-                            c.set_tf_named(n.wayland());
+                    scrgb.destroy();
+                }
+                TestColorDescription::Parametric {
+                    primaries,
+                    transfer_function,
+                    luminance,
+                    mastering,
+                } => {
+                    let desc = self.state.create_parametric_description(
+                        primaries,
+                        transfer_function,
+                        luminance,
+                        mastering,
+                        blend_description.luminance(),
+                    );
+                    struct Eh(WpImageDescriptionV1, Rc<State>);
+                    impl WpImageDescriptionV1EventHandler for Eh {
+                        fn failed(
+                            &self,
+                            _slf: &WpImageDescriptionV1Ref,
+                            _cause: WpImageDescriptionV1Cause,
+                            msg: &str,
+                        ) {
+                            let m = &mut *self.1.mutable.borrow_mut();
+                            m.pending_blend_description = None;
+                            self.1
+                                .create_description_error_message
+                                .set(Some(Some(format!("sub-surface: {msg}"))));
+                            self.0.destroy();
                         }
-                        TransferFunction::Pow => {
-                            c.set_tf_power((transfer_function.pow * 10_000.0) as u32);
+
+                        fn ready(&self, slf: &WpImageDescriptionV1Ref, identity: u32) {
+                            log::info!("created blend description: identity {identity}");
+                            let m = &mut *self.1.mutable.borrow_mut();
+                            m.pending_blend_description = None;
+                            for s2 in self.1.blend_cm_surfaces(m) {
+                                s2.set_image_description(
+                                    slf,
+                                    WpColorManagerV1RenderIntent::PERCEPTUAL,
+                                );
+                            }
+                            self.0.destroy();
+                            self.1.render_frame(m);
                         }
                     }
-                    if let Some(l) = luminance {
-                        c.set_luminances(
-                            (l.min.0 * 10000.0) as u32,
-                            l.max.0 as u32,
-                            l.white.0 as u32,
-                        );
+                    proxy::set_event_handler_local(&desc, Eh(desc.clone(), self.state.clone()));
+                    m.pending_blend_description = Some(desc);
+                }
+            }
+        }
+        let reference = match config.scene {
+            TestScene::CompareEotf(_, tf, _) => Some(description.reference(tf)),
+            s if config.srgb_reference && !s.has_subsurfaces() => Some(TestColorDescription::None),
+            _ => None,
+        };
+        if m.reference != reference {
+            m.reference = reference;
+            m.need_render = true;
+            if let Some(prev) = m.pending_reference.take() {
+                prev.destroy();
+            }
+            match reference {
+                None => {
+                    if let Some(ss) = m.reference_subsurface.take() {
+                        ss.destroy();
                     }
-                    c.set_max_cll(lum.white.0 as _);
-                    c.set_max_fall(lum.white.0 as _);
-                    let desc = c.create();
+                }
+                Some(_) if m.reference_subsurface.is_none() => {
+                    let ss = self
+                        .state
+                        .wl_subcompositor
+                        .get_subsurface(&self.state.wl_reference_surface, &self.state.wl_surface);
+                    m.reference_subsurface = Some(ss);
+                }
+                _ => {}
+            }
+            match reference {
+                Some(
+                    r @ TestColorDescription::Parametric {
+                        primaries,
+                        transfer_function,
+                        luminance,
+                        mastering,
+                    },
+                ) => {
+                    let desc = self.state.create_parametric_description(
+                        primaries,
+                        transfer_function,
+                        luminance,
+                        mastering,
+                        r.luminance(),
+                    );
                     struct Eh(WpImageDescriptionV1, Rc<State>);
                     impl WpImageDescriptionV1EventHandler for Eh {
                         fn failed(
@@ -401,23 +2015,19 @@ impl TestPane {
                             msg: &str,
                         ) {
                             let m = &mut *self.1.mutable.borrow_mut();
-                            m.pending_description = None;
+                            m.pending_reference = None;
                             self.1
                                 .create_description_error_message
-                                .set(Some(Some(msg.to_string())));
+                                .set(Some(Some(format!("reference: {msg}"))));
                             self.0.destroy();
                         }
 
-                        fn ready(&self, slf: &WpImageDescriptionV1Ref, _identity: u32) {
+                        fn ready(&self, slf: &WpImageDescriptionV1Ref, identity: u32) {
+                            log::info!("created reference description: identity {identity}");
                             let m = &mut *self.1.mutable.borrow_mut();
-                            m.pending_description = None;
-                            self.1.create_description_error_message.set(Some(None));
-                            self.1.wp_color_management_surface_v1.set_image_description(
-                                slf,
-                                WpColorManagerV1RenderIntent::PERCEPTUAL,
-                            );
+                            m.pending_reference = None;
                             self.1
-                                .wp_color_management_blend_surface_v1
+                                .wp_color_management_reference_surface_v1
                                 .set_image_description(
                                     slf,
                                     WpColorManagerV1RenderIntent::PERCEPTUAL,
@@ -427,14 +2037,24 @@ impl TestPane {
                         }
                     }
                     proxy::set_event_handler_local(&desc, Eh(desc.clone(), self.state.clone()));
-                    m.pending_description = Some(desc);
+                    m.pending_reference = Some(desc);
                 }
+                Some(_) => self
+                    .state
+                    .wp_color_management_reference_surface_v1
+                    .unset_image_description(),
+                None => {}
             }
         }
+        if m.render_description != config.render_description {
+            m.render_description = config.render_description;
+            m.need_render = true;
+        }
+        let scene = config.scene;
         if m.scene != scene {
             m.scene = scene;
             match scene {
-                TestScene::Blend(..) => {
+                TestScene::Blend(..) | TestScene::BlendBanding(..) => {
                     if m.blend_subsurface.is_none() {
                         let ss = self
                             .state
@@ -451,11 +2071,202 @@ impl TestPane {
             }
             m.need_render = true;
         }
+        if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+            vulkan.device.set_upload_mode(config.upload_mode);
+        }
+        if m.alpha_mode != config.alpha_mode {
+            m.alpha_mode = config.alpha_mode;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_alpha_mode(config.alpha_mode);
+            }
+            m.need_render = true;
+        }
+        if m.image_count != config.image_count {
+            m.image_count = config.image_count;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_image_count(config.image_count);
+            }
+            m.need_render = true;
+        }
+        if m.present_mode != config.present_mode {
+            m.present_mode = config.present_mode;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_present_mode(config.present_mode);
+            }
+            if config.present_mode == PresentMode::Immediate {
+                // Must happen before the swapchain is recreated. See
+                // `State::update_tearing_control`.
+                if let Some(tc) = m.wp_tearing_control_v1.take() {
+                    tc.destroy();
+                }
+            }
+            m.need_render = true;
+        }
+        m.verify_feedback = config.verify_feedback;
+        if m.allow_tearing != config.allow_tearing {
+            m.allow_tearing = config.allow_tearing;
+            if let Some(tc) = &m.wp_tearing_control_v1 {
+                // The hint is double-buffered and applied by the next commit.
+                tc.set_presentation_hint(tearing_hint(config.allow_tearing));
+                m.need_commit = true;
+            }
+        }
+        if m.representation != config.representation {
+            m.representation = config.representation;
+            if let Some(manager) = &self.state.wp_color_representation_manager_v1 {
+                // Destroying the object unsets all metadata with the next commit.
+                if let Some(s) = m.wp_color_representation_surface_v1.take() {
+                    s.destroy();
+                }
+                let r = config.representation;
+                if r.alpha_mode.is_some() || r.coefficients.is_some() {
+                    let s = manager.get_surface(&self.state.wl_surface);
+                    if let Some(alpha_mode) = r.alpha_mode {
+                        s.set_alpha_mode(alpha_mode);
+                    }
+                    if let Some((coefficients, range)) = r.coefficients {
+                        s.set_coefficients_and_range(coefficients, range);
+                    }
+                    m.wp_color_representation_surface_v1 = Some(s);
+                }
+                m.need_commit = true;
+            }
+        }
+        if m.dither_bits != config.dither_bits {
+            m.dither_bits = config.dither_bits;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_dither(config.dither_bits);
+            }
+            m.need_render = true;
+        }
+        if m.gamut_mapping != config.gamut_mapping {
+            m.gamut_mapping = config.gamut_mapping;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_gamut_mapping(config.gamut_mapping);
+            }
+            m.need_render = true;
+        }
+        if m.gradient_space != config.gradient_space {
+            m.gradient_space = config.gradient_space;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_gradient_space(config.gradient_space);
+            }
+            m.need_render = true;
+        }
+        if m.color_space != config.color_space {
+            m.color_space = config.color_space;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.surface.set_color_space(config.color_space);
+            }
+            m.need_render = true;
+        }
+        if m.measure_present_latency != config.measure_present_latency {
+            m.measure_present_latency = config.measure_present_latency;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan
+                    .surface
+                    .set_measure_present_latency(config.measure_present_latency);
+            }
+        }
+        if m.output_clamp != config.output_clamp {
+            m.output_clamp = config.output_clamp;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan.device.set_output_clamp(config.output_clamp);
+            }
+            m.need_render = true;
+        }
+        if m.channel_adjustment != config.channel_adjustment {
+            m.channel_adjustment = config.channel_adjustment;
+            if let Ok(vulkan) = &*self.state.vulkan.borrow() {
+                vulkan
+                    .device
+                    .set_channel_adjustment(config.channel_adjustment);
+            }
+            m.need_render = true;
+        }
+        let output_changed = m.fullscreen_output != config.fullscreen_output;
+        if m.fullscreen != config.fullscreen || (config.fullscreen && output_changed) {
+            match config.fullscreen {
+                true => {
+                    let outputs = self.state.outputs.borrow();
+                    let output = config
+                        .fullscreen_output
+                        .and_then(|g| outputs.iter().find(|o| o.global == g));
+                    self.state
+                        .xdg_toplevel
+                        .set_fullscreen(output.map(|o| &*o.wl_output));
+                }
+                false => self.state.xdg_toplevel.unset_fullscreen(),
+            }
+        }
+        m.fullscreen = config.fullscreen;
+        m.fullscreen_output = config.fullscreen_output;
+        if m.clear != config.clear {
+            m.clear = config.clear;
+            m.need_render = true;
+        }
+        if m.overlay != config.overlay {
+            m.overlay = config.overlay;
+            m.need_render = true;
+        }
+        if m.legend != config.legend {
+            m.legend = config.legend;
+            m.need_render = true;
+        }
+        if m.peak_lumen != config.peak_lumen {
+            m.peak_lumen = config.peak_lumen;
+            m.need_render = true;
+        }
+        // Singular matrices are ignored so that the last invertible matrix stays in use.
+        if m.xyz_from_lms != config.xyz_from_lms && config.xyz_from_lms.inverse().is_some() {
+            m.xyz_from_lms = config.xyz_from_lms;
+            m.need_render = true;
+        }
+        let viewport = config.viewport.filter(|_| self.has_viewporter());
+        if m.viewport != viewport {
+            m.viewport = viewport;
+            m.need_render = true;
+        }
+        let damage = config.damage.map(|mut d| {
+            if !self.supports_damage_buffer() {
+                d.coordinates = DamageCoordinates::Surface;
+            }
+            d
+        });
+        if m.damage != damage {
+            m.damage = damage;
+            m.need_render = true;
+        }
         self.state.render_frame(m);
     }
 
     pub fn dispatch(&self) {
         self.queue.dispatch_pending().unwrap();
+        // Compositors do not necessarily send preferred_changed when the surface moves
+        // to another output.
+        if self.state.outputs_changed.take() {
+            self.state.get_feedback();
+        }
+        if self.state.is_animating() && self.state.frame_callback.borrow().is_none() {
+            let m = &mut *self.state.mutable.borrow_mut();
+            m.need_render = true;
+            self.state.render_frame(m);
+        }
+    }
+
+    /// Renders a single frame while rendering is paused. Animated scenes advance by
+    /// one frame at 60 Hz.
+    pub fn step(&self) {
+        let m = &mut *self.state.mutable.borrow_mut();
+        let Some(time) = &mut m.paused else {
+            return;
+        };
+        if matches!(m.scene, TestScene::MovingBar(..)) {
+            *time += Duration::from_secs(1) / 60;
+        }
+        m.step = true;
+        m.need_render = true;
+        self.state.render_frame(m);
     }
 
     pub async fn wait_for_events(&self) {
@@ -463,6 +2274,12 @@ impl TestPane {
     }
 }
 
+impl Drop for TestPane {
+    fn drop(&mut self) {
+        self.state.destroy();
+    }
+}
+
 struct ColorManagerEventHandler<'a> {
     features: &'a RefCell<HashSet<WpColorManagerV1Feature>>,
     tf: &'a RefCell<HashSet<WpColorManagerV1TransferFunction>>,
@@ -487,64 +2304,552 @@ impl WpColorManagerV1EventHandler for ColorManagerEventHandler<'_> {
     }
 }
 
+struct ColorRepresentationManagerEventHandler<'a> {
+    alpha_modes: &'a RefCell<HashSet<WpColorRepresentationSurfaceV1AlphaMode>>,
+    coefficients: &'a RefCell<
+        HashSet<(
+            WpColorRepresentationSurfaceV1Coefficients,
+            WpColorRepresentationSurfaceV1Range,
+        )>,
+    >,
+}
+
+impl WpColorRepresentationManagerV1EventHandler for ColorRepresentationManagerEventHandler<'_> {
+    fn supported_alpha_mode(
+        &self,
+        _slf: &WpColorRepresentationManagerV1Ref,
+        alpha_mode: WpColorRepresentationSurfaceV1AlphaMode,
+    ) {
+        self.alpha_modes.borrow_mut().insert(alpha_mode);
+    }
+
+    fn supported_coefficients_and_ranges(
+        &self,
+        _slf: &WpColorRepresentationManagerV1Ref,
+        coefficients: WpColorRepresentationSurfaceV1Coefficients,
+        range: WpColorRepresentationSurfaceV1Range,
+    ) {
+        self.coefficients.borrow_mut().insert((coefficients, range));
+    }
+}
+
 impl State {
-    fn render_frame(&self, m: &mut Mutable) {
-        if !m.need_render || m.pending_description.is_some() {
-            return;
-        }
-        if m.width <= 1 || m.height <= 1 {
-            return;
+    fn create_parametric_description(
+        &self,
+        primaries: TestPrimaries,
+        transfer_function: TransferFunctionWithArgs,
+        luminance: Option<Luminance>,
+        mastering: Option<Mastering>,
+        lum: Luminance,
+    ) -> WpImageDescriptionV1 {
+        let c = self.wp_color_manager_v1.create_parametric_creator();
+        match primaries {
+            TestPrimaries::Named(n) => c.set_primaries_named(n.wayland()),
+            TestPrimaries::Custom(p) => {
+                let map = chromaticity_to_protocol;
+                c.set_primaries(
+                    map(p.r.0),
+                    map(p.r.1),
+                    map(p.g.0),
+                    map(p.g.1),
+                    map(p.b.0),
+                    map(p.b.1),
+                    map(p.wp.0),
+                    map(p.wp.1),
+                );
+            }
         }
-        let tf = match m.description {
-            TestColorDescription::None => TransferFunction::Named(NamedTransferFunction::Gamma22),
-            TestColorDescription::ScRgb => TransferFunction::Named(NamedTransferFunction::Linear),
-            TestColorDescription::Parametric {
-                transfer_function, ..
-            } => transfer_function.tf,
-        };
-        let scene = match m.scene {
-            TestScene::Fill(color) => Scene::Fill(color.to_lab()),
-            TestScene::FillLeftRight(colors) => Scene::FillLeftRight(colors.map(|c| c.to_lab())),
-            TestScene::FillTopBottom(colors) => Scene::FillTopBottom(colors.map(|c| c.to_lab())),
-            TestScene::FillFour(colors) => Scene::FillFour(colors.map(|c| c.to_lab())),
-            TestScene::CenterBox(colors, size) => {
-                Scene::CenterBox(colors.map(|c| c.to_lab()), size / 100.0)
+        match transfer_function.tf {
+            TransferFunction::Named(n) => {
+                c.set_tf_named(n.wayland());
             }
-            TestScene::Grid(colors, rows, cols) => {
-                Scene::Grid(colors.map(|c| c.to_lab()), rows, cols)
+            TransferFunction::Pow => {
+                c.set_tf_power(tf_power_to_protocol(transfer_function.pow));
             }
-            TestScene::Blend(colors, alpha) => {
-                self.vulkan_blend_surface
-                    .render(
-                        m.width as u32 / 2,
-                        m.height as _,
-                        Scene::BlendLeft(colors[1].to_lab_alpha(alpha)),
-                        m.matrix,
-                        tf,
-                        m.vulkan_tf_args,
-                    )
-                    .unwrap();
-                Scene::BlendRight([colors[0].to_lab(), colors[1].to_lab_alpha(alpha)])
+        }
+        if let Some(l) = luminance {
+            let (min, max, white) = l.to_protocol();
+            c.set_luminances(min, max, white);
+        }
+        match mastering {
+            Some(m) => {
+                if let Some(p) = m.primaries {
+                    let map = chromaticity_to_protocol;
+                    c.set_mastering_display_primaries(
+                        map(p.r.0),
+                        map(p.r.1),
+                        map(p.g.0),
+                        map(p.g.1),
+                        map(p.b.0),
+                        map(p.b.1),
+                        map(p.wp.0),
+                        map(p.wp.1),
+                    );
+                }
+                if let Some((min, max)) = m.luminance {
+                    c.set_mastering_luminance(
+                        (min.0 * 10_000.0).round() as u32,
+                        max.0.round() as u32,
+                    );
+                }
+                if let Some(max_cll) = m.max_cll {
+                    c.set_max_cll(max_cll);
+                }
+                if let Some(max_fall) = m.max_fall {
+                    c.set_max_fall(max_fall);
+                }
             }
-        };
-        self.vulkan_surface
-            .render(
-                m.width as _,
-                m.height as _,
-                scene,
-                m.matrix,
-                tf,
-                m.vulkan_tf_args,
-            )
-            .unwrap();
-        m.need_render = false;
+            None => {
+                c.set_max_cll(lum.white.0 as _);
+                c.set_max_fall(lum.white.0 as _);
+            }
+        }
+        c.create()
     }
 
-    fn get_feedback(self: &Rc<Self>) {
-        if self
-            .caps
-            .features
-            .not_contains(&WpColorManagerV1Feature::PARAMETRIC)
+    /// Returns the color management surfaces that use the blend description.
+    fn blend_cm_surfaces<'a>(
+        &'a self,
+        m: &'a Mutable,
+    ) -> impl Iterator<Item = &'a WpColorManagementSurfaceV1> {
+        let layers = m.blend_layers.iter();
+        iter::once(&self.wp_color_management_blend_surface_v1)
+            .chain(layers.map(|l| &l.wp_color_management_surface_v1))
+    }
+
+    /// Replaces the layers of `TestScene::BlendLayers` according to
+    /// `m.blend_layer_config`.
+    fn create_blend_layers(&self, m: &mut Mutable) {
+        for layer in m.blend_layers.drain(..) {
+            layer.destroy();
+        }
+        let vulkan = self.vulkan.borrow();
+        let Ok(vulkan) = &*vulkan else {
+            return;
+        };
+        let (count, stacking) = m.blend_layer_config;
+        let mut layers = Vec::<BlendLayer>::with_capacity(count);
+        for _ in 0..count {
+            let wl_surface = self.wl_compositor.create_surface();
+            let surface = match vulkan.device.create_surface(self.wl_display, &wl_surface) {
+                Ok(s) => s,
+                Err(e) => {
+                    wl_surface.destroy();
+                    self.check_render(Err(e));
+                    break;
+                }
+            };
+            let wl_subsurface = self
+                .wl_subcompositor
+                .get_subsurface(&wl_surface, &self.wl_surface);
+            // New sub-surfaces are placed at the top of the stack.
+            match (stacking, layers.last()) {
+                (LayerStacking::PlaceAbove, None) => wl_subsurface.place_above(&self.wl_surface),
+                (LayerStacking::PlaceAbove, Some(prev)) => {
+                    wl_subsurface.place_above(&prev.wl_surface)
+                }
+                (LayerStacking::PlaceBelow, None) => {}
+                (LayerStacking::PlaceBelow, Some(prev)) => {
+                    wl_subsurface.place_below(&prev.wl_surface)
+                }
+            }
+            layers.push(BlendLayer {
+                wp_color_management_surface_v1: self.wp_color_manager_v1.get_surface(&wl_surface),
+                wp_viewport: self
+                    .wp_viewporter
+                    .as_ref()
+                    .map(|v| v.get_viewport(&wl_surface)),
+                wl_subsurface,
+                wl_surface,
+                vulkan: surface,
+            });
+        }
+        if stacking == LayerStacking::PlaceBelow {
+            layers.reverse();
+        }
+        m.blend_layers = layers;
+    }
+
+    /// Returns whether frames of an animated scene can currently be rendered.
+    fn is_animating(&self) -> bool {
+        let m = self.mutable.borrow();
+        matches!(m.scene, TestScene::MovingBar(..))
+            && !m.frozen
+            && m.paused.is_none()
+            && m.width > 1
+            && m.height > 1
+            && self.vulkan.borrow().is_ok()
+            && !self.render_failed.get()
+    }
+
+    /// Creates the tearing control of the main surface after a frame has been presented
+    /// with a present mode other than immediate.
+    ///
+    /// With the immediate present mode, the Vulkan WSI creates its own
+    /// wp_tearing_control_v1 for the surface and asks for asynchronous presentation.
+    /// A second object for the same surface is a protocol error, so ours is destroyed
+    /// before such a swapchain is created. The WSI releases its object when the
+    /// swapchain is replaced, which has happened once a frame with another mode has
+    /// been rendered.
+    fn update_tearing_control(&self, m: &mut Mutable) {
+        let Some(manager) = &self.wp_tearing_control_manager_v1 else {
+            return;
+        };
+        if m.present_mode == PresentMode::Immediate || m.wp_tearing_control_v1.is_some() {
+            return;
+        }
+        let tc = manager.get_tearing_control(&self.wl_surface);
+        if m.allow_tearing {
+            // The hint is double-buffered and applied by the next commit.
+            tc.set_presentation_hint(tearing_hint(true));
+            m.need_commit = true;
+        }
+        m.wp_tearing_control_v1 = Some(tc);
+    }
+
+    /// Requests a frame callback that is committed together with the next buffer.
+    fn request_frame(&self) {
+        if self.frame_callback.borrow().is_some() {
+            return;
+        }
+        let callback = self.wl_surface.frame();
+        let pending = self.frame_callback.clone();
+        proxy::set_event_handler_local(
+            &callback,
+            WlCallback::on_done(move |_, _| {
+                if let Some(callback) = pending.take() {
+                    proxy::destroy(&callback);
+                }
+            }),
+        );
+        *self.frame_callback.borrow_mut() = Some(callback);
+    }
+
+    fn render_frame(&self, m: &mut Mutable) {
+        if m.frozen || (m.paused.is_some() && !m.step) {
+            return;
+        }
+        let vulkan = self.vulkan.borrow();
+        let Ok(vulkan) = &*vulkan else {
+            return;
+        };
+        if m.pending_description.is_some()
+            || m.pending_reference.is_some()
+            || m.pending_blend_description.is_some()
+        {
+            return;
+        }
+        if m.need_commit && !m.need_render {
+            if m.blend_subsurface.is_some() {
+                self.wl_blend_surface.commit();
+            }
+            for layer in &m.blend_layers {
+                layer.wl_surface.commit();
+            }
+            self.damage(m);
+            self.wl_surface.commit();
+            m.need_commit = false;
+            m.step = false;
+            return;
+        }
+        if !m.need_render {
+            return;
+        }
+        m.need_commit = false;
+        if m.width <= 1 || m.height <= 1 {
+            return;
+        }
+        let mut params = m.render_params(m.content_description());
+        // The viewport state is double-buffered. It is applied by the commit that
+        // presents the next buffer and therefore always matches the buffer size.
+        let (width, height, destination) = match m.viewport {
+            Some(v) => (v.source.0, v.source.1, v.destination),
+            None => (m.width, m.height, None),
+        };
+        let (dest_width, dest_height) = destination.unwrap_or((m.width, m.height));
+        // Buffers are rendered at the preferred scale. The sizes above are in
+        // surface coordinates.
+        let scale = m.buffer_scale;
+        if proxy::version(&*self.wl_surface) >= 6 {
+            self.wl_surface.set_buffer_scale(scale);
+            self.wl_surface.set_buffer_transform(m.buffer_transform);
+            self.wl_blend_surface.set_buffer_scale(scale);
+            self.wl_reference_surface.set_buffer_scale(scale);
+            for layer in &m.blend_layers {
+                layer.wl_surface.set_buffer_scale(scale);
+            }
+        }
+        let blend_params = m.blend_params();
+        // Sub-surfaces that cover the test pane draw the overlay as well so that it stays
+        // visible.
+        let overlay = Overlay {
+            grid: m.overlay.map(|o| Grid {
+                spacing: o.spacing,
+                color: o.color.to_lab(),
+            }),
+            legend: m.legend.then(|| Legend {
+                text: format!("scene: {}\n{}", m.scene.name(), m.description.legend()),
+                scale: 2 * scale as u32,
+                color: Color {
+                    lumen: 203.0,
+                    lightness: 1.0,
+                    chroma: 0.0,
+                    hue: 0.0,
+                }
+                .to_lab(),
+                background: Color::default().to_lab(),
+            }),
+        };
+        let scene = match m.scene {
+            TestScene::Fill(color) => Scene::Fill(color.to_lab()),
+            TestScene::LinearGradient(colors, angle) => {
+                Scene::LinearGradient(colors.map(|c| c.to_lab()), angle)
+            }
+            TestScene::FillFour(colors, alpha) => {
+                Scene::FillFour(array::from_fn(|i| colors[i].to_lab_alpha(alpha[i])))
+            }
+            TestScene::CenterBox(colors, size) => {
+                Scene::CenterBox(colors.map(|c| c.to_lab()), size / 100.0)
+            }
+            TestScene::Grid(colors, rows, cols) => {
+                Scene::Grid(colors.map(|c| c.to_lab()), rows, cols)
+            }
+            TestScene::ColorGrid(sweep) => {
+                let colors = sweep.colors().into_iter().map(|c| c.to_lab()).collect();
+                Scene::ColorGrid(sweep.rows, sweep.cols, colors)
+            }
+            TestScene::Crosshair(colors) => Scene::Crosshair(colors.map(|c| c.to_lab())),
+            TestScene::Patch(color, size, offset) => Scene::Patch(color.to_lab(), size, offset),
+            TestScene::Blend(colors, alpha) => {
+                set_viewport(
+                    &self.wp_blend_viewport,
+                    m.viewport
+                        .map(|_| (width / 2, height, dest_width / 2, dest_height)),
+                );
+                let res = vulkan.blend_surface.render(
+                    (width / 2 * scale) as _,
+                    (height * scale) as _,
+                    Scene::BlendLeft(colors[1].to_lab_alpha(alpha)),
+                    None,
+                    &overlay,
+                    &blend_params,
+                );
+                if !self.check_render(res) {
+                    return;
+                }
+                Scene::BlendRight([colors[0].to_lab(), colors[1].to_lab_alpha(alpha)])
+            }
+            TestScene::BlendBanding(colors, alpha) => {
+                set_viewport(
+                    &self.wp_blend_viewport,
+                    m.viewport.map(|_| (width, height, dest_width, dest_height)),
+                );
+                let gradient = alpha.map(|a| colors[1].to_lab_alpha(a));
+                let res = vulkan.blend_surface.render(
+                    (width * scale) as _,
+                    (height * scale) as _,
+                    Scene::BlendGradient(gradient),
+                    None,
+                    &overlay,
+                    &blend_params,
+                );
+                if !self.check_render(res) {
+                    return;
+                }
+                Scene::BlendGradientReference(colors[0].to_lab(), gradient)
+            }
+            TestScene::BlendLayers(background, layers) => {
+                let colors: Vec<_> = (0..m.blend_layers.len())
+                    .map(|i| layers.colors[i].to_lab_alpha(layers.alpha[i]))
+                    .collect();
+                let no_overlay = Overlay::default();
+                for (i, (layer, &color)) in m.blend_layers.iter().zip(&colors).enumerate() {
+                    // The layers are ordered from the bottom up.
+                    let overlay = match i + 1 == colors.len() {
+                        true => &overlay,
+                        false => &no_overlay,
+                    };
+                    set_viewport(
+                        &layer.wp_viewport,
+                        m.viewport
+                            .map(|_| (width / 2, height, dest_width / 2, dest_height)),
+                    );
+                    let res = layer.vulkan.render(
+                        (width / 2 * scale) as _,
+                        (height * scale) as _,
+                        Scene::BlendLeft(color),
+                        None,
+                        overlay,
+                        &blend_params,
+                    );
+                    if !self.check_render(res) {
+                        return;
+                    }
+                }
+                Scene::BlendLayers(background.to_lab(), colors)
+            }
+            TestScene::Primaries(white) => Scene::Primaries(white / 203.0),
+            TestScene::BlackDetail(steps) => Scene::BlackDetail(steps.values()),
+            TestScene::Image => match &m.image {
+                Some(texture) => Scene::Image(texture.clone()),
+                None => Scene::Fill(Color::default().to_lab()),
+            },
+            TestScene::MovingBar(colors, speed) => {
+                let time = m
+                    .paused
+                    .unwrap_or_else(|| self.animation_start.get().elapsed());
+                let position = time.as_secs_f32() * speed;
+                Scene::MovingBar(colors.map(|c| c.to_lab()), position.rem_euclid(1.0))
+            }
+            TestScene::TransferFunctions(lumen) => {
+                let gray = |lumen: f32| {
+                    Color {
+                        lumen,
+                        lightness: 1.0,
+                        chroma: 0.0,
+                        hue: 0.0,
+                    }
+                    .to_lab()
+                };
+                let rows = NamedTransferFunction::variants()
+                    .filter(|tf| self.caps.tf.contains(&tf.wayland()))
+                    .map(|tf| {
+                        let params = m.description.reference(tf).render_params(m.xyz_from_lms);
+                        (tf.name().to_string(), params)
+                    })
+                    .collect();
+                let text = [gray(0.0), gray(203.0)];
+                Scene::TransferFunctions(rows, lumen / 203.0, 2 * scale as u32, text)
+            }
+            TestScene::CompareEotf(lumen, _, pq) => {
+                let pq_luminance = |desc: TestColorDescription, params: &mut RenderParams| {
+                    let is_pq =
+                        params.tf == TransferFunction::Named(NamedTransferFunction::St2084Pq);
+                    if is_pq && pq == PqLuminance::Absolute {
+                        params.tf_args[0] = desc.pq_absolute_scale();
+                    }
+                };
+                pq_luminance(m.content_description(), &mut params);
+                let white = Color {
+                    lumen,
+                    lightness: 1.0,
+                    chroma: 0.0,
+                    hue: 0.0,
+                };
+                let ramp = [Color::default().to_lab(), white.to_lab()];
+                if let (Some(reference), Some(ss)) = (m.reference, &m.reference_subsurface) {
+                    let mut params = m.render_params(reference);
+                    pq_luminance(reference, &mut params);
+                    ss.set_position(0, dest_height / 2);
+                    let res = vulkan.reference_surface.render(
+                        (dest_width * scale) as _,
+                        ((dest_height - dest_height / 2) * scale) as _,
+                        Scene::LinearGradient(ramp, 0.0),
+                        None,
+                        &Overlay::default(),
+                        &params,
+                    );
+                    if !self.check_render(res) {
+                        return;
+                    }
+                }
+                Scene::LinearGradient(ramp, 0.0)
+            }
+        };
+        if let (Some(TestColorDescription::None), Some(ss)) = (m.reference, &m.reference_subsurface)
+        {
+            let params = m.render_params(TestColorDescription::None);
+            ss.set_position(dest_width / 2, 0);
+            let section = Scene::Section(
+                Box::new(scene.clone()),
+                ((dest_width * scale) as _, (dest_height * scale) as _),
+                ((dest_width / 2 * scale) as _, 0),
+            );
+            let res = vulkan.reference_surface.render(
+                ((dest_width - dest_width / 2) * scale) as _,
+                (dest_height * scale) as _,
+                section,
+                m.clear.map(|c| c.to_lab()),
+                &overlay,
+                &params,
+            );
+            if !self.check_render(res) {
+                return;
+            }
+        }
+        set_viewport(
+            &self.wp_viewport,
+            m.viewport.map(|_| (width, height, dest_width, dest_height)),
+        );
+        // The presentation engine adds its own damage to the commit of the new buffer.
+        self.damage(m);
+        if matches!(m.scene, TestScene::MovingBar(..)) {
+            self.request_frame();
+        }
+        let frame = Frame {
+            width: (width * scale) as _,
+            height: (height * scale) as _,
+            scene,
+            clear: m.clear.map(|c| c.to_lab()),
+            overlay,
+            params,
+            description: m.attached_description,
+        };
+        let res = vulkan.surface.render(
+            frame.width,
+            frame.height,
+            frame.scene.clone(),
+            frame.clear,
+            &frame.overlay,
+            &frame.params,
+        );
+        if !self.check_render(res) {
+            return;
+        }
+        self.update_tearing_control(m);
+        *self.last_frame.borrow_mut() = Some(frame);
+        m.need_render = false;
+        m.step = false;
+    }
+
+    /// Forwards render errors to the control pane. Returns whether rendering succeeded.
+    /// After a failure, the frame is rendered again by the next call to `render_frame`.
+    fn check_render(&self, res: Result<(), vulkan::Error>) -> bool {
+        match res {
+            Ok(()) => {
+                self.last_render.set(Some(Instant::now()));
+                if self.render_failed.replace(false) {
+                    self.render_error_message.set(Some(None));
+                }
+                true
+            }
+            Err(e) => {
+                let msg = error_chain(&e);
+                log::warn!("Could not render the test pane: {msg}");
+                self.render_failed.set(true);
+                self.render_error_message.set(Some(Some(msg)));
+                false
+            }
+        }
+    }
+
+    /// Sends the configured damage for the next commit of the test pane.
+    fn damage(&self, m: &Mutable) {
+        let Some(damage) = m.damage else {
+            return;
+        };
+        let (x, y, width, height) = damage.rect;
+        match damage.coordinates {
+            DamageCoordinates::Buffer => self.wl_surface.damage_buffer(x, y, width, height),
+            DamageCoordinates::Surface => self.wl_surface.damage(x, y, width, height),
+        }
+    }
+
+    fn get_feedback(self: &Rc<Self>) {
+        if self
+            .caps
+            .features
+            .not_contains(&WpColorManagerV1Feature::PARAMETRIC)
         {
             self.preferred_description_error_message.set(Some(Some(
                 "Compositor does not support parametric descriptions".to_string(),
@@ -553,224 +2858,776 @@ impl State {
         }
         dbg!(&self.caps.features);
         let m = &mut *self.mutable.borrow_mut();
+        if m.frozen {
+            return;
+        }
         if let Some(desc) = m.preferred_description.take() {
             desc.destroy();
         }
         self.preferred_description_data.set(None);
         self.preferred_description_error_message.set(Some(None));
+        let generation = self.feedback_generation.get() + 1;
+        self.feedback_generation.set(generation);
 
-        struct Eh(WpImageDescriptionV1, Rc<State>);
-        impl WpImageDescriptionV1EventHandler for Eh {
-            fn failed(
-                &self,
-                _slf: &WpImageDescriptionV1Ref,
-                _cause: WpImageDescriptionV1Cause,
-                msg: &str,
-            ) {
-                self.1
-                    .preferred_description_error_message
-                    .set(Some(Some(msg.to_string())));
-                self.0.destroy();
-            }
-
-            fn ready(&self, _slf: &WpImageDescriptionV1Ref, _identity: u32) {
-                struct Eh {
-                    desc: WpImageDescriptionV1,
-                    info: WpImageDescriptionInfoV1,
-                    state: Rc<State>,
-                    primaries: Cell<Option<TestPrimaries>>,
-                    tf: Cell<Option<TransferFunction>>,
-                    tf_power: Cell<f32>,
-                    luminance: Cell<Option<Luminance>>,
-                }
-                impl Eh {
-                    fn error(&self, msg: String) {
-                        self.state
-                            .preferred_description_error_message
-                            .set(Some(Some(msg)));
-                        self.desc.destroy();
-                        proxy::destroy(&self.info);
-                    }
+        let desc = self
+            .wp_color_management_surface_feedback_v1
+            .get_preferred_parametric();
+        let slf = self.clone();
+        get_description_data(&desc.clone(), move |res| {
+            if slf.feedback_generation.get() != generation {
+                desc.destroy();
+                return;
+            }
+            match res {
+                Ok((identity, data)) => {
+                    slf.mutable.borrow_mut().preferred_description = Some(desc);
+                    slf.preferred_description_data.set(Some(data));
+                    slf.update_preferred_identity(identity, data);
                 }
-                impl WpImageDescriptionInfoV1EventHandler for Eh {
-                    fn done(&self, _slf: &WpImageDescriptionInfoV1Ref) {
-                        let Some(primaries) = self.primaries.take() else {
-                            self.error("compositor did not send any primaries".to_string());
-                            return;
-                        };
-                        let Some(tf) = self.tf.take() else {
-                            self.error("compositor did not send any transfer function".to_string());
-                            return;
-                        };
-                        let m = &mut *self.state.mutable.borrow_mut();
-                        m.preferred_description = Some(self.desc.clone());
-                        self.state
-                            .preferred_description_data
-                            .set(Some(DescriptionData {
-                                primaries,
-                                tf,
-                                tf_power: self.tf_power.get(),
-                                luminance: self.luminance.get(),
-                            }));
-                        proxy::destroy(&self.info);
-                    }
+                Err(msg) => {
+                    slf.preferred_description_error_message.set(Some(Some(msg)));
+                    desc.destroy();
+                }
+            }
+        });
+    }
+}
 
-                    fn primaries(
-                        &self,
-                        _slf: &WpImageDescriptionInfoV1Ref,
-                        r_x: i32,
-                        r_y: i32,
-                        g_x: i32,
-                        g_y: i32,
-                        b_x: i32,
-                        b_y: i32,
-                        w_x: i32,
-                        w_y: i32,
-                    ) {
-                        let map = |x: i32| F64(x as f64 / 1_000_000.0);
-                        let map = |x: i32, y: i32| (map(x), map(y));
-                        self.primaries.set(Some(TestPrimaries::Custom(Primaries {
-                            r: map(r_x, r_y),
-                            g: map(g_x, g_y),
-                            b: map(b_x, b_y),
-                            wp: map(w_x, w_y),
-                        })));
-                    }
+impl State {
+    fn update_output_list(&self) {
+        let list = self
+            .outputs
+            .borrow()
+            .iter()
+            .map(|o| OutputInfo {
+                global: o.global,
+                name: o.name.borrow().clone(),
+                description: o.description.borrow().clone(),
+            })
+            .collect();
+        self.output_list.set(Some(list));
+    }
 
-                    fn primaries_named(
-                        &self,
-                        _slf: &WpImageDescriptionInfoV1Ref,
-                        primaries: WpColorManagerV1Primaries,
-                    ) {
-                        let primaries = match primaries {
-                            WpColorManagerV1Primaries::SRGB => NamedPrimaries::Srgb,
-                            WpColorManagerV1Primaries::PAL_M => NamedPrimaries::PalM,
-                            WpColorManagerV1Primaries::PAL => NamedPrimaries::Pal,
-                            WpColorManagerV1Primaries::NTSC => NamedPrimaries::Ntsc,
-                            WpColorManagerV1Primaries::GENERIC_FILM => NamedPrimaries::GenericFilm,
-                            WpColorManagerV1Primaries::BT2020 => NamedPrimaries::Bt2020,
-                            WpColorManagerV1Primaries::CIE1931_XYZ => NamedPrimaries::Cie1931Xyz,
-                            WpColorManagerV1Primaries::DCI_P3 => NamedPrimaries::DciP3,
-                            WpColorManagerV1Primaries::DISPLAY_P3 => NamedPrimaries::DisplayP3,
-                            WpColorManagerV1Primaries::ADOBE_RGB => NamedPrimaries::AdobeRgb,
-                            _ => {
-                                self.error(format!("unsupported primaries {primaries:?}"));
-                                return;
-                            }
-                        };
-                        self.primaries.set(Some(TestPrimaries::Named(primaries)));
-                    }
+    /// Destroys all objects of the test pane. The swapchains are destroyed before the
+    /// surfaces they present to. Destroying the proxies drops their event handlers,
+    /// which hold references to the state.
+    fn destroy(&self) {
+        drop(
+            self.vulkan
+                .replace(Err("The test pane has been destroyed".to_string())),
+        );
+        self.last_frame.take();
+        let m = &mut *self.mutable.borrow_mut();
+        let descriptions = [
+            m.preferred_description.take(),
+            m.pending_description.take(),
+            m.pending_blend_description.take(),
+            m.pending_reference.take(),
+        ];
+        for desc in descriptions.into_iter().flatten() {
+            desc.destroy();
+        }
+        for ss in [m.blend_subsurface.take(), m.reference_subsurface.take()]
+            .into_iter()
+            .flatten()
+        {
+            ss.destroy();
+        }
+        for layer in m.blend_layers.drain(..) {
+            layer.destroy();
+        }
+        if let Some(surface) = m.wp_color_management_surface_v1.take() {
+            surface.destroy();
+        }
+        if let Some(surface) = m.wp_color_representation_surface_v1.take() {
+            surface.destroy();
+        }
+        if let Some(tc) = m.wp_tearing_control_v1.take() {
+            tc.destroy();
+        }
+        for viewport in [&self.wp_viewport, &self.wp_blend_viewport]
+            .into_iter()
+            .flatten()
+        {
+            viewport.destroy();
+        }
+        if let Some(callback) = self.frame_callback.take() {
+            proxy::destroy(&callback);
+        }
+        self.wp_color_management_surface_feedback_v1.destroy();
+        self.wp_color_management_blend_surface_v1.destroy();
+        self.wp_color_management_reference_surface_v1.destroy();
+        self.xdg_toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.wl_surface.destroy();
+        self.wl_blend_surface.destroy();
+        self.wl_reference_surface.destroy();
+        for output in self.outputs.take() {
+            output.destroy();
+        }
+        proxy::destroy(&self.wl_registry);
+        if let Some(viewporter) = &self.wp_viewporter {
+            viewporter.destroy();
+        }
+        if let Some(manager) = &self.wp_tearing_control_manager_v1 {
+            manager.destroy();
+        }
+        if let Some(manager) = &self.wp_color_representation_manager_v1 {
+            manager.destroy();
+        }
+        self.wp_color_manager_v1.destroy();
+        self.wl_subcompositor.destroy();
+        self.xdg_wm_base.destroy();
+    }
 
-                    fn tf_power(&self, _slf: &WpImageDescriptionInfoV1Ref, eexp: u32) {
-                        self.tf.set(Some(TransferFunction::Pow));
-                        self.tf_power.set(eexp as f32 / 10_000.0);
-                    }
+    /// Records the identity of a new preferred description and logs if the identity
+    /// changed without the contents changing.
+    fn update_preferred_identity(&self, identity: u32, data: DescriptionData) {
+        let announced = self.announced_identity.get();
+        let prev = self.last_preferred.replace(Some((identity, data)));
+        let unchanged_content = match prev {
+            Some((prev_identity, prev_data)) => prev_identity != identity && prev_data == data,
+            None => false,
+        };
+        log::info!("preferred description: identity {identity}");
+        if let Some(announced) = announced.filter(|&a| a != identity) {
+            log::info!("preferred description: announced identity was {announced}");
+        }
+        if unchanged_content {
+            log::info!("preferred description: identity changed but the contents did not");
+        }
+        self.preferred_identity.set(Some(DescriptionIdentity {
+            announced,
+            identity,
+            unchanged_content,
+        }));
+    }
 
-                    fn tf_named(
-                        &self,
-                        _slf: &WpImageDescriptionInfoV1Ref,
-                        tf: WpColorManagerV1TransferFunction,
-                    ) {
-                        let tf = match tf {
-                            WpColorManagerV1TransferFunction::BT1886 => {
-                                NamedTransferFunction::Bt1886
-                            }
-                            WpColorManagerV1TransferFunction::GAMMA22 => {
-                                NamedTransferFunction::Gamma22
-                            }
-                            WpColorManagerV1TransferFunction::GAMMA28 => {
-                                NamedTransferFunction::Gamma28
-                            }
-                            WpColorManagerV1TransferFunction::ST240 => NamedTransferFunction::St240,
-                            WpColorManagerV1TransferFunction::EXT_LINEAR => {
-                                NamedTransferFunction::Linear
-                            }
-                            WpColorManagerV1TransferFunction::LOG_100 => {
-                                NamedTransferFunction::Log100
-                            }
-                            WpColorManagerV1TransferFunction::LOG_316 => {
-                                NamedTransferFunction::Log316
-                            }
-                            WpColorManagerV1TransferFunction::SRGB => NamedTransferFunction::Srgb,
-                            WpColorManagerV1TransferFunction::EXT_SRGB => {
-                                NamedTransferFunction::ExtSrgb
-                            }
-                            WpColorManagerV1TransferFunction::ST2084_PQ => {
-                                NamedTransferFunction::St2084Pq
-                            }
-                            WpColorManagerV1TransferFunction::ST428 => NamedTransferFunction::St428,
-                            _ => {
-                                self.error(format!("unsupported transfer function {tf:?}"));
-                                return;
-                            }
-                        };
-                        self.tf.set(Some(TransferFunction::Named(tf)));
-                    }
+    /// Requests the description of the output that the test pane entered last.
+    fn get_output_feedback(self: &Rc<Self>) {
+        if self.mutable.borrow().frozen {
+            return;
+        }
+        let generation = self.output_generation.get() + 1;
+        self.output_generation.set(generation);
+        let output = self.mutable.borrow().entered_outputs.last().and_then(|id| {
+            self.outputs
+                .borrow()
+                .iter()
+                .find(|o| proxy::id(&*o.wl_output) == *id)
+                .cloned()
+        });
+        let Some(output) = output else {
+            self.output_error_message
+                .set(Some(Some("The test pane is not on any output".to_string())));
+            return;
+        };
+        let name = output.name.borrow().clone();
+        let desc = output.wp_color_management_output_v1.get_image_description();
+        let slf = self.clone();
+        get_description_data(&desc.clone(), move |res| {
+            desc.destroy();
+            if slf.output_generation.get() != generation {
+                return;
+            }
+            match res {
+                Ok((_, description)) => {
+                    slf.output_error_message.set(Some(None));
+                    slf.output_data.set(Some(OutputData { name, description }));
+                }
+                Err(msg) => slf.output_error_message.set(Some(Some(msg))),
+            }
+        });
+    }
+}
 
-                    fn luminances(
-                        &self,
-                        _slf: &WpImageDescriptionInfoV1Ref,
-                        min_lum: u32,
-                        max_lum: u32,
-                        reference_lum: u32,
-                    ) {
-                        self.luminance.set(Some(Luminance {
-                            min: F64(min_lum as f64 / 10_000.0),
-                            max: F64(max_lum as f64),
-                            white: F64(reference_lum as f64),
-                        }));
-                    }
+/// Retrieves the information of the description and calls `done` with the result.
+fn get_description_data(
+    desc: &WpImageDescriptionV1,
+    done: impl FnOnce(Result<(u32, DescriptionData), String>) + 'static,
+) {
+    type Done = Box<dyn FnOnce(Result<(u32, DescriptionData), String>)>;
+
+    struct Eh(WpImageDescriptionV1, Cell<Option<Done>>);
+    impl WpImageDescriptionV1EventHandler for Eh {
+        fn failed(
+            &self,
+            _slf: &WpImageDescriptionV1Ref,
+            _cause: WpImageDescriptionV1Cause,
+            msg: &str,
+        ) {
+            if let Some(done) = self.1.take() {
+                done(Err(msg.to_string()));
+            }
+        }
+
+        fn ready(&self, _slf: &WpImageDescriptionV1Ref, identity: u32) {
+            let Some(done) = self.1.take() else {
+                return;
+            };
+            let info = self.0.get_information();
+            proxy::set_event_handler_local(
+                &info.clone(),
+                InfoEh {
+                    info,
+                    identity,
+                    done: Cell::new(Some(done)),
+                    primaries: Default::default(),
+                    tf: Default::default(),
+                    tf_power: Default::default(),
+                    luminance: Default::default(),
+                    target_luminance: Default::default(),
+                    target_primaries: Default::default(),
+                    target_max_cll: Default::default(),
+                    target_max_fall: Default::default(),
+                },
+            );
+        }
+    }
+
+    struct InfoEh {
+        info: WpImageDescriptionInfoV1,
+        identity: u32,
+        done: Cell<Option<Done>>,
+        primaries: Cell<Option<TestPrimaries>>,
+        tf: Cell<Option<TransferFunction>>,
+        tf_power: Cell<f32>,
+        luminance: Cell<Option<Luminance>>,
+        target_luminance: Cell<Option<(F64, F64)>>,
+        target_primaries: Cell<Option<Primaries>>,
+        target_max_cll: Cell<Option<u32>>,
+        target_max_fall: Cell<Option<u32>>,
+    }
+    impl InfoEh {
+        fn finish(&self, res: Result<DescriptionData, String>) {
+            proxy::destroy(&self.info);
+            if let Some(done) = self.done.take() {
+                done(res.map(|data| (self.identity, data)));
+            }
+        }
+
+        fn error(&self, msg: String) {
+            self.finish(Err(msg));
+        }
+    }
+    impl WpImageDescriptionInfoV1EventHandler for InfoEh {
+        fn done(&self, _slf: &WpImageDescriptionInfoV1Ref) {
+            let Some(primaries) = self.primaries.take() else {
+                self.error("compositor did not send any primaries".to_string());
+                return;
+            };
+            let Some(tf) = self.tf.take() else {
+                self.error("compositor did not send any transfer function".to_string());
+                return;
+            };
+            self.finish(Ok(DescriptionData {
+                primaries,
+                tf,
+                tf_power: self.tf_power.get(),
+                luminance: self.luminance.get(),
+                target_luminance: self.target_luminance.get(),
+                target_primaries: self.target_primaries.get(),
+                target_max_cll: self.target_max_cll.get(),
+                target_max_fall: self.target_max_fall.get(),
+            }));
+        }
+
+        fn primaries(
+            &self,
+            _slf: &WpImageDescriptionInfoV1Ref,
+            r_x: i32,
+            r_y: i32,
+            g_x: i32,
+            g_y: i32,
+            b_x: i32,
+            b_y: i32,
+            w_x: i32,
+            w_y: i32,
+        ) {
+            let map =
+                |x: i32, y: i32| (chromaticity_from_protocol(x), chromaticity_from_protocol(y));
+            self.primaries.set(Some(TestPrimaries::Custom(Primaries {
+                r: map(r_x, r_y),
+                g: map(g_x, g_y),
+                b: map(b_x, b_y),
+                wp: map(w_x, w_y),
+            })));
+        }
+
+        fn primaries_named(
+            &self,
+            _slf: &WpImageDescriptionInfoV1Ref,
+            primaries: WpColorManagerV1Primaries,
+        ) {
+            let primaries = match primaries {
+                WpColorManagerV1Primaries::SRGB => NamedPrimaries::Srgb,
+                WpColorManagerV1Primaries::PAL_M => NamedPrimaries::PalM,
+                WpColorManagerV1Primaries::PAL => NamedPrimaries::Pal,
+                WpColorManagerV1Primaries::NTSC => NamedPrimaries::Ntsc,
+                WpColorManagerV1Primaries::GENERIC_FILM => NamedPrimaries::GenericFilm,
+                WpColorManagerV1Primaries::BT2020 => NamedPrimaries::Bt2020,
+                WpColorManagerV1Primaries::CIE1931_XYZ => NamedPrimaries::Cie1931Xyz,
+                WpColorManagerV1Primaries::DCI_P3 => NamedPrimaries::DciP3,
+                WpColorManagerV1Primaries::DISPLAY_P3 => NamedPrimaries::DisplayP3,
+                WpColorManagerV1Primaries::ADOBE_RGB => NamedPrimaries::AdobeRgb,
+                _ => {
+                    self.error(format!("unsupported primaries {primaries:?}"));
+                    return;
                 }
-                let info = self.0.get_information();
-                proxy::set_event_handler_local(
-                    &info.clone(),
-                    Eh {
-                        desc: self.0.clone(),
-                        info,
-                        state: self.1.clone(),
-                        primaries: Default::default(),
-                        tf: Default::default(),
-                        tf_power: Default::default(),
-                        luminance: Default::default(),
-                    },
-                );
+            };
+            self.primaries.set(Some(TestPrimaries::Named(primaries)));
+        }
+
+        fn tf_power(&self, _slf: &WpImageDescriptionInfoV1Ref, eexp: u32) {
+            self.tf.set(Some(TransferFunction::Pow));
+            self.tf_power.set(tf_power_from_protocol(eexp));
+        }
+
+        fn tf_named(
+            &self,
+            _slf: &WpImageDescriptionInfoV1Ref,
+            tf: WpColorManagerV1TransferFunction,
+        ) {
+            let tf = match tf {
+                WpColorManagerV1TransferFunction::BT1886 => NamedTransferFunction::Bt1886,
+                WpColorManagerV1TransferFunction::GAMMA22 => NamedTransferFunction::Gamma22,
+                WpColorManagerV1TransferFunction::GAMMA28 => NamedTransferFunction::Gamma28,
+                WpColorManagerV1TransferFunction::ST240 => NamedTransferFunction::St240,
+                WpColorManagerV1TransferFunction::EXT_LINEAR => NamedTransferFunction::Linear,
+                WpColorManagerV1TransferFunction::LOG_100 => NamedTransferFunction::Log100,
+                WpColorManagerV1TransferFunction::LOG_316 => NamedTransferFunction::Log316,
+                WpColorManagerV1TransferFunction::SRGB => NamedTransferFunction::Srgb,
+                WpColorManagerV1TransferFunction::EXT_SRGB => NamedTransferFunction::ExtSrgb,
+                WpColorManagerV1TransferFunction::ST2084_PQ => NamedTransferFunction::St2084Pq,
+                WpColorManagerV1TransferFunction::ST428 => NamedTransferFunction::St428,
+                _ => {
+                    self.error(format!("unsupported transfer function {tf:?}"));
+                    return;
+                }
+            };
+            self.tf.set(Some(TransferFunction::Named(tf)));
+        }
+
+        fn luminances(
+            &self,
+            _slf: &WpImageDescriptionInfoV1Ref,
+            min_lum: u32,
+            max_lum: u32,
+            reference_lum: u32,
+        ) {
+            self.luminance.set(Some(Luminance::from_protocol(
+                min_lum,
+                max_lum,
+                reference_lum,
+            )));
+        }
+
+        fn target_luminance(&self, _slf: &WpImageDescriptionInfoV1Ref, min_lum: u32, max_lum: u32) {
+            self.target_luminance.set(Some((
+                min_luminance_from_protocol(min_lum),
+                F64(max_lum as f64),
+            )));
+        }
+
+        fn target_primaries(
+            &self,
+            _slf: &WpImageDescriptionInfoV1Ref,
+            r_x: i32,
+            r_y: i32,
+            g_x: i32,
+            g_y: i32,
+            b_x: i32,
+            b_y: i32,
+            w_x: i32,
+            w_y: i32,
+        ) {
+            let map =
+                |x: i32, y: i32| (chromaticity_from_protocol(x), chromaticity_from_protocol(y));
+            self.target_primaries.set(Some(Primaries {
+                r: map(r_x, r_y),
+                g: map(g_x, g_y),
+                b: map(b_x, b_y),
+                wp: map(w_x, w_y),
+            }));
+        }
+
+        fn target_max_cll(&self, _slf: &WpImageDescriptionInfoV1Ref, max_cll: u32) {
+            self.target_max_cll.set(Some(max_cll));
+        }
+
+        fn target_max_fall(&self, _slf: &WpImageDescriptionInfoV1Ref, max_fall: u32) {
+            self.target_max_fall.set(Some(max_fall));
+        }
+    }
+
+    proxy::set_event_handler_local(
+        &desc.clone(),
+        Eh(desc.clone(), Cell::new(Some(Box::new(done)))),
+    );
+}
+
+/// Formats the error and its sources.
+pub fn error_chain(e: &dyn Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(s) = source {
+        msg.push_str(&format!(": {s}"));
+        source = s.source();
+    }
+    msg
+}
+
+fn tearing_hint(allow_tearing: bool) -> WpTearingControlV1PresentationHint {
+    match allow_tearing {
+        true => WpTearingControlV1PresentationHint::ASYNC,
+        false => WpTearingControlV1PresentationHint::VSYNC,
+    }
+}
+
+/// Sets the source and destination size of the viewport or unsets them if `size` is
+/// `None`.
+fn set_viewport(viewport: &Option<WpViewport>, size: Option<(i32, i32, i32, i32)>) {
+    let Some(viewport) = viewport else {
+        return;
+    };
+    match size {
+        Some((src_width, src_height, dst_width, dst_height)) => {
+            viewport.set_source(
+                Fixed::ZERO,
+                Fixed::ZERO,
+                Fixed::from_i32_saturating(src_width),
+                Fixed::from_i32_saturating(src_height),
+            );
+            viewport.set_destination(dst_width, dst_height);
+        }
+        None => {
+            let unset = Fixed::from_i32_saturating(-1);
+            viewport.set_source(unset, unset, unset, unset);
+            viewport.set_destination(-1, -1);
+        }
+    }
+}
+
+impl WlSurfaceEventHandler for Rc<State> {
+    fn enter(&self, _slf: &WlSurfaceRef, output: Option<&WlOutputRef>) {
+        let Some(output) = output else {
+            return;
+        };
+        let id = proxy::id(output);
+        {
+            let entered = &mut self.mutable.borrow_mut().entered_outputs;
+            if entered.contains(&id) {
+                return;
             }
+            entered.push(id);
         }
+        self.outputs_changed.set(true);
+        self.get_output_feedback();
+    }
 
-        let desc = self
-            .wp_color_management_surface_feedback_v1
-            .get_preferred_parametric();
-        proxy::set_event_handler_local(&desc.clone(), Eh(desc, self.clone()))
+    fn leave(&self, _slf: &WlSurfaceRef, output: Option<&WlOutputRef>) {
+        let Some(output) = output else {
+            return;
+        };
+        let id = proxy::id(output);
+        {
+            let entered = &mut self.mutable.borrow_mut().entered_outputs;
+            if !entered.contains(&id) {
+                return;
+            }
+            entered.retain(|o| *o != id);
+        }
+        self.outputs_changed.set(true);
+        self.get_output_feedback();
+    }
+
+    fn preferred_buffer_scale(&self, _slf: &WlSurfaceRef, factor: i32) {
+        let m = &mut *self.mutable.borrow_mut();
+        m.buffer_scale = factor.max(1);
+        m.need_render = true;
+        self.render_frame(m);
+    }
+
+    fn preferred_buffer_transform(&self, _slf: &WlSurfaceRef, transform: WlOutputTransform) {
+        let m = &mut *self.mutable.borrow_mut();
+        m.buffer_transform = transform;
+        if let Ok(vulkan) = &*self.vulkan.borrow() {
+            vulkan.surface.set_transform(transform);
+        }
+        m.need_render = true;
+        self.render_frame(m);
+    }
+}
+
+impl Output {
+    fn destroy(&self) {
+        self.wp_color_management_output_v1.destroy();
+        match proxy::version(&*self.wl_output) >= 3 {
+            true => self.wl_output.release(),
+            false => proxy::destroy(&self.wl_output),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct OutputEventHandler(Rc<State>, Rc<Output>);
+
+impl OutputEventHandler {
+    fn is_current(&self) -> bool {
+        let id = proxy::id(&*self.1.wl_output);
+        self.0.mutable.borrow().entered_outputs.last() == Some(&id)
+    }
+}
+
+impl WlOutputEventHandler for OutputEventHandler {
+    fn name(&self, _slf: &WlOutputRef, name: &str) {
+        *self.1.name.borrow_mut() = Some(name.to_string());
+    }
+
+    fn description(&self, _slf: &WlOutputRef, description: &str) {
+        *self.1.description.borrow_mut() = Some(description.to_string());
+    }
+
+    fn done(&self, _slf: &WlOutputRef) {
+        self.0.update_output_list();
+        if self.is_current() {
+            self.0.get_output_feedback();
+        }
+    }
+}
+
+impl WlRegistryEventHandler for Rc<State> {
+    fn global(&self, _slf: &WlRegistryRef, name: u32, interface: &str, version: u32) {
+        if interface != WlOutput::INTERFACE {
+            return;
+        }
+        let wl_output: WlOutput = self.wl_registry.bind(name, version.min(4));
+        let output = Rc::new(Output {
+            global: name,
+            wp_color_management_output_v1: self.wp_color_manager_v1.get_output(&wl_output),
+            wl_output,
+            name: Default::default(),
+            description: Default::default(),
+        });
+        let eh = OutputEventHandler(self.clone(), output.clone());
+        proxy::set_event_handler_local(&output.wl_output, eh.clone());
+        proxy::set_event_handler_local(&output.wp_color_management_output_v1, eh);
+        self.outputs.borrow_mut().push(output);
+        self.update_output_list();
+    }
+
+    fn global_remove(&self, _slf: &WlRegistryRef, name: u32) {
+        let output = {
+            let outputs = &mut *self.outputs.borrow_mut();
+            let Some(idx) = outputs.iter().position(|o| o.global == name) else {
+                return;
+            };
+            outputs.remove(idx)
+        };
+        let id = proxy::id(&*output.wl_output);
+        self.mutable
+            .borrow_mut()
+            .entered_outputs
+            .retain(|o| *o != id);
+        output.destroy();
+        self.outputs_changed.set(true);
+        self.update_output_list();
+    }
+}
+
+impl WpColorManagementOutputV1EventHandler for OutputEventHandler {
+    fn image_description_changed(&self, _slf: &WpColorManagementOutputV1Ref) {
+        if self.is_current() {
+            self.0.get_output_feedback();
+        }
     }
 }
 
 impl XdgSurfaceEventHandler for Rc<State> {
     fn configure(&self, _slf: &XdgSurfaceRef, serial: u32) {
         self.xdg_surface.ack_configure(serial);
+        if !self.configured.replace(true) {
+            self.configure_deadline.take();
+        }
         self.render_frame(&mut self.mutable.borrow_mut());
     }
 }
 
 impl XdgToplevelEventHandler for Rc<State> {
-    fn configure(&self, _slf: &XdgToplevelRef, mut width: i32, mut height: i32, _states: &[u8]) {
+    fn configure(&self, _slf: &XdgToplevelRef, mut width: i32, mut height: i32, states: &[u8]) {
+        let states = ToplevelStates::parse(states);
+        let (saved_width, saved_height) = self.geometry.test_pane.get().unwrap_or((800, 600));
         if width <= 0 {
-            width = 800;
+            width = saved_width;
         }
         if height <= 0 {
-            height = 600;
+            height = saved_height;
+        }
+        // The windowed size is restored when leaving fullscreen or maximized.
+        if !states.fullscreen && !states.maximized {
+            self.geometry.test_pane.set(Some((width, height)));
         }
         let m = &mut *self.mutable.borrow_mut();
+        if mem::replace(&mut m.toplevel_states, states) != states {
+            self.toplevel_states.set(Some(states));
+        }
         m.need_render |= mem::replace(&mut m.width, width) != width;
         m.need_render |= mem::replace(&mut m.height, height) != height;
     }
 
     fn close(&self, _slf: &XdgToplevelRef) {
+        self.geometry.save();
         std::process::exit(0);
     }
 }
 
 impl WpColorManagementSurfaceFeedbackV1EventHandler for Rc<State> {
-    fn preferred_changed(&self, _slf: &WpColorManagementSurfaceFeedbackV1Ref, _identity: u32) {
+    fn preferred_changed(&self, _slf: &WpColorManagementSurfaceFeedbackV1Ref, identity: u32) {
+        log::debug!("preferred_changed: identity {identity}");
+        self.announced_identity.set(Some(identity));
         self.get_feedback();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_to_lab_matches_lch_to_lab() {
+        let lab = |lumen, lightness, chroma, hue| {
+            vulkan::lch_to_lab(
+                Color {
+                    lumen,
+                    lightness,
+                    chroma,
+                    hue,
+                }
+                .to_lab(),
+            )
+        };
+        let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+        assert_eq!(lab(203.0, 0.5, 0.0, 123.0), [0.5, 0.0, 0.0, 1.0]);
+        assert!(close(lab(203.0, 0.5, 0.25, 0.0), [0.5, 0.25, 0.0, 1.0]));
+        assert!(close(lab(203.0, 0.5, 0.25, 90.0), [0.5, 0.0, 0.25, 1.0]));
+        assert!(close(lab(203.0, 0.5, 0.25, 180.0), [0.5, -0.25, 0.0, 1.0]));
+        assert!(close(lab(203.0, 0.5, 0.25, 270.0), [0.5, 0.0, -0.25, 1.0]));
+        // The luminance scales lightness and chroma with its cube root.
+        assert!(close(
+            lab(8.0 * 203.0, 0.5, 0.25, 0.0),
+            [1.0, 0.5, 0.0, 1.0]
+        ));
+        assert_eq!(lab(0.0, 0.5, 0.25, 45.0), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn failed_description_falls_back() {
+        let mut m = Mutable {
+            description: TestColorDescription::ScRgb,
+            ..Default::default()
+        };
+        m.description_attached(TestColorDescription::ScRgb);
+        let requested = TestColorDescription::Parametric {
+            primaries: TestPrimaries::Named(NamedPrimaries::Bt2020),
+            transfer_function: TransferFunctionWithArgs {
+                tf: TransferFunction::Named(NamedTransferFunction::St2084Pq),
+                pow: 1.0,
+            },
+            luminance: None,
+            mastering: None,
+        };
+        m.description = requested;
+        m.description_failed();
+        assert!(m.need_render);
+        assert!(m.pending_description.is_none());
+        assert!(m.content_description() == TestColorDescription::ScRgb);
+        m.description_attached(requested);
+        assert!(m.content_description() == requested);
+    }
+
+    #[test]
+    fn named_primaries_compare_by_chromaticity() {
+        let description = TestColorDescription::Parametric {
+            primaries: TestPrimaries::Named(NamedPrimaries::Srgb),
+            transfer_function: TransferFunctionWithArgs {
+                tf: TransferFunction::Named(NamedTransferFunction::Srgb),
+                pow: 1.0,
+            },
+            luminance: None,
+            mastering: None,
+        };
+        let mut data = DescriptionData {
+            primaries: TestPrimaries::Custom(Primaries::SRGB),
+            tf: TransferFunction::Named(NamedTransferFunction::Srgb),
+            tf_power: 0.0,
+            luminance: Some(description.luminance()),
+            target_luminance: None,
+            target_primaries: None,
+            target_max_cll: None,
+            target_max_fall: None,
+        };
+        assert!(compare_description(
+            description,
+            &data,
+            DescriptionDeltas::DEFAULT_CHROMATICITY_EPSILON
+        )
+        .unwrap()
+        .pass());
+        data.primaries = TestPrimaries::Named(NamedPrimaries::Bt2020);
+        assert!(!compare_description(
+            description,
+            &data,
+            DescriptionDeltas::DEFAULT_CHROMATICITY_EPSILON
+        )
+        .unwrap()
+        .pass());
+    }
+
+    #[test]
+    fn unreported_mastering_does_not_fail() {
+        let description = TestColorDescription::Parametric {
+            primaries: TestPrimaries::Named(NamedPrimaries::Bt2020),
+            transfer_function: TransferFunctionWithArgs {
+                tf: TransferFunction::Named(NamedTransferFunction::St2084Pq),
+                pow: 1.0,
+            },
+            luminance: None,
+            mastering: Some(Mastering {
+                primaries: None,
+                luminance: Some((F64(0.005), F64(1000.0))),
+                max_cll: Some(1000),
+                max_fall: None,
+            }),
+        };
+        let mut data = DescriptionData {
+            primaries: TestPrimaries::Named(NamedPrimaries::Bt2020),
+            tf: TransferFunction::Named(NamedTransferFunction::St2084Pq),
+            tf_power: 0.0,
+            luminance: None,
+            target_luminance: None,
+            target_primaries: None,
+            target_max_cll: None,
+            target_max_fall: None,
+        };
+        let matches = compare_mastering(
+            description,
+            &data,
+            DescriptionDeltas::DEFAULT_CHROMATICITY_EPSILON,
+        )
+        .unwrap();
+        assert_eq!(matches.luminance, Some(MetadataMatch::NotReported));
+        assert_eq!(matches.max_fall, None);
+        assert!(matches.pass());
+        data.target_luminance = Some((F64(0.005), F64(1000.0)));
+        data.target_max_cll = Some(4000);
+        let matches = compare_mastering(
+            description,
+            &data,
+            DescriptionDeltas::DEFAULT_CHROMATICITY_EPSILON,
+        )
+        .unwrap();
+        assert_eq!(matches.luminance, Some(MetadataMatch::Match));
+        assert_eq!(matches.max_cll, Some(MetadataMatch::Mismatch));
+        assert!(!matches.pass());
+    }
+}
@@ -0,0 +1,259 @@
+//! A CPU implementation of the color pipeline in `fill.encode.glsl` that records every
+//! intermediate value.
+
+use {
+    crate::{
+        cmm::{ColorMatrix, Lms, Xyz},
+        vulkan::{
+            eotf_id, lch_to_lab, ChannelAdjustment, GamutMapping, OutputClamp, RenderParams,
+            LAB_TO_LMS_PRIME,
+        },
+    },
+    std::fmt::Write,
+};
+
+/// The device settings that change the encoded values.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Options {
+    pub gamut_mapping: GamutMapping,
+    pub output_clamp: OutputClamp,
+    pub channel_adjustment: ChannelAdjustment,
+}
+
+/// The intermediate values of the color pipeline.
+struct Steps {
+    lab: [f32; 4],
+    /// The factor applied to a and b by the gamut mapping.
+    chroma_scale: f32,
+    lms_prime: [f32; 3],
+    lms: [f32; 3],
+    xyz: [f32; 3],
+    local: [f32; 3],
+    clamped: [f32; 3],
+    encoded: [f32; 3],
+    /// The encoded values after the channel adjustment.
+    adjusted: [f32; 3],
+    /// The adjusted values after the output clamp.
+    output: [f32; 3],
+}
+
+fn run(
+    lch: [f32; 4],
+    params: &RenderParams,
+    options: &Options,
+    xyz_from_lms: ColorMatrix<Xyz, Lms>,
+) -> Steps {
+    let lab = lch_to_lab(lch);
+    let lms_to_local = params.lms_to_local.to_f32();
+    let chroma_scale = match options.gamut_mapping {
+        GamutMapping::Clip => 1.0,
+        GamutMapping::Compress => compress_chroma(&lms_to_local, lab),
+    };
+    let lms_prime = mul3(
+        &LAB_TO_LMS_PRIME,
+        [lab[0], chroma_scale * lab[1], chroma_scale * lab[2]],
+    );
+    let lms = lms_prime.map(|c| c * c * c);
+    let xyz = (xyz_from_lms * lms.map(|c| c as f64)).map(|c| c as f32);
+    let local = lms_to_local3(&lms_to_local, lms);
+    let clamped = match params.peak > 0.0 {
+        true => local.map(|c| c.min(params.peak)),
+        false => local,
+    };
+    let eotf = eotf_id(params.tf);
+    let encoded = clamped.map(|c| inv_eotf(eotf, &params.tf_args, c));
+    let ChannelAdjustment { gain, offset } = options.channel_adjustment;
+    let adjusted: [f32; 3] = std::array::from_fn(|i| encoded[i] * gain[i] + offset[i]);
+    let output = adjusted.map(|c| match options.output_clamp {
+        OutputClamp::Extended => c,
+        OutputClamp::NonNegative => c.max(0.0),
+        OutputClamp::Unit => c.clamp(0.0, 1.0),
+    });
+    Steps {
+        lab,
+        chroma_scale,
+        lms_prime,
+        lms,
+        xyz,
+        local,
+        clamped,
+        encoded,
+        adjusted,
+        output,
+    }
+}
+
+/// Returns the value that the fragment shader writes for the color.
+pub fn encode(lch: [f32; 4], params: &RenderParams, options: &Options) -> [f32; 4] {
+    let [r, g, b] = run(lch, params, options, ColorMatrix::XYZ_FROM_LMS).output;
+    [r, g, b, lch[3]]
+}
+
+/// Traces a color given in the LCh representation used by the test pane and returns
+/// the result as JSON. `xyz_from_lms` is only used for the informational XYZ values.
+pub fn trace(
+    lch: [f32; 4],
+    params: &RenderParams,
+    options: &Options,
+    xyz_from_lms: ColorMatrix<Xyz, Lms>,
+) -> String {
+    let Steps {
+        lab,
+        chroma_scale,
+        lms_prime,
+        lms,
+        xyz,
+        local,
+        clamped,
+        encoded,
+        adjusted,
+        output,
+    } = run(lch, params, options, xyz_from_lms);
+    let eotf = eotf_id(params.tf);
+    let lms_to_local = params.lms_to_local.to_f32();
+
+    let mut s = String::new();
+    s.push_str("{\n");
+    let mut field = |name: &str, value: String| {
+        if s.len() > 2 {
+            s.push_str(",\n");
+        }
+        let _ = write!(s, "  \"{name}\": {value}");
+    };
+    field("lch", array(&lch));
+    field("lab", array(&lab));
+    field("gamut_mapping", format!("\"{:?}\"", options.gamut_mapping));
+    field("chroma_scale", number(chroma_scale));
+    field("lms_prime", array(&lms_prime));
+    field("lms", array(&lms));
+    field("xyz", array(&xyz));
+    field("local", array(&local));
+    field("peak", number(params.peak));
+    field("clamped", array(&clamped));
+    field("encoded", array(&encoded));
+    field("channel_gain", array(&options.channel_adjustment.gain));
+    field("channel_offset", array(&options.channel_adjustment.offset));
+    field("adjusted", array(&adjusted));
+    field("output_clamp", format!("\"{:?}\"", options.output_clamp));
+    field("output", array(&output));
+    field("alpha", number(lch[3]));
+    field("transfer_function", format!("\"{:?}\"", params.tf));
+    field("eotf", eotf.to_string());
+    field("eotf_args", array(&params.tf_args));
+    let lab_to_lms_prime = LAB_TO_LMS_PRIME.map(|r| r.map(|v| v as f32));
+    field(
+        "lab_to_lms_prime",
+        matrix(lab_to_lms_prime.iter().map(|r| &r[..])),
+    );
+    field(
+        "xyz_from_lms",
+        matrix(xyz_from_lms.to_f32()[..3].iter().map(|r| &r[..3])),
+    );
+    field(
+        "lms_to_local",
+        matrix(lms_to_local[..3].iter().map(|r| &r[..])),
+    );
+    s.push_str("\n}");
+    s
+}
+
+/// Applies the affine `lms_to_local` matrix.
+fn lms_to_local3(lms_to_local: &[[f32; 4]; 4], lms: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| {
+        let m = &lms_to_local[i];
+        m[0] * lms[0] + m[1] * lms[1] + m[2] * lms[2] + m[3]
+    })
+}
+
+/// Returns the largest factor for a and b for which no local channel is negative, like
+/// `compress_chroma` in `fill.encode.glsl`.
+fn compress_chroma(lms_to_local: &[[f32; 4]; 4], lab: [f32; 4]) -> f32 {
+    let inside = |scale: f32| {
+        let lms_prime = mul3(&LAB_TO_LMS_PRIME, [lab[0], scale * lab[1], scale * lab[2]]);
+        let local = lms_to_local3(lms_to_local, lms_prime.map(|c| c * c * c));
+        local.iter().all(|c| *c >= 0.0)
+    };
+    if inside(1.0) {
+        return 1.0;
+    }
+    let mut lo = 0.0;
+    let mut hi = 1.0;
+    for _ in 0..16 {
+        let mid = (lo + hi) / 2.0;
+        match inside(mid) {
+            true => lo = mid,
+            false => hi = mid,
+        }
+    }
+    lo
+}
+
+fn mul3(m: &[[f64; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    let v = v.map(|v| v as f64);
+    std::array::from_fn(|i| (m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2]) as f32)
+}
+
+/// Applies the inverse EOTF with the given `eotf` id like `fill.encode.glsl`.
+pub fn inv_eotf(eotf: u32, args: &[f32; 4], c: f32) -> f32 {
+    let [a1, a2, a3, a4] = *args;
+    match eotf {
+        1 => c,
+        2 => {
+            let c = (a1 * c).clamp(0.0, 1.0).powf(2610.0 / 16384.0);
+            let num = 3424.0 / 4096.0 + 2413.0 / 128.0 * c;
+            let den = 1.0 + 2392.0 / 128.0 * c;
+            (num / den).powf(2523.0 / 32.0)
+        }
+        3 => a1 * ((a2 * c.clamp(0.0, 1.0) + a3).powf(1.0 / 2.4) - a4),
+        4 => c.signum() * c.abs().powf(1.0 / 2.2),
+        5 => c.signum() * c.abs().powf(1.0 / 2.8),
+        6 => match c >= 0.0228 {
+            true => 1.1115 * c.powf(0.45) - 0.1115,
+            false => 4.0 * c,
+        },
+        7 => {
+            let a = c.abs();
+            let e = match a > 0.0031308 {
+                true => 1.055 * a.powf(1.0 / 2.4) - 0.055,
+                false => 12.92 * a,
+            };
+            c.signum() * e
+        }
+        8 => {
+            let c = c.clamp(0.0, 1.0);
+            match c >= 0.01 {
+                true => 1.0 + c.log10() / 2.0,
+                false => 0.0,
+            }
+        }
+        9 => {
+            let c = c.clamp(0.0, 1.0);
+            match c >= 10.0f32.sqrt() / 1000.0 {
+                true => 1.0 + c.log10() / 2.5,
+                false => 0.0,
+            }
+        }
+        10 => (48.0 * c.max(0.0) / 52.37).powf(1.0 / 2.6),
+        11 => c.signum() * c.abs().powf(a1),
+        _ => c,
+    }
+}
+
+fn number(v: f32) -> String {
+    match v.is_finite() {
+        true => format!("{v}"),
+        false => "null".to_string(),
+    }
+}
+
+fn array(v: &[f32]) -> String {
+    format!(
+        "[{}]",
+        v.iter().map(|v| number(*v)).collect::<Vec<_>>().join(", ")
+    )
+}
+
+fn matrix<'a>(rows: impl Iterator<Item = &'a [f32]>) -> String {
+    let rows: Vec<_> = rows.map(array).collect();
+    format!("[\n    {}\n  ]", rows.join(",\n    "))
+}
@@ -1,22 +1,34 @@
 use {
     crate::{
-        cmm::{ColorMatrix, Lms, Local, NamedTransferFunction, TransferFunction},
-        protocols::wayland::wl_surface::WlSurface,
+        cmm::{
+            lms_from_ictcp_lms, ColorMatrix, IctcpLms, Lms, Local, NamedTransferFunction,
+            TransferFunction,
+        },
+        font,
+        protocols::wayland::{wl_output::WlOutputTransform, wl_surface::WlSurface},
+        trace,
     },
     ash::{
-        ext::swapchain_maintenance1,
+        ext::{debug_utils, swapchain_maintenance1},
         khr::{surface, swapchain, wayland_surface},
         vk::{
             self, AccessFlags2, AcquireNextImageInfoKHR, ApplicationInfo, AttachmentLoadOp,
             AttachmentStoreOp, BlendFactor, BlendOp, Buffer, BufferCreateInfo,
-            BufferDeviceAddressInfo, BufferMemoryBarrier2, BufferUsageFlags, ClearColorValue,
-            ClearValue, ColorComponentFlags, ColorSpaceKHR, CommandBuffer,
+            BufferDeviceAddressInfo, BufferImageCopy, BufferMemoryBarrier2, BufferUsageFlags,
+            ClearColorValue, ClearValue, ColorComponentFlags, ColorSpaceKHR, CommandBuffer,
             CommandBufferAllocateInfo, CommandBufferBeginInfo, CommandBufferLevel,
             CommandBufferUsageFlags, CommandPool, CommandPoolCreateInfo, CompositeAlphaFlagsKHR,
-            DependencyInfo, DeviceCreateInfo, DeviceMemory, DeviceQueueCreateInfo, DynamicState,
-            Extent2D, Fence, FenceCreateInfo, Format, GraphicsPipelineCreateInfo, Image,
-            ImageAspectFlags, ImageLayout, ImageMemoryBarrier2, ImageSubresourceRange,
-            ImageUsageFlags, ImageView, ImageViewCreateInfo, ImageViewType, InstanceCreateInfo,
+            DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
+            DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerCreateInfoEXT,
+            DebugUtilsMessengerEXT, DependencyInfo, DescriptorImageInfo, DescriptorPool,
+            DescriptorPoolCreateFlags, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet,
+            DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBinding,
+            DescriptorSetLayoutCreateInfo, DescriptorType, DeviceCreateInfo, DeviceMemory,
+            DeviceQueueCreateInfo, DynamicState, ExtensionProperties, Extent2D, Extent3D, Fence,
+            FenceCreateInfo, Filter, Format, GraphicsPipelineCreateInfo, Image, ImageAspectFlags,
+            ImageCreateInfo, ImageLayout, ImageMemoryBarrier2, ImageSubresourceLayers,
+            ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags, ImageView,
+            ImageViewCreateInfo, ImageViewType, InstanceCreateInfo, MemoryRequirements,
             PhysicalDevice, PhysicalDeviceSwapchainMaintenance1FeaturesEXT,
             PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features, Pipeline,
             PipelineBindPoint, PipelineCache, PipelineColorBlendAttachmentState,
@@ -28,28 +40,39 @@ use {
             PipelineTessellationStateCreateInfo, PipelineVertexInputStateCreateInfo,
             PipelineViewportStateCreateInfo, PresentInfoKHR, PresentModeKHR, PrimitiveTopology,
             PushConstantRange, Queue, Rect2D, RenderingAttachmentInfo, RenderingInfo,
-            SampleCountFlags, Semaphore, SemaphoreCreateInfo, ShaderModule, ShaderModuleCreateInfo,
-            ShaderStageFlags, SharingMode, SubmitInfo, SurfaceFormatKHR, SurfaceKHR,
+            SampleCountFlags, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
+            Semaphore, SemaphoreCreateInfo, ShaderModule, ShaderModuleCreateInfo, ShaderStageFlags,
+            SharingMode, SubmitInfo, SurfaceCapabilitiesKHR, SurfaceFormatKHR, SurfaceKHR,
             SurfaceTransformFlagsKHR, SwapchainCreateInfoKHR, SwapchainKHR,
             SwapchainPresentFenceInfoEXT, Viewport, WaylandSurfaceCreateInfoKHR,
-            EXT_SURFACE_MAINTENANCE1_NAME, EXT_SWAPCHAIN_COLORSPACE_NAME,
-            EXT_SWAPCHAIN_MAINTENANCE1_NAME, KHR_GET_SURFACE_CAPABILITIES2_NAME, KHR_SURFACE_NAME,
-            KHR_SWAPCHAIN_NAME, KHR_WAYLAND_SURFACE_NAME,
+            WriteDescriptorSet, EXT_DEBUG_UTILS_NAME, EXT_SURFACE_MAINTENANCE1_NAME,
+            EXT_SWAPCHAIN_COLORSPACE_NAME, EXT_SWAPCHAIN_MAINTENANCE1_NAME,
+            KHR_GET_SURFACE_CAPABILITIES2_NAME, KHR_SURFACE_NAME, KHR_SWAPCHAIN_NAME,
+            KHR_WAYLAND_SURFACE_NAME,
         },
         Device, Entry, Instance,
     },
     bytemuck::{bytes_of, NoUninit},
-    gpu_alloc::{AllocationError, Config, GpuAllocator, MemoryBlock, Request, UsageFlags},
+    gpu_alloc::{
+        AllocationError, Config, GpuAllocator, MapError, MemoryBlock, Request, UsageFlags,
+    },
     gpu_alloc_ash::AshMemoryDevice,
+    isnt::std_1::vec::IsntVecExt,
     itertools::Itertools,
+    linearize::{Linearize, LinearizeExt},
     run_on_drop::on_drop,
     std::{
+        array,
         cell::{Cell, RefCell, RefMut},
         collections::VecDeque,
+        ffi::{c_void, CStr},
         iter,
+        mem::offset_of,
         ptr::NonNull,
         rc::Rc,
         slice,
+        sync::atomic::{AtomicBool, Ordering::Relaxed},
+        time::{Duration, Instant},
     },
     thiserror::Error,
     wl_client::{ffi::wl_display, proxy},
@@ -57,6 +80,14 @@ use {
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("could not enumerate instance extensions")]
+    EnumerateInstanceExtensions(#[source] vk::Result),
+    #[error("the Vulkan instance does not support {}", .0.to_string_lossy())]
+    MissingInstanceExtension(&'static CStr),
+    #[error("could not enumerate instance layers")]
+    EnumerateInstanceLayers(#[source] vk::Result),
+    #[error("could not create a debug messenger")]
+    CreateDebugMessenger(#[source] vk::Result),
     #[error("could not create an instance")]
     CreateInstance(#[source] vk::Result),
     #[error("could not enumerate physical devices")]
@@ -65,16 +96,20 @@ pub enum Error {
     NoPhysicalDevices,
     #[error("physical device has no graphics queues")]
     NoQueues,
+    #[error("could not enumerate device extensions")]
+    EnumerateDeviceExtensions(#[source] vk::Result),
+    #[error("the physical device does not support {}", .0.to_string_lossy())]
+    MissingDeviceExtension(&'static CStr),
     #[error("could not create device")]
     CreateDevice(#[source] vk::Result),
     #[error("could not create wayland surface")]
     CreateWaylandSurface(#[source] vk::Result),
     #[error("could not get the supported surface formats")]
     GetSurfaceFormats(#[source] vk::Result),
+    #[error("could not get the surface capabilities")]
+    GetSurfaceCapabilities(#[source] vk::Result),
     #[error("surface does not support F16 pass through format")]
     F16NotSupported,
-    #[error("could not wait for device idle")]
-    WaitIdle(#[source] vk::Result),
     #[error("could not create a swapchain")]
     CreateSwapchain(#[source] vk::Result),
     #[error("could not retrieve swapchain images")]
@@ -87,8 +122,12 @@ pub enum Error {
     CreateFence(#[source] vk::Result),
     #[error("could not get fence status")]
     GetFenceStatus(#[source] vk::Result),
+    #[error("could not reset a fence")]
+    ResetFence(#[source] vk::Result),
     #[error("could not acquire the next swapchain image")]
     AcquireNextImage(#[source] vk::Result),
+    #[error("no swapchain image became available within {0:?}, is the compositor not releasing buffers?")]
+    AcquireTimeout(Duration),
     #[error("could not begin command buffer")]
     BeginCommandBuffer(#[source] vk::Result),
     #[error("could not end command buffer")]
@@ -115,6 +154,28 @@ pub enum Error {
     AllocateMemory(#[source] AllocationError),
     #[error("could not bind buffer memory")]
     BindBufferMemory(#[source] vk::Result),
+    #[error("could not create an image")]
+    CreateImage(#[source] vk::Result),
+    #[error("could not bind image memory")]
+    BindImageMemory(#[source] vk::Result),
+    #[error("could not wait for a fence")]
+    WaitForFence(#[source] vk::Result),
+    #[error("could not read buffer memory")]
+    ReadMemory(#[source] MapError),
+    #[error("could not write buffer memory")]
+    WriteMemory(#[source] MapError),
+    #[error("could not retrieve the surface present modes")]
+    GetSurfacePresentModes(#[source] vk::Result),
+    #[error("could not create a sampler")]
+    CreateSampler(#[source] vk::Result),
+    #[error("could not create a descriptor set layout")]
+    CreateDescriptorSetLayout(#[source] vk::Result),
+    #[error("could not create a descriptor pool")]
+    CreateDescriptorPool(#[source] vk::Result),
+    #[error("could not allocate a descriptor set")]
+    AllocateDescriptorSet(#[source] vk::Result),
+    #[error("the texture is {0}x{1} pixels but the device supports at most {2}x{2}")]
+    TextureTooLarge(u32, u32, u32),
 }
 
 struct VulkanSwapchain {
@@ -123,29 +184,97 @@ struct VulkanSwapchain {
     image_views: Vec<ImageView>,
     width: u32,
     height: u32,
+    alpha_mode: AlphaMode,
+    /// The requested number of images. The swapchain might have more.
+    image_count: u32,
+    /// The requested present mode. The swapchain might use FIFO instead.
+    present_mode: PresentMode,
+    transform: WlOutputTransform,
+    color_space: SwapchainColorSpace,
+    /// Whether the swapchain uses post-multiplied instead of pre-multiplied composite
+    /// alpha.
+    post_multiplied: bool,
+    /// The image count range of the surface capabilities at creation.
+    image_count_range: (u32, Option<u32>),
 }
 
 struct VulkanSubmission {
+    swapchain: SwapchainKHR,
     release_fence: Fence,
     acquire_semaphore: Semaphore,
     release_semaphore: Semaphore,
     owns_release_semaphore: Rc<Cell<bool>>,
     command_buffer: CommandBuffer,
+    upload: Option<VulkanUpload>,
     fill_buffers: Vec<FillBuffer>,
+    /// The texture sampled by the submission, if any.
+    _texture: Option<Rc<Texture>>,
+}
+
+struct VulkanUpload {
+    command_buffer: CommandBuffer,
+    semaphore: Semaphore,
 }
 
 struct VulkanPresentation {
+    swapchain: SwapchainKHR,
     release_fence: Fence,
     release_semaphore: Semaphore,
+    /// The time of the present request if the latency is measured.
+    presented: Option<Instant>,
+}
+
+/// The number of samples averaged by `VulkanSurface::present_latency`.
+const PRESENT_LATENCY_SAMPLES: usize = 60;
+
+/// The device memory used by a device or surface.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MemoryStats {
+    /// The number of bytes allocated for fill buffers and other resources owned by the
+    /// application.
+    pub allocated: u64,
+    /// The number of allocated blocks.
+    pub allocations: u64,
+}
+
+/// The color parameters of the first fill of a frame as uploaded to the GPU. Fills
+/// that are interpolated in ICtCp use a different `lms_to_local`.
+#[derive(Copy, Clone, Debug)]
+pub struct UploadedParams {
+    pub eotf: u32,
+    pub eotf_args: [f32; 4],
+    pub lms_to_local: [[f32; 4]; 4],
+}
+
+/// Sizes in pixels of a surface.
+#[derive(Copy, Clone, Debug)]
+pub struct SurfaceExtent {
+    /// The extent of the current swapchain, if any.
+    pub swapchain: Option<(u32, u32)>,
+    /// The limits of the swapchain extent.
+    pub min: (u32, u32),
+    pub max: (u32, u32),
 }
 
 pub struct VulkanSurface {
     submissions: RefCell<VecDeque<VulkanSubmission>>,
     presents: RefCell<VecDeque<VulkanPresentation>>,
     swapchain: RefCell<Option<VulkanSwapchain>>,
+    /// Swapchains that have been replaced but might still be in use by submissions or
+    /// presentations.
+    retired_swapchains: RefCell<Vec<VulkanSwapchain>>,
     suboptimal: Cell<bool>,
+    transform: Cell<WlOutputTransform>,
+    color_space: Cell<SwapchainColorSpace>,
     surface: SurfaceKHR,
     fill_buffers: RefCell<Vec<FillBuffer>>,
+    semaphores: RefCell<Vec<Semaphore>>,
+    fences: RefCell<Vec<Fence>>,
+    /// The parameters of the last rendered frame.
+    uploaded_params: Cell<Option<UploadedParams>>,
+    /// The times between presenting and the release of the present fence if the
+    /// latency is measured.
+    present_latencies: RefCell<Option<VecDeque<Duration>>>,
     device: Rc<VulkanDevice>,
     _wl_surface: WlSurface,
 }
@@ -153,48 +282,331 @@ pub struct VulkanSurface {
 pub struct VulkanDevice {
     queue: Queue,
     queue_idx: u32,
+    transfer: Option<TransferQueue>,
+    upload_mode: Cell<UploadMode>,
+    alpha_mode: Cell<AlphaMode>,
+    image_count: Cell<u32>,
+    present_mode: Cell<PresentMode>,
+    dither_bits: Cell<Option<u32>>,
+    gamut_mapping: Cell<GamutMapping>,
+    gradient_space: Cell<GradientSpace>,
+    output_clamp: Cell<OutputClamp>,
+    channel_adjustment: Cell<ChannelAdjustment>,
     khr_swapchain: swapchain::Device,
     _ext_swapchain_maintenance1: swapchain_maintenance1::Device,
     command_pool: CommandPool,
     pipeline: Pipeline,
+    straight_pipeline: Pipeline,
     pipeline_layout: PipelineLayout,
+    texture_pipeline: Pipeline,
+    straight_texture_pipeline: Pipeline,
+    texture_pipeline_layout: PipelineLayout,
+    texture_set_layout: DescriptorSetLayout,
+    descriptor_pool: DescriptorPool,
+    sampler: Sampler,
     fill_vert: ShaderModule,
     fill_frag: ShaderModule,
+    texture_frag: ShaderModule,
     allocator: RefCell<GpuAllocator<DeviceMemory>>,
+    /// The number of bytes currently allocated through `allocator`.
+    allocated_memory: Cell<u64>,
+    /// The number of blocks currently allocated through `allocator`.
+    allocations: Cell<u64>,
     device: Device,
     physical_device: PhysicalDevice,
     khr_wayland_surface: wayland_surface::Instance,
     khr_surface: surface::Instance,
+    debug_messenger: Option<(debug_utils::Instance, DebugUtilsMessengerEXT)>,
     instance: Instance,
 }
 
+struct TransferQueue {
+    queue: Queue,
+    queue_idx: u32,
+    command_pool: CommandPool,
+}
+
+/// How fill buffers are uploaded to the GPU.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum UploadMode {
+    /// Upload on the graphics queue.
+    #[default]
+    Graphics,
+    /// Upload on a dedicated transfer queue and transfer the ownership of the buffers
+    /// to the graphics queue.
+    TransferExclusive,
+    /// Upload on a dedicated transfer queue into buffers that are shared between the
+    /// queue families.
+    TransferConcurrent,
+}
+
+/// How the alpha channel of the rendered content is interpreted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum AlphaMode {
+    /// The color channels are already multiplied by the alpha channel and the surface
+    /// uses pre-multiplied composite alpha. Fills are added to what is below them.
+    #[default]
+    Premultiplied,
+    /// The color channels are not multiplied by the alpha channel and the surface uses
+    /// post-multiplied composite alpha if it is supported. Otherwise the compositor
+    /// assumes pre-multiplied alpha and translucent content appears brighter.
+    Straight,
+}
+
+/// The present mode of the swapchains.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum PresentMode {
+    /// Frames replace queued frames and are presented without tearing.
+    #[default]
+    Mailbox,
+    /// Frames are queued and presented at the refresh rate.
+    Fifo,
+    /// Frames are presented as soon as possible and may tear.
+    Immediate,
+}
+
+impl PresentMode {
+    fn vk(self) -> PresentModeKHR {
+        match self {
+            PresentMode::Mailbox => PresentModeKHR::MAILBOX,
+            PresentMode::Fifo => PresentModeKHR::FIFO,
+            PresentMode::Immediate => PresentModeKHR::IMMEDIATE,
+        }
+    }
+}
+
+/// The color space of the swapchain images. With color spaces other than
+/// `PassThrough`, the Vulkan implementation describes the content to the compositor.
+///
+/// Only color spaces that can be used with the F16 format of the pipelines and that
+/// can be expressed as a parametric description are included.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum SwapchainColorSpace {
+    #[default]
+    PassThrough,
+    SrgbNonlinear,
+    ExtendedSrgbLinear,
+    ExtendedSrgbNonlinear,
+    DisplayP3Nonlinear,
+    DisplayP3Linear,
+    DciP3Nonlinear,
+    Bt709Linear,
+    Bt709Nonlinear,
+    Bt2020Linear,
+    Hdr10St2084,
+    AdobeRgbLinear,
+    AdobeRgbNonlinear,
+}
+
+impl SwapchainColorSpace {
+    fn vk(self) -> ColorSpaceKHR {
+        match self {
+            SwapchainColorSpace::PassThrough => ColorSpaceKHR::PASS_THROUGH_EXT,
+            SwapchainColorSpace::SrgbNonlinear => ColorSpaceKHR::SRGB_NONLINEAR,
+            SwapchainColorSpace::ExtendedSrgbLinear => ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            SwapchainColorSpace::ExtendedSrgbNonlinear => {
+                ColorSpaceKHR::EXTENDED_SRGB_NONLINEAR_EXT
+            }
+            SwapchainColorSpace::DisplayP3Nonlinear => ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT,
+            SwapchainColorSpace::DisplayP3Linear => ColorSpaceKHR::DISPLAY_P3_LINEAR_EXT,
+            SwapchainColorSpace::DciP3Nonlinear => ColorSpaceKHR::DCI_P3_NONLINEAR_EXT,
+            SwapchainColorSpace::Bt709Linear => ColorSpaceKHR::BT709_LINEAR_EXT,
+            SwapchainColorSpace::Bt709Nonlinear => ColorSpaceKHR::BT709_NONLINEAR_EXT,
+            SwapchainColorSpace::Bt2020Linear => ColorSpaceKHR::BT2020_LINEAR_EXT,
+            SwapchainColorSpace::Hdr10St2084 => ColorSpaceKHR::HDR10_ST2084_EXT,
+            SwapchainColorSpace::AdobeRgbLinear => ColorSpaceKHR::ADOBERGB_LINEAR_EXT,
+            SwapchainColorSpace::AdobeRgbNonlinear => ColorSpaceKHR::ADOBERGB_NONLINEAR_EXT,
+        }
+    }
+}
+
+/// How colors outside of the gamut of the target primaries are rendered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum GamutMapping {
+    /// Negative channels are clipped by the encoding or by the compositor.
+    #[default]
+    Clip,
+    /// The chroma is reduced at constant lightness and hue until the color is inside
+    /// the gamut.
+    Compress,
+}
+
+/// The space in which the colors of gradients are interpolated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum GradientSpace {
+    #[default]
+    Oklab,
+    /// ICtCp with PQ-encoded LMS. Fills whose colors are all the same are still
+    /// rendered from Oklab.
+    Ictcp,
+}
+
+/// How the encoded values are clamped before they are written to the swapchain image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Linearize)]
+pub enum OutputClamp {
+    /// Negative and values above 1 are passed to the compositor, as used by scRGB.
+    /// Some transfer functions still clamp their input.
+    #[default]
+    Extended,
+    /// Negative values are clamped to 0.
+    NonNegative,
+    /// Values are clamped to [0, 1].
+    Unit,
+}
+
+/// A per-channel adjustment of the encoded values, `value * gain + offset`, that
+/// emulates a miscalibrated display. It is applied after the color pipeline and before
+/// the output clamp.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChannelAdjustment {
+    pub gain: [f32; 3],
+    pub offset: [f32; 3],
+}
+
+impl Default for ChannelAdjustment {
+    fn default() -> Self {
+        Self {
+            gain: [1.0; 3],
+            offset: [0.0; 3],
+        }
+    }
+}
+
+/// The Oklab image of `Scene::Image`, sampled by `texture.frag`.
+pub struct Texture {
+    image: Image,
+    view: ImageView,
+    descriptor_set: DescriptorSet,
+    width: u32,
+    height: u32,
+    memory: Cell<Option<MemoryBlock<DeviceMemory>>>,
+    device: Rc<VulkanDevice>,
+}
+
 struct FillBuffer {
     buffer: Buffer,
     addr: u64,
     size: u64,
+    concurrent: bool,
     memory: Cell<Option<MemoryBlock<DeviceMemory>>>,
     device: Rc<VulkanDevice>,
 }
 
+#[derive(Copy, Clone)]
+pub struct RenderParams {
+    pub lms_to_local: ColorMatrix<Local, Lms>,
+    pub tf: TransferFunction,
+    pub tf_args: [f32; 4],
+    /// The maximum value of each channel in the local color space before encoding.
+    /// Values <= 0 disable the clamp.
+    pub peak: f32,
+}
+
+/// Content drawn on top of the scene.
+#[derive(Clone, Default)]
+pub struct Overlay {
+    pub grid: Option<Grid>,
+    pub legend: Option<Legend>,
+}
+
+/// Lines drawn on top of the scene.
+#[derive(Copy, Clone)]
+pub struct Grid {
+    /// The distance between lines in pixels.
+    pub spacing: u32,
+    pub color: [f32; 4],
+}
+
+/// Text drawn in the top-left corner.
+#[derive(Clone)]
+pub struct Legend {
+    pub text: String,
+    /// The size of a font pixel in pixels.
+    pub scale: u32,
+    pub color: [f32; 4],
+    pub background: [f32; 4],
+}
+
+#[derive(Clone)]
 pub enum Scene {
     Fill([f32; 4]),
-    FillLeftRight([[f32; 4]; 2]),
-    FillTopBottom([[f32; 4]; 2]),
+    /// A gradient from the first to the second color. The angle is in degrees,
+    /// counterclockwise, with 0 going from left to right.
+    LinearGradient([[f32; 4]; 2], f32),
     FillFour([[f32; 4]; 4]),
     CenterBox([[f32; 4]; 2], f32),
     Grid([[f32; 4]; 2], u32, u32),
+    /// A grid with the given number of rows and columns. The colors of the cells are
+    /// given row by row, starting at the top left.
+    ColorGrid(u32, u32, Vec<[f32; 4]>),
+    /// Pixel-aligned lines through the center of the surface and markers in the corners.
+    /// The first color is the background, the second the color of the lines.
+    Crosshair([[f32; 4]; 2]),
+    /// A square patch with the given side length in pixels over a surround at code
+    /// value 0. The patch is centered and then moved by the offset in pixels, positive
+    /// values moving it right and down.
+    Patch([f32; 4], u32, (i32, i32)),
     BlendLeft([f32; 4]),
     BlendRight([[f32; 4]; 2]),
+    /// A horizontal gradient from the first to the second color, including their alpha,
+    /// in the top half. The bottom half is transparent.
+    BlendGradient([[f32; 4]; 2]),
+    /// The first color in the top half. The bottom half shows the `BlendGradient` of the
+    /// other colors blended over it by the client.
+    BlendGradientReference([f32; 4], [[f32; 4]; 2]),
+    /// The pixels scaled to fit the surface, keeping their aspect ratio, over black.
+    Image(Rc<Texture>),
+    /// The first color in the left half. The right half shows the other colors without
+    /// alpha in the top and blended over the first color by the client in the bottom.
+    BlendLayers([f32; 4], Vec<[f32; 4]>),
+    /// A vertical bar of the second color over the first. The position of the left
+    /// edge of the bar is given as a fraction of the width.
+    MovingBar([[f32; 4]; 2], f32),
+    /// Vertical red, green, and blue bars of the target primaries, scaled by the given
+    /// factor relative to the reference white.
+    Primaries(f32),
+    /// A row of square patches over a surround of 0. The values are encoded gray levels
+    /// in `[0, 1]` and bypass the color pipeline.
+    BlackDetail(Vec<f32>),
+    /// One row per entry with a horizontal ramp that is linear in light from black to
+    /// the reference white scaled by the given factor. Each row is encoded with the
+    /// transfer function of its parameters instead of those of the frame. The label of
+    /// each row is drawn in its top-left corner with the font scale and the first color
+    /// as the background and the second as the text color.
+    TransferFunctions(Vec<(String, RenderParams)>, f32, u32, [[f32; 4]; 2]),
+    /// The section of the scene rendered at the given size in pixels whose top-left
+    /// corner is at the given offset in pixels. The section has the size of the surface.
+    Section(Box<Scene>, (u32, u32), (u32, u32)),
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        let dev = &self.device.device;
+        unsafe {
+            let _ = dev.free_descriptor_sets(self.device.descriptor_pool, &[self.descriptor_set]);
+            dev.destroy_image_view(self.view, None);
+            dev.destroy_image(self.image, None);
+            self.device.dealloc(self.memory.take().unwrap());
+        }
+    }
+}
+
+impl Scene {
+    /// Returns the texture that must be kept alive while the scene is rendered.
+    fn texture(&self) -> Option<Rc<Texture>> {
+        match self {
+            Scene::Image(texture) => Some(texture.clone()),
+            Scene::Section(scene, ..) => scene.texture(),
+            _ => None,
+        }
+    }
 }
 
 impl Drop for FillBuffer {
     fn drop(&mut self) {
         unsafe {
             self.device.device.destroy_buffer(self.buffer, None);
-            self.device.allocator.borrow_mut().dealloc(
-                AshMemoryDevice::wrap(&self.device.device),
-                self.memory.take().unwrap(),
-            );
+            self.device.dealloc(self.memory.take().unwrap());
         }
     }
 }
@@ -210,6 +622,12 @@ impl Drop for VulkanSurface {
         }
         self.fill_buffers.borrow_mut().clear();
         unsafe {
+            for semaphore in self.semaphores.take() {
+                self.device.device.destroy_semaphore(semaphore, None);
+            }
+            for fence in self.fences.take() {
+                self.device.device.destroy_fence(fence, None);
+            }
             self.device.khr_surface.destroy_surface(self.surface, None);
         }
     }
@@ -220,12 +638,31 @@ impl Drop for VulkanDevice {
         unsafe {
             let _ = self.device.device_wait_idle();
             self.device.destroy_pipeline(self.pipeline, None);
+            self.device.destroy_pipeline(self.straight_pipeline, None);
             self.device
                 .destroy_pipeline_layout(self.pipeline_layout, None);
+            self.device.destroy_pipeline(self.texture_pipeline, None);
+            self.device
+                .destroy_pipeline(self.straight_texture_pipeline, None);
+            self.device
+                .destroy_pipeline_layout(self.texture_pipeline_layout, None);
+            self.device
+                .destroy_descriptor_set_layout(self.texture_set_layout, None);
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device.destroy_sampler(self.sampler, None);
             self.device.destroy_shader_module(self.fill_vert, None);
             self.device.destroy_shader_module(self.fill_frag, None);
+            self.device.destroy_shader_module(self.texture_frag, None);
             self.device.destroy_command_pool(self.command_pool, None);
+            if let Some(transfer) = &self.transfer {
+                self.device
+                    .destroy_command_pool(transfer.command_pool, None);
+            }
             self.device.destroy_device(None);
+            if let Some((debug_utils, messenger)) = &self.debug_messenger {
+                debug_utils.destroy_debug_utils_messenger(*messenger, None);
+            }
             self.instance.destroy_instance(None);
         }
     }
@@ -250,15 +687,54 @@ impl VulkanDevice {
         let app_info = ApplicationInfo::default()
             .api_version(vk::API_VERSION_1_3)
             .application_name(c"wayland-color-test");
-        let extensions = [
-            KHR_SURFACE_NAME.as_ptr(),
-            EXT_SURFACE_MAINTENANCE1_NAME.as_ptr(),
-            KHR_GET_SURFACE_CAPABILITIES2_NAME.as_ptr(),
-            KHR_WAYLAND_SURFACE_NAME.as_ptr(),
-            EXT_SWAPCHAIN_COLORSPACE_NAME.as_ptr(),
+        let mut extensions = vec![
+            KHR_SURFACE_NAME,
+            EXT_SURFACE_MAINTENANCE1_NAME,
+            KHR_GET_SURFACE_CAPABILITIES2_NAME,
+            KHR_WAYLAND_SURFACE_NAME,
+            EXT_SWAPCHAIN_COLORSPACE_NAME,
         ];
+        let available = unsafe {
+            entry
+                .enumerate_instance_extension_properties(None)
+                .map_err(Error::EnumerateInstanceExtensions)?
+        };
+        if let Some(missing) = find_missing_extension(&available, &extensions) {
+            return Err(Error::MissingInstanceExtension(missing));
+        }
+        let mut layers = vec![];
+        let validation = VALIDATION.load(Relaxed) && {
+            let available_layers = unsafe {
+                entry
+                    .enumerate_instance_layer_properties()
+                    .map_err(Error::EnumerateInstanceLayers)?
+            };
+            let has_layer = available_layers
+                .iter()
+                .any(|l| l.layer_name_as_c_str() == Ok(VALIDATION_LAYER));
+            let has_debug_utils =
+                find_missing_extension(&available, &[EXT_DEBUG_UTILS_NAME]).is_none();
+            if !has_layer {
+                log::warn!(
+                    "{} is not available, validation is disabled",
+                    VALIDATION_LAYER.to_string_lossy(),
+                );
+            } else if !has_debug_utils {
+                log::warn!(
+                    "{} is not available, validation is disabled",
+                    EXT_DEBUG_UTILS_NAME.to_string_lossy(),
+                );
+            }
+            has_layer && has_debug_utils
+        };
+        if validation {
+            layers.push(VALIDATION_LAYER.as_ptr());
+            extensions.push(EXT_DEBUG_UTILS_NAME);
+        }
+        let extensions: Vec<_> = extensions.iter().map(|e| e.as_ptr()).collect();
         let create_info = InstanceCreateInfo::default()
             .application_info(&app_info)
+            .enabled_layer_names(&layers)
             .enabled_extension_names(&extensions);
         let instance = unsafe {
             entry
@@ -266,6 +742,34 @@ impl VulkanDevice {
                 .map_err(Error::CreateInstance)?
         };
         let destroy_instance = on_drop(|| unsafe { instance.destroy_instance(None) });
+        let debug_messenger = match validation {
+            false => None,
+            true => {
+                let debug_utils = debug_utils::Instance::new(&entry, &instance);
+                let create_info = DebugUtilsMessengerCreateInfoEXT::default()
+                    .message_severity(
+                        DebugUtilsMessageSeverityFlagsEXT::WARNING
+                            | DebugUtilsMessageSeverityFlagsEXT::ERROR,
+                    )
+                    .message_type(
+                        DebugUtilsMessageTypeFlagsEXT::GENERAL
+                            | DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                            | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+                    )
+                    .pfn_user_callback(Some(debug_callback));
+                let messenger = unsafe {
+                    debug_utils
+                        .create_debug_utils_messenger(&create_info, None)
+                        .map_err(Error::CreateDebugMessenger)?
+                };
+                Some((debug_utils, messenger))
+            }
+        };
+        let destroy_debug_messenger = on_drop(|| {
+            if let Some((debug_utils, messenger)) = &debug_messenger {
+                unsafe { debug_utils.destroy_debug_utils_messenger(*messenger, None) }
+            }
+        });
         let khr_surface = surface::Instance::new(&entry, &instance);
         let khr_wayland_surface = wayland_surface::Instance::new(&entry, &instance);
         let physical_devices = unsafe {
@@ -280,20 +784,45 @@ impl VulkanDevice {
         let queues =
             unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
         let queue_idx = 'queue: {
-            for (idx, queue) in queues.into_iter().enumerate() {
+            for (idx, queue) in queues.iter().enumerate() {
                 if queue.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
                     break 'queue idx as u32;
                 }
             }
             return Err(Error::NoQueues);
         };
-        let queue_create_info = DeviceQueueCreateInfo::default()
+        let transfer_queue_idx = {
+            let transfer_only = |compute: bool| {
+                queues.iter().position(|q| {
+                    q.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                        && !q.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                        && q.queue_flags.contains(vk::QueueFlags::COMPUTE) == compute
+                })
+            };
+            transfer_only(false)
+                .or_else(|| transfer_only(true))
+                .map(|idx| idx as u32)
+        };
+        let mut queue_create_infos = vec![DeviceQueueCreateInfo::default()
             .queue_family_index(queue_idx)
-            .queue_priorities(&[0.0]);
-        let extensions = [
-            KHR_SWAPCHAIN_NAME.as_ptr(),
-            EXT_SWAPCHAIN_MAINTENANCE1_NAME.as_ptr(),
-        ];
+            .queue_priorities(&[0.0])];
+        if let Some(idx) = transfer_queue_idx {
+            queue_create_infos.push(
+                DeviceQueueCreateInfo::default()
+                    .queue_family_index(idx)
+                    .queue_priorities(&[0.0]),
+            );
+        }
+        let extensions = [KHR_SWAPCHAIN_NAME, EXT_SWAPCHAIN_MAINTENANCE1_NAME];
+        let available = unsafe {
+            instance
+                .enumerate_device_extension_properties(physical_device)
+                .map_err(Error::EnumerateDeviceExtensions)?
+        };
+        if let Some(missing) = find_missing_extension(&available, &extensions) {
+            return Err(Error::MissingDeviceExtension(missing));
+        }
+        let extensions = extensions.map(|e| e.as_ptr());
         let mut device_features12 =
             PhysicalDeviceVulkan12Features::default().buffer_device_address(true);
         let mut device_features13 = PhysicalDeviceVulkan13Features::default()
@@ -302,7 +831,7 @@ impl VulkanDevice {
         let mut swapchain_maintenance1_features =
             PhysicalDeviceSwapchainMaintenance1FeaturesEXT::default().swapchain_maintenance1(true);
         let create_info = DeviceCreateInfo::default()
-            .queue_create_infos(slice::from_ref(&queue_create_info))
+            .queue_create_infos(&queue_create_infos)
             .enabled_extension_names(&extensions)
             .push_next(&mut device_features12)
             .push_next(&mut device_features13)
@@ -331,8 +860,30 @@ impl VulkanDevice {
         };
         let destroy_command_pool =
             on_drop(|| unsafe { device.destroy_command_pool(command_pool, None) });
+        let transfer = match transfer_queue_idx {
+            None => None,
+            Some(queue_idx) => {
+                let create_info = CommandPoolCreateInfo::default().queue_family_index(queue_idx);
+                let command_pool = unsafe {
+                    device
+                        .create_command_pool(&create_info, None)
+                        .map_err(Error::CreateCommandPool)?
+                };
+                Some(TransferQueue {
+                    queue: unsafe { device.get_device_queue(queue_idx, 0) },
+                    queue_idx,
+                    command_pool,
+                })
+            }
+        };
+        let destroy_transfer_command_pool = on_drop(|| {
+            if let Some(transfer) = &transfer {
+                unsafe { device.destroy_command_pool(transfer.command_pool, None) }
+            }
+        });
         const FILL_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/fill.vert.spv"));
         const FILL_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/fill.frag.spv"));
+        const TEXTURE_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/texture.frag.spv"));
         let create_shader = |bytes: &[u8]| {
             let mut iter = bytes.iter().copied();
             let code: Vec<_> = iter::from_fn(|| iter.next_array::<4>())
@@ -351,10 +902,61 @@ impl VulkanDevice {
         let fill_frag = create_shader(FILL_FRAG)?;
         let destroy_fill_frag =
             on_drop(|| unsafe { device.destroy_shader_module(fill_frag, None) });
+        let texture_frag = create_shader(TEXTURE_FRAG)?;
+        let destroy_texture_frag =
+            on_drop(|| unsafe { device.destroy_shader_module(texture_frag, None) });
+        let sampler = {
+            let create_info = SamplerCreateInfo::default()
+                .mag_filter(Filter::NEAREST)
+                .min_filter(Filter::NEAREST)
+                .mipmap_mode(SamplerMipmapMode::NEAREST)
+                .address_mode_u(SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_v(SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_w(SamplerAddressMode::CLAMP_TO_EDGE);
+            unsafe {
+                device
+                    .create_sampler(&create_info, None)
+                    .map_err(Error::CreateSampler)?
+            }
+        };
+        let destroy_sampler = on_drop(|| unsafe { device.destroy_sampler(sampler, None) });
+        let texture_set_layout = {
+            let binding = DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .stage_flags(ShaderStageFlags::FRAGMENT)
+                .immutable_samplers(slice::from_ref(&sampler));
+            let create_info =
+                DescriptorSetLayoutCreateInfo::default().bindings(slice::from_ref(&binding));
+            unsafe {
+                device
+                    .create_descriptor_set_layout(&create_info, None)
+                    .map_err(Error::CreateDescriptorSetLayout)?
+            }
+        };
+        let destroy_texture_set_layout =
+            on_drop(|| unsafe { device.destroy_descriptor_set_layout(texture_set_layout, None) });
+        let descriptor_pool = {
+            let pool_size = DescriptorPoolSize::default()
+                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(MAX_TEXTURES);
+            let create_info = DescriptorPoolCreateInfo::default()
+                .flags(DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+                .max_sets(MAX_TEXTURES)
+                .pool_sizes(slice::from_ref(&pool_size));
+            unsafe {
+                device
+                    .create_descriptor_pool(&create_info, None)
+                    .map_err(Error::CreateDescriptorPool)?
+            }
+        };
+        let destroy_descriptor_pool =
+            on_drop(|| unsafe { device.destroy_descriptor_pool(descriptor_pool, None) });
+        let range = PushConstantRange::default()
+            .size(size_of::<FillPushConstant>() as _)
+            .stage_flags(ShaderStageFlags::FRAGMENT | ShaderStageFlags::VERTEX);
         let pipeline_layout = {
-            let range = PushConstantRange::default()
-                .size(size_of::<FillPushConstant>() as _)
-                .stage_flags(ShaderStageFlags::FRAGMENT | ShaderStageFlags::VERTEX);
             let create_info =
                 PipelineLayoutCreateInfo::default().push_constant_ranges(slice::from_ref(&range));
             unsafe {
@@ -365,16 +967,32 @@ impl VulkanDevice {
         };
         let destroy_pipeline_layout =
             on_drop(|| unsafe { device.destroy_pipeline_layout(pipeline_layout, None) });
-        let pipeline = {
-            let stages = [
-                PipelineShaderStageCreateInfo::default()
-                    .stage(ShaderStageFlags::VERTEX)
-                    .name(c"main")
-                    .module(fill_vert),
+        let texture_pipeline_layout = {
+            let create_info = PipelineLayoutCreateInfo::default()
+                .set_layouts(slice::from_ref(&texture_set_layout))
+                .push_constant_ranges(slice::from_ref(&range));
+            unsafe {
+                device
+                    .create_pipeline_layout(&create_info, None)
+                    .map_err(Error::CreatePipelineLayout)?
+            }
+        };
+        let destroy_texture_pipeline_layout =
+            on_drop(|| unsafe { device.destroy_pipeline_layout(texture_pipeline_layout, None) });
+        let [pipeline, straight_pipeline, texture_pipeline, straight_texture_pipeline] = {
+            let stage = |stage, module| {
                 PipelineShaderStageCreateInfo::default()
-                    .stage(ShaderStageFlags::FRAGMENT)
+                    .stage(stage)
                     .name(c"main")
-                    .module(fill_frag),
+                    .module(module)
+            };
+            let stages = [
+                stage(ShaderStageFlags::VERTEX, fill_vert),
+                stage(ShaderStageFlags::FRAGMENT, fill_frag),
+            ];
+            let texture_stages = [
+                stage(ShaderStageFlags::VERTEX, fill_vert),
+                stage(ShaderStageFlags::FRAGMENT, texture_frag),
             ];
             let vertex_input_state = PipelineVertexInputStateCreateInfo::default();
             let input_assembly_state = PipelineInputAssemblyStateCreateInfo::default()
@@ -390,7 +1008,7 @@ impl VulkanDevice {
                 .scissor_count(1);
             let color_blend_attachment_state = PipelineColorBlendAttachmentState::default()
                 .blend_enable(true)
-                .src_color_blend_factor(BlendFactor::SRC_ALPHA)
+                .src_color_blend_factor(BlendFactor::ONE)
                 .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
                 .color_blend_op(BlendOp::ADD)
                 .src_alpha_blend_factor(BlendFactor::ONE)
@@ -399,6 +1017,14 @@ impl VulkanDevice {
                 .color_write_mask(ColorComponentFlags::RGBA);
             let color_blend_state = PipelineColorBlendStateCreateInfo::default()
                 .attachments(slice::from_ref(&color_blend_attachment_state));
+            // Fills overwrite the content so that the framebuffer contains the straight
+            // color of the last fill.
+            let straight_color_blend_attachment_state =
+                PipelineColorBlendAttachmentState::default()
+                    .blend_enable(false)
+                    .color_write_mask(ColorComponentFlags::RGBA);
+            let straight_color_blend_state = PipelineColorBlendStateCreateInfo::default()
+                .attachments(slice::from_ref(&straight_color_blend_attachment_state));
             let dynamic_states = [DynamicState::VIEWPORT, DynamicState::SCISSOR];
             let dynamic_state =
                 PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
@@ -417,129 +1043,983 @@ impl VulkanDevice {
                 .dynamic_state(&dynamic_state)
                 .layout(pipeline_layout)
                 .push_next(&mut rendering_create_info);
-            let pipeline = unsafe {
+            let straight_create_info = create_info.color_blend_state(&straight_color_blend_state);
+            let texture_create_info = create_info
+                .stages(&texture_stages)
+                .layout(texture_pipeline_layout);
+            let straight_texture_create_info =
+                texture_create_info.color_blend_state(&straight_color_blend_state);
+            let create_infos = [
+                create_info,
+                straight_create_info,
+                texture_create_info,
+                straight_texture_create_info,
+            ];
+            let pipelines = unsafe {
                 device
-                    .create_graphics_pipelines(
-                        PipelineCache::null(),
-                        slice::from_ref(&create_info),
-                        None,
-                    )
+                    .create_graphics_pipelines(PipelineCache::null(), &create_infos, None)
                     .map_err(|(_, e)| Error::CreateGraphicsPipeline(e))?
             };
-            assert_eq!(pipeline.len(), 1);
-            pipeline[0]
+            assert_eq!(pipelines.len(), 4);
+            [pipelines[0], pipelines[1], pipelines[2], pipelines[3]]
         };
-        let destroy_pipeline = on_drop(|| unsafe { device.destroy_pipeline(pipeline, None) });
+        let destroy_pipeline = on_drop(|| unsafe {
+            device.destroy_pipeline(pipeline, None);
+            device.destroy_pipeline(straight_pipeline, None);
+            device.destroy_pipeline(texture_pipeline, None);
+            device.destroy_pipeline(straight_texture_pipeline, None);
+        });
         destroy_pipeline.forget();
+        destroy_texture_pipeline_layout.forget();
         destroy_pipeline_layout.forget();
+        destroy_descriptor_pool.forget();
+        destroy_texture_set_layout.forget();
+        destroy_sampler.forget();
+        destroy_texture_frag.forget();
         destroy_fill_frag.forget();
         destroy_fill_vert.forget();
+        destroy_transfer_command_pool.forget();
         destroy_command_pool.forget();
         destroy_device.forget();
+        destroy_debug_messenger.forget();
         destroy_instance.forget();
         Ok(Rc::new(VulkanDevice {
             queue,
             queue_idx,
+            transfer,
+            upload_mode: Default::default(),
+            alpha_mode: Default::default(),
+            image_count: Cell::new(DEFAULT_IMAGE_COUNT),
+            present_mode: Default::default(),
+            dither_bits: Default::default(),
+            gamut_mapping: Default::default(),
+            gradient_space: Default::default(),
+            output_clamp: Default::default(),
+            channel_adjustment: Default::default(),
             khr_swapchain,
             _ext_swapchain_maintenance1: ext_swapchain_maintenance1,
             command_pool,
             pipeline,
+            straight_pipeline,
             pipeline_layout,
+            texture_pipeline,
+            straight_texture_pipeline,
+            texture_pipeline_layout,
+            texture_set_layout,
+            descriptor_pool,
+            sampler,
             fill_vert,
             fill_frag,
+            texture_frag,
             allocator: RefCell::new(allocator),
+            allocated_memory: Default::default(),
+            allocations: Default::default(),
             physical_device,
             device,
             khr_wayland_surface,
             khr_surface,
+            debug_messenger,
             instance,
         }))
     }
 
-    pub fn create_surface(
-        self: &Rc<Self>,
-        wl_display: NonNull<wl_display>,
-        wl_surface: &WlSurface,
-    ) -> Result<VulkanSurface, Error> {
-        let create_info = WaylandSurfaceCreateInfoKHR::default()
-            .display(wl_display.as_ptr().cast())
-            .surface(proxy::wl_proxy(&**wl_surface).unwrap().cast().as_ptr());
-        let surface = unsafe {
-            self.khr_wayland_surface
-                .create_wayland_surface(&create_info, None)
-                .map_err(Error::CreateWaylandSurface)?
+    pub fn has_transfer_queue(&self) -> bool {
+        self.transfer.is_some()
+    }
+
+    pub fn set_upload_mode(&self, mode: UploadMode) {
+        self.upload_mode.set(mode);
+    }
+
+    pub fn set_alpha_mode(&self, mode: AlphaMode) {
+        self.alpha_mode.set(mode);
+    }
+
+    /// Sets the minimum number of swapchain images. The value is clamped to the range
+    /// supported by the surface.
+    pub fn set_image_count(&self, count: u32) {
+        self.image_count.set(count);
+    }
+
+    /// Sets the present mode of the swapchains. Modes that are not supported by a
+    /// surface fall back to FIFO.
+    pub fn set_present_mode(&self, mode: PresentMode) {
+        self.present_mode.set(mode);
+    }
+
+    /// Enables ordered dithering of the encoded colors with an amplitude of one step at
+    /// the given bit depth.
+    pub fn set_dither(&self, bits: Option<u32>) {
+        self.dither_bits.set(bits);
+    }
+
+    pub fn set_gamut_mapping(&self, mapping: GamutMapping) {
+        self.gamut_mapping.set(mapping);
+    }
+
+    pub fn set_gradient_space(&self, space: GradientSpace) {
+        self.gradient_space.set(space);
+    }
+
+    pub fn set_output_clamp(&self, clamp: OutputClamp) {
+        self.output_clamp.set(clamp);
+    }
+
+    pub fn set_channel_adjustment(&self, adjustment: ChannelAdjustment) {
+        self.channel_adjustment.set(adjustment);
+    }
+
+    /// Returns the number of bytes of device memory allocated for fill buffers and
+    /// other resources owned by the application. Swapchain images are not included.
+    pub fn allocated_memory(&self) -> u64 {
+        self.allocated_memory.get()
+    }
+
+    /// Returns the memory allocated by the device.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            allocated: self.allocated_memory.get(),
+            allocations: self.allocations.get(),
+        }
+    }
+
+    /// Returns unused memory of the allocator to the driver.
+    fn cleanup(&self) {
+        unsafe {
+            self.allocator
+                .borrow_mut()
+                .cleanup(AshMemoryDevice::wrap(&self.device));
+        }
+    }
+
+    unsafe fn alloc(&self, request: Request) -> Result<MemoryBlock<DeviceMemory>, Error> {
+        let block = unsafe {
+            self.allocator
+                .borrow_mut()
+                .alloc(AshMemoryDevice::wrap(&self.device), request)
+                .map_err(Error::AllocateMemory)?
         };
-        let destroy_surface =
-            on_drop(|| unsafe { self.khr_surface.destroy_surface(surface, None) });
-        let formats = unsafe {
-            self.khr_surface
-                .get_physical_device_surface_formats(self.physical_device, surface)
-                .map_err(Error::GetSurfaceFormats)?
+        self.allocated_memory
+            .set(self.allocated_memory.get() + block.size());
+        self.allocations.set(self.allocations.get() + 1);
+        Ok(block)
+    }
+
+    unsafe fn dealloc(&self, block: MemoryBlock<DeviceMemory>) {
+        self.allocated_memory
+            .set(self.allocated_memory.get() - block.size());
+        self.allocations.set(self.allocations.get() - 1);
+        unsafe {
+            self.allocator
+                .borrow_mut()
+                .dealloc(AshMemoryDevice::wrap(&self.device), block);
+        }
+    }
+
+    fn create_fill_buffer(self: &Rc<Self>, concurrent: bool) -> Result<FillBuffer, Error> {
+        let size = size_of::<FillData>().next_multiple_of(16) as u64;
+        let mut queue_family_indices = vec![self.queue_idx];
+        let mut sharing_mode = SharingMode::EXCLUSIVE;
+        if concurrent {
+            queue_family_indices.push(self.transfer.as_ref().unwrap().queue_idx);
+            sharing_mode = SharingMode::CONCURRENT;
+        }
+        let create_info = BufferCreateInfo::default()
+            .size(size)
+            .usage(BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::SHADER_DEVICE_ADDRESS)
+            .sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_family_indices);
+        let buffer = unsafe {
+            self.device
+                .create_buffer(&create_info, None)
+                .map_err(Error::CreateBuffer)?
         };
-        let supports_format = formats.contains(&SurfaceFormatKHR {
-            format: Format::R16G16B16A16_SFLOAT,
-            color_space: ColorSpaceKHR::PASS_THROUGH_EXT,
-        });
-        if !supports_format {
-            return Err(Error::F16NotSupported);
+        let destroy_buffer = on_drop(|| unsafe { self.device.destroy_buffer(buffer, None) });
+        let req = unsafe { self.device.get_buffer_memory_requirements(buffer) };
+        let request = Request {
+            size: req.size,
+            align_mask: req.alignment - 1,
+            usage: UsageFlags::FAST_DEVICE_ACCESS | UsageFlags::DEVICE_ADDRESS,
+            memory_types: req.memory_type_bits,
         };
-        destroy_surface.forget();
-        Ok(VulkanSurface {
-            submissions: Default::default(),
-            presents: Default::default(),
-            swapchain: Default::default(),
-            suboptimal: Default::default(),
-            surface,
-            fill_buffers: Default::default(),
+        let alloc = unsafe { self.alloc(request)? };
+        let memory = *alloc.memory();
+        let offset = alloc.offset();
+        let alloc = Cell::new(Some(alloc));
+        let dealloc_memory = on_drop(|| unsafe { self.dealloc(alloc.take().unwrap()) });
+        unsafe {
+            self.device
+                .bind_buffer_memory(buffer, memory, offset)
+                .map_err(Error::BindBufferMemory)?;
+        }
+        let addr = {
+            let info = BufferDeviceAddressInfo::default().buffer(buffer);
+            unsafe { self.device.get_buffer_device_address(&info) }
+        };
+        dealloc_memory.forget();
+        destroy_buffer.forget();
+        Ok(FillBuffer {
+            buffer,
+            addr,
+            size,
+            concurrent,
+            memory: alloc,
             device: self.clone(),
-            _wl_surface: wl_surface.clone(),
         })
     }
-}
 
-impl VulkanSurface {
-    fn ensure_swapchain(
-        &self,
+    /// Uploads the texture of `Scene::Image`. The pixels are in Oklab, row by row.
+    pub fn create_texture(
+        self: &Rc<Self>,
         width: u32,
         height: u32,
-    ) -> Result<RefMut<'_, VulkanSwapchain>, Error> {
-        let mut sc = self.swapchain.borrow_mut();
-        let mut recreate = false;
-        if !recreate {
-            recreate = sc.is_none();
+        pixels: &[[f32; 4]],
+    ) -> Result<Rc<Texture>, Error> {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        let dev = &self.device;
+        let max = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+                .limits
+                .max_image_dimension2_d
+        };
+        if width > max || height > max {
+            return Err(Error::TextureTooLarge(width, height, max));
         }
-        if !recreate {
+        let alloc = |req: MemoryRequirements, usage: UsageFlags| {
+            let request = Request {
+                size: req.size,
+                align_mask: req.alignment - 1,
+                usage,
+                memory_types: req.memory_type_bits,
+            };
+            let block = unsafe { self.alloc(request)? };
+            Ok(RefCell::new(Some(block)))
+        };
+        let dealloc = |block: &RefCell<Option<MemoryBlock<DeviceMemory>>>| unsafe {
+            if let Some(block) = block.take() {
+                self.dealloc(block);
+            }
+        };
+        let extent = Extent3D {
+            width,
+            height,
+            depth: 1,
+        };
+        let create_info = ImageCreateInfo::default()
+            .image_type(ImageType::TYPE_2D)
+            .format(Format::R32G32B32A32_SFLOAT)
+            .extent(extent)
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(SampleCountFlags::TYPE_1)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(ImageUsageFlags::SAMPLED | ImageUsageFlags::TRANSFER_DST)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .initial_layout(ImageLayout::UNDEFINED);
+        let image = unsafe {
+            dev.create_image(&create_info, None)
+                .map_err(Error::CreateImage)?
+        };
+        let destroy_image = on_drop(|| unsafe { dev.destroy_image(image, None) });
+        let req = unsafe { dev.get_image_memory_requirements(image) };
+        let image_memory = alloc(req, UsageFlags::FAST_DEVICE_ACCESS)?;
+        let dealloc_image_memory = on_drop(|| dealloc(&image_memory));
+        unsafe {
+            let block = image_memory.borrow();
+            let block = block.as_ref().unwrap();
+            dev.bind_image_memory(image, *block.memory(), block.offset())
+                .map_err(Error::BindImageMemory)?;
+        }
+        let bytes: &[u8] = bytemuck::cast_slice(pixels);
+        let create_info = BufferCreateInfo::default()
+            .size(bytes.len() as u64)
+            .usage(BufferUsageFlags::TRANSFER_SRC)
+            .sharing_mode(SharingMode::EXCLUSIVE);
+        let buffer = unsafe {
+            dev.create_buffer(&create_info, None)
+                .map_err(Error::CreateBuffer)?
+        };
+        let _destroy_buffer = on_drop(|| unsafe { dev.destroy_buffer(buffer, None) });
+        let req = unsafe { dev.get_buffer_memory_requirements(buffer) };
+        let buffer_memory = alloc(req, UsageFlags::UPLOAD | UsageFlags::HOST_ACCESS)?;
+        let _dealloc_buffer_memory = on_drop(|| dealloc(&buffer_memory));
+        // Host writes before the submission are visible to the device without a
+        // barrier.
+        unsafe {
+            let mut block = buffer_memory.borrow_mut();
+            let block = block.as_mut().unwrap();
+            dev.bind_buffer_memory(buffer, *block.memory(), block.offset())
+                .map_err(Error::BindBufferMemory)?;
+            block
+                .write_bytes(AshMemoryDevice::wrap(dev), 0, bytes)
+                .map_err(Error::WriteMemory)?;
+        }
+        let create_info = CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pool)
+            .command_buffer_count(1)
+            .level(CommandBufferLevel::PRIMARY);
+        let cmd = unsafe {
+            dev.allocate_command_buffers(&create_info)
+                .map_err(Error::AllocateCommandBuffer)?[0]
+        };
+        let _free_cmd = on_drop(|| unsafe { dev.free_command_buffers(self.command_pool, &[cmd]) });
+        let begin_info =
+            CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        {
+            let transfer_barrier = ImageMemoryBarrier2::default()
+                .dst_stage_mask(PipelineStageFlags2::TRANSFER)
+                .dst_access_mask(AccessFlags2::TRANSFER_WRITE)
+                .old_layout(ImageLayout::UNDEFINED)
+                .new_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                .image(image)
+                .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+            let transfer_dependency_info =
+                DependencyInfo::default().image_memory_barriers(slice::from_ref(&transfer_barrier));
+            let region = BufferImageCopy::default()
+                .image_subresource(ImageSubresourceLayers {
+                    aspect_mask: ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(extent);
+            // Later submissions are ordered after the fence wait below.
+            let shader_barrier = ImageMemoryBarrier2::default()
+                .src_stage_mask(PipelineStageFlags2::TRANSFER)
+                .src_access_mask(AccessFlags2::TRANSFER_WRITE)
+                .old_layout(ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image(image)
+                .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+            let shader_dependency_info =
+                DependencyInfo::default().image_memory_barriers(slice::from_ref(&shader_barrier));
+            unsafe {
+                dev.begin_command_buffer(cmd, &begin_info)
+                    .map_err(Error::BeginCommandBuffer)?;
+                dev.cmd_pipeline_barrier2(cmd, &transfer_dependency_info);
+                dev.cmd_copy_buffer_to_image(
+                    cmd,
+                    buffer,
+                    image,
+                    ImageLayout::TRANSFER_DST_OPTIMAL,
+                    slice::from_ref(&region),
+                );
+                dev.cmd_pipeline_barrier2(cmd, &shader_dependency_info);
+                dev.end_command_buffer(cmd)
+                    .map_err(Error::EndCommandBuffer)?;
+            }
+        }
+        let fence = unsafe {
+            dev.create_fence(&FenceCreateInfo::default(), None)
+                .map_err(Error::CreateFence)?
+        };
+        let _destroy_fence = on_drop(|| unsafe { dev.destroy_fence(fence, None) });
+        let submit_info = SubmitInfo::default().command_buffers(slice::from_ref(&cmd));
+        unsafe {
+            dev.queue_submit(self.queue, &[submit_info], fence)
+                .map_err(Error::Submit)?;
+            dev.wait_for_fences(&[fence], true, u64::MAX)
+                .map_err(Error::WaitForFence)?;
+        }
+        let create_info = ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(ImageViewType::TYPE_2D)
+            .format(Format::R32G32B32A32_SFLOAT)
+            .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+        let view = unsafe {
+            dev.create_image_view(&create_info, None)
+                .map_err(Error::CreateImageView)?
+        };
+        let destroy_view = on_drop(|| unsafe { dev.destroy_image_view(view, None) });
+        let descriptor_set = {
+            let allocate_info = DescriptorSetAllocateInfo::default()
+                .descriptor_pool(self.descriptor_pool)
+                .set_layouts(slice::from_ref(&self.texture_set_layout));
+            unsafe {
+                dev.allocate_descriptor_sets(&allocate_info)
+                    .map_err(Error::AllocateDescriptorSet)?[0]
+            }
+        };
+        let image_info = DescriptorImageInfo::default()
+            .image_view(view)
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        let write = WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(slice::from_ref(&image_info));
+        unsafe {
+            dev.update_descriptor_sets(slice::from_ref(&write), &[]);
+        }
+        destroy_view.forget();
+        dealloc_image_memory.forget();
+        destroy_image.forget();
+        Ok(Rc::new(Texture {
+            image,
+            view,
+            descriptor_set,
+            width,
+            height,
+            memory: Cell::new(image_memory.take()),
+            device: self.clone(),
+        }))
+    }
+
+    /// Fills an offscreen image of the given size with the color and returns the
+    /// rendered pixels in row-major order. Blending and dithering are disabled.
+    pub fn read_back_fill(
+        self: &Rc<Self>,
+        width: u32,
+        height: u32,
+        color: [f32; 4],
+        params: &RenderParams,
+    ) -> Result<Vec<[f32; 4]>, Error> {
+        let overlay = Overlay::default();
+        self.render_offscreen(width, height, Scene::Fill(color), None, &overlay, params)
+    }
+
+    /// Renders the scene into an offscreen image of the given size and returns the
+    /// rendered pixels in row-major order. Blending and dithering are disabled.
+    pub fn render_offscreen(
+        self: &Rc<Self>,
+        width: u32,
+        height: u32,
+        scene: Scene,
+        clear: Option<[f32; 4]>,
+        overlay: &Overlay,
+        params: &RenderParams,
+    ) -> Result<Vec<[f32; 4]>, Error> {
+        let dev = &self.device;
+        let alloc = |req: MemoryRequirements, usage: UsageFlags| {
+            let request = Request {
+                size: req.size,
+                align_mask: req.alignment - 1,
+                usage,
+                memory_types: req.memory_type_bits,
+            };
+            let block = unsafe { self.alloc(request)? };
+            Ok(RefCell::new(Some(block)))
+        };
+        let dealloc = |block: &RefCell<Option<MemoryBlock<DeviceMemory>>>| unsafe {
+            if let Some(block) = block.take() {
+                self.dealloc(block);
+            }
+        };
+        let extent = Extent3D {
+            width,
+            height,
+            depth: 1,
+        };
+        let create_info = ImageCreateInfo::default()
+            .image_type(ImageType::TYPE_2D)
+            .format(Format::R16G16B16A16_SFLOAT)
+            .extent(extent)
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(SampleCountFlags::TYPE_1)
+            .tiling(ImageTiling::OPTIMAL)
+            .usage(ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC)
+            .sharing_mode(SharingMode::EXCLUSIVE)
+            .initial_layout(ImageLayout::UNDEFINED);
+        let image = unsafe {
+            dev.create_image(&create_info, None)
+                .map_err(Error::CreateImage)?
+        };
+        let _destroy_image = on_drop(|| unsafe { dev.destroy_image(image, None) });
+        let req = unsafe { dev.get_image_memory_requirements(image) };
+        let image_memory = alloc(req, UsageFlags::FAST_DEVICE_ACCESS)?;
+        let _dealloc_image_memory = on_drop(|| dealloc(&image_memory));
+        unsafe {
+            let block = image_memory.borrow();
+            let block = block.as_ref().unwrap();
+            dev.bind_image_memory(image, *block.memory(), block.offset())
+                .map_err(Error::BindImageMemory)?;
+        }
+        let create_info = ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(ImageViewType::TYPE_2D)
+            .format(Format::R16G16B16A16_SFLOAT)
+            .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+        let image_view = unsafe {
+            dev.create_image_view(&create_info, None)
+                .map_err(Error::CreateImageView)?
+        };
+        let _destroy_image_view = on_drop(|| unsafe { dev.destroy_image_view(image_view, None) });
+        let size = width as u64 * height as u64 * 8;
+        let create_info = BufferCreateInfo::default()
+            .size(size)
+            .usage(BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(SharingMode::EXCLUSIVE);
+        let buffer = unsafe {
+            dev.create_buffer(&create_info, None)
+                .map_err(Error::CreateBuffer)?
+        };
+        let _destroy_buffer = on_drop(|| unsafe { dev.destroy_buffer(buffer, None) });
+        let req = unsafe { dev.get_buffer_memory_requirements(buffer) };
+        let buffer_memory = alloc(req, UsageFlags::DOWNLOAD | UsageFlags::HOST_ACCESS)?;
+        let _dealloc_buffer_memory = on_drop(|| dealloc(&buffer_memory));
+        unsafe {
+            let block = buffer_memory.borrow();
+            let block = block.as_ref().unwrap();
+            dev.bind_buffer_memory(buffer, *block.memory(), block.offset())
+                .map_err(Error::BindBufferMemory)?;
+        }
+        let style = FillStyle {
+            transform: 0,
+            dither: 0.0,
+            alpha_mode: AlphaMode::Straight,
+            gamut_mapping: self.gamut_mapping.get(),
+            gradient_space: self.gradient_space.get(),
+            output_clamp: self.output_clamp.get(),
+            channel_adjustment: self.channel_adjustment.get(),
+        };
+        let texture = scene.texture();
+        let fills = scene_fills(width, height, scene, clear, overlay, params, style)
+            .into_iter()
+            .map(|data| Ok((self.create_fill_buffer(false)?, data)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let create_info = CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pool)
+            .command_buffer_count(1)
+            .level(CommandBufferLevel::PRIMARY);
+        let cmd = unsafe {
+            dev.allocate_command_buffers(&create_info)
+                .map_err(Error::AllocateCommandBuffer)?[0]
+        };
+        let _free_cmd = on_drop(|| unsafe { dev.free_command_buffers(self.command_pool, &[cmd]) });
+        let begin_info =
+            CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        unsafe {
+            dev.begin_command_buffer(cmd, &begin_info)
+                .map_err(Error::BeginCommandBuffer)?;
+            for (fill, data) in &fills {
+                dev.cmd_update_buffer(cmd, fill.buffer, 0, bytes_of(data));
+            }
+        }
+        {
+            let buffer_barriers: Vec<_> = fills
+                .iter()
+                .map(|(fill, _)| {
+                    BufferMemoryBarrier2::default()
+                        .src_stage_mask(PipelineStageFlags2::TRANSFER)
+                        .src_access_mask(AccessFlags2::TRANSFER_WRITE)
+                        .dst_stage_mask(
+                            PipelineStageFlags2::VERTEX_SHADER
+                                | PipelineStageFlags2::FRAGMENT_SHADER,
+                        )
+                        .dst_access_mask(AccessFlags2::SHADER_READ)
+                        .buffer(fill.buffer)
+                        .size(fill.size)
+                })
+                .collect();
+            let image_barrier = ImageMemoryBarrier2::default()
+                .dst_stage_mask(PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .dst_access_mask(AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .old_layout(ImageLayout::UNDEFINED)
+                .new_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .image(image)
+                .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+            let dependency_info = DependencyInfo::default()
+                .buffer_memory_barriers(&buffer_barriers)
+                .image_memory_barriers(slice::from_ref(&image_barrier));
+            unsafe {
+                dev.cmd_pipeline_barrier2(cmd, &dependency_info);
+            }
+        }
+        {
+            let area = Rect2D {
+                offset: Default::default(),
+                extent: Extent2D { width, height },
+            };
+            let attachment_info = RenderingAttachmentInfo::default()
+                .image_view(image_view)
+                .image_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .load_op(AttachmentLoadOp::CLEAR)
+                .store_op(AttachmentStoreOp::STORE);
+            let rendering_info = RenderingInfo::default()
+                .render_area(area)
+                .layer_count(1)
+                .color_attachments(slice::from_ref(&attachment_info));
+            let viewport = Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as _,
+                height: height as _,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            };
+            unsafe {
+                dev.cmd_begin_rendering(cmd, &rendering_info);
+                dev.cmd_set_viewport(cmd, 0, slice::from_ref(&viewport));
+                dev.cmd_set_scissor(cmd, 0, slice::from_ref(&area));
+            }
+            for (fill, data) in &fills {
+                let (pipeline, layout) = match data.textured != 0 {
+                    true => (self.straight_texture_pipeline, self.texture_pipeline_layout),
+                    false => (self.straight_pipeline, self.pipeline_layout),
+                };
+                let constants = FillPushConstant { data: fill.addr };
+                unsafe {
+                    dev.cmd_bind_pipeline(cmd, PipelineBindPoint::GRAPHICS, pipeline);
+                    if let (true, Some(texture)) = (data.textured != 0, &texture) {
+                        dev.cmd_bind_descriptor_sets(
+                            cmd,
+                            PipelineBindPoint::GRAPHICS,
+                            layout,
+                            0,
+                            slice::from_ref(&texture.descriptor_set),
+                            &[],
+                        );
+                    }
+                    dev.cmd_push_constants(
+                        cmd,
+                        layout,
+                        ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                        0,
+                        bytes_of(&constants),
+                    );
+                    dev.cmd_draw(cmd, 4, 1, 0, 0);
+                }
+            }
+            unsafe {
+                dev.cmd_end_rendering(cmd);
+            }
+        }
+        {
+            let image_barrier = ImageMemoryBarrier2::default()
+                .src_stage_mask(PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .src_access_mask(AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(PipelineStageFlags2::TRANSFER)
+                .dst_access_mask(AccessFlags2::TRANSFER_READ)
+                .old_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .new_layout(ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .image(image)
+                .subresource_range(IMAGE_SUBRESOURCE_RANGE);
+            let dependency_info =
+                DependencyInfo::default().image_memory_barriers(slice::from_ref(&image_barrier));
+            let region = BufferImageCopy::default()
+                .image_subresource(ImageSubresourceLayers {
+                    aspect_mask: ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(extent);
+            let buffer_barrier = BufferMemoryBarrier2::default()
+                .src_stage_mask(PipelineStageFlags2::TRANSFER)
+                .src_access_mask(AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(PipelineStageFlags2::HOST)
+                .dst_access_mask(AccessFlags2::HOST_READ)
+                .buffer(buffer)
+                .size(size);
+            let host_dependency_info =
+                DependencyInfo::default().buffer_memory_barriers(slice::from_ref(&buffer_barrier));
+            unsafe {
+                dev.cmd_pipeline_barrier2(cmd, &dependency_info);
+                dev.cmd_copy_image_to_buffer(
+                    cmd,
+                    image,
+                    ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    buffer,
+                    slice::from_ref(&region),
+                );
+                dev.cmd_pipeline_barrier2(cmd, &host_dependency_info);
+                dev.end_command_buffer(cmd)
+                    .map_err(Error::EndCommandBuffer)?;
+            }
+        }
+        let fence = unsafe {
+            dev.create_fence(&FenceCreateInfo::default(), None)
+                .map_err(Error::CreateFence)?
+        };
+        let _destroy_fence = on_drop(|| unsafe { dev.destroy_fence(fence, None) });
+        let submit_info = SubmitInfo::default().command_buffers(slice::from_ref(&cmd));
+        unsafe {
+            dev.queue_submit(self.queue, &[submit_info], fence)
+                .map_err(Error::Submit)?;
+            dev.wait_for_fences(&[fence], true, u64::MAX)
+                .map_err(Error::WaitForFence)?;
+        }
+        let mut bytes = vec![0u8; size as usize];
+        unsafe {
+            buffer_memory
+                .borrow_mut()
+                .as_mut()
+                .unwrap()
+                .read_bytes(AshMemoryDevice::wrap(dev), 0, &mut bytes)
+                .map_err(Error::ReadMemory)?;
+        }
+        let pixels = bytes
+            .chunks_exact(8)
+            .map(|px| {
+                array::from_fn(|i| f16_to_f32(u16::from_ne_bytes([px[2 * i], px[2 * i + 1]])))
+            })
+            .collect();
+        Ok(pixels)
+    }
+
+    pub fn create_surface(
+        self: &Rc<Self>,
+        wl_display: NonNull<wl_display>,
+        wl_surface: &WlSurface,
+    ) -> Result<VulkanSurface, Error> {
+        let create_info = WaylandSurfaceCreateInfoKHR::default()
+            .display(wl_display.as_ptr().cast())
+            .surface(proxy::wl_proxy(&**wl_surface).unwrap().cast().as_ptr());
+        let surface = unsafe {
+            self.khr_wayland_surface
+                .create_wayland_surface(&create_info, None)
+                .map_err(Error::CreateWaylandSurface)?
+        };
+        let destroy_surface =
+            on_drop(|| unsafe { self.khr_surface.destroy_surface(surface, None) });
+        let formats = unsafe {
+            self.khr_surface
+                .get_physical_device_surface_formats(self.physical_device, surface)
+                .map_err(Error::GetSurfaceFormats)?
+        };
+        let supports_format = formats.contains(&SurfaceFormatKHR {
+            format: Format::R16G16B16A16_SFLOAT,
+            color_space: ColorSpaceKHR::PASS_THROUGH_EXT,
+        });
+        if !supports_format {
+            return Err(Error::F16NotSupported);
+        };
+        destroy_surface.forget();
+        Ok(VulkanSurface {
+            submissions: Default::default(),
+            presents: Default::default(),
+            swapchain: Default::default(),
+            retired_swapchains: Default::default(),
+            suboptimal: Default::default(),
+            transform: Cell::new(WlOutputTransform::NORMAL),
+            color_space: Default::default(),
+            surface,
+            fill_buffers: Default::default(),
+            semaphores: Default::default(),
+            fences: Default::default(),
+            uploaded_params: Default::default(),
+            present_latencies: Default::default(),
+            device: self.clone(),
+            _wl_surface: wl_surface.clone(),
+        })
+    }
+}
+
+impl VulkanSurface {
+    /// Returns the minimum and maximum number of swapchain images. The maximum is
+    /// `None` if there is no limit. The capabilities are only queried if there is no
+    /// swapchain yet.
+    pub fn image_count_range(&self) -> Result<(u32, Option<u32>), Error> {
+        if let Some(sc) = &*self.swapchain.borrow() {
+            return Ok(sc.image_count_range);
+        }
+        Ok(image_count_range(&self.capabilities()?))
+    }
+
+    /// Returns the present modes supported by the surface.
+    pub fn present_modes(&self) -> Result<Vec<PresentMode>, Error> {
+        let modes = unsafe {
+            self.device
+                .khr_surface
+                .get_physical_device_surface_present_modes(
+                    self.device.physical_device,
+                    self.surface,
+                )
+                .map_err(Error::GetSurfacePresentModes)?
+        };
+        Ok(PresentMode::variants()
+            .filter(|m| modes.contains(&m.vk()))
+            .collect())
+    }
+
+    /// Returns the color parameters of the last rendered frame.
+    pub fn uploaded_params(&self) -> Option<UploadedParams> {
+        self.uploaded_params.get()
+    }
+
+    /// Enables or disables measuring the time between presenting and the release of the
+    /// present fence.
+    pub fn set_measure_present_latency(&self, enabled: bool) {
+        let latencies = &mut *self.present_latencies.borrow_mut();
+        match enabled {
+            true => {
+                latencies.get_or_insert_with(Default::default);
+            }
+            false => *latencies = None,
+        }
+    }
+
+    /// Returns the average present latency of the last frames and the number of
+    /// samples.
+    ///
+    /// Fences are only checked when the next frame is rendered, so the latency of
+    /// frames that are not followed by another frame immediately is overestimated.
+    pub fn present_latency(&self) -> Option<(Duration, usize)> {
+        let latencies = self.present_latencies.borrow();
+        let latencies = latencies.as_ref().filter(|l| !l.is_empty())?;
+        let sum: Duration = latencies.iter().sum();
+        Some((sum / latencies.len() as u32, latencies.len()))
+    }
+
+    /// Returns the color spaces that the surface supports with the F16 format.
+    pub fn color_spaces(&self) -> Result<Vec<SwapchainColorSpace>, Error> {
+        let formats = unsafe {
+            self.device
+                .khr_surface
+                .get_physical_device_surface_formats(self.device.physical_device, self.surface)
+                .map_err(Error::GetSurfaceFormats)?
+        };
+        Ok(SwapchainColorSpace::variants()
+            .filter(|cs| {
+                formats.contains(&SurfaceFormatKHR {
+                    format: Format::R16G16B16A16_SFLOAT,
+                    color_space: cs.vk(),
+                })
+            })
+            .collect())
+    }
+
+    /// Sets the color space of the swapchain. The swapchain is recreated by the next
+    /// frame.
+    pub fn set_color_space(&self, color_space: SwapchainColorSpace) {
+        self.color_space.set(color_space);
+    }
+
+    /// Destroys the swapchain and all retired swapchains. The next frame creates a new
+    /// swapchain.
+    pub fn destroy_swapchain(&self) {
+        unsafe {
+            let _ = self.device.device.device_wait_idle();
+        }
+        let _ = self.gc(true);
+        if let Some(sc) = self.swapchain.take() {
+            unsafe { sc.destroy(&self.device.device, &self.device.khr_swapchain) }
+        }
+    }
+
+    /// Returns the number of fill buffers that are kept for reuse.
+    pub fn pooled_fill_buffers(&self) -> usize {
+        self.fill_buffers.borrow().len()
+    }
+
+    /// Releases finished submissions, drops pooled fill buffers beyond
+    /// `MAX_POOLED_FILL_BUFFERS`, and returns unused memory to the driver. Called
+    /// when the surface has been idle for a while.
+    pub fn trim(&self) -> Result<(), Error> {
+        self.gc(false)?;
+        self.fill_buffers
+            .borrow_mut()
+            .truncate(MAX_POOLED_FILL_BUFFERS);
+        self.device.cleanup();
+        Ok(())
+    }
+
+    /// Returns whether the current swapchain uses post-multiplied composite alpha.
+    /// Surfaces that do not support it fall back to pre-multiplied composite alpha.
+    pub fn post_multiplied(&self) -> Option<bool> {
+        self.swapchain.borrow().as_ref().map(|s| s.post_multiplied)
+    }
+
+    /// Returns the extent of the current swapchain and the limits of the surface.
+    pub fn extent(&self) -> Result<SurfaceExtent, Error> {
+        let capabilities = self.capabilities()?;
+        let size = |e: Extent2D| (e.width, e.height);
+        Ok(SurfaceExtent {
+            swapchain: self
+                .swapchain
+                .borrow()
+                .as_ref()
+                .map(|s| (s.width, s.height)),
+            min: size(capabilities.min_image_extent),
+            max: size(capabilities.max_image_extent),
+        })
+    }
+
+    /// Sets the buffer transform of the surface. The content is rendered with this
+    /// transform applied and the swapchain uses the matching pre-transform if it is
+    /// supported. The caller must set the buffer transform of the wayland surface.
+    pub fn set_transform(&self, transform: WlOutputTransform) {
+        self.transform.set(transform);
+    }
+
+    fn capabilities(&self) -> Result<SurfaceCapabilitiesKHR, Error> {
+        unsafe {
+            self.device
+                .khr_surface
+                .get_physical_device_surface_capabilities(self.device.physical_device, self.surface)
+                .map_err(Error::GetSurfaceCapabilities)
+        }
+    }
+
+    fn ensure_swapchain(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<RefMut<'_, VulkanSwapchain>, Error> {
+        let mut sc = self.swapchain.borrow_mut();
+        let mut recreate = false;
+        if !recreate {
+            recreate = sc.is_none();
+        }
+        if !recreate {
             recreate = self.suboptimal.get();
         }
         if !recreate {
             if let Some(sc) = &*sc {
-                if sc.width != width || sc.height != height {
+                if sc.width != width
+                    || sc.height != height
+                    || sc.alpha_mode != self.device.alpha_mode.get()
+                    || sc.image_count != self.device.image_count.get()
+                    || sc.present_mode != self.device.present_mode.get()
+                    || sc.transform != self.transform.get()
+                    || sc.color_space != self.color_space.get()
+                {
                     recreate = true;
                 }
             }
         }
         if recreate {
             let old = sc.take();
-            if old.is_some() {
-                unsafe {
-                    self.device
-                        .device
-                        .device_wait_idle()
-                        .map_err(Error::WaitIdle)?;
-                }
+            let alpha_mode = self.device.alpha_mode.get();
+            let image_count = self.device.image_count.get();
+            let present_mode = self.device.present_mode.get();
+            let transform = self.transform.get();
+            let color_space = self.color_space.get();
+            let capabilities = self.capabilities()?;
+            let mut min_image_count = image_count.max(capabilities.min_image_count);
+            if capabilities.max_image_count != 0 {
+                min_image_count = min_image_count.min(capabilities.max_image_count);
+            }
+            let mut composite_alpha = CompositeAlphaFlagsKHR::PRE_MULTIPLIED;
+            if alpha_mode == AlphaMode::Straight
+                && capabilities
+                    .supported_composite_alpha
+                    .contains(CompositeAlphaFlagsKHR::POST_MULTIPLIED)
+            {
+                composite_alpha = CompositeAlphaFlagsKHR::POST_MULTIPLIED;
+            }
+            let mut pre_transform = pre_transform(transform);
+            if !capabilities.supported_transforms.contains(pre_transform) {
+                pre_transform = SurfaceTransformFlagsKHR::IDENTITY;
+            }
+            let mut vk_present_mode = PresentModeKHR::FIFO;
+            if self.present_modes()?.contains(&present_mode) {
+                vk_present_mode = present_mode.vk();
             }
             let create_info = SwapchainCreateInfoKHR::default()
                 .surface(self.surface)
-                .pre_transform(SurfaceTransformFlagsKHR::IDENTITY)
-                .composite_alpha(CompositeAlphaFlagsKHR::PRE_MULTIPLIED)
+                .pre_transform(pre_transform)
+                .composite_alpha(composite_alpha)
                 .image_extent(Extent2D { width, height })
-                .min_image_count(3)
+                .min_image_count(min_image_count)
                 .image_format(Format::R16G16B16A16_SFLOAT)
-                .image_color_space(ColorSpaceKHR::PASS_THROUGH_EXT)
+                .image_color_space(color_space.vk())
                 .image_array_layers(1)
                 .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
                 .image_sharing_mode(SharingMode::EXCLUSIVE)
-                .present_mode(PresentModeKHR::MAILBOX)
+                .present_mode(vk_present_mode)
                 .clipped(true)
                 .old_swapchain(old.as_ref().map(|o| o.swapchain).unwrap_or_default());
             let swapchain = unsafe {
@@ -549,9 +2029,10 @@ impl VulkanSurface {
                     .map_err(Error::CreateSwapchain)?
             };
             if let Some(sc) = old {
-                unsafe {
-                    sc.destroy(&self.device.device, &self.device.khr_swapchain);
-                }
+                // With EXT_swapchain_maintenance1, the old swapchain can be destroyed
+                // once the present fences of its presentations have signaled. This
+                // happens in gc and avoids waiting for the device to become idle.
+                self.retired_swapchains.borrow_mut().push(sc);
             }
             let destroy_swapchain =
                 on_drop(|| unsafe { self.device.khr_swapchain.destroy_swapchain(swapchain, None) });
@@ -588,6 +2069,13 @@ impl VulkanSurface {
                 image_views,
                 width,
                 height,
+                alpha_mode,
+                image_count,
+                present_mode,
+                transform,
+                color_space,
+                post_multiplied: composite_alpha == CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+                image_count_range: image_count_range(&capabilities),
             });
             self.suboptimal.set(false);
         }
@@ -608,17 +2096,22 @@ impl VulkanSurface {
             unsafe {
                 dev.free_command_buffers(self.device.command_pool, &[first.command_buffer]);
             }
-            unsafe {
-                dev.destroy_semaphore(first.acquire_semaphore, None);
+            if let Some(upload) = &first.upload {
+                let transfer = self.device.transfer.as_ref().unwrap();
+                unsafe {
+                    dev.free_command_buffers(transfer.command_pool, &[upload.command_buffer]);
+                }
+                self.semaphores.borrow_mut().push(upload.semaphore);
             }
+            self.semaphores.borrow_mut().push(first.acquire_semaphore);
             if first.owns_release_semaphore.get() {
+                // The semaphore was signaled but never waited on since presentation
+                // failed. It cannot be reused.
                 unsafe {
                     dev.destroy_semaphore(first.release_semaphore, None);
                 }
             }
-            unsafe {
-                dev.destroy_fence(first.release_fence, None);
-            }
+            self.recycle_fence(first.release_fence)?;
             for buffer in first.fill_buffers.drain(..) {
                 self.fill_buffers.borrow_mut().push(buffer);
             }
@@ -633,20 +2126,74 @@ impl VulkanSurface {
             if !done && !force {
                 break;
             }
-            unsafe {
-                dev.destroy_semaphore(first.release_semaphore, None);
+            // Forced collection does not wait for the fence.
+            let presented = first.presented.filter(|_| done);
+            if let (Some(presented), Some(latencies)) =
+                (presented, &mut *self.present_latencies.borrow_mut())
+            {
+                if latencies.len() == PRESENT_LATENCY_SAMPLES {
+                    latencies.pop_front();
+                }
+                latencies.push_back(presented.elapsed());
+            }
+            self.semaphores.borrow_mut().push(first.release_semaphore);
+            self.recycle_fence(first.release_fence)?;
+            presents.pop_front();
+        }
+        self.retired_swapchains.borrow_mut().retain(|sc| {
+            let in_use = submissions.iter().any(|s| s.swapchain == sc.swapchain)
+                || presents.iter().any(|p| p.swapchain == sc.swapchain);
+            if !in_use {
+                unsafe {
+                    sc.destroy(dev, &self.device.khr_swapchain);
+                }
             }
+            in_use
+        });
+        Ok(())
+    }
+
+    fn recycle_fence(&self, fence: Fence) -> Result<(), Error> {
+        let dev = &self.device.device;
+        if let Err(e) = unsafe { dev.reset_fences(&[fence]) } {
             unsafe {
-                dev.destroy_fence(first.release_fence, None);
+                dev.destroy_fence(fence, None);
             }
-            presents.pop_front();
+            return Err(Error::ResetFence(e));
         }
+        self.fences.borrow_mut().push(fence);
         Ok(())
     }
 
-    fn get_command_buffer(&self) -> Result<CommandBuffer, Error> {
+    fn get_semaphore(&self) -> Result<Semaphore, Error> {
+        if let Some(semaphore) = self.semaphores.borrow_mut().pop() {
+            return Ok(semaphore);
+        }
+        let create_info = SemaphoreCreateInfo::default();
+        unsafe {
+            self.device
+                .device
+                .create_semaphore(&create_info, None)
+                .map_err(Error::CreateSemaphore)
+        }
+    }
+
+    fn get_fence(&self) -> Result<Fence, Error> {
+        if let Some(fence) = self.fences.borrow_mut().pop() {
+            return Ok(fence);
+        }
+        let create_info = FenceCreateInfo::default();
+        unsafe {
+            self.device
+                .device
+                .create_fence(&create_info, None)
+                .map_err(Error::CreateFence)
+        }
+    }
+
+    fn get_command_buffer(&self, command_pool: CommandPool) -> Result<CommandBuffer, Error> {
         let allocate_info = CommandBufferAllocateInfo::default()
-            .command_pool(self.device.command_pool)
+            .command_pool(command_pool)
             .level(CommandBufferLevel::PRIMARY)
             .command_buffer_count(1);
         let buffers = unsafe {
@@ -664,168 +2211,121 @@ impl VulkanSurface {
         width: u32,
         height: u32,
         scene: Scene,
-        lms_to_local: ColorMatrix<Local, Lms>,
-        tf: TransferFunction,
-        tf_args: [f32; 4],
+        clear: Option<[f32; 4]>,
+        overlay: &Overlay,
+        params: &RenderParams,
     ) -> Result<(), Error> {
         self.gc(false)?;
         let dev = &self.device.device;
-        let swapchain = self.ensure_swapchain(width, height)?;
-        let create_semaphore = || {
-            let create_info = SemaphoreCreateInfo::default();
-            unsafe {
-                dev.create_semaphore(&create_info, None)
-                    .map_err(Error::CreateSemaphore)
-            }
+        let texture = scene.texture();
+        let transform = self.transform.get();
+        let swapchain = match transform.0 & 1 {
+            1 => self.ensure_swapchain(height, width)?,
+            _ => self.ensure_swapchain(width, height)?,
         };
-        let acquire_semaphore = create_semaphore()?;
+        let alpha_mode = swapchain.alpha_mode;
+        let acquire_semaphore = self.get_semaphore()?;
         let destroy_acquire_semaphore =
             on_drop(|| unsafe { dev.destroy_semaphore(acquire_semaphore, None) });
-        let release_semaphore = create_semaphore()?;
+        let release_semaphore = self.get_semaphore()?;
         let destroy_release_semaphore =
             on_drop(|| unsafe { dev.destroy_semaphore(release_semaphore, None) });
-        let create_fence = || {
-            let create_info = FenceCreateInfo::default();
-            unsafe {
-                dev.create_fence(&create_info, None)
-                    .map_err(Error::CreateFence)
-            }
-        };
-        let queue_release_fence = create_fence()?;
+        let queue_release_fence = self.get_fence()?;
         let destroy_queue_release_fence =
             on_drop(|| unsafe { dev.destroy_fence(queue_release_fence, None) });
-        let present_release_fence = create_fence()?;
+        let present_release_fence = self.get_fence()?;
         let destroy_present_release_fence =
             on_drop(|| unsafe { dev.destroy_fence(present_release_fence, None) });
+        let start = Instant::now();
         let (image, suboptimal) = {
             let acquire_info = AcquireNextImageInfoKHR::default()
                 .device_mask(1)
                 .swapchain(swapchain.swapchain)
-                .timeout(u64::MAX)
+                .timeout(ACQUIRE_TIMEOUT.as_nanos() as u64)
                 .semaphore(acquire_semaphore);
-            unsafe {
-                self.device
-                    .khr_swapchain
-                    .acquire_next_image2(&acquire_info)
-                    .map_err(Error::AcquireNextImage)?
+            loop {
+                let res = unsafe { self.device.khr_swapchain.acquire_next_image2(&acquire_info) };
+                match res {
+                    Ok(res) => break res,
+                    Err(vk::Result::TIMEOUT | vk::Result::NOT_READY) => {
+                        if start.elapsed() >= STALL_TIMEOUT {
+                            self.log_diagnostics(
+                                "acquiring a swapchain image timed out",
+                                &swapchain,
+                            );
+                            return Err(Error::AcquireTimeout(STALL_TIMEOUT));
+                        }
+                    }
+                    Err(e) => return Err(Error::AcquireNextImage(e)),
+                }
             }
         };
         if suboptimal {
             self.suboptimal.set(true);
         }
-        let buffer = self.get_command_buffer()?;
-        let free_buffer =
-            on_drop(|| unsafe { dev.free_command_buffers(self.device.command_pool, &[buffer]) });
-        {
+        let transfer = match self.device.transfer.as_ref() {
+            Some(t) if self.device.upload_mode.get() != UploadMode::Graphics => Some(t),
+            _ => None,
+        };
+        let concurrent =
+            transfer.is_some() && self.device.upload_mode.get() == UploadMode::TransferConcurrent;
+        let begin_command_buffer = |buffer: CommandBuffer| {
             let begin_info =
                 CommandBufferBeginInfo::default().flags(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
             unsafe {
                 dev.begin_command_buffer(buffer, &begin_info)
-                    .map_err(Error::BeginCommandBuffer)?;
+                    .map_err(Error::BeginCommandBuffer)
+            }
+        };
+        let buffer = self.get_command_buffer(self.device.command_pool)?;
+        let free_buffer =
+            on_drop(|| unsafe { dev.free_command_buffers(self.device.command_pool, &[buffer]) });
+        begin_command_buffer(buffer)?;
+        let upload_buffer = match transfer {
+            Some(t) => self.get_command_buffer(t.command_pool)?,
+            None => buffer,
+        };
+        let free_upload_buffer = on_drop(|| {
+            if let Some(t) = transfer {
+                unsafe { dev.free_command_buffers(t.command_pool, &[upload_buffer]) }
             }
+        });
+        if transfer.is_some() {
+            begin_command_buffer(upload_buffer)?;
         }
         struct Op {
             fill: FillBuffer,
+            textured: bool,
         }
-        let mut ops = vec![];
-        let lms_to_local = lms_to_local.to_f32();
-        let eotf = match tf {
-            TransferFunction::Named(n) => match n {
-                NamedTransferFunction::Srgb => 4,
-                NamedTransferFunction::Linear => 1,
-                NamedTransferFunction::St2084Pq => 2,
-                NamedTransferFunction::Bt1886 => 3,
-                NamedTransferFunction::Gamma22 => 4,
-                NamedTransferFunction::Gamma28 => 5,
-                NamedTransferFunction::St240 => 6,
-                NamedTransferFunction::ExtSrgb => 4,
-                NamedTransferFunction::Log100 => 8,
-                NamedTransferFunction::Log316 => 9,
-                NamedTransferFunction::St428 => 10,
+        let style = FillStyle {
+            transform: transform.0,
+            dither: match self.device.dither_bits.get() {
+                Some(bits) => 1.0 / ((1u64 << bits) - 1) as f32,
+                None => 0.0,
             },
-            TransferFunction::Pow => 11,
+            alpha_mode,
+            gamut_mapping: self.device.gamut_mapping.get(),
+            gradient_space: self.device.gradient_space.get(),
+            output_clamp: self.device.output_clamp.get(),
+            channel_adjustment: self.device.channel_adjustment.get(),
         };
-        let mut fill = |x1: f32, y1: f32, x2: f32, y2: f32, color: [[f32; 4]; 4]| {
-            let fill = self.allocate_fill_buffer()?;
-            let data = FillData {
-                lms_to_local,
-                x1,
-                y1,
-                x2,
-                y2,
-                color,
-                eotf,
-                eotf_args: tf_args,
-            };
+        let fills = scene_fills(width, height, scene, clear, overlay, params, style);
+        self.uploaded_params
+            .set(fills.first().map(|f| UploadedParams {
+                eotf: f.eotf,
+                eotf_args: f.eotf_args,
+                lms_to_local: f.lms_to_local,
+            }));
+        let mut ops = vec![];
+        for data in &fills {
+            let fill = self.allocate_fill_buffer(concurrent)?;
             unsafe {
-                dev.cmd_update_buffer(buffer, fill.buffer, 0, bytes_of(&data));
-            }
-            ops.push(Op { fill });
-            Ok(())
-        };
-        match scene {
-            Scene::Fill(c) => {
-                fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c); 4])?;
-            }
-            Scene::FillLeftRight([l, r]) => {
-                fill(
-                    -1.0,
-                    -1.0,
-                    1.0,
-                    1.0,
-                    [lch_to_lab(r), lch_to_lab(l), lch_to_lab(r), lch_to_lab(l)],
-                )?;
-            }
-            Scene::FillTopBottom([t, b]) => {
-                fill(
-                    -1.0,
-                    -1.0,
-                    1.0,
-                    1.0,
-                    [lch_to_lab(t), lch_to_lab(t), lch_to_lab(b), lch_to_lab(b)],
-                )?;
-            }
-            Scene::FillFour(c) => {
-                fill(-1.0, -1.0, 1.0, 1.0, c.map(lch_to_lab))?;
-            }
-            Scene::CenterBox(c, size) => {
-                fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4])?;
-                fill(-size, -size, size, size, [lch_to_lab(c[1]); 4])?;
-            }
-            Scene::Grid(c, rows, cols) => {
-                fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4])?;
-                let c1 = [lch_to_lab(c[1]); 4];
-                let height = 2.0 / rows as f32;
-                let width = 2.0 / cols as f32;
-                for row in 0..rows {
-                    let y1 = -1.0 + height * row as f32;
-                    for col in 0..cols {
-                        if (row + col) % 2 == 0 {
-                            continue;
-                        }
-                        let x1 = -1.0 + width * col as f32;
-                        fill(x1, y1, x1 + width, y1 + height, c1)?;
-                    }
-                }
-            }
-            Scene::BlendLeft(c) => {
-                fill(-1.0, 0.0, 1.0, 1.0, [lch_to_lab(c); 4])?;
-            }
-            Scene::BlendRight(c) => {
-                let mut b = lch_to_lab(c[0]);
-                let mut f = lch_to_lab(c[1]);
-                let mut r = f;
-                let a = f[3];
-                for i in 0..3 {
-                    r[i] = r[i] * a + (1.0 - a) * b[i];
-                }
-                r[3] = 1.0;
-                b[3] = 1.0;
-                f[3] = 1.0;
-                fill(-1.0, -1.0, 0.0, 1.0, [b; 4])?;
-                fill(0.0, -1.0, 1.0, 0.0, [f; 4])?;
-                fill(0.0, 0.0, 1.0, 1.0, [r; 4])?;
+                dev.cmd_update_buffer(upload_buffer, fill.buffer, 0, bytes_of(data));
             }
+            ops.push(Op {
+                fill,
+                textured: data.textured != 0,
+            });
         }
         {
             let image_barrier = ImageMemoryBarrier2::default()
@@ -837,17 +2337,46 @@ impl VulkanSurface {
                 .image(swapchain.images[image as usize])
                 .subresource_range(IMAGE_SUBRESOURCE_RANGE);
             let mut buffer_barriers = vec![];
+            let mut release_barriers = vec![];
             for op in &ops {
                 let buffer_barrier = BufferMemoryBarrier2::default()
-                    .src_stage_mask(PipelineStageFlags2::TRANSFER)
-                    .src_access_mask(AccessFlags2::TRANSFER_WRITE)
                     .dst_stage_mask(
                         PipelineStageFlags2::VERTEX_SHADER | PipelineStageFlags2::FRAGMENT_SHADER,
                     )
                     .dst_access_mask(AccessFlags2::SHADER_READ)
                     .buffer(op.fill.buffer)
                     .size(op.fill.size);
-                buffer_barriers.push(buffer_barrier);
+                match transfer {
+                    None => {
+                        buffer_barriers.push(
+                            buffer_barrier
+                                .src_stage_mask(PipelineStageFlags2::TRANSFER)
+                                .src_access_mask(AccessFlags2::TRANSFER_WRITE),
+                        );
+                    }
+                    // The semaphore between the submissions makes the writes visible.
+                    Some(_) if concurrent => {}
+                    Some(t) => {
+                        let buffer_barrier = buffer_barrier
+                            .src_queue_family_index(t.queue_idx)
+                            .dst_queue_family_index(self.device.queue_idx);
+                        release_barriers.push(
+                            buffer_barrier
+                                .src_stage_mask(PipelineStageFlags2::TRANSFER)
+                                .src_access_mask(AccessFlags2::TRANSFER_WRITE)
+                                .dst_stage_mask(PipelineStageFlags2::NONE)
+                                .dst_access_mask(AccessFlags2::NONE),
+                        );
+                        buffer_barriers.push(buffer_barrier);
+                    }
+                }
+            }
+            if release_barriers.is_not_empty() {
+                let dependency_info =
+                    DependencyInfo::default().buffer_memory_barriers(&release_barriers);
+                unsafe {
+                    dev.cmd_pipeline_barrier2(upload_buffer, &dependency_info);
+                }
             }
             let dependency_info = DependencyInfo::default()
                 .image_memory_barriers(slice::from_ref(&image_barrier))
@@ -872,7 +2401,10 @@ impl VulkanSurface {
             let rendering_info = RenderingInfo::default()
                 .render_area(Rect2D {
                     offset: Default::default(),
-                    extent: Extent2D { width, height },
+                    extent: Extent2D {
+                        width: swapchain.width,
+                        height: swapchain.height,
+                    },
                 })
                 .layer_count(1)
                 .color_attachments(slice::from_ref(&attachment_info));
@@ -884,8 +2416,8 @@ impl VulkanSurface {
             let viewport = Viewport {
                 x: 0.0,
                 y: 0.0,
-                width: width as _,
-                height: height as _,
+                width: swapchain.width as _,
+                height: swapchain.height as _,
                 min_depth: 0.0,
                 max_depth: 1.0,
             };
@@ -894,26 +2426,46 @@ impl VulkanSurface {
             }
             let scissor = Rect2D {
                 offset: Default::default(),
-                extent: Extent2D { width, height },
+                extent: Extent2D {
+                    width: swapchain.width,
+                    height: swapchain.height,
+                },
             };
             unsafe {
                 dev.cmd_set_scissor(buffer, 0, slice::from_ref(&scissor));
             }
         }
         {
-            let fill = |addr: u64| {
+            let d = &*self.device;
+            let fill = |op: &Op| {
+                let (pipeline, layout) = match (alpha_mode, op.textured) {
+                    (AlphaMode::Premultiplied, false) => (d.pipeline, d.pipeline_layout),
+                    (AlphaMode::Straight, false) => (d.straight_pipeline, d.pipeline_layout),
+                    (AlphaMode::Premultiplied, true) => {
+                        (d.texture_pipeline, d.texture_pipeline_layout)
+                    }
+                    (AlphaMode::Straight, true) => {
+                        (d.straight_texture_pipeline, d.texture_pipeline_layout)
+                    }
+                };
                 unsafe {
-                    dev.cmd_bind_pipeline(
-                        buffer,
-                        PipelineBindPoint::GRAPHICS,
-                        self.device.pipeline,
-                    );
+                    dev.cmd_bind_pipeline(buffer, PipelineBindPoint::GRAPHICS, pipeline);
+                    if let (true, Some(texture)) = (op.textured, &texture) {
+                        dev.cmd_bind_descriptor_sets(
+                            buffer,
+                            PipelineBindPoint::GRAPHICS,
+                            layout,
+                            0,
+                            slice::from_ref(&texture.descriptor_set),
+                            &[],
+                        );
+                    }
                 }
-                let constants = FillPushConstant { data: addr };
+                let constants = FillPushConstant { data: op.fill.addr };
                 unsafe {
                     dev.cmd_push_constants(
                         buffer,
-                        self.device.pipeline_layout,
+                        layout,
                         ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
                         0,
                         bytes_of(&constants),
@@ -924,7 +2476,7 @@ impl VulkanSurface {
                 }
             };
             for op in &ops {
-                fill(op.fill.addr);
+                fill(op);
             }
         }
         unsafe {
@@ -949,10 +2501,38 @@ impl VulkanSurface {
             dev.end_command_buffer(buffer)
                 .map_err(Error::EndCommandBuffer)?;
         }
+        let mut upload = None;
+        let mut wait_semaphores = vec![acquire_semaphore];
+        let mut wait_stages = vec![PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        if let Some(t) = transfer {
+            unsafe {
+                dev.end_command_buffer(upload_buffer)
+                    .map_err(Error::EndCommandBuffer)?;
+            }
+            let upload_semaphore = self.get_semaphore()?;
+            let destroy_upload_semaphore =
+                on_drop(|| unsafe { dev.destroy_semaphore(upload_semaphore, None) });
+            let submit_info = SubmitInfo::default()
+                .signal_semaphores(slice::from_ref(&upload_semaphore))
+                .command_buffers(slice::from_ref(&upload_buffer));
+            unsafe {
+                dev.queue_submit(t.queue, &[submit_info], Fence::null())
+                    .map_err(Error::Submit)?;
+            }
+            destroy_upload_semaphore.forget();
+            upload = Some(VulkanUpload {
+                command_buffer: upload_buffer,
+                semaphore: upload_semaphore,
+            });
+            wait_semaphores.push(upload_semaphore);
+            wait_stages
+                .push(PipelineStageFlags::VERTEX_SHADER | PipelineStageFlags::FRAGMENT_SHADER);
+        }
+        free_upload_buffer.forget();
         {
             let submit_info = SubmitInfo::default()
-                .wait_semaphores(slice::from_ref(&acquire_semaphore))
-                .wait_dst_stage_mask(&[PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT])
+                .wait_semaphores(&wait_semaphores)
+                .wait_dst_stage_mask(&wait_stages)
                 .signal_semaphores(slice::from_ref(&release_semaphore))
                 .command_buffers(slice::from_ref(&buffer));
             unsafe {
@@ -966,12 +2546,15 @@ impl VulkanSurface {
         free_buffer.forget();
         let owns_release_semaphore = Rc::new(Cell::new(true));
         self.submissions.borrow_mut().push_back(VulkanSubmission {
+            swapchain: swapchain.swapchain,
             acquire_semaphore,
             release_semaphore,
             owns_release_semaphore: owns_release_semaphore.clone(),
             release_fence: queue_release_fence,
             command_buffer: buffer,
+            upload,
             fill_buffers: ops.into_iter().map(|op| op.fill).collect(),
+            _texture: texture,
         });
         let suboptimal = {
             let mut fence_info = SwapchainPresentFenceInfoEXT::default()
@@ -994,74 +2577,486 @@ impl VulkanSurface {
         owns_release_semaphore.set(false);
         destroy_present_release_fence.forget();
         self.presents.borrow_mut().push_back(VulkanPresentation {
+            swapchain: swapchain.swapchain,
             release_fence: present_release_fence,
             release_semaphore,
+            presented: self.present_latencies.borrow().is_some().then(Instant::now),
         });
+        let elapsed = start.elapsed();
+        if elapsed >= SLOW_FRAME {
+            self.log_diagnostics(&format!("rendering a frame took {elapsed:?}"), &swapchain);
+        }
         Ok(())
     }
 
-    fn allocate_fill_buffer(&self) -> Result<FillBuffer, Error> {
+    /// Logs the state of the in-flight submissions and presentations.
+    fn log_diagnostics(&self, reason: &str, swapchain: &VulkanSwapchain) {
+        let dev = &self.device.device;
+        let signaled = |fences: &mut dyn Iterator<Item = Fence>| {
+            fences
+                .filter(|&f| unsafe { dev.get_fence_status(f) } == Ok(true))
+                .count()
+        };
+        let submissions = self.submissions.borrow();
+        let presents = self.presents.borrow();
+        log::warn!("{reason}");
+        log::warn!("  swapchain images: {}", swapchain.images.len());
+        log::warn!(
+            "  submissions in flight: {}, completed: {}",
+            submissions.len(),
+            signaled(&mut submissions.iter().map(|s| s.release_fence)),
+        );
+        log::warn!(
+            "  presentations in flight: {}, released: {}",
+            presents.len(),
+            signaled(&mut presents.iter().map(|p| p.release_fence)),
+        );
+    }
+
+    fn allocate_fill_buffer(&self, concurrent: bool) -> Result<FillBuffer, Error> {
         let buffers = &mut *self.fill_buffers.borrow_mut();
-        if let Some(buffer) = buffers.pop() {
-            return Ok(buffer);
+        while let Some(buffer) = buffers.pop() {
+            if buffer.concurrent == concurrent {
+                return Ok(buffer);
+            }
         }
-        let size = size_of::<FillData>().next_multiple_of(16) as u64;
-        let create_info = BufferCreateInfo::default()
-            .size(size)
-            .usage(BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::SHADER_DEVICE_ADDRESS)
-            .queue_family_indices(slice::from_ref(&self.device.queue_idx));
-        let buffer = unsafe {
-            self.device
-                .device
-                .create_buffer(&create_info, None)
-                .map_err(Error::CreateBuffer)?
-        };
-        let destroy_buffer = on_drop(|| unsafe { self.device.device.destroy_buffer(buffer, None) });
-        let req = unsafe { self.device.device.get_buffer_memory_requirements(buffer) };
-        let request = Request {
-            size: req.size,
-            align_mask: req.alignment - 1,
-            usage: UsageFlags::FAST_DEVICE_ACCESS | UsageFlags::DEVICE_ADDRESS,
-            memory_types: req.memory_type_bits,
-        };
-        let alloc = unsafe {
-            self.device
-                .allocator
-                .borrow_mut()
-                .alloc(AshMemoryDevice::wrap(&self.device.device), request)
-                .map_err(Error::AllocateMemory)?
-        };
-        let memory = *alloc.memory();
-        let offset = alloc.offset();
-        let alloc = Cell::new(Some(alloc));
-        let dealloc_memory = on_drop(|| unsafe {
-            self.device.allocator.borrow_mut().dealloc(
-                AshMemoryDevice::wrap(&self.device.device),
-                alloc.take().unwrap(),
-            );
+        self.device.create_fill_buffer(concurrent)
+    }
+}
+
+/// The value of `FillData::space` for colors that are already linear in the local
+/// space. `lms_to_local` is unused.
+const SPACE_LOCAL: u32 = 2;
+
+/// Settings that are the same for all fills of a frame.
+#[derive(Copy, Clone)]
+struct FillStyle {
+    transform: u32,
+    dither: f32,
+    alpha_mode: AlphaMode,
+    gamut_mapping: GamutMapping,
+    gradient_space: GradientSpace,
+    output_clamp: OutputClamp,
+    channel_adjustment: ChannelAdjustment,
+}
+
+/// Converts the scene and overlay into fills that are drawn in order.
+fn scene_fills(
+    width: u32,
+    height: u32,
+    scene: Scene,
+    clear: Option<[f32; 4]>,
+    overlay: &Overlay,
+    params: &RenderParams,
+    style: FillStyle,
+) -> Vec<FillData> {
+    if let Scene::Section(scene, (scene_width, scene_height), (x, y)) = scene {
+        let (sw, sh) = (scene_width as f32, scene_height as f32);
+        let section = [
+            sw / width as f32,
+            sh / height as f32,
+            (sw - 2.0 * x as f32) / width as f32 - 1.0,
+            (sh - 2.0 * y as f32) / height as f32 - 1.0,
+        ];
+        let mut fills = scene_fills(
+            scene_width,
+            scene_height,
+            *scene,
+            clear,
+            overlay,
+            params,
+            style,
+        );
+        for fill in &mut fills {
+            fill.section = section;
+        }
+        return fills;
+    }
+    let mut fills = vec![];
+    let lms_to_local = params.lms_to_local.to_f32();
+    let eotf = eotf_id(params.tf);
+    let gradient = Cell::new([0.0; 4]);
+    let raw = Cell::new(false);
+    let textured = Cell::new(false);
+    // If set, the colors are linear in the local space and encoded with these
+    // parameters.
+    let encoding = Cell::new(None::<RenderParams>);
+    let ictcp = (style.gradient_space == GradientSpace::Ictcp).then(|| {
+        let lms_from_ictcp = lms_from_ictcp_lms(ColorMatrix::XYZ_FROM_LMS);
+        (
+            (params.lms_to_local * lms_from_ictcp).to_f32(),
+            lms_from_ictcp.inverse().unwrap(),
+        )
+    });
+    let extend = |[r, g, b]: [f32; 3]| [r, g, b, 0.0];
+    let mut fill = |x1: f32, y1: f32, x2: f32, y2: f32, mut color: [[f32; 4]; 4]| {
+        let mut lms_to_local = lms_to_local;
+        let mut space = GradientSpace::Oklab as u32;
+        let (mut eotf, mut eotf_args, mut peak) = (eotf, params.tf_args, params.peak);
+        if let Some(p) = encoding.get() {
+            space = SPACE_LOCAL;
+            (eotf, eotf_args, peak) = (eotf_id(p.tf), p.tf_args, p.peak);
+        } else if let Some((ictcp_to_local, ictcp_from_lms)) = ictcp {
+            // The space only matters if the colors are interpolated.
+            if !raw.get() && !textured.get() && color.iter().any(|c| *c != color[0]) {
+                lms_to_local = ictcp_to_local;
+                space = GradientSpace::Ictcp as u32;
+                color = color.map(|c| lab_to_ictcp(c, ictcp_from_lms));
+            }
+        }
+        fills.push(FillData {
+            lms_to_local,
+            x1,
+            y1,
+            x2,
+            y2,
+            color,
+            eotf,
+            eotf_args,
+            peak,
+            dither: style.dither,
+            transform: style.transform,
+            gradient: gradient.get(),
+            raw: raw.get() as u32,
+            gamut_mapping: style.gamut_mapping as u32,
+            textured: textured.get() as u32,
+            _padding: [0; 3],
+            space,
+            output_clamp: style.output_clamp as u32,
+            channel_gain: extend(style.channel_adjustment.gain),
+            channel_offset: extend(style.channel_adjustment.offset),
+            section: [1.0, 1.0, 0.0, 0.0],
         });
-        unsafe {
-            self.device
-                .device
-                .bind_buffer_memory(buffer, memory, offset)
-                .map_err(Error::BindBufferMemory)?;
+    };
+    let px_width = 2.0 / width as f32;
+    let px_height = 2.0 / height as f32;
+    if let Some(c) = clear {
+        // Drawn instead of using the clear value so that the color goes through the
+        // same color path as the scene.
+        fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c); 4]);
+    }
+    match scene {
+        Scene::Fill(c) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c); 4]);
         }
-        let addr = {
-            let info = BufferDeviceAddressInfo::default().buffer(buffer);
-            unsafe { self.device.device.get_buffer_device_address(&info) }
-        };
-        dealloc_memory.forget();
-        destroy_buffer.forget();
-        Ok(FillBuffer {
-            buffer,
-            addr,
-            size,
-            memory: alloc,
-            device: self.device.clone(),
-        })
+        Scene::LinearGradient([from, to], angle) => {
+            gradient.set(linear_gradient(angle, width, height));
+            let c = [lch_to_lab(from), lch_to_lab(to), [0.0; 4], [0.0; 4]];
+            fill(-1.0, -1.0, 1.0, 1.0, c);
+            gradient.set([0.0; 4]);
+        }
+        Scene::FillFour(c) => {
+            fill(-1.0, -1.0, 1.0, 1.0, c.map(lch_to_lab));
+        }
+        Scene::CenterBox(c, size) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4]);
+            fill(-size, -size, size, size, [lch_to_lab(c[1]); 4]);
+        }
+        Scene::Grid(c, rows, cols) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4]);
+            let c1 = [lch_to_lab(c[1]); 4];
+            let height = 2.0 / rows as f32;
+            let width = 2.0 / cols as f32;
+            for row in 0..rows {
+                let y1 = -1.0 + height * row as f32;
+                for col in 0..cols {
+                    if (row + col) % 2 == 0 {
+                        continue;
+                    }
+                    let x1 = -1.0 + width * col as f32;
+                    fill(x1, y1, x1 + width, y1 + height, c1);
+                }
+            }
+        }
+        Scene::ColorGrid(rows, cols, colors) => {
+            let height = 2.0 / rows as f32;
+            let width = 2.0 / cols as f32;
+            for (idx, c) in colors.iter().enumerate() {
+                let y1 = -1.0 + height * (idx as u32 / cols) as f32;
+                let x1 = -1.0 + width * (idx as u32 % cols) as f32;
+                fill(x1, y1, x1 + width, y1 + height, [lch_to_lab(*c); 4]);
+            }
+        }
+        Scene::Crosshair(c) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4]);
+            let c1 = [lch_to_lab(c[1]); 4];
+            let mut rect = |x1: u32, y1: u32, x2: u32, y2: u32| {
+                fill(
+                    -1.0 + px_width * x1 as f32,
+                    -1.0 + px_height * y1 as f32,
+                    -1.0 + px_width * x2 as f32,
+                    -1.0 + px_height * y2 as f32,
+                    c1,
+                )
+            };
+            // If the dimension is even, the center falls between two pixels and the
+            // line covers both of them.
+            let (cx1, cx2) = ((width - 1) / 2, width / 2 + 1);
+            let (cy1, cy2) = ((height - 1) / 2, height / 2 + 1);
+            rect(cx1, 0, cx2, height);
+            rect(0, cy1, width, cy2);
+            let len = (width.min(height) / 16).max(1);
+            for x in [0, width - len] {
+                rect(x, 0, x + len, 1);
+                rect(x, height - 1, x + len, height);
+            }
+            for y in [0, height - len] {
+                rect(0, y, 1, y + len);
+                rect(width - 1, y, width, y + len);
+            }
+        }
+        Scene::Patch(c, size, (dx, dy)) => {
+            raw.set(true);
+            fill(-1.0, -1.0, 1.0, 1.0, [[0.0, 0.0, 0.0, 1.0]; 4]);
+            raw.set(false);
+            let x1 = (width as i32 - size as i32) / 2 + dx;
+            let y1 = (height as i32 - size as i32) / 2 + dy;
+            fill(
+                -1.0 + px_width * x1 as f32,
+                -1.0 + px_height * y1 as f32,
+                -1.0 + px_width * (x1 + size as i32) as f32,
+                -1.0 + px_height * (y1 + size as i32) as f32,
+                [lch_to_lab(c); 4],
+            );
+        }
+        Scene::BlendLeft(c) => {
+            fill(-1.0, 0.0, 1.0, 1.0, [lch_to_lab(c); 4]);
+        }
+        Scene::BlendRight(c) => {
+            let mut b = lch_to_lab(c[0]);
+            let mut f = lch_to_lab(c[1]);
+            let r = client_blend(b, f, style.alpha_mode);
+            b[3] = 1.0;
+            f[3] = 1.0;
+            fill(-1.0, -1.0, 0.0, 1.0, [b; 4]);
+            fill(0.0, -1.0, 1.0, 0.0, [f; 4]);
+            fill(0.0, 0.0, 1.0, 1.0, [r; 4]);
+        }
+        Scene::BlendGradient(c) => {
+            gradient.set(linear_gradient(0.0, width, height));
+            let c = [lch_to_lab(c[0]), lch_to_lab(c[1]), [0.0; 4], [0.0; 4]];
+            fill(-1.0, -1.0, 1.0, 0.0, c);
+            gradient.set([0.0; 4]);
+        }
+        Scene::BlendGradientReference(b, f) => {
+            let mut b = lch_to_lab(b);
+            let [f1, f2] = f.map(lch_to_lab);
+            b[3] = 1.0;
+            fill(-1.0, -1.0, 1.0, 0.0, [b; 4]);
+            gradient.set(linear_gradient(0.0, width, height));
+            let r = [
+                client_blend(b, f1, style.alpha_mode),
+                client_blend(b, f2, style.alpha_mode),
+            ];
+            fill(-1.0, 0.0, 1.0, 1.0, [r[0], r[1], [0.0; 4], [0.0; 4]]);
+            gradient.set([0.0; 4]);
+        }
+        Scene::Image(texture) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [[0.0, 0.0, 0.0, 1.0]; 4]);
+            let scale =
+                (width as f32 / texture.width as f32).min(height as f32 / texture.height as f32);
+            let x = texture.width as f32 * scale / width as f32;
+            let y = texture.height as f32 * scale / height as f32;
+            textured.set(true);
+            fill(-x, -y, x, y, [[0.0; 4]; 4]);
+            textured.set(false);
+        }
+        Scene::BlendLayers(b, layers) => {
+            let mut b = lch_to_lab(b);
+            b[3] = 1.0;
+            fill(-1.0, -1.0, 1.0, 1.0, [b; 4]);
+            let mut r = b;
+            let width = 1.0 / layers.len().max(1) as f32;
+            for (i, f) in layers.into_iter().enumerate() {
+                let mut f = lch_to_lab(f);
+                r = client_blend(r, f, style.alpha_mode);
+                f[3] = 1.0;
+                let x = i as f32 * width;
+                fill(x, -1.0, x + width, 0.0, [f; 4]);
+            }
+            fill(0.0, 0.0, 1.0, 1.0, [r; 4]);
+        }
+        Scene::MovingBar(c, position) => {
+            fill(-1.0, -1.0, 1.0, 1.0, [lch_to_lab(c[0]); 4]);
+            let x1 = -1.0 + 2.0 * position;
+            fill(x1, -1.0, x1 + 2.0 / 16.0, 1.0, [lch_to_lab(c[1]); 4]);
+        }
+        Scene::Primaries(scale) => {
+            // Degenerate primaries have no bars.
+            if let Some(local_to_lms) = params.lms_to_local.inverse() {
+                for i in 0..3 {
+                    let mut rgb = [0.0; 3];
+                    rgb[i] = scale as f64;
+                    let c = [lms_to_lab(local_to_lms * rgb); 4];
+                    let x1 = -1.0 + 2.0 / 3.0 * i as f32;
+                    fill(x1, -1.0, x1 + 2.0 / 3.0, 1.0, c);
+                }
+            }
+        }
+        Scene::BlackDetail(values) => {
+            raw.set(true);
+            fill(-1.0, -1.0, 1.0, 1.0, [[0.0, 0.0, 0.0, 1.0]; 4]);
+            // The patches and the gaps between them have the same size.
+            let n = values.len().max(1) as u32;
+            let size = (width / (2 * n + 1)).min(height / 3).max(1);
+            let x0 = width.saturating_sub((2 * n - 1) * size) / 2;
+            let y0 = height.saturating_sub(size) / 2;
+            for (i, v) in values.iter().enumerate() {
+                let x1 = x0 + 2 * i as u32 * size;
+                fill(
+                    -1.0 + px_width * x1 as f32,
+                    -1.0 + px_height * y0 as f32,
+                    -1.0 + px_width * (x1 + size) as f32,
+                    -1.0 + px_height * (y0 + size) as f32,
+                    [[*v, *v, *v, 1.0]; 4],
+                );
+            }
+            raw.set(false);
+        }
+        Scene::TransferFunctions(rows, white, scale, [background, color]) => {
+            let n = rows.len().max(1) as u32;
+            gradient.set(linear_gradient(0.0, width, height));
+            for (row, (_, row_params)) in rows.iter().enumerate() {
+                let y1 = -1.0 + 2.0 / n as f32 * row as f32;
+                encoding.set(Some(*row_params));
+                let c = [
+                    [0.0, 0.0, 0.0, 1.0],
+                    [white, white, white, 1.0],
+                    [0.0; 4],
+                    [0.0; 4],
+                ];
+                fill(-1.0, y1, 1.0, y1 + 2.0 / n as f32, c);
+            }
+            encoding.set(None);
+            gradient.set([0.0; 4]);
+            for (row, (label, _)) in rows.iter().enumerate() {
+                let top = height * row as u32 / n;
+                let pos = (0, top);
+                draw_text(
+                    &mut fill,
+                    (width, height),
+                    pos,
+                    label,
+                    scale,
+                    background,
+                    color,
+                );
+            }
+        }
+        Scene::Section(..) => unreachable!(),
     }
+    if let Some(o) = &overlay.grid {
+        let c = [lch_to_lab(o.color); 4];
+        let spacing = o.spacing.max(1) as usize;
+        for x in (0..width).step_by(spacing) {
+            let x1 = -1.0 + px_width * x as f32;
+            fill(x1, -1.0, x1 + px_width, 1.0, c);
+        }
+        for y in (0..height).step_by(spacing) {
+            let y1 = -1.0 + px_height * y as f32;
+            fill(-1.0, y1, 1.0, y1 + px_height, c);
+        }
+    }
+    if let Some(l) = &overlay.legend {
+        draw_text(
+            &mut fill,
+            (width, height),
+            (0, 0),
+            &l.text,
+            l.scale,
+            l.background,
+            l.color,
+        );
+    }
+    fills
 }
 
+/// Draws text over a box of the background color. The top-left corner of the box is
+/// at the given pixel. The text is surrounded by a margin of one font pixel.
+fn draw_text(
+    fill: &mut impl FnMut(f32, f32, f32, f32, [[f32; 4]; 4]),
+    (width, height): (u32, u32),
+    (left, top): (u32, u32),
+    text: &str,
+    scale: u32,
+    background: [f32; 4],
+    color: [f32; 4],
+) {
+    let scale = scale.max(1);
+    let advance = font::WIDTH + 1;
+    let line_height = font::HEIGHT + 2;
+    let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+    let rows = text.lines().count();
+    let px_width = 2.0 / width as f32;
+    let px_height = 2.0 / height as f32;
+    // Positions are in font pixels.
+    let x = |x: u32| -1.0 + px_width * (left + x * scale) as f32;
+    let y = |y: u32| -1.0 + px_height * (top + y * scale) as f32;
+    fill(
+        x(0),
+        y(0),
+        x(columns as u32 * advance + 1),
+        y(rows as u32 * line_height),
+        [lch_to_lab(background); 4],
+    );
+    let c = [lch_to_lab(color); 4];
+    for (row, line) in text.lines().enumerate() {
+        let top = 1 + row as u32 * line_height;
+        for glyph_row in 0..font::HEIGHT {
+            let y1 = y(top + glyph_row);
+            let y2 = y(top + glyph_row + 1);
+            // Adjacent pixels are merged into a single fill.
+            let mut run: Option<(u32, u32)> = None;
+            for (column, ch) in line.chars().enumerate() {
+                let bits = font::glyph(ch)[glyph_row as usize];
+                for glyph_column in 0..font::WIDTH {
+                    if bits & (1 << (font::WIDTH - 1 - glyph_column)) == 0 {
+                        continue;
+                    }
+                    let px = 1 + column as u32 * advance + glyph_column;
+                    match &mut run {
+                        Some((_, end)) if *end == px => *end += 1,
+                        _ => {
+                            if let Some((start, end)) = run {
+                                fill(x(start), y1, x(end), y2, c);
+                            }
+                            run = Some((px, px + 1));
+                        }
+                    }
+                }
+            }
+            if let Some((start, end)) = run {
+                fill(x(start), y1, x(end), y2, c);
+            }
+        }
+    }
+}
+
+pub const DEFAULT_IMAGE_COUNT: u32 = 3;
+
+/// The number of fill buffers kept by a surface when it is trimmed. Enough for the
+/// fills of most scenes in flight.
+const MAX_POOLED_FILL_BUFFERS: usize = 64;
+
+/// The timeout of a single attempt to acquire a swapchain image.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_millis(100);
+/// After this time without an available image, rendering fails.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// Frames that take longer than this are logged.
+const SLOW_FRAME: Duration = Duration::from_millis(500);
+
+const VALIDATION_LAYER: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+static VALIDATION: AtomicBool = AtomicBool::new(false);
+
+/// Enables the validation layer for all devices created afterwards if it is available.
+pub fn enable_validation() {
+    VALIDATION.store(true, Relaxed);
+}
+
+/// The maximum number of textures that can exist at the same time.
+const MAX_TEXTURES: u32 = 16;
+
 const IMAGE_SUBRESOURCE_RANGE: ImageSubresourceRange = ImageSubresourceRange {
     aspect_mask: ImageAspectFlags::COLOR,
     base_mip_level: 0,
@@ -1070,6 +3065,31 @@ const IMAGE_SUBRESOURCE_RANGE: ImageSubresourceRange = ImageSubresourceRange {
     layer_count: 1,
 };
 
+/// Mirrors the `Data` block in `fill.common.glsl` (std430):
+///
+/// | field            | offset |
+/// |------------------|--------|
+/// | `lms_to_local`   | 0      |
+/// | `x1`             | 64     |
+/// | `y1`             | 68     |
+/// | `x2`             | 72     |
+/// | `y2`             | 76     |
+/// | `color`          | 80     |
+/// | `eotf`           | 144    |
+/// | `eotf_args`      | 148    |
+/// | `peak`           | 164    |
+/// | `dither`         | 168    |
+/// | `transform`      | 172    |
+/// | `gradient`       | 176    |
+/// | `raw`            | 192    |
+/// | `gamut_mapping`  | 196    |
+/// | `textured`       | 200    |
+/// | `_padding`       | 204    |
+/// | `space`          | 216    |
+/// | `output_clamp`   | 220    |
+/// | `channel_gain`   | 224    |
+/// | `channel_offset` | 240    |
+/// | `section`        | 256    |
 #[derive(NoUninit, Copy, Clone)]
 #[repr(C)]
 struct FillData {
@@ -1081,18 +3101,206 @@ struct FillData {
     color: [[f32; 4]; 4],
     eotf: u32,
     eotf_args: [f32; 4],
+    peak: f32,
+    dither: f32,
+    transform: u32,
+    gradient: [f32; 4],
+    raw: u32,
+    gamut_mapping: u32,
+    textured: u32,
+    _padding: [u32; 3],
+    space: u32,
+    output_clamp: u32,
+    channel_gain: [f32; 4],
+    channel_offset: [f32; 4],
+    section: [f32; 4],
 }
 
+const _: () = {
+    assert!(offset_of!(FillData, lms_to_local) == 0);
+    assert!(offset_of!(FillData, x1) == 64);
+    assert!(offset_of!(FillData, y1) == 68);
+    assert!(offset_of!(FillData, x2) == 72);
+    assert!(offset_of!(FillData, y2) == 76);
+    assert!(offset_of!(FillData, color) == 80);
+    assert!(offset_of!(FillData, eotf) == 144);
+    assert!(offset_of!(FillData, eotf_args) == 148);
+    assert!(offset_of!(FillData, gradient) == 176);
+    assert!(offset_of!(FillData, peak) == 164);
+    assert!(offset_of!(FillData, dither) == 168);
+    assert!(offset_of!(FillData, transform) == 172);
+    assert!(offset_of!(FillData, raw) == 192);
+    assert!(offset_of!(FillData, gamut_mapping) == 196);
+    assert!(offset_of!(FillData, textured) == 200);
+    assert!(offset_of!(FillData, space) == 216);
+    assert!(offset_of!(FillData, output_clamp) == 220);
+    assert!(offset_of!(FillData, channel_gain) == 224);
+    assert!(offset_of!(FillData, channel_offset) == 240);
+    assert!(offset_of!(FillData, section) == 256);
+    assert!(size_of::<FillData>() == 272);
+};
+
 #[derive(NoUninit, Copy, Clone)]
 #[repr(C)]
 struct FillPushConstant {
     data: u64,
 }
 
-fn lch_to_lab(mut lch: [f32; 4]) -> [f32; 4] {
+/// The `eotf` value in `FillData` that selects the inverse EOTF in `fill.encode.glsl`.
+pub fn eotf_id(tf: TransferFunction) -> u32 {
+    match tf {
+        TransferFunction::Named(n) => match n {
+            NamedTransferFunction::Srgb => 7,
+            NamedTransferFunction::Linear => 1,
+            NamedTransferFunction::St2084Pq => 2,
+            NamedTransferFunction::Bt1886 => 3,
+            NamedTransferFunction::Gamma22 => 4,
+            NamedTransferFunction::Gamma28 => 5,
+            NamedTransferFunction::St240 => 6,
+            NamedTransferFunction::ExtSrgb => 7,
+            NamedTransferFunction::Log100 => 8,
+            NamedTransferFunction::Log316 => 9,
+            NamedTransferFunction::St428 => 10,
+        },
+        TransferFunction::Pow => 11,
+    }
+}
+
+/// Returns the minimum and maximum number of swapchain images allowed by the
+/// capabilities. The maximum is `None` if there is no limit.
+fn image_count_range(capabilities: &SurfaceCapabilitiesKHR) -> (u32, Option<u32>) {
+    let max = Some(capabilities.max_image_count).filter(|&n| n != 0);
+    (capabilities.min_image_count, max)
+}
+
+/// Returns the swapchain pre-transform that corresponds to the wl_output.transform.
+/// Vulkan rotates clockwise, wayland counter-clockwise.
+fn pre_transform(transform: WlOutputTransform) -> SurfaceTransformFlagsKHR {
+    match transform {
+        WlOutputTransform::_90 => SurfaceTransformFlagsKHR::ROTATE_270,
+        WlOutputTransform::_180 => SurfaceTransformFlagsKHR::ROTATE_180,
+        WlOutputTransform::_270 => SurfaceTransformFlagsKHR::ROTATE_90,
+        WlOutputTransform::FLIPPED => SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR,
+        WlOutputTransform::FLIPPED_90 => SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_270,
+        WlOutputTransform::FLIPPED_180 => SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_180,
+        WlOutputTransform::FLIPPED_270 => SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_90,
+        _ => SurfaceTransformFlagsKHR::IDENTITY,
+    }
+}
+
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = (h >> 10) & 0x1f;
+    let mantissa = (h & 0x3ff) as f32;
+    sign * match exp {
+        0 => mantissa * 2.0f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exp as i32 - 15),
+    }
+}
+
+unsafe extern "system" fn debug_callback(
+    severity: DebugUtilsMessageSeverityFlagsEXT,
+    _ty: DebugUtilsMessageTypeFlagsEXT,
+    data: *const DebugUtilsMessengerCallbackDataEXT<'_>,
+    _user_data: *mut c_void,
+) -> vk::Bool32 {
+    let message = unsafe { (*data).message_as_c_str() };
+    let message = message.map(|m| m.to_string_lossy()).unwrap_or_default();
+    let level = match severity {
+        DebugUtilsMessageSeverityFlagsEXT::ERROR => log::Level::Error,
+        _ => log::Level::Warn,
+    };
+    log::log!(level, "{message}");
+    vk::FALSE
+}
+
+/// Returns the first of the `required` extensions that is not `available`.
+fn find_missing_extension(
+    available: &[ExtensionProperties],
+    required: &[&'static CStr],
+) -> Option<&'static CStr> {
+    required.iter().copied().find(|&name| {
+        !available
+            .iter()
+            .any(|a| a.extension_name_as_c_str() == Ok(name))
+    })
+}
+
+/// Returns the opaque color that the compositor computes when it blends `f` over `b`,
+/// assuming that it interprets the alpha channel correctly. The colors are blended in
+/// the Lab representation.
+fn client_blend(b: [f32; 4], f: [f32; 4], alpha_mode: AlphaMode) -> [f32; 4] {
+    let a = f[3];
+    let mut r = f;
+    for i in 0..3 {
+        r[i] = match alpha_mode {
+            AlphaMode::Premultiplied => f[i] + (1.0 - a) * b[i],
+            AlphaMode::Straight => f[i] * a + (1.0 - a) * b[i],
+        };
+    }
+    r[3] = 1.0;
+    r
+}
+
+/// Returns the `gradient` field of a full-screen linear gradient.
+fn linear_gradient(angle: f32, width: u32, height: u32) -> [f32; 4] {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    // The direction in pixels. The y axis points down.
+    let ax = cos * width as f32 / 2.0;
+    let ay = -sin * height as f32 / 2.0;
+    // Maps the projection of the corners onto the axis to [0, 1].
+    let extent = ax.abs() + ay.abs();
+    let range = 2.0 * extent;
+    [ax / range, ay / range, extent / range, 1.0]
+}
+
+pub fn lch_to_lab(mut lch: [f32; 4]) -> [f32; 4] {
     let a = lch[1] * lch[2].cos();
     let b = lch[1] * lch[2].sin();
     lch[1] = a;
     lch[2] = b;
     lch
 }
+
+/// Converts the Oklab color to ICtCp. The alpha channel is unchanged.
+fn lab_to_ictcp(lab: [f32; 4], ictcp_from_lms: ColorMatrix<IctcpLms, Lms>) -> [f32; 4] {
+    let [l, a, b, alpha] = lab.map(|c| c as f64);
+    let lms = ictcp_from_lms * lab_to_lms([l, a, b]);
+    let pq = eotf_id(TransferFunction::Named(NamedTransferFunction::St2084Pq));
+    let [l, m, s] = lms.map(|c| trace::inv_eotf(pq, &[1.0, 0.0, 0.0, 0.0], c as f32) as f64);
+    [
+        (0.5 * l + 0.5 * m) as f32,
+        ((6610.0 * l - 13613.0 * m + 7003.0 * s) / 4096.0) as f32,
+        ((17933.0 * l - 17390.0 * m - 543.0 * s) / 4096.0) as f32,
+        alpha as f32,
+    ]
+}
+
+/// The matrix from Oklab to the cube roots of LMS, as used by the shaders.
+pub const LAB_TO_LMS_PRIME: [[f64; 3]; 3] = [
+    [1.0, 0.3963377774, 0.2158037573],
+    [1.0, -0.1055613458, -0.0638541728],
+    [1.0, -0.0894841775, -1.2914855480],
+];
+
+/// Converts Oklab to LMS. Inverse of `lms_to_lab`.
+pub fn lab_to_lms(lab: [f64; 3]) -> [f64; 3] {
+    let m = &LAB_TO_LMS_PRIME;
+    let lms_prime: [f64; 3] =
+        array::from_fn(|i| m[i][0] * lab[0] + m[i][1] * lab[1] + m[i][2] * lab[2]);
+    lms_prime.map(|c| c * c * c)
+}
+
+/// Converts LMS to the LAB values expected by the shader. Inverse of `LAB_TO_LMS_PRIME`
+/// in `fill.encode.glsl` after taking the cube root.
+pub fn lms_to_lab(lms: [f64; 3]) -> [f32; 4] {
+    let [l, m, s] = lms.map(f64::cbrt);
+    [
+        (0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s) as f32,
+        (1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s) as f32,
+        (0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s) as f32,
+        1.0,
+    ]
+}
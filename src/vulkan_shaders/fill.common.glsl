@@ -12,6 +12,37 @@ layout(buffer_reference, buffer_reference_align = 16, row_major, std430) readonl
 	float eotf_arg2;
 	float eotf_arg3;
 	float eotf_arg4;
+	float peak;
+	// If not 0, the encoded color is dithered with this amplitude.
+	float dither;
+	// The wl_output.transform that is applied to the content.
+	uint transform;
+	// If w is not 0, the color is interpolated between color[0] and color[1] at
+	// dot(gradient.xyz, vec3(pos, 1.0)).
+	vec4 gradient;
+	// If not 0, the interpolated color is already encoded and written unchanged.
+	uint raw;
+	// 0: out-of-gamut colors are clipped, 1: their chroma is compressed.
+	uint gamut_mapping;
+	// If not 0, the fill is drawn by texture.frag, which samples the color from the
+	// Oklab texture of the scene, stretched over the rectangle.
+	uint textured;
+	uint _padding0;
+	uint _padding1;
+	uint _padding2;
+	// 0: the colors are in Oklab, 1: in ICtCp and lms_to_local converts from the LMS
+	// of ICtCp, 2: linear in the local space and lms_to_local is unused.
+	uint space;
+	// 0: the encoded color is written unchanged, 1: negative values are clamped to 0,
+	// 2: values are clamped to [0, 1].
+	uint output_clamp;
+	// Applied to the encoded color before clamping to emulate a miscalibrated display.
+	// The w components are unused.
+	vec4 channel_gain;
+	vec4 channel_offset;
+	// Maps positions in the scene to positions in the surface before the transform:
+	// xy scale and zw offset.
+	vec4 section;
 };
 
 layout(push_constant, std430) uniform PushData {
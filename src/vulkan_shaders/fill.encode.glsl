@@ -0,0 +1,195 @@
+#define TF_LINEAR 1
+#define TF_ST2084_PQ 2
+#define TF_BT1886 3
+#define TF_GAMMA22 4
+#define TF_GAMMA28 5
+#define TF_ST240 6
+#define TF_SRGB 7
+#define TF_LOG100 8
+#define TF_LOG316 9
+#define TF_ST428 10
+#define TF_POW 11
+
+vec3 inv_eotf_bt1886(Data data, vec3 c) {
+	c = clamp(c, 0.0, 1.0);
+	float a1 = data.eotf_arg1;
+	float a2 = data.eotf_arg2;
+	float a3 = data.eotf_arg3;
+	float a4 = data.eotf_arg4;
+	return a1 * (pow(a2 * c + a3, vec3(1.0 / 2.4)) - a4);
+}
+
+vec3 inv_eotf_st2084_pq(Data data, vec3 c) {
+	c = clamp(data.eotf_arg1 * c, 0.0, 1.0);
+	vec3 num = vec3(0.8359375) + vec3(18.8515625) * pow(c, vec3(0.1593017578125));
+	vec3 den = vec3(1.0) + vec3(18.6875) * pow(c, vec3(0.1593017578125));
+	return pow(num / den, vec3(78.84375));
+}
+
+vec3 inv_eotf_st240(vec3 c) {
+	return mix(
+		vec3(4.0) * c,
+		vec3(1.1115) * pow(c, vec3(0.45)) - vec3(0.1115),
+		greaterThanEqual(c, vec3(0.0228))
+	);
+}
+
+vec3 inv_eotf_srgb(vec3 c) {
+	vec3 a = abs(c);
+	return sign(c) * mix(
+		vec3(12.92) * a,
+		vec3(1.055) * pow(a, vec3(1.0 / 2.4)) - vec3(0.055),
+		greaterThan(a, vec3(0.0031308))
+	);
+}
+
+vec3 inv_eotf_log100(vec3 c) {
+	c = clamp(c, 0.0, 1.0);
+	return mix(
+		vec3(0.0),
+		vec3(1.0) + log2(c) / vec3(log2(10)) / vec3(2.0),
+		greaterThanEqual(c, vec3(0.01))
+	);
+}
+
+vec3 inv_eotf_log316(vec3 c) {
+	c = clamp(c, 0.0, 1.0);
+	return mix(
+		vec3(0.0),
+		vec3(1.0) + log2(c) / vec3(log2(10)) / vec3(2.5),
+		greaterThanEqual(c, vec3(sqrt(10) / 1000.0))
+	);
+}
+
+vec3 inv_eotf_st428(vec3 c) {
+	c = max(c, 0.0);
+	return pow(vec3(48.0) * c / vec3(52.37), vec3(1.0 / 2.6));
+}
+
+vec3 apply_inv_eotf(Data data, vec3 c) {
+	switch (data.eotf) {
+		case TF_LINEAR: return c;
+		case TF_ST2084_PQ: return inv_eotf_st2084_pq(data, c);
+		case TF_BT1886: return inv_eotf_bt1886(data, c);
+		case TF_GAMMA22: return sign(c) * pow(abs(c), vec3(1.0 / 2.2));
+		case TF_GAMMA28: return sign(c) * pow(abs(c), vec3(1.0 / 2.8));
+		case TF_ST240: return inv_eotf_st240(c);
+		case TF_SRGB: return inv_eotf_srgb(c);
+		case TF_LOG100: return inv_eotf_log100(c);
+		case TF_LOG316: return inv_eotf_log316(c);
+		case TF_ST428: return inv_eotf_st428(c);
+		case TF_POW: return sign(c) * pow(abs(c), vec3(data.eotf_arg1));
+		default: return c;
+	}
+}
+
+const mat3 LAB_TO_LMS_PRIME = mat3(
+	1.0,           1.0,           1.0,
+	0.3963377774, -0.1055613458, -0.0894841775,
+	0.2158037573, -0.0638541728, -1.2914855480
+);
+
+const mat3 ICTCP_TO_LMS_PRIME = mat3(
+	1.0,           1.0,           1.0,
+	0.0086090370, -0.0086090370,  0.5600313357,
+	0.1110296250, -0.1110296250, -0.3206271750
+);
+
+#define GAMUT_MAPPING_COMPRESS 1
+#define SPACE_ICTCP 1
+#define SPACE_LOCAL 2
+
+#define OUTPUT_CLAMP_NON_NEGATIVE 1
+#define OUTPUT_CLAMP_UNIT 2
+
+// Emulates a miscalibrated display. This is not part of the color pipeline and only
+// changes the encoded color.
+vec3 adjust_channels(Data data, vec3 c) {
+	return c * data.channel_gain.rgb + data.channel_offset.rgb;
+}
+
+vec3 clamp_output(Data data, vec3 c) {
+	switch (data.output_clamp) {
+		case OUTPUT_CLAMP_NON_NEGATIVE: return max(c, 0.0);
+		case OUTPUT_CLAMP_UNIT: return clamp(c, 0.0, 1.0);
+		default: return c;
+	}
+}
+
+vec3 eotf_st2084_pq(vec3 c) {
+	vec3 p = pow(clamp(c, 0.0, 1.0), vec3(1.0 / 78.84375));
+	vec3 num = max(p - vec3(0.8359375), 0.0);
+	vec3 den = vec3(18.8515625) - vec3(18.6875) * p;
+	return pow(num / den, vec3(1.0 / 0.1593017578125));
+}
+
+// Converts from Oklab or ICtCp, depending on data.space. The first component is the
+// lightness or intensity in both spaces. Colors in the local space are returned
+// unchanged.
+vec3 lab_to_local(Data data, vec3 lab) {
+	if (data.space == SPACE_LOCAL) {
+		return lab;
+	}
+	vec3 c;
+	if (data.space == SPACE_ICTCP) {
+		c = eotf_st2084_pq(ICTCP_TO_LMS_PRIME * lab);
+	} else {
+		c = LAB_TO_LMS_PRIME * lab;
+		c = c * c * c;
+	}
+	return (data.lms_to_local * vec4(c, 1.0)).rgb;
+}
+
+// Scales a and b by the largest factor for which no channel is negative. The
+// achromatic color of the same lightness is always inside the gamut.
+vec3 compress_chroma(Data data, vec3 lab) {
+	vec3 c = lab_to_local(data, lab);
+	if (min(c.r, min(c.g, c.b)) >= 0.0) {
+		return c;
+	}
+	float lo = 0.0;
+	float hi = 1.0;
+	for (int i = 0; i < 16; i++) {
+		float mid = (lo + hi) / 2.0;
+		vec3 m = lab_to_local(data, vec3(lab.x, mid * lab.yz));
+		if (min(m.r, min(m.g, m.b)) >= 0.0) {
+			lo = mid;
+		} else {
+			hi = mid;
+		}
+	}
+	return lab_to_local(data, vec3(lab.x, lo * lab.yz));
+}
+
+const float BAYER[64] = float[](
+	 0, 32,  8, 40,  2, 34, 10, 42,
+	48, 16, 56, 24, 50, 18, 58, 26,
+	12, 44,  4, 36, 14, 46,  6, 38,
+	60, 28, 52, 20, 62, 30, 54, 22,
+	 3, 35, 11, 43,  1, 33,  9, 41,
+	51, 19, 59, 27, 49, 17, 57, 25,
+	15, 47,  7, 39, 13, 45,  5, 37,
+	63, 31, 55, 23, 61, 29, 53, 21
+);
+
+// Converts the Oklab or ICtCp color to the encoded output color.
+vec4 encode_color(Data data, vec4 color) {
+	if (data.raw != 0) {
+		return vec4(clamp_output(data, adjust_channels(data, color.rgb)), color.a);
+	}
+	vec3 c;
+	if (data.gamut_mapping == GAMUT_MAPPING_COMPRESS) {
+		c = compress_chroma(data, color.rgb);
+	} else {
+		c = lab_to_local(data, color.rgb);
+	}
+	if (data.peak > 0.0) {
+		c = min(c, vec3(data.peak));
+	}
+	c = apply_inv_eotf(data, c);
+	if (data.dither != 0.0) {
+		uvec2 p = uvec2(gl_FragCoord.xy) % 8;
+		c += data.dither * ((BAYER[p.y * 8 + p.x] + 0.5) / 64.0 - 0.5);
+	}
+	return vec4(clamp_output(data, adjust_channels(data, c)), color.a);
+}
@@ -4,6 +4,19 @@
 
 layout(location = 0) out vec2 pos;
 
+// Applies a wl_output.transform. Rotations are counter-clockwise.
+vec2 apply_transform(uint transform, vec2 p) {
+	if (transform >= 4) {
+		p.x = -p.x;
+	}
+	switch (transform & 3) {
+		case 1: return vec2(p.y, -p.x);
+		case 2: return -p;
+		case 3: return vec2(-p.y, p.x);
+	}
+	return p;
+}
+
 void main() {
 	Data data = push_data.data;
 	switch (gl_VertexIndex) {
@@ -12,5 +25,6 @@ void main() {
 		case 2: pos = vec2(data.x2, data.y2); break;
 		case 3: pos = vec2(data.x1, data.y2); break;
 	}
-	gl_Position = vec4(pos, 0.0, 1.0);
+	vec2 p = pos * data.section.xy + data.section.zw;
+	gl_Position = vec4(apply_transform(data.transform, p), 0.0, 1.0);
 }
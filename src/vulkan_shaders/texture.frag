@@ -0,0 +1,15 @@
+#version 450
+
+#include "fill.common.glsl"
+#include "fill.encode.glsl"
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+layout(location = 0) in vec2 pos;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+	Data data = push_data.data;
+	vec2 t = (pos - vec2(data.x1, data.y1)) / vec2(data.x2 - data.x1, data.y2 - data.y1);
+	out_color = encode_color(data, texture(tex, t));
+}
@@ -22,9 +22,17 @@ struct FragIn {
     @location(0) pos: vec2f,
 }
 
+struct Data {
+    offset: vec2f,
+    scale: f32,
+    background: vec4f,
+};
+
+var<push_constant> data: Data;
+
 @fragment
 fn frag_main(in: FragIn) -> @location(0) vec4f {
-    let xy = in.pos * vec2f(0.85, 0.85);
+    let xy = data.offset + in.pos * data.scale;
 
     const Y = 1.0;
     let y_ratio = Y / xy.y;
@@ -34,7 +42,7 @@ fn frag_main(in: FragIn) -> @location(0) vec4f {
     col = normalize(col);
 
     let distance = distance_to_horseshoe(xy);
-    col = mix(col, vec3f(1.0), smoothstep(0.005, 0.007, distance));
+    col = mix(col, data.background.rgb, smoothstep(0.005, 0.007, distance));
 
     return vec4f(col, 1.0);
 }
@@ -27,13 +27,16 @@ struct Data {
     g: vec2f,
     b: vec2f,
     wp: vec2f,
+    offset: vec2f,
+    scale: f32,
+    line: vec4f,
 };
 
 var<push_constant> data: Data;
 
 @fragment
 fn triangle_frag_main(in: FragIn) -> @location(0) vec4f {
-    let xy = in.pos * vec2f(0.85, 0.85);
+    let xy = data.offset + in.pos * data.scale;
 
     const TRIANGLE_WHITE = 0.007;
     const TRIANGLE_BLACK = 0.005;
@@ -48,7 +51,7 @@ fn triangle_frag_main(in: FragIn) -> @location(0) vec4f {
     var wp_alpha = 1.0 - smoothstep(WP_BLACK, WP_WHITE, length(xy - data.wp));
 
     let alpha = max(triangle_alpha, wp_alpha);
-    return vec4f(0.0, 0.0, 0.0, alpha);
+    return vec4f(data.line.rgb, alpha);
 }
 
 fn distance_to_triangle_edge(p: vec2f, p0: vec2f, p1: vec2f, p2: vec2f) -> f32 {